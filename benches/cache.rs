@@ -0,0 +1,343 @@
+//! Throughput of the hot paths, next to `dashmap` and `moka` for reference: `cargo bench`.
+//!
+//! Criterion keeps the last run under `target/criterion`, so running the suite before and after
+//! a change reports any regression.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use dashmap::DashMap;
+use tokio::runtime::Runtime;
+use tokio_cache::prelude::*;
+
+/// Keys cached before measuring, so lookups hit.
+const N_KEY: u64 = 10_000;
+const BUFFER: usize = 1024;
+const N_SHARD: usize = 4;
+const MGET_KEYS: u64 = 16;
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+async fn bounded(expiration_policy: ExpirationPolicy) -> HashMapCache<u64, u64> {
+    HashMapCache::new(expiration_policy, BUFFER).await.unwrap()
+}
+
+async fn unbounded(expiration_policy: ExpirationPolicy) -> UnboundedHashMapCache<u64, u64> {
+    UnboundedHashMapCache::new(expiration_policy).await.unwrap()
+}
+
+async fn sharded(expiration_policy: ExpirationPolicy) -> ShardedHashMapCache<u64, u64> {
+    ShardedHashMapCache::new(expiration_policy, BUFFER, N_SHARD)
+        .await
+        .unwrap()
+}
+
+fn entries() -> Vec<InsertEntry<u64, u64>> {
+    (0..N_KEY).map(|key| InsertEntry::new(key, key)).collect()
+}
+
+fn get(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("get");
+    let entries = entries();
+
+    let cache = rt.block_on(bounded(ExpirationPolicy::None));
+    rt.block_on(cache.minsert_entries(&entries)).unwrap();
+    let mut key = 0;
+    group.bench_function("bounded", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move { black_box(cache.get(key).await.unwrap()) }
+        })
+    });
+
+    let cache = rt.block_on(unbounded(ExpirationPolicy::None));
+    rt.block_on(cache.minsert_entries(&entries)).unwrap();
+    group.bench_function("unbounded", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move { black_box(cache.get(key).await.unwrap()) }
+        })
+    });
+
+    let cache = rt.block_on(sharded(ExpirationPolicy::None));
+    rt.block_on(cache.minsert_entries(&entries)).unwrap();
+    group.bench_function("sharded", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move { black_box(cache.get(key).await.unwrap()) }
+        })
+    });
+
+    let map: DashMap<u64, u64> = (0..N_KEY).map(|key| (key, key)).collect();
+    group.bench_function("dashmap", |b| {
+        b.iter(|| {
+            key = (key + 1) % N_KEY;
+            black_box(map.get(&key).map(|val| *val))
+        })
+    });
+
+    let cache = moka::future::Cache::new(N_KEY);
+    rt.block_on(async {
+        for key in 0..N_KEY {
+            cache.insert(key, key).await;
+        }
+    });
+    group.bench_function("moka", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move { black_box(cache.get(&key).await) }
+        })
+    });
+
+    group.finish();
+}
+
+fn insert(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("insert");
+
+    let cache = rt.block_on(bounded(ExpirationPolicy::None));
+    let mut key = 0;
+    group.bench_function("bounded", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move {
+                cache
+                    .insert(key, key, None, InsertPolicy::Always)
+                    .await
+                    .unwrap()
+            }
+        })
+    });
+
+    let cache = rt.block_on(unbounded(ExpirationPolicy::None));
+    group.bench_function("unbounded", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move {
+                cache
+                    .insert(key, key, None, InsertPolicy::Always)
+                    .await
+                    .unwrap()
+            }
+        })
+    });
+
+    let cache = rt.block_on(sharded(ExpirationPolicy::None));
+    group.bench_function("sharded", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move {
+                cache
+                    .insert(key, key, None, InsertPolicy::Always)
+                    .await
+                    .unwrap()
+            }
+        })
+    });
+
+    let map = DashMap::new();
+    group.bench_function("dashmap", |b| {
+        b.iter(|| {
+            key = (key + 1) % N_KEY;
+            black_box(map.insert(key, key))
+        })
+    });
+
+    let cache = moka::future::Cache::new(N_KEY);
+    group.bench_function("moka", |b| {
+        b.to_async(&rt).iter(|| {
+            key = (key + 1) % N_KEY;
+            let cache = &cache;
+            async move { cache.insert(key, key).await }
+        })
+    });
+
+    group.finish();
+}
+
+fn mget(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("mget");
+    let entries = entries();
+    let keys: Vec<u64> = (0..MGET_KEYS).collect();
+
+    let cache = rt.block_on(bounded(ExpirationPolicy::None));
+    rt.block_on(cache.minsert_entries(&entries)).unwrap();
+    group.bench_function("bounded", |b| {
+        b.to_async(&rt)
+            .iter(|| async { black_box(cache.mget(&keys).await.unwrap()) })
+    });
+
+    let cache = rt.block_on(unbounded(ExpirationPolicy::None));
+    rt.block_on(cache.minsert_entries(&entries)).unwrap();
+    group.bench_function("unbounded", |b| {
+        b.to_async(&rt)
+            .iter(|| async { black_box(cache.mget(&keys).await.unwrap()) })
+    });
+
+    let cache = rt.block_on(sharded(ExpirationPolicy::None));
+    rt.block_on(cache.minsert_entries(&entries)).unwrap();
+    group.bench_function("sharded", |b| {
+        b.to_async(&rt)
+            .iter(|| async { black_box(cache.mget(&keys).await.unwrap()) })
+    });
+
+    group.finish();
+}
+
+/// Filling an empty cache with `N_KEY` entries.
+fn fill(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("fill");
+    let entries = entries();
+
+    group.bench_function("minsert", |b| {
+        b.to_async(&rt).iter(|| async {
+            let cache = bounded(ExpirationPolicy::None).await;
+            cache.minsert_entries(&entries).await.unwrap();
+            cache.barrier().await.unwrap()
+        })
+    });
+
+    group.bench_function("bulk_load", |b| {
+        b.to_async(&rt).iter(|| async {
+            let cache = bounded(ExpirationPolicy::None).await;
+            let entries = (0..N_KEY).map(|key| (key, key, None));
+            black_box(cache.bulk_load(entries).await.unwrap())
+        })
+    });
+
+    group.finish();
+}
+
+/// Inserting new keys into full caches, so each insert is followed by an eviction.
+fn eviction(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("eviction");
+    let capacity = N_KEY as usize / 10;
+
+    for (name, expiration_policy) in [
+        ("lru", ExpirationPolicy::LRU(capacity)),
+        ("lfu", ExpirationPolicy::LFU(capacity)),
+    ] {
+        let cache = rt.block_on(bounded(expiration_policy));
+        let mut key = 0;
+        group.bench_function(BenchmarkId::new("bounded", name), |b| {
+            b.to_async(&rt).iter(|| {
+                key += 1;
+                let cache = &cache;
+                async move {
+                    cache
+                        .insert(key, key, None, InsertPolicy::Always)
+                        .await
+                        .unwrap()
+                }
+            })
+        });
+
+        let cache = rt.block_on(unbounded(expiration_policy));
+        group.bench_function(BenchmarkId::new("unbounded", name), |b| {
+            b.to_async(&rt).iter(|| {
+                key += 1;
+                let cache = &cache;
+                async move {
+                    cache
+                        .insert(key, key, None, InsertPolicy::Always)
+                        .await
+                        .unwrap()
+                }
+            })
+        });
+    }
+
+    let cache = moka::future::Cache::new(capacity as u64);
+    let mut key = 0;
+    group.bench_function(BenchmarkId::new("moka", "tinylfu"), |b| {
+        b.to_async(&rt).iter(|| {
+            key += 1;
+            let cache = &cache;
+            async move { cache.insert(key, key).await }
+        })
+    });
+
+    group.finish();
+}
+
+/// Inserts on a master with a growing number of replicas.
+fn replication(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("replication");
+
+    for n_replica in [0, 1, 4] {
+        let master = rt.block_on(bounded(ExpirationPolicy::None));
+        let replicas: Vec<_> = (0..n_replica)
+            .map(|_| {
+                rt.block_on(async {
+                    let replica = bounded(ExpirationPolicy::None).await;
+                    replica.replicate(&master).await.unwrap();
+                    replica
+                })
+            })
+            .collect();
+        let mut key = 0;
+        group.bench_function(BenchmarkId::new("bounded", n_replica), |b| {
+            b.to_async(&rt).iter(|| {
+                key = (key + 1) % N_KEY;
+                let master = &master;
+                async move {
+                    master
+                        .insert(key, key, None, InsertPolicy::Always)
+                        .await
+                        .unwrap()
+                }
+            })
+        });
+        drop(replicas);
+    }
+
+    for n_replica in [0, 1, 4] {
+        let master = rt.block_on(unbounded(ExpirationPolicy::None));
+        let replicas: Vec<_> = (0..n_replica)
+            .map(|_| {
+                rt.block_on(async {
+                    let replica = unbounded(ExpirationPolicy::None).await;
+                    replica.replicate(&master).await.unwrap();
+                    replica
+                })
+            })
+            .collect();
+        let mut key = 0;
+        group.bench_function(BenchmarkId::new("unbounded", n_replica), |b| {
+            b.to_async(&rt).iter(|| {
+                key = (key + 1) % N_KEY;
+                let master = &master;
+                async move {
+                    master
+                        .insert(key, key, None, InsertPolicy::Always)
+                        .await
+                        .unwrap()
+                }
+            })
+        });
+        drop(replicas);
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, get, insert, mget, fill, eviction, replication);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::compile();
+}
+
+/// The gRPC service is declared here rather than in a `.proto` file, so building it doesn't need
+/// `protoc`. The messages are the prost structs in `src/tokio_cache/grpc.rs`.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, MethodBuilder, Service};
+
+    pub fn compile() {
+        let service = Service::builder()
+            .name("CacheService")
+            .package("tokio_cache")
+            .method(unary("get", "Get", "GetRequest", "GetResponse"))
+            .method(unary("set", "Set", "SetRequest", "SetResponse"))
+            .method(unary("m_get", "MGet", "MGetRequest", "MGetResponse"))
+            .method(unary("remove", "Remove", "RemoveRequest", "RemoveResponse"))
+            .method(
+                method("subscribe", "Subscribe", "SubscribeRequest", "Event")
+                    .server_streaming()
+                    .build(),
+            )
+            .build();
+        Builder::new().compile(&[service]);
+    }
+
+    fn unary(name: &str, route_name: &str, input: &str, output: &str) -> Method {
+        method(name, route_name, input, output).build()
+    }
+
+    fn method(name: &str, route_name: &str, input: &str, output: &str) -> MethodBuilder {
+        Method::builder()
+            .name(name)
+            .route_name(route_name)
+            .input_type(format!("super::{input}"))
+            .output_type(format!("super::{output}"))
+            .codec_path("tonic_prost::ProstCodec")
+    }
+}
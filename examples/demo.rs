@@ -0,0 +1,50 @@
+//! A tour of the caches: `cargo run --example demo`.
+
+use std::time::Duration;
+
+use tokio_cache::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<(), TokioActorCacheError> {
+    // Key-value entries, evicting the least recently used beyond 1000 entries.
+    let sessions = HashMapCache::<String, u64>::new(ExpirationPolicy::LRU(1000), 32).await?;
+    sessions
+        .insert(
+            "alice".to_string(),
+            42,
+            Some(Duration::from_secs(60)),
+            InsertPolicy::Always,
+        )
+        .await?;
+    println!("alice -> {:?}", sessions.get("alice".to_string()).await?);
+    println!("ttl -> {:?}", sessions.ttl(&["alice".to_string()]).await?);
+
+    // Only write keys that aren't cached yet.
+    sessions
+        .insert("alice".to_string(), 7, None, InsertPolicy::IfAbsent)
+        .await?;
+    println!("still -> {:?}", sessions.get("alice".to_string()).await?);
+
+    // A set and a list.
+    let tags = UnboundedHashSetCache::<&str>::new(ExpirationPolicy::None).await?;
+    tags.insert("rust", None, InsertPolicy::Always).await?;
+    tags.insert("tokio", None, InsertPolicy::Always).await?;
+    println!("tags -> {:?}", tags.get_all().await?);
+
+    let events = VecCache::<u32>::new(ExpirationPolicy::None, 32).await?;
+    for event in 0..3 {
+        events.push(event, None, InsertPolicy::Always).await?;
+    }
+    println!("events -> {:?}", events.get_all().await?);
+
+    // Keys spread over 4 nodes.
+    let cluster = HashMapCacheCluster::<u64, String>::new(ExpirationPolicy::None, 32, 4).await?;
+    for id in 0..8 {
+        cluster
+            .insert(id, format!("user {id}"), None, InsertPolicy::Always)
+            .await?;
+    }
+    println!("cluster -> {:?}", cluster.mget(&[0, 5, 9]).await?);
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Actor-based in-memory caches for tokio: every cache is a task that owns its data and is driven
+//! through a cloneable handle. Start with `use tokio_cache::prelude::*;`.
+
+pub mod prelude;
+pub mod tokio_cache {
+    #[cfg(feature = "admin")]
+    pub mod admin;
+    pub mod bounded {
+        pub mod hm;
+        pub mod hm_cluster;
+        pub mod hs;
+        pub mod hs_cluster;
+        pub mod sharded;
+        pub mod vec;
+        pub mod vec_cluster;
+    }
+    pub mod unbounded {
+        pub mod hm;
+        pub mod hm_cluster;
+        pub mod hs;
+        pub mod hs_cluster;
+        pub mod sharded;
+        pub mod vec;
+        pub mod vec_cluster;
+    }
+    pub mod local {
+        pub mod hm;
+    }
+    pub mod blocking;
+    pub mod bytes_cache;
+    pub mod cancel;
+    mod channel;
+    mod cmd;
+    #[cfg(any(feature = "lz4", feature = "zstd"))]
+    pub mod compression;
+    mod compute;
+    mod cow;
+    mod data_struct;
+    #[cfg(feature = "serde")]
+    pub mod dump;
+    #[cfg(feature = "encryption")]
+    pub mod encryption;
+    pub mod error;
+    #[cfg(feature = "grpc")]
+    pub mod grpc;
+    pub mod health;
+    pub mod histogram;
+    mod hm;
+    mod hm_cluster;
+    mod hs;
+    mod hs_cluster;
+    mod index;
+    mod ingestion;
+    mod invalidation;
+    mod jitter;
+    pub mod join;
+    #[cfg(feature = "tower")]
+    pub mod layer;
+    pub mod mirror;
+    pub mod namespace;
+    pub mod near;
+    mod ops;
+    pub mod option;
+    pub mod pipeline;
+    pub mod pressure;
+    mod projection;
+    pub mod rebalance;
+    pub mod registry;
+    pub mod removal;
+    pub mod replication;
+    pub mod router;
+    pub mod runtime;
+    #[cfg(feature = "server")]
+    pub mod server;
+    mod sharded;
+    pub mod snapshot;
+    #[cfg(any(test, feature = "test-utils"))]
+    pub mod test_utils;
+    #[cfg(feature = "tiered")]
+    pub mod tiered;
+    mod trace;
+    mod txn;
+    mod vec;
+    mod vec_cluster;
+    mod warmup;
+}
+#[cfg(test)]
+mod unittests {
+    pub mod bounded {
+        #[cfg(feature = "admin")]
+        pub mod admin;
+        pub mod blocking;
+        pub mod bytes_cache;
+        #[cfg(any(feature = "lz4", feature = "zstd"))]
+        pub mod compression;
+        #[cfg(feature = "grpc")]
+        pub mod grpc;
+        pub mod hm;
+        pub mod hm_cluster;
+        pub mod hs;
+        pub mod hs_cluster;
+        pub mod join;
+        #[cfg(feature = "tower")]
+        pub mod layer;
+        pub mod namespace;
+        pub mod pressure;
+        pub mod registry;
+        #[cfg(feature = "runtime")]
+        pub mod runtime;
+        #[cfg(feature = "server")]
+        pub mod server;
+        pub mod sharded;
+        #[cfg(feature = "tiered")]
+        pub mod tiered;
+        pub mod vec;
+        pub mod vec_cluster;
+    }
+    pub mod unbounded {
+        pub mod hm;
+        pub mod hm_cluster;
+        pub mod hs;
+        pub mod hs_cluster;
+        pub mod sharded;
+        pub mod vec;
+        pub mod vec_cluster;
+    }
+    pub mod local {
+        pub mod hm;
+    }
+}
@@ -1,62 +0,0 @@
-pub mod tokio_cache {
-    pub mod bounded {
-        mod cmd;
-        pub mod hm;
-        pub mod hm_cluster;
-        pub mod hs;
-        pub mod hs_cluster;
-        pub mod vec;
-        pub mod vec_cluster;
-    }
-    pub mod unbounded {
-        pub mod hm;
-        pub mod hm_cluster;
-        pub mod hs;
-        pub mod hs_cluster;
-        pub mod vec;
-        pub mod vec_cluster;
-        mod cmd;
-    }
-    mod compute;
-    mod data_struct;
-    pub mod error;
-    pub mod option;
-}
-pub mod unittests {
-    pub mod bounded {
-        pub mod hm;
-        pub mod hm_cluster;
-        pub mod hs;
-        pub mod hs_cluster;
-        pub mod vec;
-        pub mod vec_cluster;
-    }
-    pub mod unbounded {
-        pub mod hm;
-        pub mod hm_cluster;
-        pub mod hs;
-        pub mod hs_cluster;
-        pub mod vec;
-        pub mod vec_cluster;
-    }
-}
-
-#[tokio::main]
-async fn main() {
-    // 2. Advanced Caching Patterns
-    // Cache-Aside Pattern: Facilitate application-side loading and updating of cache entries.
-    // Read-Through and Write-Through: Automate cache population and updates on misses/hits and writes, reducing logic in client code and keeping cached data consistent.
-    // Write-Behind: Optionally support background updates to the underlying data store, batching or delaying disk/database writes.
-
-    // 3. Persistence Support
-    // Add options to persist cached data to disk, e.g., periodic snapshots or append-only logs, to recover from crashes or restarts.
-
-    // 4. Cache Invalidation and Pub/Sub
-    // Implement mechanisms to propagate invalidation messages (Pub/Sub) to synchronize cache state across multiple replicas or instances.
-
-    // 5. Metrics and Monitoring
-    // Track cache hits/misses, evictions, memory usage, and performance statistics for effective monitoring and tuning.
-
-    // 7. Near Cache (Local Caching)
-    // Implement an optional per-process or per-node local cache to speed up repetitive reads and reduce network hops, mirroring near cache designs in high-performance systems.
-}
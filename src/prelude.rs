@@ -0,0 +1,29 @@
+//! The types most programs need.
+//!
+//! Cache names refer to the bounded variants, whose command channels push back on senders once
+//! full; the unbounded ones carry an `Unbounded` prefix, and those whose actors run on a
+//! `LocalSet` a `Local` one.
+
+pub use crate::tokio_cache::bounded::hm::{ArcHashMapCache, HashMapCache};
+pub use crate::tokio_cache::bounded::hm_cluster::HashMapCacheCluster;
+pub use crate::tokio_cache::bounded::hs::HashSetCache;
+pub use crate::tokio_cache::bounded::hs_cluster::HashSetCacheCluster;
+pub use crate::tokio_cache::bounded::sharded::ShardedHashMapCache;
+pub use crate::tokio_cache::bounded::vec::VecCache;
+pub use crate::tokio_cache::bounded::vec_cluster::VecCacheCluster;
+pub use crate::tokio_cache::error::{ConfigError, TokioActorCacheError};
+pub use crate::tokio_cache::local::hm::HashMapCache as LocalHashMapCache;
+pub use crate::tokio_cache::option::{
+    CacheConfig, ClusterConfig, ExpirationPolicy, InsertEntry, InsertOptions, InsertPolicy,
+    RemovePolicy, ShedPolicy, TtlOnWrite, VecEnd,
+};
+pub use crate::tokio_cache::removal::{Eviction, Removal, RemovalReason};
+pub use crate::tokio_cache::unbounded::hm::{
+    ArcHashMapCache as UnboundedArcHashMapCache, HashMapCache as UnboundedHashMapCache,
+};
+pub use crate::tokio_cache::unbounded::hm_cluster::HashMapCacheCluster as UnboundedHashMapCacheCluster;
+pub use crate::tokio_cache::unbounded::hs::HashSetCache as UnboundedHashSetCache;
+pub use crate::tokio_cache::unbounded::hs_cluster::HashSetCacheCluster as UnboundedHashSetCacheCluster;
+pub use crate::tokio_cache::unbounded::sharded::ShardedHashMapCache as UnboundedShardedHashMapCache;
+pub use crate::tokio_cache::unbounded::vec::VecCache as UnboundedVecCache;
+pub use crate::tokio_cache::unbounded::vec_cluster::VecCacheCluster as UnboundedVecCacheCluster;
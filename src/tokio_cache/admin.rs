@@ -0,0 +1,370 @@
+//! An HTTP admin endpoint, so operators can inspect and purge the caches of a running service.
+//!
+//! Every cache registered in `AdminCaches` gets these routes under `/{cache}`:
+//!
+//! - `GET /{cache}/stats`: entry count, the hottest and idlest keys, and a histogram of the
+//!   remaining TTLs
+//! - `GET /{cache}/keys?pattern=`: keys matching a glob with `*` and `?`, all keys without one
+//! - `GET /{cache}/entry/{key}` and `DELETE /{cache}/entry/{key}`
+//! - `POST /{cache}/flush`: remove every entry
+//! - `GET /{cache}/replication`: role and replication lag
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::replication::Role;
+
+/// Number of hot and idle keys reported by `/stats`.
+const STATS_TOP_N: usize = 10;
+
+pub type AdminFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, TokioActorCacheError>> + Send + 'a>>;
+
+/// What the admin endpoint needs from a cache. Keys cross HTTP as strings.
+pub trait AdminCache: Send + Sync + 'static {
+    fn stats(&self) -> AdminFuture<'_, CacheStats>;
+
+    fn keys(&self, pattern: Option<String>) -> AdminFuture<'_, Vec<String>>;
+
+    fn entry(&self, key: String) -> AdminFuture<'_, Option<Entry>>;
+
+    /// Returns whether there was an entry to delete.
+    fn delete(&self, key: String) -> AdminFuture<'_, bool>;
+
+    fn flush(&self) -> AdminFuture<'_, ()>;
+
+    fn replication(&self) -> AdminFuture<'_, Replication>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    /// Most used first.
+    pub hot_keys: Vec<HotKey>,
+    /// Unused for longest first.
+    pub idle_keys: Vec<IdleKey>,
+    /// Entries without a TTL.
+    pub persistent: usize,
+    /// Entries with a TTL by how long they have left, shortest first, see `TtlHistogram`.
+    pub ttl_histogram: Vec<TtlBucket>,
+    /// Callers gone before their answer, see `HashMapCache::dropped_receivers`.
+    pub dropped_receivers: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TtlBucket {
+    /// Exclusive, `None` for the last bucket.
+    pub under_ms: Option<u64>,
+    pub entries: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotKey {
+    pub key: String,
+    pub calls: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdleKey {
+    pub key: String,
+    pub idle_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub key: String,
+    pub val: serde_json::Value,
+    pub ttl_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replication {
+    /// `"master"` or `"replica"`.
+    pub role: String,
+    pub master_id: Option<u64>,
+    pub replicas: usize,
+    pub entries_synced: u64,
+    /// Time since the last write from the master was applied.
+    pub last_sync_ms_ago: Option<u64>,
+    pub lag_ms: Option<u64>,
+}
+
+/// Keys are parsed with `FromStr` and listed with `Display`; values are rendered as JSON.
+/// Reading an entry counts as a use, like any `get`.
+impl<K, V, C> AdminCache for HashMapCache<K, V, C>
+where
+    K: Clone + Debug + Display + FromStr + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Serialize + Send + Sync + 'static,
+    C: Send + Sync + 'static,
+{
+    fn stats(&self) -> AdminFuture<'_, CacheStats> {
+        Box::pin(async move {
+            let entries = self.get_all().await?.len();
+            let hot_keys = self.hot_keys(STATS_TOP_N).await?;
+            let idle_keys = self.idle_keys(STATS_TOP_N).await?;
+            let ttl_histogram = self.ttl_histogram().await?;
+            Ok(CacheStats {
+                entries,
+                hot_keys: hot_keys
+                    .into_iter()
+                    .map(|(key, calls)| HotKey {
+                        key: key.to_string(),
+                        calls,
+                    })
+                    .collect(),
+                idle_keys: idle_keys
+                    .into_iter()
+                    .map(|(key, idle)| IdleKey {
+                        key: key.to_string(),
+                        idle_ms: idle.as_millis() as u64,
+                    })
+                    .collect(),
+                persistent: ttl_histogram.persistent(),
+                ttl_histogram: ttl_histogram
+                    .buckets()
+                    .map(|(under, entries)| TtlBucket {
+                        under_ms: under.map(|under| under.as_millis() as u64),
+                        entries,
+                    })
+                    .collect(),
+                dropped_receivers: self.dropped_receivers(),
+            })
+        })
+    }
+
+    fn keys(&self, pattern: Option<String>) -> AdminFuture<'_, Vec<String>> {
+        Box::pin(async move {
+            let mut keys: Vec<String> = self
+                .get_all()
+                .await?
+                .into_keys()
+                .map(|key| key.to_string())
+                .filter(|key| {
+                    pattern
+                        .as_ref()
+                        .is_none_or(|pattern| glob_match(pattern, key))
+                })
+                .collect();
+            keys.sort();
+            Ok(keys)
+        })
+    }
+
+    fn entry(&self, key: String) -> AdminFuture<'_, Option<Entry>> {
+        Box::pin(async move {
+            // A key that doesn't parse can't be cached.
+            let Ok(parsed) = key.parse::<K>() else {
+                return Ok(None);
+            };
+            let Some(val) = self.get(parsed.clone()).await? else {
+                return Ok(None);
+            };
+            let ttl = self.ttl(&[parsed]).await?.pop().flatten();
+            let val = serde_json::to_value(&val).map_err(|err| TokioActorCacheError::Dump {
+                reason: err.to_string(),
+            })?;
+            Ok(Some(Entry {
+                key,
+                val,
+                ttl_ms: ttl.map(|ttl| ttl.as_millis() as u64),
+            }))
+        })
+    }
+
+    fn delete(&self, key: String) -> AdminFuture<'_, bool> {
+        Box::pin(async move {
+            let Ok(key) = key.parse::<K>() else {
+                return Ok(false);
+            };
+            let removed = self.remove(&[key]).await?;
+            Ok(removed.into_iter().flatten().next().is_some())
+        })
+    }
+
+    fn flush(&self) -> AdminFuture<'_, ()> {
+        Box::pin(self.clear())
+    }
+
+    fn replication(&self) -> AdminFuture<'_, Replication> {
+        Box::pin(async move {
+            let info = self.replication_info().await?;
+            let role = match info.role {
+                Role::Master => "master",
+                Role::Replica => "replica",
+            };
+            Ok(Replication {
+                role: role.to_string(),
+                master_id: info.master_id,
+                replicas: info.replicas,
+                entries_synced: info.entries_synced,
+                last_sync_ms_ago: info
+                    .last_sync
+                    .map(|last_sync| last_sync.elapsed().as_millis() as u64),
+                lag_ms: info.lag.map(|lag| lag.as_millis() as u64),
+            })
+        })
+    }
+}
+
+/// The caches served by `admin_router`, by name.
+#[derive(Clone, Default)]
+pub struct AdminCaches {
+    caches: BTreeMap<String, Arc<dyn AdminCache>>,
+}
+
+impl AdminCaches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `cache` under `/{name}`, replacing any cache registered with the same name.
+    pub fn register(mut self, name: impl Into<String>, cache: impl AdminCache) -> Self {
+        self.caches.insert(name.into(), Arc::new(cache));
+        self
+    }
+
+    fn get(&self, name: &str) -> Result<&dyn AdminCache, AdminError> {
+        self.caches
+            .get(name)
+            .map(|cache| cache.as_ref())
+            .ok_or(AdminError::UnknownCache)
+    }
+}
+
+/// Routes to inspect and purge `caches`; see the module docs. Nest it under a prefix of your
+/// choice and put your own authentication in front of it.
+pub fn admin_router(caches: AdminCaches) -> Router {
+    Router::new()
+        .route("/{cache}/stats", get(stats))
+        .route("/{cache}/keys", get(keys))
+        .route("/{cache}/entry/{key}", get(entry).delete(delete))
+        .route("/{cache}/flush", post(flush))
+        .route("/{cache}/replication", get(replication))
+        .with_state(Arc::new(caches))
+}
+
+type Caches = State<Arc<AdminCaches>>;
+
+async fn stats(State(caches): Caches, Path(cache): Path<String>) -> AdminResult<CacheStats> {
+    Ok(Json(caches.get(&cache)?.stats().await?))
+}
+
+#[derive(Debug, Deserialize)]
+struct KeysQuery {
+    pattern: Option<String>,
+}
+
+async fn keys(
+    State(caches): Caches,
+    Path(cache): Path<String>,
+    Query(query): Query<KeysQuery>,
+) -> AdminResult<Vec<String>> {
+    Ok(Json(caches.get(&cache)?.keys(query.pattern).await?))
+}
+
+async fn entry(
+    State(caches): Caches,
+    Path((cache, key)): Path<(String, String)>,
+) -> AdminResult<Entry> {
+    match caches.get(&cache)?.entry(key).await? {
+        Some(entry) => Ok(Json(entry)),
+        None => Err(AdminError::NoSuchEntry),
+    }
+}
+
+async fn delete(
+    State(caches): Caches,
+    Path((cache, key)): Path<(String, String)>,
+) -> Result<StatusCode, AdminError> {
+    match caches.get(&cache)?.delete(key).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(AdminError::NoSuchEntry),
+    }
+}
+
+async fn flush(State(caches): Caches, Path(cache): Path<String>) -> Result<StatusCode, AdminError> {
+    caches.get(&cache)?.flush().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn replication(State(caches): Caches, Path(cache): Path<String>) -> AdminResult<Replication> {
+    Ok(Json(caches.get(&cache)?.replication().await?))
+}
+
+type AdminResult<T> = Result<Json<T>, AdminError>;
+
+enum AdminError {
+    UnknownCache,
+    NoSuchEntry,
+    Cache(TokioActorCacheError),
+}
+
+impl From<TokioActorCacheError> for AdminError {
+    fn from(err: TokioActorCacheError) -> Self {
+        AdminError::Cache(err)
+    }
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        match self {
+            AdminError::UnknownCache => (StatusCode::NOT_FOUND, "no such cache").into_response(),
+            AdminError::NoSuchEntry => (StatusCode::NOT_FOUND, "no such entry").into_response(),
+            AdminError::Cache(err) => {
+                let status = match err {
+                    TokioActorCacheError::ChannelFull { .. }
+                    | TokioActorCacheError::Shed
+                    | TokioActorCacheError::ActorGone
+                    | TokioActorCacheError::Timeout { .. } => StatusCode::SERVICE_UNAVAILABLE,
+                    TokioActorCacheError::Config(_) => StatusCode::BAD_REQUEST,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                };
+                (status, err.to_string()).into_response()
+            }
+        }
+    }
+}
+
+/// Redis `KEYS`-style matching: `*` matches any run of characters and `?` any one character.
+fn glob_match(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+    let (mut p, mut k) = (0, 0);
+    // Where the last `*` was, and how much of `key` it has swallowed so far.
+    let mut backtrack = None;
+    while k < key.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, k));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == key[k] => {
+                p += 1;
+                k += 1;
+            }
+            _ => match backtrack {
+                Some((star, swallowed)) => {
+                    p = star + 1;
+                    k = swallowed + 1;
+                    backtrack = Some((star, k));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,137 @@
+//! A synchronous facade, so code running outside of tokio (e.g. rayon workers) can use a cache
+//! without bridging by hand.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::hash::Hash;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::tokio_cache::error::{ConfigError, TokioActorCacheError};
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::option::{InsertEntry, InsertOptions, InsertPolicy};
+
+/// Blocking version of `HashMapCache`. Its methods block the calling thread, so they must not be
+/// called from async code; use `cache()` there instead.
+#[derive(Debug, Clone)]
+pub struct BlockingHashMapCache<K, V, C> {
+    cache: HashMapCache<K, V, C>,
+    handle: Handle,
+    // Keeps the runtime started by `spawn` alive; `None` when the cache runs on the caller's.
+    _runtime: Option<Arc<Runtime>>,
+}
+
+impl<K, V, C> BlockingHashMapCache<K, V, C>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Send + Sync + 'static,
+{
+    /// Wrap `cache`, whose actor runs on the runtime of `handle`.
+    pub fn new(cache: HashMapCache<K, V, C>, handle: Handle) -> Self {
+        Self {
+            cache,
+            handle,
+            _runtime: None,
+        }
+    }
+
+    /// Start a runtime of its own, with one worker thread that runs the actor between calls, and
+    /// build the cache on it with `make`, e.g.
+    /// `BlockingHashMapCache::spawn(|| HashMapCache::new(policy, 32))`. The runtime stops once
+    /// every clone is dropped, which must not happen inside async code. A `ConfigError` from
+    /// `make` comes back as an `InvalidInput` error.
+    pub fn spawn<F, Fut>(make: F) -> io::Result<Self>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<HashMapCache<K, V, C>, ConfigError>>,
+    {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("tokio-cache-blocking")
+            .enable_all()
+            .build()?;
+        let cache = runtime
+            .block_on(make())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        Ok(Self {
+            cache,
+            handle: runtime.handle().clone(),
+            _runtime: Some(Arc::new(runtime)),
+        })
+    }
+
+    /// The async handle to the same cache.
+    pub fn cache(&self) -> &HashMapCache<K, V, C> {
+        &self.cache
+    }
+
+    pub fn get(&self, key: K) -> Result<Option<V>, TokioActorCacheError> {
+        self.handle.block_on(self.cache.get(key))
+    }
+
+    pub fn mget(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        self.handle.block_on(self.cache.mget(keys))
+    }
+
+    pub fn get_all(&self) -> Result<HashMap<K, V>, TokioActorCacheError> {
+        self.handle.block_on(self.cache.get_all())
+    }
+
+    pub fn contains_key(&self, keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> {
+        self.handle.block_on(self.cache.contains_key(keys))
+    }
+
+    pub fn ttl(&self, keys: &[K]) -> Result<Vec<Option<Duration>>, TokioActorCacheError> {
+        self.handle.block_on(self.cache.ttl(keys))
+    }
+
+    pub fn insert(
+        &self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+    ) -> Result<(), TokioActorCacheError> {
+        self.handle
+            .block_on(self.cache.insert(key, val, ex, policy))
+    }
+
+    pub fn set(
+        &self,
+        key: K,
+        val: V,
+        options: InsertOptions,
+    ) -> Result<Option<V>, TokioActorCacheError> {
+        self.handle.block_on(self.cache.set(key, val, options))
+    }
+
+    pub fn minsert_entries(
+        &self,
+        entries: &[InsertEntry<K, V>],
+    ) -> Result<(), TokioActorCacheError> {
+        self.handle.block_on(self.cache.minsert_entries(entries))
+    }
+
+    pub fn update_value<F>(&self, key: K, f: F) -> Result<bool, TokioActorCacheError>
+    where
+        F: FnOnce(&mut V) + Send + 'static,
+    {
+        self.handle.block_on(self.cache.update_value(key, f))
+    }
+
+    pub fn expire(&self, key: K, ex: Option<Duration>) -> Result<bool, TokioActorCacheError> {
+        self.handle.block_on(self.cache.expire(key, ex))
+    }
+
+    pub fn remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        self.handle.block_on(self.cache.remove(keys))
+    }
+
+    pub fn clear(&self) -> Result<(), TokioActorCacheError> {
+        self.handle.block_on(self.cache.clear())
+    }
+}
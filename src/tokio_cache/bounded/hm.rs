@@ -1,537 +1,50 @@
-use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
-use std::time::Duration;
+use std::sync::Arc;
 
-use crate::tokio_cache::bounded::cmd::HashMapCmd;
-use crate::tokio_cache::data_struct::ValueWithState;
-use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::channel::{self, Bounded};
+use crate::tokio_cache::error::ConfigError;
 use crate::tokio_cache::option::ExpirationPolicy;
+#[cfg(feature = "runtime")]
+use crate::tokio_cache::runtime::Runtime;
 
-use tokio::sync::mpsc::Sender;
-use tokio::sync::{mpsc, oneshot};
-use tokio::time::{Instant, interval};
+pub type HashMapCache<K, V> = crate::tokio_cache::hm::HashMapCache<K, V, Bounded>;
 
-#[derive(Debug, Clone)]
-pub struct HashMapCache<K, V> {
-    pub tx: Sender<HashMapCmd<K, V>>,
-}
+/// A `HashMapCache` holding `Arc<V>`, so reads hand out pointer clones instead of deep copies of `V`.
+pub type ArcHashMapCache<K, V> = HashMapCache<K, Arc<V>>;
 
 impl<K, V> HashMapCache<K, V>
 where
-    K: Clone,
-    V: Clone,
+    K: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+    V: Debug + Clone + Send + Sync + 'static,
 {
-    pub async fn try_stop_replicating(&self) -> Result<(), TokioActorCacheError> {
-        let stop_replicating_cmd = HashMapCmd::StopReplicating;
-        self.tx
-            .try_send(stop_replicating_cmd)
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn try_replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
-        let replicate_cmd = HashMapCmd::Replicate { master: master.clone() };
-        self.tx
-            .try_send(replicate_cmd)
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn try_ttl(&self, keys: &[K]) -> Result<Vec<Option<Duration>>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let ttl_cmd = HashMapCmd::TTL { keys, resp_tx };
-        self.tx
-            .try_send(ttl_cmd)
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn try_get_all(&self) -> Result<HashMap<K, V>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let get_all_cmd = HashMapCmd::GetAll { resp_tx };
-        self.tx
-            .try_send(get_all_cmd)
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn try_clear(&self) -> Result<(), TokioActorCacheError> {
-        let clear_cmd = HashMapCmd::Clear;
-        self.tx
-            .try_send(clear_cmd)
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn try_remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let remove_cmd = HashMapCmd::Remove { keys, resp_tx };
-        self.tx
-            .try_send(remove_cmd)
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn try_contains_key(&self, keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let contains_key_cmd = HashMapCmd::ContainsKey { keys, resp_tx };
-        self.tx
-            .try_send(contains_key_cmd)
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn try_mget(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let mget_cmd = HashMapCmd::MGet { keys, resp_tx };
-        self.tx
-            .try_send(mget_cmd)
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn try_minsert(
-        &self,
-        keys: &[K],
-        vals: &[V],
-        ex: &[Option<Duration>],
-        nx: &[bool],
-    ) -> Result<(), TokioActorCacheError> {
-        if keys.len() != vals.len() || vals.len() != ex.len() || ex.len() != nx.len() {
-            return Err(TokioActorCacheError::InconsistentLen);
-        }
-
-        let keys = keys.to_vec();
-        let vals = vals.to_vec();
-        let ex = ex.to_vec();
-        let nx = nx.to_vec();
-        let minsert_cmd = HashMapCmd::MInsert { keys, vals, ex, nx };
-        self.tx
-            .try_send(minsert_cmd)
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn try_get(&self, key: K) -> Result<Option<V>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let get_cmd = HashMapCmd::Get { key, resp_tx };
-        self.tx
-            .try_send(get_cmd)
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn try_insert(
-        &self,
-        key: K,
-        val: V,
-        ex: Option<Duration>,
-        nx: bool,
-    ) -> Result<(), TokioActorCacheError> {
-        let insert_cmd = HashMapCmd::Insert { key, val, ex, nx };
-        self.tx
-            .try_send(insert_cmd)
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn stop_replicating(&self) -> Result<(), TokioActorCacheError> {
-        let stop_replicating_cmd = HashMapCmd::StopReplicating;
-        self.tx
-            .send(stop_replicating_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
-        let replicate_cmd = HashMapCmd::Replicate { master: master.clone() };
-        self.tx
-            .send(replicate_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn ttl(&self, keys: &[K]) -> Result<Vec<Option<Duration>>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let ttl_cmd = HashMapCmd::TTL { keys, resp_tx };
-        self.tx
-            .send(ttl_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn get_all(&self) -> Result<HashMap<K, V>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let get_all_cmd = HashMapCmd::GetAll { resp_tx };
-        self.tx
-            .send(get_all_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn clear(&self) -> Result<(), TokioActorCacheError> {
-        let clear_cmd = HashMapCmd::Clear;
-        self.tx
-            .send(clear_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let remove_cmd = HashMapCmd::Remove { keys, resp_tx };
-        self.tx
-            .send(remove_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn contains_key(&self, keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let contains_key_cmd = HashMapCmd::ContainsKey { keys, resp_tx };
-        self.tx
-            .send(contains_key_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn mget(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let keys = keys.to_vec();
-        let mget_cmd = HashMapCmd::MGet { keys, resp_tx };
-        self.tx
-            .send(mget_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn minsert(
-        &self,
-        keys: &[K],
-        vals: &[V],
-        ex: &[Option<Duration>],
-        nx: &[bool],
-    ) -> Result<(), TokioActorCacheError> {
-        if keys.len() != vals.len() || vals.len() != ex.len() || ex.len() != nx.len() {
-            return Err(TokioActorCacheError::InconsistentLen);
-        }
-
-        let keys = keys.to_vec();
-        let vals = vals.to_vec();
-        let ex = ex.to_vec();
-        let nx = nx.to_vec();
-        let minsert_cmd = HashMapCmd::MInsert { keys, vals, ex, nx };
-        self.tx
-            .send(minsert_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn get(&self, key: K) -> Result<Option<V>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let get_cmd = HashMapCmd::Get { key, resp_tx };
-        self.tx
-            .send(get_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn insert(
-        &self,
-        key: K,
-        val: V,
-        ex: Option<Duration>,
-        nx: bool,
-    ) -> Result<(), TokioActorCacheError> {
-        let insert_cmd = HashMapCmd::Insert { key, val, ex, nx };
-        self.tx
-            .send(insert_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn new(expiration_policy: ExpirationPolicy, buffer: usize) -> Self
-    where
-        K: Debug + Clone + Eq + Hash + Send + 'static,
-        V: Debug + Clone + Eq + Hash + Send + 'static,
-    {
-        let mut hm = match expiration_policy {
-            ExpirationPolicy::LFU(capacity) | ExpirationPolicy::LRU(capacity) => {
-                HashMap::<K, ValueWithState<V>>::with_capacity(capacity)
-            },
-            ExpirationPolicy::None => HashMap::<K, ValueWithState<V>>::new(),
-        };
-        let mut replica_of: Option<HashMapCache<K, V>> = None;
-
-        let (tx, mut rx) = mpsc::channel(buffer);
-
-        tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_millis(100));
-            loop {
-                tokio::select! {
-                    _ = ticker.tick() => {
-
-                        // Replicate master.
-                        if let Some(ref master) = replica_of {
-                            let (resp_tx, resp_rx) = oneshot::channel();
-                            let get_all_raw_cmd = HashMapCmd::GetAllRaw { resp_tx };
-                            if let Err(_) = master.tx.try_send(get_all_raw_cmd) {
-                                eprintln!("the receiver dropped")
-                            }
-                            match resp_rx.await {
-                                Ok(master_hm) => hm = master_hm,
-                                Err(_) => eprintln!("the receiver dropped"),
-                            }
-                        }
-
-                        // Expire key-val.
-                        hm.retain(|_k, val_with_state| match val_with_state.expiration {
-                            Some(exp) => Instant::now() < exp,
-                            None => true,
-                        });
-
-                        // Invalidate cache according to expiration policy.
-                        match expiration_policy {
-                            ExpirationPolicy::LFU(capacity) => {
-                                if hm.len() > capacity {
-                                     // Find the key with the minimum call_cnt (least frequently used).
-                                    let n_exceed = hm.len() - capacity;
-                                    for _ in 0..n_exceed {
-                                        if let Some(lfu_key) = hm
-                                            .iter()
-                                            .min_by_key(|(_key, val_with_state)| val_with_state.call_cnt)
-                                            .map(|(key, _val_with_state)| key.clone())
-                                        {
-                                            hm.remove(&lfu_key);
-                                        }
-                                    }
-                                }
-                            },
-                            ExpirationPolicy::LRU(capacity) => {
-                                if hm.len() > capacity {
-                                    // Find the key with the minimum last_accessed (least recently used).
-                                    let n_exceed = hm.len() - capacity;
-                                    for _ in 0..n_exceed {
-                                        if let Some(lru_key) = hm
-                                            .iter()
-                                            .min_by_key(|(_key, val_with_state)| val_with_state.last_accessed)
-                                            .map(|(key, _val_with_state)| key.clone())
-                                        {
-                                            hm.remove(&lru_key);
-                                        }
-                                    }
-                                }
-                            },
-                            ExpirationPolicy::None => (),
-                        };
-                    }
-
-                    // Handle commands.
-                    command = rx.recv() => {
-                        if let Some(cmd) = command {
-                            match cmd {
-                                HashMapCmd::<K, V>::StopReplicating => {
-                                    replica_of = None;
-                                }
-                                HashMapCmd::<K, V>::IsReplica { resp_tx } => {
-                                    let is_replica = replica_of.is_some();
-                                    
-                                    if let Err(_) = resp_tx.send(is_replica) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::Replicate { master } => {
-                                    replica_of = Some(master);
-                                }
-                                HashMapCmd::<K, V>::GetAllRaw { resp_tx } => {
-                                    let val = hm.clone();
-                                    
-                                    if let Err(_) = resp_tx.send(val) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::TTL { keys, resp_tx } => {
-                                    let ttl = keys.iter().map(|key| {
-
-                                        // Get 'val_with_state' by 'key'.
-                                        hm.get_mut(&key).and_then(|val_with_state| {
-
-                                            // incr 'call_cnt' by 1 and update 'last_accessed'.
-                                            val_with_state.call_cnt += 1;
-                                            val_with_state.last_accessed = Instant::now();
-
-                                            // Get ttl from 'val_with_state'.
-                                            val_with_state.expiration.and_then(|ex| {
-                                                    ex.checked_duration_since(Instant::now())
-                                            })
-                                        })
-                                    }).collect::<Vec<Option<Duration>>>();
-                                    if let Err(_) = resp_tx.send(ttl) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::GetAll { resp_tx } => {
-                                    let vals = hm.iter_mut().map(|(key, val_with_state)| {
-                                        val_with_state.call_cnt += 1;
-                                        val_with_state.last_accessed = Instant::now();
-
-                                        (key.clone(), val_with_state.val.clone())
-                                    }).collect::<HashMap<K, V>>();
-
-                                    if let Err(_) = resp_tx.send(vals) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::Clear => {
-                                    hm.clear();
-                                }
-                                HashMapCmd::<K, V>::Remove { keys, resp_tx } => {
-                                    let vals = keys.iter().map(|key| {
-                                        hm.remove(&key).and_then(|val_with_state| {
-                                            Some(val_with_state.val)
-                                        })
-                                    }).collect::<Vec<Option<V>>>();
-                                    if let Err(_) = resp_tx.send(vals) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::ContainsKey {keys, resp_tx } => {
-                                    let is_contains_keys = keys.iter().map(|key| {
-
-                                        // Incr 'call_cnt' by 1 and update 'last_accessed'.
-                                        hm.get_mut(key).and_then(|val_with_state| {
-                                            val_with_state.call_cnt += 1;
-                                            val_with_state.last_accessed = Instant::now();
-                                            Some(())
-                                        });
-
-                                        hm.contains_key(&key)
-                                    }).collect::<Vec<bool>>();
-
-                                    if let Err(_) = resp_tx.send(is_contains_keys) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::MGet { keys, resp_tx } => {
-                                    let vals = keys.iter().map(|key| {
-                                        hm.get_mut(&key).and_then(|val_with_state| {
-                                            val_with_state.call_cnt += 1;
-                                            val_with_state.last_accessed = Instant::now();
-                                            Some(val_with_state.val.clone())
-                                        })
-                                    }).collect::<Vec<Option<V>>>();
-                                    if let Err(_) = resp_tx.send(vals) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::MInsert { keys, vals, ex, nx } => {
-                                    for (((key, val), ex), nx) in keys.into_iter().zip(vals).zip(ex).zip(nx) {
-                                        let expiration = ex.and_then(|d| Some(Instant::now() + d));
-                                        let last_accessed = Instant::now();
-
-                                        match (hm.get(&key), nx) {
-                                            (Some(val_with_state), false) => {
-                                                let call_cnt = val_with_state.call_cnt + 1;
-                                                let val_with_state = ValueWithState { 
-                                                    val, 
-                                                    expiration, 
-                                                    call_cnt, 
-                                                    last_accessed,
-                                                };
-                                                hm.insert(key, val_with_state);
-                                            },
-                                            (None, true) | (None, false) => {
-                                                let call_cnt = 0;
-                                                let val_with_state = ValueWithState { 
-                                                    val, 
-                                                    expiration, 
-                                                    call_cnt, 
-                                                    last_accessed,
-                                                };
-                                                hm.insert(key, val_with_state);
-                                            },
-                                            _ => (),
-                                        }
-                                    }
-                                }
-                                HashMapCmd::<K, V>::Get { key, resp_tx } => {
-                                    let val = hm.get_mut(&key).and_then(|val_with_state| {
-                                        val_with_state.call_cnt += 1;
-                                        val_with_state.last_accessed = Instant::now();
-                                        Some(val_with_state.val.clone())
-                                    });
-
-                                    if let Err(_) = resp_tx.send(val) {
-                                        println!("the receiver dropped");
-                                    }
-                                }
-                                HashMapCmd::<K, V>::Insert { key, val, ex, nx } => {
-                                    let expiration = ex.and_then(|d| Some(Instant::now() + d));
-                                    let last_accessed = Instant::now();
-
-                                    match (hm.get(&key), nx) {
-                                        (Some(val_with_state), false) => {
-                                            let call_cnt = val_with_state.call_cnt + 1;
-                                            let val_with_state = ValueWithState { 
-                                                val, 
-                                                expiration, 
-                                                call_cnt, 
-                                                last_accessed,
-                                            };
-                                            hm.insert(key, val_with_state);
-                                        },
-                                        (None, true) | (None, false) => {
-                                            let call_cnt = 0;
-                                            let val_with_state = ValueWithState { 
-                                                val, 
-                                                expiration, 
-                                                call_cnt, 
-                                                last_accessed,
-                                            };
-                                            hm.insert(key, val_with_state);
-                                        },
-                                        _ => (),
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        });
-        Self { tx }
+    pub async fn new(
+        expiration_policy: ExpirationPolicy,
+        buffer: usize,
+    ) -> Result<Self, ConfigError> {
+        ConfigError::check_buffer(buffer)?;
+        let (tx, rx) = channel::bounded(buffer);
+        Self::spawn(expiration_policy, tx, rx)
+    }
+
+    /// Like `new`, but runs the actor on `runtime` rather than on tokio, e.g. on async-std or
+    /// smol.
+    #[cfg(feature = "runtime")]
+    pub async fn new_on(
+        runtime: Arc<dyn Runtime>,
+        expiration_policy: ExpirationPolicy,
+        buffer: usize,
+    ) -> Result<Self, ConfigError> {
+        ConfigError::check_buffer(buffer)?;
+        let (tx, rx) = channel::bounded(buffer);
+        Self::spawn_on(runtime, expiration_policy, tx, rx)
+    }
+
+    /// Send `get`, `mget`, `contains_key` and `ttl` over a channel of their own, holding up to
+    /// `buffer` of them, which the actor serves first so reads don't queue behind a burst of
+    /// writes. Reads can then overtake writes that are still queued, including the caller's own.
+    pub fn with_read_lane(self, buffer: usize) -> Self {
+        let (reads, rx) = channel::bounded(buffer);
+        self.with_read_lane_channel(reads, rx)
     }
 }
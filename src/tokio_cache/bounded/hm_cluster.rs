@@ -1,423 +1,93 @@
 use std::collections::HashMap;
-use std::fmt::{Debug, Display};
+use std::fmt::Debug;
 use std::hash::Hash;
-use std::time::Duration;
-use tokio::sync::oneshot;
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, de::DeserializeOwned};
 
-use crate::tokio_cache::bounded::cmd::HashMapCmd;
 use crate::tokio_cache::bounded::hm::HashMapCache;
-use crate::tokio_cache::compute::hash_id;
+use crate::tokio_cache::channel::Bounded;
+#[cfg(feature = "serde")]
+use crate::tokio_cache::dump::RestoreRouter;
+#[cfg(feature = "encryption")]
+use crate::tokio_cache::encryption::Keyring;
+use crate::tokio_cache::error::ConfigError;
+#[cfg(feature = "serde")]
 use crate::tokio_cache::error::TokioActorCacheError;
-use crate::tokio_cache::option::ExpirationPolicy;
+use crate::tokio_cache::health::{NodeHealth, Standbys};
+use crate::tokio_cache::option::{ClusterConfig, ExpirationPolicy};
+use crate::tokio_cache::router::HashRouter;
 
-#[derive(Debug, Clone)]
-pub struct HashMapCacheCluster<K, V> {
-    pub nodes: HashMap<u64, HashMapCache<K, V>>,
-}
+pub type HashMapCacheCluster<K, V, R = HashRouter> =
+    crate::tokio_cache::hm_cluster::HashMapCacheCluster<K, V, Bounded, R>;
 
 impl<K, V> HashMapCacheCluster<K, V>
 where
-    K: Clone + Debug + Eq + Hash + Send + 'static + Display,
-    V: Clone + Debug + Eq + Hash + Send + 'static,
+    K: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+    V: Debug + Clone + Eq + Hash + Send + Sync + 'static,
 {
-    pub async fn try_ttl(&self, keys: &[K]) -> Result<Vec<Option<Duration>>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let ttl_cmd = HashMapCmd::TTL {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .try_send(ttl_cmd)
-                .map_err(|_| TokioActorCacheError::Send)?;
-            let r = resp_rx
-                .await
-                .map_err(|_| return TokioActorCacheError::Receive)?;
-            res.extend(r);
-        }
-
-        Ok(res)
-    }
-
-    pub async fn try_get_all(&self) -> Result<HashMap<K, V>, TokioActorCacheError> {
-        let mut res = HashMap::new();
-        for node in self.nodes.values() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let get_all_cmd = HashMapCmd::GetAll { resp_tx };
-            node.tx
-                .try_send(get_all_cmd)
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
-    }
-
-    pub async fn try_clear(&self) -> Result<(), TokioActorCacheError> {
-        for node in self.nodes.values() {
-            let clear_cmd = HashMapCmd::Clear;
-            node.tx
-                .try_send(clear_cmd)
-                .map_err(|_| TokioActorCacheError::Send)?
-        }
-
-        Ok(())
-    }
-
-    pub async fn try_remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let remove_cmd = HashMapCmd::Remove {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .try_send(remove_cmd)
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
+    pub async fn new(
+        expiration_policy: ExpirationPolicy,
+        buffer: usize,
+        n_node: u64,
+    ) -> Result<Self, ConfigError> {
+        Self::with_config(ClusterConfig::new(expiration_policy, n_node).with_buffer(buffer)).await
     }
 
-    pub async fn try_contains_key(&self, keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let contains_key_cmd = HashMapCmd::ContainsKey {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .try_send(contains_key_cmd)
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
-    }
-
-    pub async fn try_mget(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let mget_cmd = HashMapCmd::MGet {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .try_send(mget_cmd)
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
-    }
-
-    pub async fn try_minsert(
-        &self,
-        keys: &[K],
-        vals: &[V],
-        ex: &[Option<Duration>],
-        nx: &[bool],
-    ) -> Result<(), TokioActorCacheError> {
-        if keys.len() != vals.len() || vals.len() != ex.len() || ex.len() != nx.len() {
-            return Err(TokioActorCacheError::InconsistentLen);
-        }
-
-        let keys = keys.to_vec();
-        let vals = vals.to_vec();
-        let ex = ex.to_vec();
-        let nx = nx.to_vec();
-
-        for (key, val) in keys.into_iter().zip(vals).clone() {
-            let minsert_cmd = HashMapCmd::MInsert {
-                keys: vec![key.clone()],
-                vals: vec![val.clone()],
-                ex: ex.clone(),
-                nx: nx.clone(),
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .try_send(minsert_cmd)
-                .map_err(|_| TokioActorCacheError::Send)?;
-        }
-
-        Ok(())
-    }
-
-    pub async fn try_get(&self, key: K) -> Result<Option<V>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let get_cmd = HashMapCmd::Get {
-            key: key.clone(),
-            resp_tx,
-        };
-        let node = self.get_node(key)?;
-        node.tx
-            .try_send(get_cmd)
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn try_insert(
-        &self,
-        key: K,
-        val: V,
-        ex: Option<Duration>,
-        nx: bool,
-    ) -> Result<(), TokioActorCacheError> {
-        let insert_cmd = HashMapCmd::Insert {
-            key: key.clone(),
-            val,
-            ex,
-            nx,
-        };
-        let node = self.get_node(key)?;
-        node.tx
-            .try_send(insert_cmd)
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn ttl(&self, keys: &[K]) -> Result<Vec<Option<Duration>>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let ttl_cmd = HashMapCmd::TTL {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .send(ttl_cmd)
-                .await
-                .map_err(|_| TokioActorCacheError::Send)?;
-            let r = resp_rx
-                .await
-                .map_err(|_| return TokioActorCacheError::Receive)?;
-            res.extend(r);
-        }
-
-        Ok(res)
-    }
-
-    pub async fn get_all(&self) -> Result<HashMap<K, V>, TokioActorCacheError> {
-        let mut res = HashMap::new();
-        for node in self.nodes.values() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let get_all_cmd = HashMapCmd::GetAll { resp_tx };
-            node.tx
-                .send(get_all_cmd)
-                .await
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
-    }
-
-    pub async fn clear(&self) -> Result<(), TokioActorCacheError> {
-        for node in self.nodes.values() {
-            let clear_cmd = HashMapCmd::Clear;
-            node.tx
-                .send(clear_cmd)
-                .await
-                .map_err(|_| TokioActorCacheError::Send)?
-        }
-
-        Ok(())
-    }
-
-    pub async fn remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let remove_cmd = HashMapCmd::Remove {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .send(remove_cmd)
-                .await
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
-    }
-
-    pub async fn contains_key(&self, keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let contains_key_cmd = HashMapCmd::ContainsKey {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .send(contains_key_cmd)
-                .await
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
-    }
-
-    pub async fn mget(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
-        let keys = keys.to_vec();
-        let mut res = Vec::new();
-        for key in keys.clone() {
-            let (resp_tx, resp_rx) = oneshot::channel();
-            let mget_cmd = HashMapCmd::MGet {
-                keys: vec![key.clone()],
-                resp_tx,
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .send(mget_cmd)
-                .await
-                .map_err(|_| TokioActorCacheError::Send)?;
-            res.extend(
-                resp_rx
-                    .await
-                    .map_err(|_| return TokioActorCacheError::Receive)?,
-            );
-        }
-
-        Ok(res)
-    }
-
-    pub async fn minsert(
-        &self,
-        keys: &[K],
-        vals: &[V],
-        ex: &[Option<Duration>],
-        nx: &[bool],
-    ) -> Result<(), TokioActorCacheError> {
-        if keys.len() != vals.len() || vals.len() != ex.len() || ex.len() != nx.len() {
-            return Err(TokioActorCacheError::InconsistentLen);
-        }
-
-        let keys = keys.to_vec();
-        let vals = vals.to_vec();
-        let ex = ex.to_vec();
-        let nx = nx.to_vec();
-
-        for (key, val) in keys.into_iter().zip(vals).clone() {
-            let minsert_cmd = HashMapCmd::MInsert {
-                keys: vec![key.clone()],
-                vals: vec![val.clone()],
-                ex: ex.clone(),
-                nx: nx.clone(),
-            };
-            let node = self.get_node(key)?;
-            node.tx
-                .send(minsert_cmd)
-                .await
-                .map_err(|_| TokioActorCacheError::Send)?;
-        }
-
-        Ok(())
-    }
-
-    pub async fn get(&self, key: K) -> Result<Option<V>, TokioActorCacheError> {
-        let (resp_tx, resp_rx) = oneshot::channel();
-        let get_cmd = HashMapCmd::Get {
-            key: key.clone(),
-            resp_tx,
-        };
-        let node = self.get_node(key)?;
-        node.tx
-            .send(get_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)?;
-        resp_rx
-            .await
-            .map_err(|_| return TokioActorCacheError::Receive)
-    }
-
-    pub async fn insert(
-        &self,
-        key: K,
-        val: V,
-        ex: Option<Duration>,
-        nx: bool,
-    ) -> Result<(), TokioActorCacheError> {
-        let insert_cmd = HashMapCmd::Insert {
-            key: key.clone(),
-            val,
-            ex,
-            nx,
-        };
-        let node = self.get_node(key)?;
-        node.tx
-            .send(insert_cmd)
-            .await
-            .map_err(|_| TokioActorCacheError::Send)
-    }
-
-    pub async fn new(expiration_policy: ExpirationPolicy, buffer: usize, n_node: u64) -> Self {
+    /// Build a cluster whose nodes can differ in expiration policy and buffer size.
+    pub async fn with_config(config: ClusterConfig) -> Result<Self, ConfigError> {
+        ConfigError::check_n_node(config.n_node)?;
         let mut nodes = HashMap::new();
-        for i in 0..n_node {
-            let vec_cache = HashMapCache::<K, V>::new(expiration_policy, buffer).await;
-            nodes.insert(i, vec_cache);
-        }
-        Self { nodes }
+        for i in 0..config.n_node {
+            let hm_cache = HashMapCache::<K, V>::new(config.expiration_policy(i), config.buffer(i))
+                .await?
+                .with_ttl_jitter(config.ttl_jitter);
+            nodes.insert(i, hm_cache);
+        }
+        Ok(Self {
+            nodes,
+            router: HashRouter::default(),
+            health: NodeHealth::default(),
+            standbys: Standbys::default(),
+        })
     }
+}
 
-    fn get_node(&self, key: K) -> Result<HashMapCache<K, V>, TokioActorCacheError> {
-        let key_str = format!("{}", key);
-        let h_id = hash_id(&key_str, self.nodes.len() as u16) as u64;
-        match self.nodes.get(&h_id) {
-            Some(n) => Ok(n.clone()),
-            None => return Err(TokioActorCacheError::NodeNotExists),
-        }
+#[cfg(feature = "serde")]
+impl<K, V, R> HashMapCacheCluster<K, V, R>
+where
+    K: Debug + Clone + Eq + Hash + Send + Sync + Serialize + DeserializeOwned + 'static,
+    V: Debug + Clone + Eq + Hash + Send + Sync + Serialize + DeserializeOwned + 'static,
+    R: RestoreRouter,
+{
+    /// Rebuild the cluster `snapshot_all` saved to `dir`, on nodes of `buffer` commands with the
+    /// expiration policies they were saved with. Keys are routed by the router saved, which has to
+    /// be an `R`, e.g. `HashMapCacheCluster::<K, V, WeightedRouter>::restore` for a weighted
+    /// cluster.
+    pub async fn restore(
+        dir: impl AsRef<Path>,
+        buffer: usize,
+    ) -> Result<Self, TokioActorCacheError> {
+        Self::restore_with(dir.as_ref(), Ok, |expiration_policy| {
+            HashMapCache::<K, V>::new(expiration_policy, buffer)
+        })
+        .await
+    }
+
+    /// `restore` what `snapshot_all_encrypted` saved, with any of the keys of `keyring`.
+    #[cfg(feature = "encryption")]
+    pub async fn restore_encrypted(
+        dir: impl AsRef<Path>,
+        buffer: usize,
+        keyring: &Keyring,
+    ) -> Result<Self, TokioActorCacheError> {
+        Self::restore_with(
+            dir.as_ref(),
+            |bytes| keyring.open(&bytes),
+            |expiration_policy| HashMapCache::<K, V>::new(expiration_policy, buffer),
+        )
+        .await
     }
 }
@@ -0,0 +1,36 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::tokio_cache::bounded::hm::HashMapCache;
+use crate::tokio_cache::channel::Bounded;
+use crate::tokio_cache::error::ConfigError;
+use crate::tokio_cache::option::ExpirationPolicy;
+use crate::tokio_cache::router::HashRouter;
+use crate::tokio_cache::sharded::shard_policy;
+
+pub type ShardedHashMapCache<K, V> =
+    crate::tokio_cache::sharded::ShardedHashMapCache<K, V, Bounded>;
+
+impl<K, V> ShardedHashMapCache<K, V>
+where
+    K: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+    V: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+{
+    /// Spawn `n_shard` actors, at least one, sharing the capacity of `expiration_policy`.
+    pub async fn new(
+        expiration_policy: ExpirationPolicy,
+        buffer: usize,
+        n_shard: usize,
+    ) -> Result<Self, ConfigError> {
+        let n_shard = n_shard.max(1);
+        let policy = shard_policy(expiration_policy, n_shard);
+        let mut shards = Vec::with_capacity(n_shard);
+        for _ in 0..n_shard {
+            shards.push(HashMapCache::new(policy, buffer).await?);
+        }
+        Ok(Self {
+            shards,
+            router: HashRouter::default(),
+        })
+    }
+}
@@ -0,0 +1,205 @@
+//! Caches of `Bytes` values, e.g. HTTP bodies or objects, that hold at most so many bytes rather
+//! than so many entries.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::Range;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::sync::oneshot;
+
+use crate::tokio_cache::channel::{SendMode, reply};
+use crate::tokio_cache::cmd::{ByteBudgetAccess, HashMapCmd};
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::ops::mode_pairs;
+use crate::tokio_cache::option::InsertPolicy;
+
+/// The bytes the values of a `HashMapCache` take, kept by its actor once a `BytesCache` wraps it.
+#[derive(Debug)]
+pub(crate) struct ByteBudget<V> {
+    weigh: fn(&V) -> usize,
+    max_bytes: usize,
+    total_bytes: usize,
+}
+
+impl<V> ByteBudget<V> {
+    pub(crate) fn weigh(&self, val: &V) -> usize {
+        (self.weigh)(val)
+    }
+
+    /// `val` is now cached.
+    pub(crate) fn added(&mut self, val: &V) {
+        self.total_bytes += self.weigh(val);
+    }
+
+    /// `val` is no longer cached.
+    pub(crate) fn removed(&mut self, val: &V) {
+        self.total_bytes = self.total_bytes.saturating_sub(self.weigh(val));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.total_bytes = 0;
+    }
+
+    /// How many bytes the values take over the budget.
+    pub(crate) fn excess(&self) -> usize {
+        self.total_bytes.saturating_sub(self.max_bytes)
+    }
+}
+
+/// A `HashMapCache` of `Bytes` whose actor adds up the lengths of the values and, when a write
+/// takes them over `max_bytes`, evicts entries by the expiration policy, least recently used
+/// first without one, until they fit again. Reads hand out the cached buffer itself, shared
+/// rather than copied. Keys are not counted, and the entry capacity of the policy still applies
+/// on top.
+#[derive(Debug, Clone)]
+pub struct BytesCache<K, C> {
+    cache: HashMapCache<K, Bytes, C>,
+}
+
+impl<K, C> BytesCache<K, C>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+{
+    /// Hold at most `max_bytes` of values in `cache`, counting those it already holds and
+    /// evicting right away if they take more.
+    pub async fn new(
+        cache: HashMapCache<K, Bytes, C>,
+        max_bytes: usize,
+    ) -> Result<Self, TokioActorCacheError> {
+        let bytes_cache = Self { cache };
+        bytes_cache
+            .access_with(
+                move |byte_budget| byte_budget.max_bytes = max_bytes,
+                SendMode::Wait,
+            )
+            .await?;
+        Ok(bytes_cache)
+    }
+
+    /// The cache underneath.
+    pub fn cache(&self) -> &HashMapCache<K, Bytes, C> {
+        &self.cache
+    }
+
+    mode_pairs! {
+        fn get / try_get(key: K) -> Result<Option<Bytes>, TokioActorCacheError> => get_with;
+        /// The bytes of the value of `key` within `range`, cut short at its end, without copying
+        /// them, e.g. for HTTP range requests.
+        fn get_range / try_get_range(key: K, range: Range<usize>)
+            -> Result<Option<Bytes>, TokioActorCacheError> => get_range_with;
+        fn insert / try_insert(
+            key: K,
+            val: Bytes,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) -> Result<(), TokioActorCacheError> => insert_with;
+        fn remove / try_remove(keys: &[K]) -> Result<Vec<Option<Bytes>>, TokioActorCacheError>
+            => remove_with;
+        /// How many bytes the cached values take.
+        fn total_bytes / try_total_bytes() -> Result<usize, TokioActorCacheError>
+            => total_bytes_with;
+        fn max_bytes / try_max_bytes() -> Result<usize, TokioActorCacheError> => max_bytes_with;
+        /// Hold at most `max_bytes` from now on, evicting right away if the values take more.
+        fn set_max_bytes / try_set_max_bytes(max_bytes: usize)
+            -> Result<(), TokioActorCacheError> => set_max_bytes_with;
+    }
+
+    async fn get_with(
+        &self,
+        key: K,
+        mode: SendMode,
+    ) -> Result<Option<Bytes>, TokioActorCacheError> {
+        match mode {
+            SendMode::Try => self.cache.try_get(key).await,
+            SendMode::Wait => self.cache.get(key).await,
+        }
+    }
+
+    async fn get_range_with(
+        &self,
+        key: K,
+        range: Range<usize>,
+        mode: SendMode,
+    ) -> Result<Option<Bytes>, TokioActorCacheError> {
+        self.cache
+            .read_value_with(
+                key,
+                move |val| {
+                    let end = range.end.min(val.len());
+                    val.slice(range.start.min(end)..end)
+                },
+                mode,
+            )
+            .await
+    }
+
+    async fn insert_with(
+        &self,
+        key: K,
+        val: Bytes,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        match mode {
+            SendMode::Try => self.cache.try_insert(key, val, ex, policy).await,
+            SendMode::Wait => self.cache.insert(key, val, ex, policy).await,
+        }
+    }
+
+    async fn remove_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<Option<Bytes>>, TokioActorCacheError> {
+        match mode {
+            SendMode::Try => self.cache.try_remove(keys).await,
+            SendMode::Wait => self.cache.remove(keys).await,
+        }
+    }
+
+    async fn total_bytes_with(&self, mode: SendMode) -> Result<usize, TokioActorCacheError> {
+        self.access_with(|byte_budget| byte_budget.total_bytes, mode)
+            .await
+    }
+
+    async fn max_bytes_with(&self, mode: SendMode) -> Result<usize, TokioActorCacheError> {
+        self.access_with(|byte_budget| byte_budget.max_bytes, mode)
+            .await
+    }
+
+    async fn set_max_bytes_with(
+        &self,
+        max_bytes: usize,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        self.access_with(move |byte_budget| byte_budget.max_bytes = max_bytes, mode)
+            .await
+    }
+
+    /// Run `f` on the actor's `ByteBudget`, setting one up first if there is none yet. The actor
+    /// evicts down to the budget once `f` is done.
+    async fn access_with<R, F>(&self, f: F, mode: SendMode) -> Result<R, TokioActorCacheError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut ByteBudget<Bytes>) -> R + Send + 'static,
+    {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let access = ByteBudgetAccess(Box::new(move |byte_budget| {
+            let byte_budget = byte_budget.get_or_insert_with(|| ByteBudget {
+                weigh: Bytes::len,
+                max_bytes: usize::MAX,
+                total_bytes: 0,
+            });
+            reply(resp_tx, f(byte_budget));
+        }));
+        self.cache
+            .tx
+            .send(HashMapCmd::ByteBudget { access }, mode)
+            .await?;
+        self.cache.tx.wait_reply(resp_rx).await
+    }
+}
@@ -0,0 +1,74 @@
+//! Operations that can be called off while they are queued or the actor is working on them.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// How many keys a cancellable multi-key read gets between checks for its cancellation.
+pub(crate) const CANCEL_CHECK_EVERY: usize = 256;
+
+/// Identifies an operation started by one of the `_cancellable` methods of a cache, for its
+/// `cancel`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpId(u64);
+
+/// Set once its operation is called off, which the actor checks as it goes.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub(crate) fn is_set(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The cancellable operations in flight on a cache by id, shared by its handles so any of them
+/// can cancel an operation started through another.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InFlight(Arc<Mutex<HashMap<OpId, CancelFlag>>>);
+
+impl InFlight {
+    /// Track a new operation until the returned guard is dropped, which cancels it if it is
+    /// still running, e.g. when the future driving it is dropped.
+    pub(crate) fn start(&self) -> (OpId, CancelFlag, OpGuard) {
+        static NEXT_OP_ID: AtomicU64 = AtomicU64::new(0);
+        let op = OpId(NEXT_OP_ID.fetch_add(1, Ordering::Relaxed));
+        let cancel = CancelFlag::default();
+        self.ops().insert(op, cancel.clone());
+        let guard = OpGuard {
+            in_flight: self.clone(),
+            op,
+            cancel: cancel.clone(),
+        };
+        (op, cancel, guard)
+    }
+
+    /// Cancel `op`. Returns whether it was still in flight.
+    pub(crate) fn cancel(&self, op: OpId) -> bool {
+        let cancel = self.ops().remove(&op);
+        cancel.inspect(CancelFlag::set).is_some()
+    }
+
+    fn ops(&self) -> MutexGuard<'_, HashMap<OpId, CancelFlag>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Cancels its operation and stops tracking it once dropped.
+#[derive(Debug)]
+pub(crate) struct OpGuard {
+    in_flight: InFlight,
+    op: OpId,
+    cancel: CancelFlag,
+}
+
+impl Drop for OpGuard {
+    fn drop(&mut self) {
+        self.cancel.set();
+        self.in_flight.ops().remove(&self.op);
+    }
+}
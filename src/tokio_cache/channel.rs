@@ -0,0 +1,298 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tokio::time;
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::option::DroppedReceiver;
+use crate::tokio_cache::trace;
+
+/// Marker for caches whose actor is fed by a bounded `mpsc` channel.
+#[derive(Debug, Clone, Copy)]
+pub struct Bounded;
+
+/// Marker for caches whose actor is fed by an unbounded `mpsc` channel.
+#[derive(Debug, Clone, Copy)]
+pub struct Unbounded;
+
+/// Marker for caches whose actor runs on a `LocalSet`, fed by an unbounded `mpsc` channel.
+#[derive(Debug, Clone, Copy)]
+pub struct Local;
+
+/// How a command is handed to the actor.
+///
+/// `Try` fails immediately when a bounded channel is full, `Wait` waits for capacity.
+/// Both behave the same on an unbounded channel.
+#[derive(Debug, Clone, Copy)]
+pub enum SendMode {
+    Try,
+    Wait,
+}
+
+#[derive(Debug)]
+enum Chan<T> {
+    Bounded(Sender<T>),
+    // Unbounded senders can't tell how many commands are queued, so count them.
+    Unbounded(UnboundedSender<T>, Arc<AtomicUsize>),
+}
+
+#[derive(Debug)]
+pub struct CacheSender<T> {
+    chan: Chan<T>,
+    timeout: Option<Duration>,
+}
+
+impl<T> Clone for CacheSender<T> {
+    fn clone(&self) -> Self {
+        let chan = match &self.chan {
+            Chan::Bounded(tx) => Chan::Bounded(tx.clone()),
+            Chan::Unbounded(tx, depth) => Chan::Unbounded(tx.clone(), depth.clone()),
+        };
+        Self {
+            chan,
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<T> CacheSender<T> {
+    pub async fn send(&self, cmd: T, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        match (&self.chan, mode) {
+            (Chan::Bounded(tx), SendMode::Wait) => {
+                let send = tx.send(cmd);
+                match self.timeout {
+                    Some(after) => time::timeout(after, send)
+                        .await
+                        .map_err(|_| TokioActorCacheError::Timeout { after })?,
+                    None => send.await,
+                }
+                .map_err(|_| TokioActorCacheError::ActorGone)
+            }
+            _ => self.send_now(cmd),
+        }
+    }
+
+    /// Hand `cmd` to the actor without waiting, for use from inside another actor.
+    pub(crate) fn send_now(&self, cmd: T) -> Result<(), TokioActorCacheError> {
+        match &self.chan {
+            Chan::Bounded(tx) => tx.try_send(cmd).map_err(|e| match e {
+                TrySendError::Full(_) => TokioActorCacheError::ChannelFull {
+                    len: tx.max_capacity() - tx.capacity(),
+                    capacity: tx.max_capacity(),
+                },
+                TrySendError::Closed(_) => TokioActorCacheError::ActorGone,
+            }),
+            Chan::Unbounded(tx, depth) => {
+                depth.fetch_add(1, Ordering::Relaxed);
+                tx.send(cmd).map_err(|_| {
+                    depth.fetch_sub(1, Ordering::Relaxed);
+                    TokioActorCacheError::ActorGone
+                })
+            }
+        }
+    }
+
+    /// Like `send_now`, but hands `cmd` back when the actor can't take it, so the caller can
+    /// handle it some other way.
+    pub(crate) fn hand_over(&self, cmd: T) -> Result<(), T> {
+        match &self.chan {
+            Chan::Bounded(tx) => tx.try_send(cmd).map_err(TrySendError::into_inner),
+            Chan::Unbounded(tx, depth) => {
+                depth.fetch_add(1, Ordering::Relaxed);
+                tx.send(cmd).map_err(|e| {
+                    depth.fetch_sub(1, Ordering::Relaxed);
+                    e.0
+                })
+            }
+        }
+    }
+
+    /// Number of commands waiting for the actor to pick them up.
+    pub fn queue_depth(&self) -> usize {
+        match &self.chan {
+            Chan::Bounded(tx) => tx.max_capacity() - tx.capacity(),
+            Chan::Unbounded(_, depth) => depth.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        match &self.chan {
+            Chan::Bounded(tx) => tx.is_closed(),
+            Chan::Unbounded(tx, _) => tx.is_closed(),
+        }
+    }
+
+    /// Whether both senders feed the same actor.
+    pub(crate) fn same_channel(&self, other: &Self) -> bool {
+        match (&self.chan, &other.chan) {
+            (Chan::Bounded(a), Chan::Bounded(b)) => a.same_channel(b),
+            (Chan::Unbounded(a, _), Chan::Unbounded(b, _)) => a.same_channel(b),
+            _ => false,
+        }
+    }
+
+    /// Send the command built by `build` and wait for the actor's response.
+    pub async fn request<R>(
+        &self,
+        mode: SendMode,
+        build: impl FnOnce(oneshot::Sender<R>) -> T,
+    ) -> Result<R, TokioActorCacheError> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.send(build(resp_tx), mode).await?;
+        self.wait_reply(resp_rx).await
+    }
+
+    /// Wait for the actor to answer through `resp_rx`, honouring the sender's timeout. An actor
+    /// that is still running but dropped the request without answering shed it, see
+    /// `ShedPolicy`.
+    pub async fn wait_reply<R>(
+        &self,
+        resp_rx: oneshot::Receiver<R>,
+    ) -> Result<R, TokioActorCacheError> {
+        match self.timeout {
+            Some(after) => time::timeout(after, resp_rx)
+                .await
+                .map_err(|_| TokioActorCacheError::Timeout { after })?,
+            None => resp_rx.await,
+        }
+        .map_err(|_| {
+            if self.is_closed() {
+                TokioActorCacheError::ActorGone
+            } else {
+                TokioActorCacheError::Shed
+            }
+        })
+    }
+
+    /// Fail requests with `TokioActorCacheError::Timeout` when the actor hasn't answered within `after`.
+    pub fn set_timeout(&mut self, after: Option<Duration>) {
+        self.timeout = after;
+    }
+
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// Answer a request, logging when the caller has already gone away.
+pub(crate) fn reply<R>(resp_tx: oneshot::Sender<R>, val: R) {
+    if resp_tx.send(val).is_err() {
+        trace::warn_event!("the receiver dropped");
+    }
+}
+
+/// How an actor answers requests, shared by a cache's handles and its actor so
+/// `with_dropped_receiver` takes effect for the commands that follow it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Replies {
+    on_dropped: Arc<AtomicU8>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Replies {
+    pub(crate) fn set_on_dropped(&self, on_dropped: DroppedReceiver) {
+        self.on_dropped.store(on_dropped as u8, Ordering::Relaxed);
+    }
+
+    fn on_dropped(&self) -> DroppedReceiver {
+        match self.on_dropped.load(Ordering::Relaxed) {
+            1 => DroppedReceiver::Count,
+            2 => DroppedReceiver::Skip,
+            _ => DroppedReceiver::Log,
+        }
+    }
+
+    /// How many callers were gone by the time their answer was sent, or skipped.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Like `reply`, counting callers that have gone away unless only logging.
+    pub(crate) fn send<R>(&self, resp_tx: oneshot::Sender<R>, val: R) {
+        if resp_tx.send(val).is_err() {
+            trace::warn_event!("the receiver dropped");
+            self.count_dropped();
+        }
+    }
+
+    /// Whether to skip a read whose caller is gone when `abandoned`, counting it if so.
+    pub(crate) fn skip(&self, abandoned: bool) -> bool {
+        let skip = abandoned && self.on_dropped() == DroppedReceiver::Skip;
+        if skip {
+            trace::debug_event!("skipped a read nobody waits for");
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        skip
+    }
+
+    fn count_dropped(&self) {
+        if self.on_dropped() != DroppedReceiver::Log {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CacheReceiver<T> {
+    Bounded(Receiver<T>),
+    Unbounded(UnboundedReceiver<T>, Arc<AtomicUsize>),
+}
+
+impl<T> CacheReceiver<T> {
+    pub async fn recv(&mut self) -> Option<T> {
+        match self {
+            CacheReceiver::Bounded(rx) => rx.recv().await,
+            CacheReceiver::Unbounded(rx, depth) => {
+                let cmd = rx.recv().await;
+                if cmd.is_some() {
+                    depth.fetch_sub(1, Ordering::Relaxed);
+                }
+                cmd
+            }
+        }
+    }
+
+    /// The next queued command, if any, without waiting for one.
+    pub(crate) fn try_recv(&mut self) -> Option<T> {
+        match self {
+            CacheReceiver::Bounded(rx) => rx.try_recv().ok(),
+            CacheReceiver::Unbounded(rx, depth) => {
+                let cmd = rx.try_recv().ok();
+                if cmd.is_some() {
+                    depth.fetch_sub(1, Ordering::Relaxed);
+                }
+                cmd
+            }
+        }
+    }
+
+    /// Number of commands queued.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            CacheReceiver::Bounded(rx) => rx.len(),
+            CacheReceiver::Unbounded(_, depth) => depth.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub fn bounded<T>(buffer: usize) -> (CacheSender<T>, CacheReceiver<T>) {
+    let (tx, rx) = mpsc::channel(buffer);
+    let tx = CacheSender {
+        chan: Chan::Bounded(tx),
+        timeout: None,
+    };
+    (tx, CacheReceiver::Bounded(rx))
+}
+
+pub fn unbounded<T>() -> (CacheSender<T>, CacheReceiver<T>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let depth = Arc::new(AtomicUsize::new(0));
+    let tx = CacheSender {
+        chan: Chan::Unbounded(tx, depth.clone()),
+        timeout: None,
+    };
+    (tx, CacheReceiver::Unbounded(rx, depth))
+}
@@ -0,0 +1,883 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::tokio_cache::bytes_cache::ByteBudget;
+use crate::tokio_cache::cancel::CancelFlag;
+use crate::tokio_cache::channel::{CacheReceiver, CacheSender};
+use crate::tokio_cache::data_struct::{
+    HashMapSnapshot, HashSetSnapshot, HashSetState, ValueWithState,
+};
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::histogram::TtlHistogram;
+use crate::tokio_cache::invalidation::Invalidation;
+use crate::tokio_cache::namespace::NamespaceIndex;
+use crate::tokio_cache::option::{
+    ExpirationPolicy, InsertEntry, InsertOptions, InsertPolicy, RemovePolicy, VecEnd,
+};
+use crate::tokio_cache::registry::CacheUsage;
+use crate::tokio_cache::removal::Removal;
+use crate::tokio_cache::replication::ReplicationInfo;
+use crate::tokio_cache::snapshot::Versions;
+
+#[derive(Debug)]
+pub(crate) enum VecCmd<V> {
+    StopReplicating {
+        resp_tx: oneshot::Sender<Option<CacheSender<VecCmd<V>>>>,
+    },
+    IsReplica {
+        resp_tx: oneshot::Sender<bool>,
+    },
+    ReplicationInfo {
+        resp_tx: oneshot::Sender<ReplicationInfo>,
+    },
+    Replicate {
+        master: CacheSender<VecCmd<V>>,
+        master_id: u64,
+    },
+    AddReplica {
+        replica: CacheSender<VecCmd<V>>,
+    },
+    RemoveReplica {
+        replica: CacheSender<VecCmd<V>>,
+    },
+    ReplicaCount {
+        resp_tx: oneshot::Sender<usize>,
+    },
+    Failover {
+        new_master: CacheSender<VecCmd<V>>,
+        new_master_id: u64,
+        resp_tx: oneshot::Sender<usize>,
+    },
+    // Sent by the master to its replicas.
+    Sync {
+        vec: Vec<ValueWithState<V>>,
+        sent_at: Instant,
+    },
+    Append {
+        val: ValueWithState<V>,
+        sent_at: Instant,
+    },
+    RemoveAt {
+        indices: Vec<usize>,
+        sent_at: Instant,
+    },
+    SetAt {
+        index: usize,
+        val: ValueWithState<V>,
+        sent_at: Instant,
+    },
+    #[cfg(feature = "serde")]
+    Dump {
+        resp_tx: oneshot::Sender<(ExpirationPolicy, Vec<ValueWithState<V>>)>,
+    },
+    // Entries read back by `load`, appended in order.
+    #[cfg(feature = "serde")]
+    Load {
+        vals: Vec<ValueWithState<V>>,
+    },
+    Ttl {
+        vals: Vec<V>,
+        resp_tx: oneshot::Sender<Vec<Option<Duration>>>,
+    },
+    Clear,
+    /// Answered once every command queued before it is handled.
+    Barrier {
+        resp_tx: oneshot::Sender<()>,
+    },
+    Remove {
+        vals: Vec<V>,
+        policy: RemovePolicy,
+        resp_tx: oneshot::Sender<Vec<usize>>,
+    },
+    Contains {
+        vals: Vec<V>,
+        resp_tx: oneshot::Sender<Vec<bool>>,
+    },
+    GetAll {
+        resp_tx: oneshot::Sender<Vec<V>>,
+    },
+    /// Every element with its state, for the clusters.
+    Snapshot {
+        resp_tx: oneshot::Sender<Vec<ValueWithState<V>>>,
+    },
+    /// Append elements a rebalance moves here with their state, answered once they are.
+    Migrate {
+        vals: Vec<ValueWithState<V>>,
+        resp_tx: oneshot::Sender<()>,
+    },
+    MPush {
+        vals: Vec<V>,
+        ex: Vec<Option<Duration>>,
+        policy: Vec<InsertPolicy>,
+    },
+    Push {
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+    },
+    PopFront {
+        resp_tx: oneshot::Sender<Option<V>>,
+    },
+    PopBack {
+        resp_tx: oneshot::Sender<Option<V>>,
+    },
+    Truncate {
+        len: usize,
+    },
+    GetIndex {
+        index: usize,
+        resp_tx: oneshot::Sender<Option<V>>,
+    },
+    SetIndex {
+        index: usize,
+        val: V,
+        resp_tx: oneshot::Sender<bool>,
+    },
+    /// Trim from `trim_from` on push, to keep at most `max_len` elements.
+    MaxLen {
+        max_len: Option<usize>,
+        trim_from: VecEnd,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) enum HashSetCmd<V> {
+    StopReplicating {
+        resp_tx: oneshot::Sender<Option<CacheSender<HashSetCmd<V>>>>,
+    },
+    IsReplica {
+        resp_tx: oneshot::Sender<bool>,
+    },
+    ReplicationInfo {
+        resp_tx: oneshot::Sender<ReplicationInfo>,
+    },
+    Replicate {
+        master: CacheSender<HashSetCmd<V>>,
+        master_id: u64,
+    },
+    AddReplica {
+        replica: CacheSender<HashSetCmd<V>>,
+    },
+    RemoveReplica {
+        replica: CacheSender<HashSetCmd<V>>,
+    },
+    ReplicaCount {
+        resp_tx: oneshot::Sender<usize>,
+    },
+    Failover {
+        new_master: CacheSender<HashSetCmd<V>>,
+        new_master_id: u64,
+        resp_tx: oneshot::Sender<usize>,
+    },
+    // Sent by the master to its replicas.
+    Sync {
+        hm: HashMap<V, HashSetState>,
+        sent_at: Instant,
+    },
+    Put {
+        val: V,
+        state: HashSetState,
+        sent_at: Instant,
+    },
+    Delta {
+        puts: Vec<(V, HashSetState)>,
+        deletes: Vec<V>,
+        sent_at: Instant,
+    },
+    Delete {
+        vals: Vec<V>,
+        sent_at: Instant,
+    },
+    /// Every value with its state, for the clusters and set algebra.
+    Snapshot {
+        resp_tx: oneshot::Sender<HashSetSnapshot<V>>,
+    },
+    #[cfg(feature = "serde")]
+    Dump {
+        resp_tx: oneshot::Sender<(ExpirationPolicy, HashMap<V, HashSetState>)>,
+    },
+    #[cfg(feature = "serde")]
+    Load {
+        entries: Vec<(V, HashSetState)>,
+    },
+    Ttl {
+        vals: Vec<V>,
+        resp_tx: oneshot::Sender<Vec<Option<Duration>>>,
+    },
+    Clear,
+    /// Answered once every command queued before it is handled.
+    Barrier {
+        resp_tx: oneshot::Sender<()>,
+    },
+    Remove {
+        vals: Vec<V>,
+        resp_tx: oneshot::Sender<Vec<bool>>,
+    },
+    Contains {
+        vals: Vec<V>,
+        resp_tx: oneshot::Sender<Vec<bool>>,
+    },
+    GetAll {
+        resp_tx: oneshot::Sender<HashSet<V>>,
+    },
+    HotVals {
+        n: usize,
+        resp_tx: oneshot::Sender<Vec<(V, u64)>>,
+    },
+    IdleVals {
+        n: usize,
+        resp_tx: oneshot::Sender<Vec<(V, Duration)>>,
+    },
+    RandMember {
+        n: usize,
+        resp_tx: oneshot::Sender<Vec<V>>,
+    },
+    Pop {
+        n: usize,
+        resp_tx: oneshot::Sender<Vec<V>>,
+    },
+    MInsert {
+        vals: Vec<V>,
+        ex: Vec<Option<Duration>>,
+        policy: Vec<InsertPolicy>,
+    },
+    /// Store a value a rebalance moves here with its state, unless it has been inserted since.
+    /// Answered once it is cached.
+    Migrate {
+        val: V,
+        state: HashSetState,
+        resp_tx: oneshot::Sender<()>,
+    },
+    Insert {
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+    },
+    /// `Insert`, answered once applied.
+    InsertSync {
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+        resp_tx: oneshot::Sender<()>,
+    },
+    /// Replace every value with `vals`, without TTLs.
+    Store {
+        vals: HashSet<V>,
+    },
+}
+
+/// A closure run by the actor on a cached value in place.
+pub struct ValueUpdate<V>(pub Box<dyn FnOnce(&mut V) + Send>);
+
+impl<V> Debug for ValueUpdate<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ValueUpdate")
+    }
+}
+
+/// A closure run by the actor on a cached value, or on `None` when there is none, that sends
+/// what it reads back itself.
+pub struct ValueRead<V>(pub Box<ReadFn<V>>);
+
+type ReadFn<V> = dyn FnOnce(Option<&V>) + Send;
+
+impl<V> Debug for ValueRead<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ValueRead")
+    }
+}
+
+/// A closure run by the actor over every cached entry, that sends what it computes back itself.
+pub struct EntriesScan<K, V>(pub Box<ScanFn<K, V>>);
+
+type ScanFn<K, V> = dyn for<'a> FnOnce(&mut dyn Iterator<Item = (&'a K, &'a V)>) + Send;
+
+impl<K, V> Debug for EntriesScan<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EntriesScan")
+    }
+}
+
+/// The keys a command applies to.
+pub struct KeyFilter<K>(pub Box<dyn Fn(&K) -> bool + Send>);
+
+impl<K> Debug for KeyFilter<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeyFilter")
+    }
+}
+
+/// A closure run by the actor on its namespace index, which it may set up, that sends what it
+/// reads back itself.
+pub(crate) struct NamespaceAccess<K>(pub Box<NamespaceFn<K>>);
+
+type NamespaceFn<K> = dyn FnOnce(&mut Option<Box<dyn NamespaceIndex<K>>>) + Send;
+
+impl<K> Debug for NamespaceAccess<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NamespaceAccess")
+    }
+}
+
+/// A closure run by the actor on its byte budget, which it may set up, that sends what it reads
+/// back itself.
+pub(crate) struct ByteBudgetAccess<V>(pub Box<ByteBudgetFn<V>>);
+
+type ByteBudgetFn<V> = dyn FnOnce(&mut Option<ByteBudget<V>>) + Send;
+
+impl<V> Debug for ByteBudgetAccess<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ByteBudgetAccess")
+    }
+}
+
+/// A closure run by the actor on its versions and entries, that sends the snapshot it takes back
+/// itself.
+pub(crate) struct SnapshotAccess<K, V>(pub Box<SnapshotFn<K, V>>);
+
+type SnapshotFn<K, V> = dyn FnOnce(&mut Versions, &HashMapSnapshot<K, V>) + Send;
+
+impl<K, V> Debug for SnapshotAccess<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SnapshotAccess")
+    }
+}
+
+/// The reply end of a read, to tell whether its caller still waits for it.
+pub(crate) trait Reply {
+    fn is_closed(&self) -> bool;
+}
+
+impl<T> Reply for oneshot::Sender<T> {
+    fn is_closed(&self) -> bool {
+        oneshot::Sender::is_closed(self)
+    }
+}
+
+/// What a `HashMapCmd` is to the actor, see `HashMapCmd::kind`.
+pub(crate) enum CmdKind<'a> {
+    /// A read an ingestion limit may shed, answered on the reply end it carries.
+    Read(&'a dyn Reply),
+    /// A direct write, which replicas forwarding their writes hand to their master.
+    Write,
+    /// Replication, admin and everything else.
+    Other,
+}
+
+#[derive(Debug)]
+pub(crate) enum HashMapCmd<K, V> {
+    Batch {
+        cmds: Vec<HashMapCmd<K, V>>,
+    },
+    /// Handle `cmd` unless `cancel` is set by then, stopping partway through long reads once it
+    /// is.
+    Cancellable {
+        cancel: CancelFlag,
+        cmd: Box<HashMapCmd<K, V>>,
+    },
+    StopReplicating {
+        resp_tx: oneshot::Sender<Option<CacheSender<HashMapCmd<K, V>>>>,
+    },
+    IsReplica {
+        resp_tx: oneshot::Sender<bool>,
+    },
+    ReplicationInfo {
+        resp_tx: oneshot::Sender<ReplicationInfo>,
+    },
+    Replicate {
+        master: CacheSender<HashMapCmd<K, V>>,
+        master_id: u64,
+    },
+    AddReplica {
+        replica: CacheSender<HashMapCmd<K, V>>,
+    },
+    RemoveReplica {
+        replica: CacheSender<HashMapCmd<K, V>>,
+    },
+    ReplicaCount {
+        resp_tx: oneshot::Sender<usize>,
+    },
+    Failover {
+        new_master: CacheSender<HashMapCmd<K, V>>,
+        new_master_id: u64,
+        resp_tx: oneshot::Sender<usize>,
+    },
+    // Sent by the master to its replicas.
+    Sync {
+        hm: HashMapSnapshot<K, V>,
+        sent_at: Instant,
+    },
+    Put {
+        key: K,
+        val: ValueWithState<V>,
+        sent_at: Instant,
+    },
+    Delta {
+        puts: Vec<(K, ValueWithState<V>)>,
+        deletes: Vec<K>,
+        sent_at: Instant,
+    },
+    Delete {
+        keys: Vec<K>,
+        sent_at: Instant,
+    },
+    /// Every entry with its state, for the clusters and JSON export.
+    Snapshot {
+        resp_tx: oneshot::Sender<HashMapSnapshot<K, V>>,
+    },
+    VersionedSnapshot {
+        access: SnapshotAccess<K, V>,
+    },
+    Version {
+        resp_tx: oneshot::Sender<u64>,
+    },
+    #[cfg(feature = "serde")]
+    Dump {
+        resp_tx: oneshot::Sender<(ExpirationPolicy, HashMapSnapshot<K, V>)>,
+    },
+    #[cfg(feature = "serde")]
+    Load {
+        entries: Vec<(K, ValueWithState<V>)>,
+    },
+    Ttl {
+        keys: Vec<K>,
+        resp_tx: oneshot::Sender<Vec<Option<Duration>>>,
+    },
+    GetAll {
+        resp_tx: oneshot::Sender<HashMapSnapshot<K, V>>,
+    },
+    HotKeys {
+        n: usize,
+        resp_tx: oneshot::Sender<Vec<(K, u64)>>,
+    },
+    IdleKeys {
+        n: usize,
+        resp_tx: oneshot::Sender<Vec<(K, Duration)>>,
+    },
+    TtlHistogram {
+        resp_tx: oneshot::Sender<TtlHistogram>,
+    },
+    /// Count the entries whose TTL runs out within `within`, including expired ones not yet
+    /// dropped.
+    ExpiringWithin {
+        within: Duration,
+        resp_tx: oneshot::Sender<usize>,
+    },
+    Clear,
+    /// Answered once every command queued before it is handled.
+    Barrier {
+        resp_tx: oneshot::Sender<()>,
+    },
+    Usage {
+        resp_tx: oneshot::Sender<CacheUsage>,
+    },
+    /// Pin or unpin `keys`, answering whether each is cached.
+    Pin {
+        keys: Vec<K>,
+        pinned: bool,
+        resp_tx: oneshot::Sender<Vec<bool>>,
+    },
+    Policy {
+        resp_tx: oneshot::Sender<ExpirationPolicy>,
+    },
+    SetPolicy {
+        policy: ExpirationPolicy,
+        resp_tx: oneshot::Sender<()>,
+    },
+    /// Change the capacity of the expiration policy, answering whether it has one.
+    SetCapacity {
+        capacity: usize,
+        resp_tx: oneshot::Sender<bool>,
+    },
+    /// Evict down to `target` entries and release the memory freed.
+    Shrink {
+        target: usize,
+        resp_tx: oneshot::Sender<usize>,
+    },
+    Remove {
+        keys: Vec<K>,
+        resp_tx: oneshot::Sender<Vec<Option<V>>>,
+    },
+    /// Remove every key `filter` matches, answering how many there were.
+    RemoveWhere {
+        filter: KeyFilter<K>,
+        resp_tx: oneshot::Sender<usize>,
+    },
+    /// Keep per-namespace counts and quotas, see `NamespacedCache`.
+    Namespaces {
+        access: NamespaceAccess<K>,
+    },
+    /// Add up the bytes the values take and evict over a maximum, see `BytesCache`.
+    ByteBudget {
+        access: ByteBudgetAccess<V>,
+    },
+    ContainsKey {
+        keys: Vec<K>,
+        resp_tx: oneshot::Sender<Vec<bool>>,
+    },
+    MGet {
+        keys: Vec<K>,
+        resp_tx: oneshot::Sender<Vec<Option<V>>>,
+    },
+    MInsert {
+        entries: Vec<InsertEntry<K, V>>,
+    },
+    /// Store an entry a rebalance moves here with its state, unless `key` has been written to
+    /// since. Answered once it is cached, or with why it can't be.
+    Migrate {
+        key: K,
+        val: ValueWithState<V>,
+        resp_tx: oneshot::Sender<Result<(), TokioActorCacheError>>,
+    },
+    /// Stage the writes of transaction `txn` to this node, failing if another transaction holds
+    /// any of their keys or they would take the cache over its hard capacity.
+    PrepareTxn {
+        txn: u64,
+        inserts: Vec<InsertEntry<K, V>>,
+        removes: Vec<K>,
+        resp_tx: oneshot::Sender<Result<(), TokioActorCacheError>>,
+    },
+    /// Apply the writes staged by `PrepareTxn` in one go. Answers with the removed values, or
+    /// fails when they are no longer staged.
+    CommitTxn {
+        txn: u64,
+        resp_tx: oneshot::Sender<Result<Vec<Option<V>>, TokioActorCacheError>>,
+    },
+    AbortTxn {
+        txn: u64,
+    },
+    /// Store `entries` as they are; the last chunk of a bulk load carries `resp_tx`, which is
+    /// answered once the capacity is enforced.
+    BulkLoad {
+        entries: Vec<(K, V, Option<Duration>)>,
+        resp_tx: Option<oneshot::Sender<()>>,
+    },
+    Get {
+        key: K,
+        resp_tx: oneshot::Sender<Option<V>>,
+    },
+    Insert {
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+    },
+    /// A value `GetForRefresh` asked to load, and how long loading it took.
+    Refresh {
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        cost: Duration,
+    },
+    /// `Get`, also telling whether to load the value again ahead of its expiry.
+    GetForRefresh {
+        key: K,
+        resp_tx: oneshot::Sender<Option<(V, bool)>>,
+    },
+    Set {
+        key: K,
+        val: V,
+        options: InsertOptions,
+        resp_tx: oneshot::Sender<Result<Option<V>, TokioActorCacheError>>,
+    },
+    GetVersioned {
+        key: K,
+        resp_tx: oneshot::Sender<Option<(V, u64)>>,
+    },
+    /// Insert unless the entry's version has moved on from `expected_version`, answering with
+    /// its new one.
+    InsertIfVersion {
+        key: K,
+        val: V,
+        expected_version: u64,
+        resp_tx: oneshot::Sender<Result<Option<u64>, TokioActorCacheError>>,
+    },
+    Update {
+        key: K,
+        update: ValueUpdate<V>,
+        resp_tx: oneshot::Sender<bool>,
+    },
+    Read {
+        key: K,
+        read: ValueRead<V>,
+    },
+    /// `update` in place, after caching `default` without a TTL when `key` isn't cached.
+    Upsert {
+        key: K,
+        default: V,
+        update: ValueUpdate<V>,
+    },
+    Expire {
+        key: K,
+        ex: Option<Duration>,
+        resp_tx: oneshot::Sender<bool>,
+    },
+    ExpireAt {
+        key: K,
+        at: SystemTime,
+        resp_tx: oneshot::Sender<bool>,
+    },
+    /// Run `scan` over the entries without counting it as a use of them.
+    Scan {
+        scan: EntriesScan<K, V>,
+    },
+    /// Serve the reads arriving on `rx` ahead of this channel.
+    ReadLane {
+        rx: CacheReceiver<HashMapCmd<K, V>>,
+    },
+    /// Have a replica send reads of keys it may be behind on to its master.
+    ReadYourWrites {
+        enabled: bool,
+    },
+    /// Report every later removal on `tx`.
+    ListenRemovals {
+        tx: UnboundedSender<Removal<K, V>>,
+    },
+    /// Report every key a later write changes on `tx`, see `Subscribers`.
+    Subscribe {
+        tx: UnboundedSender<Invalidation<K>>,
+    },
+}
+
+impl<V> VecCmd<V> {
+    #[cfg(feature = "tracing")]
+    pub fn name(&self) -> &'static str {
+        match self {
+            VecCmd::StopReplicating { .. } => "StopReplicating",
+            VecCmd::IsReplica { .. } => "IsReplica",
+            VecCmd::ReplicationInfo { .. } => "ReplicationInfo",
+            VecCmd::Replicate { .. } => "Replicate",
+            VecCmd::AddReplica { .. } => "AddReplica",
+            VecCmd::RemoveReplica { .. } => "RemoveReplica",
+            VecCmd::ReplicaCount { .. } => "ReplicaCount",
+            VecCmd::Failover { .. } => "Failover",
+            VecCmd::Sync { .. } => "Sync",
+            VecCmd::Append { .. } => "Append",
+            VecCmd::RemoveAt { .. } => "RemoveAt",
+            VecCmd::SetAt { .. } => "SetAt",
+            #[cfg(feature = "serde")]
+            VecCmd::Dump { .. } => "Dump",
+            #[cfg(feature = "serde")]
+            VecCmd::Load { .. } => "Load",
+            VecCmd::Ttl { .. } => "Ttl",
+            VecCmd::Clear => "Clear",
+            VecCmd::Barrier { .. } => "Barrier",
+            VecCmd::Remove { .. } => "Remove",
+            VecCmd::Contains { .. } => "Contains",
+            VecCmd::GetAll { .. } => "GetAll",
+            VecCmd::Snapshot { .. } => "Snapshot",
+            VecCmd::Migrate { .. } => "Migrate",
+            VecCmd::MPush { .. } => "MPush",
+            VecCmd::Push { .. } => "Push",
+            VecCmd::PopFront { .. } => "PopFront",
+            VecCmd::PopBack { .. } => "PopBack",
+            VecCmd::Truncate { .. } => "Truncate",
+            VecCmd::GetIndex { .. } => "GetIndex",
+            VecCmd::SetIndex { .. } => "SetIndex",
+            VecCmd::MaxLen { .. } => "MaxLen",
+        }
+    }
+}
+
+impl<V> HashSetCmd<V> {
+    #[cfg(feature = "tracing")]
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashSetCmd::StopReplicating { .. } => "StopReplicating",
+            HashSetCmd::IsReplica { .. } => "IsReplica",
+            HashSetCmd::ReplicationInfo { .. } => "ReplicationInfo",
+            HashSetCmd::Replicate { .. } => "Replicate",
+            HashSetCmd::AddReplica { .. } => "AddReplica",
+            HashSetCmd::RemoveReplica { .. } => "RemoveReplica",
+            HashSetCmd::ReplicaCount { .. } => "ReplicaCount",
+            HashSetCmd::Failover { .. } => "Failover",
+            HashSetCmd::Sync { .. } => "Sync",
+            HashSetCmd::Put { .. } => "Put",
+            HashSetCmd::Delta { .. } => "Delta",
+            HashSetCmd::Delete { .. } => "Delete",
+            HashSetCmd::Snapshot { .. } => "Snapshot",
+            #[cfg(feature = "serde")]
+            HashSetCmd::Dump { .. } => "Dump",
+            #[cfg(feature = "serde")]
+            HashSetCmd::Load { .. } => "Load",
+            HashSetCmd::Ttl { .. } => "Ttl",
+            HashSetCmd::Clear => "Clear",
+            HashSetCmd::Barrier { .. } => "Barrier",
+            HashSetCmd::Remove { .. } => "Remove",
+            HashSetCmd::Contains { .. } => "Contains",
+            HashSetCmd::GetAll { .. } => "GetAll",
+            HashSetCmd::HotVals { .. } => "HotVals",
+            HashSetCmd::IdleVals { .. } => "IdleVals",
+            HashSetCmd::RandMember { .. } => "RandMember",
+            HashSetCmd::Pop { .. } => "Pop",
+            HashSetCmd::MInsert { .. } => "MInsert",
+            HashSetCmd::Migrate { .. } => "Migrate",
+            HashSetCmd::Insert { .. } => "Insert",
+            HashSetCmd::InsertSync { .. } => "InsertSync",
+            HashSetCmd::Store { .. } => "Store",
+        }
+    }
+}
+
+impl<K, V> HashMapCmd<K, V> {
+    /// How the actor treats this command, matched on every variant so that a new one has to be
+    /// classed as it is added.
+    pub(crate) fn kind(&self) -> CmdKind<'_> {
+        match self {
+            HashMapCmd::Cancellable { cmd, .. } => cmd.kind(),
+            HashMapCmd::Batch { cmds } if cmds.iter().any(HashMapCmd::is_write) => CmdKind::Write,
+            HashMapCmd::Batch { .. } => CmdKind::Other,
+            HashMapCmd::Snapshot { resp_tx } => CmdKind::Read(resp_tx),
+            HashMapCmd::Ttl { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::GetAll { resp_tx } => CmdKind::Read(resp_tx),
+            HashMapCmd::HotKeys { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::IdleKeys { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::TtlHistogram { resp_tx } => CmdKind::Read(resp_tx),
+            HashMapCmd::ExpiringWithin { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::Usage { resp_tx } => CmdKind::Read(resp_tx),
+            HashMapCmd::ContainsKey { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::MGet { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::Get { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::GetVersioned { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::GetForRefresh { resp_tx, .. } => CmdKind::Read(resp_tx),
+            HashMapCmd::Clear
+            | HashMapCmd::Remove { .. }
+            | HashMapCmd::RemoveWhere { .. }
+            | HashMapCmd::MInsert { .. }
+            | HashMapCmd::Migrate { .. }
+            | HashMapCmd::BulkLoad { .. }
+            | HashMapCmd::Insert { .. }
+            | HashMapCmd::Set { .. }
+            | HashMapCmd::Refresh { .. }
+            | HashMapCmd::InsertIfVersion { .. }
+            | HashMapCmd::Update { .. }
+            | HashMapCmd::Upsert { .. }
+            | HashMapCmd::Expire { .. }
+            | HashMapCmd::ExpireAt { .. }
+            | HashMapCmd::Pin { .. }
+            | HashMapCmd::Shrink { .. }
+            | HashMapCmd::PrepareTxn { .. }
+            | HashMapCmd::CommitTxn { .. }
+            | HashMapCmd::AbortTxn { .. } => CmdKind::Write,
+            #[cfg(feature = "serde")]
+            HashMapCmd::Load { .. } => CmdKind::Write,
+            #[cfg(feature = "serde")]
+            HashMapCmd::Dump { .. } => CmdKind::Other,
+            // Answered from within a closure, so there is no reply to check.
+            HashMapCmd::Read { .. } | HashMapCmd::Scan { .. } => CmdKind::Other,
+            HashMapCmd::StopReplicating { .. }
+            | HashMapCmd::IsReplica { .. }
+            | HashMapCmd::ReplicationInfo { .. }
+            | HashMapCmd::Replicate { .. }
+            | HashMapCmd::AddReplica { .. }
+            | HashMapCmd::RemoveReplica { .. }
+            | HashMapCmd::ReplicaCount { .. }
+            | HashMapCmd::Failover { .. }
+            | HashMapCmd::Sync { .. }
+            | HashMapCmd::Put { .. }
+            | HashMapCmd::Delta { .. }
+            | HashMapCmd::Delete { .. }
+            | HashMapCmd::VersionedSnapshot { .. }
+            | HashMapCmd::Version { .. }
+            | HashMapCmd::Barrier { .. }
+            | HashMapCmd::Policy { .. }
+            | HashMapCmd::SetPolicy { .. }
+            | HashMapCmd::SetCapacity { .. }
+            | HashMapCmd::Namespaces { .. }
+            | HashMapCmd::ByteBudget { .. }
+            | HashMapCmd::ReadLane { .. }
+            | HashMapCmd::ReadYourWrites { .. }
+            | HashMapCmd::ListenRemovals { .. }
+            | HashMapCmd::Subscribe { .. } => CmdKind::Other,
+        }
+    }
+
+    /// Whether this is a direct write, one a replica forwarding its writes hands to its master.
+    pub(crate) fn is_write(&self) -> bool {
+        matches!(self.kind(), CmdKind::Write)
+    }
+
+    /// Whether this is a read an ingestion limit may shed, see `ShedPolicy`.
+    pub(crate) fn is_read(&self) -> bool {
+        matches!(self.kind(), CmdKind::Read(_))
+    }
+
+    /// Whether this is a read whose caller has stopped waiting for the answer.
+    pub(crate) fn is_abandoned(&self) -> bool {
+        matches!(self.kind(), CmdKind::Read(reply) if reply.is_closed())
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashMapCmd::Batch { .. } => "Batch",
+            HashMapCmd::Cancellable { .. } => "Cancellable",
+            HashMapCmd::StopReplicating { .. } => "StopReplicating",
+            HashMapCmd::IsReplica { .. } => "IsReplica",
+            HashMapCmd::ReplicationInfo { .. } => "ReplicationInfo",
+            HashMapCmd::Replicate { .. } => "Replicate",
+            HashMapCmd::AddReplica { .. } => "AddReplica",
+            HashMapCmd::RemoveReplica { .. } => "RemoveReplica",
+            HashMapCmd::ReplicaCount { .. } => "ReplicaCount",
+            HashMapCmd::Failover { .. } => "Failover",
+            HashMapCmd::Sync { .. } => "Sync",
+            HashMapCmd::Put { .. } => "Put",
+            HashMapCmd::Delta { .. } => "Delta",
+            HashMapCmd::Delete { .. } => "Delete",
+            HashMapCmd::Snapshot { .. } => "Snapshot",
+            HashMapCmd::VersionedSnapshot { .. } => "VersionedSnapshot",
+            HashMapCmd::Version { .. } => "Version",
+            #[cfg(feature = "serde")]
+            HashMapCmd::Dump { .. } => "Dump",
+            #[cfg(feature = "serde")]
+            HashMapCmd::Load { .. } => "Load",
+            HashMapCmd::Ttl { .. } => "Ttl",
+            HashMapCmd::GetAll { .. } => "GetAll",
+            HashMapCmd::HotKeys { .. } => "HotKeys",
+            HashMapCmd::IdleKeys { .. } => "IdleKeys",
+            HashMapCmd::TtlHistogram { .. } => "TtlHistogram",
+            HashMapCmd::ExpiringWithin { .. } => "ExpiringWithin",
+            HashMapCmd::Clear => "Clear",
+            HashMapCmd::Barrier { .. } => "Barrier",
+            HashMapCmd::Usage { .. } => "Usage",
+            HashMapCmd::Pin { .. } => "Pin",
+            HashMapCmd::Policy { .. } => "Policy",
+            HashMapCmd::SetPolicy { .. } => "SetPolicy",
+            HashMapCmd::SetCapacity { .. } => "SetCapacity",
+            HashMapCmd::Shrink { .. } => "Shrink",
+            HashMapCmd::Remove { .. } => "Remove",
+            HashMapCmd::RemoveWhere { .. } => "RemoveWhere",
+            HashMapCmd::Namespaces { .. } => "Namespaces",
+            HashMapCmd::ByteBudget { .. } => "ByteBudget",
+            HashMapCmd::ContainsKey { .. } => "ContainsKey",
+            HashMapCmd::MGet { .. } => "MGet",
+            HashMapCmd::MInsert { .. } => "MInsert",
+            HashMapCmd::Migrate { .. } => "Migrate",
+            HashMapCmd::BulkLoad { .. } => "BulkLoad",
+            HashMapCmd::Get { .. } => "Get",
+            HashMapCmd::Insert { .. } => "Insert",
+            HashMapCmd::Set { .. } => "Set",
+            HashMapCmd::PrepareTxn { .. } => "PrepareTxn",
+            HashMapCmd::CommitTxn { .. } => "CommitTxn",
+            HashMapCmd::AbortTxn { .. } => "AbortTxn",
+            HashMapCmd::Refresh { .. } => "Refresh",
+            HashMapCmd::GetForRefresh { .. } => "GetForRefresh",
+            HashMapCmd::GetVersioned { .. } => "GetVersioned",
+            HashMapCmd::InsertIfVersion { .. } => "InsertIfVersion",
+            HashMapCmd::Update { .. } => "Update",
+            HashMapCmd::Read { .. } => "Read",
+            HashMapCmd::Upsert { .. } => "Upsert",
+            HashMapCmd::Expire { .. } => "Expire",
+            HashMapCmd::ExpireAt { .. } => "ExpireAt",
+            HashMapCmd::Scan { .. } => "Scan",
+            HashMapCmd::ReadLane { .. } => "ReadLane",
+            HashMapCmd::ReadYourWrites { .. } => "ReadYourWrites",
+            HashMapCmd::ListenRemovals { .. } => "ListenRemovals",
+            HashMapCmd::Subscribe { .. } => "Subscribe",
+        }
+    }
+}
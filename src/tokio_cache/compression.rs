@@ -0,0 +1,190 @@
+//! Transparent compression of large byte values, e.g. JSON blobs, so they take less memory while
+//! cached.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::tokio_cache::channel::SendMode;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::ops::mode_pairs;
+use crate::tokio_cache::option::InsertPolicy;
+
+/// The algorithm values are compressed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// LZ4, fast to compress and very fast to decompress.
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Zstandard at the given level, smaller output for more work.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+/// Which values are compressed, and how.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Compression {
+    pub codec: Codec,
+    /// Values shorter than this many bytes are stored as is, as compressing them saves little.
+    pub min_len: usize,
+}
+
+/// A value as a compressing cache stores it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Packed {
+    data: Bytes,
+    // `None` when stored as is.
+    codec: Option<Codec>,
+    len: usize,
+}
+
+impl Packed {
+    /// Compress `val` if it is at least `compression.min_len` long and compressing makes it
+    /// shorter, else keep it as is.
+    pub fn pack(val: Bytes, compression: &Compression) -> Self {
+        let len = val.len();
+        if len >= compression.min_len
+            && let Some(data) = compress(&val, compression.codec)
+            && data.len() < len
+        {
+            return Self {
+                data: data.into(),
+                codec: Some(compression.codec),
+                len,
+            };
+        }
+        Self {
+            data: val,
+            codec: None,
+            len,
+        }
+    }
+
+    /// The original value.
+    pub fn unpack(&self) -> Result<Bytes, TokioActorCacheError> {
+        match self.codec {
+            None => Ok(self.data.clone()),
+            Some(codec) => decompress(&self.data, codec, self.len).map(Bytes::from),
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.codec.is_some()
+    }
+
+    /// How many bytes the value takes in the cache.
+    pub fn stored_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// How many bytes the value takes once unpacked.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+fn compress(val: &[u8], codec: Codec) -> Option<Vec<u8>> {
+    match codec {
+        #[cfg(feature = "lz4")]
+        Codec::Lz4 => Some(lz4_flex::compress(val)),
+        #[cfg(feature = "zstd")]
+        Codec::Zstd(level) => zstd::bulk::compress(val, level).ok(),
+    }
+}
+
+fn decompress(data: &[u8], codec: Codec, len: usize) -> Result<Vec<u8>, TokioActorCacheError> {
+    let decompressed = match codec {
+        #[cfg(feature = "lz4")]
+        Codec::Lz4 => lz4_flex::decompress(data, len).map_err(|err| err.to_string()),
+        #[cfg(feature = "zstd")]
+        Codec::Zstd(_) => zstd::bulk::decompress(data, len).map_err(|err| err.to_string()),
+    };
+    decompressed.map_err(|reason| TokioActorCacheError::Decompress { reason })
+}
+
+/// A `HashMapCache` of byte values that compresses those of at least `min_len` bytes on insert
+/// and decompresses them inside the actor on read, so only the requested value is ever inflated.
+#[derive(Debug, Clone)]
+pub struct CompressedHashMapCache<K, C> {
+    cache: HashMapCache<K, Packed, C>,
+    compression: Compression,
+}
+
+impl<K, C> CompressedHashMapCache<K, C>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+{
+    /// Compress the values of `cache`, which should hold nothing yet, as `compression` says.
+    pub fn new(cache: HashMapCache<K, Packed, C>, compression: Compression) -> Self {
+        Self { cache, compression }
+    }
+
+    /// The underlying cache, whose values stay packed.
+    pub fn cache(&self) -> &HashMapCache<K, Packed, C> {
+        &self.cache
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    mode_pairs! {
+        fn get / try_get(key: K) -> Result<Option<Bytes>, TokioActorCacheError> => get_with;
+        fn insert / try_insert(
+            key: K,
+            val: Bytes,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) -> Result<(), TokioActorCacheError> => insert_with;
+        fn remove / try_remove(keys: &[K]) -> Result<Vec<Option<Bytes>>, TokioActorCacheError>
+            => remove_with;
+    }
+
+    async fn get_with(
+        &self,
+        key: K,
+        mode: SendMode,
+    ) -> Result<Option<Bytes>, TokioActorCacheError> {
+        self.cache
+            .read_value_with(key, |packed| packed.unpack(), mode)
+            .await?
+            .transpose()
+    }
+
+    async fn insert_with(
+        &self,
+        key: K,
+        val: Bytes,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let packed = Packed::pack(val, &self.compression);
+        match mode {
+            SendMode::Try => self.cache.try_insert(key, packed, ex, policy).await,
+            SendMode::Wait => self.cache.insert(key, packed, ex, policy).await,
+        }
+    }
+
+    async fn remove_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<Option<Bytes>>, TokioActorCacheError> {
+        let removed = match mode {
+            SendMode::Try => self.cache.try_remove(keys).await?,
+            SendMode::Wait => self.cache.remove(keys).await?,
+        };
+        removed
+            .into_iter()
+            .map(|packed| packed.map(|packed| packed.unpack()).transpose())
+            .collect()
+    }
+}
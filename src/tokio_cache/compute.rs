@@ -1,6 +1,6 @@
 use crc16_xmodem_fast::hash;
 
-pub fn hash_id(val: &str, num_shards: u16) -> u16 {
+pub fn hash_id(val: &str, num_shards: u64) -> u64 {
     // Step 1: Hash the ISIN to CRC16 XMODEM (returns u16)
     let crc = hash(val.as_bytes());
 
@@ -9,6 +9,36 @@ pub fn hash_id(val: &str, num_shards: u16) -> u16 {
     // Convert hex string to decimal (redundant, since u16, but as per instructions)
     let decimal = u16::from_str_radix(&hex, 16).expect("Invalid hex");
 
-    // Step 3: Compute shard ID
-    decimal % num_shards
+    // Step 3: Compute shard ID, in u64 so that any number of shards is taken as it is
+    u64::from(decimal) % num_shards
+}
+
+/// The `n` items of lowest rank, in no particular order, found without sorting all of them.
+pub(crate) fn lowest_ranked<T, R: Ord>(items: impl Iterator<Item = (T, R)>, n: usize) -> Vec<T> {
+    select_lowest(items, n)
+        .into_iter()
+        .map(|(item, _)| item)
+        .collect()
+}
+
+/// The `n` items of lowest rank with their ranks, lowest first.
+pub(crate) fn lowest_ranked_sorted<T, R: Ord>(
+    items: impl Iterator<Item = (T, R)>,
+    n: usize,
+) -> Vec<(T, R)> {
+    let mut lowest = select_lowest(items, n);
+    lowest.sort_unstable_by(|(_, a), (_, b)| a.cmp(b));
+    lowest
+}
+
+fn select_lowest<T, R: Ord>(items: impl Iterator<Item = (T, R)>, n: usize) -> Vec<(T, R)> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut items = items.collect::<Vec<(T, R)>>();
+    if n < items.len() {
+        items.select_nth_unstable_by(n, |(_, a), (_, b)| a.cmp(b));
+        items.truncate(n);
+    }
+    items
 }
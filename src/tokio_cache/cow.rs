@@ -0,0 +1,214 @@
+//! A copy-on-write hash map, so actors can hand out their whole state without copying it.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Segments a `CowMap` splits its entries over. More segments make the writes that follow a
+/// clone copy less, at the cost of a longer walk over an empty map.
+const SEGMENTS: usize = 64;
+
+/// A hash map split by key hash into segments behind `Arc`s. Cloning it only clones the pointers,
+/// and a write copies the one segment it touches, and only while a clone still shares it, so an
+/// actor can send its entries to a replica or a reader and go on writing without copying them
+/// all at once.
+pub(crate) struct CowMap<K, V> {
+    segments: Box<[Arc<HashMap<K, V>>]>,
+}
+
+impl<K, V> Clone for CowMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            segments: self.segments.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for CowMap<K, V> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl<K, V> std::fmt::Debug for CowMap<K, V>
+where
+    K: std::fmt::Debug,
+    V: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> CowMap<K, V> {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        let per_segment = capacity.div_ceil(SEGMENTS);
+        Self {
+            segments: (0..SEGMENTS)
+                .map(|_| Arc::new(HashMap::with_capacity(per_segment)))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.segments.iter().map(|segment| segment.len()).sum()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.segments.iter().map(|segment| segment.capacity()).sum()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.segments.iter().flat_map(|segment| segment.iter())
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, val)| val)
+    }
+
+    /// Drop every entry, leaving any clone with its own.
+    pub(crate) fn clear(&mut self) {
+        for segment in &mut self.segments {
+            match Arc::get_mut(segment) {
+                Some(segment) => segment.clear(),
+                None => *segment = Arc::new(HashMap::new()),
+            }
+        }
+    }
+}
+
+impl<K, V> CowMap<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// The segment of a key that hashes like `key`. The hasher is fixed rather than random, so
+    /// every clone agrees on it; each segment still hashes its keys with its own random state.
+    fn segment_of<Q>(key: &Q) -> usize
+    where
+        Q: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish() as usize % SEGMENTS
+    }
+
+    /// The segment of `key` to write to, copied first if a clone shares it.
+    fn segment_mut(&mut self, key: &K) -> &mut HashMap<K, V> {
+        Arc::make_mut(&mut self.segments[Self::segment_of(key)])
+    }
+
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.segments[Self::segment_of(key)].get(key)
+    }
+
+    pub(crate) fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Copies the segment of `key` if a clone shares it, even when `key` isn't there.
+    pub(crate) fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let segment = &mut self.segments[Self::segment_of(key)];
+        if !segment.contains_key(key) {
+            return None;
+        }
+        Arc::make_mut(segment).get_mut(key)
+    }
+
+    pub(crate) fn insert(&mut self, key: K, val: V) -> Option<V> {
+        self.segment_mut(&key).insert(key, val)
+    }
+
+    pub(crate) fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let segment = &mut self.segments[Self::segment_of(key)];
+        if !segment.contains_key(key) {
+            return None;
+        }
+        Arc::make_mut(segment).remove(key)
+    }
+
+    /// Every entry mutably, copying the segments a clone shares.
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.segments
+            .iter_mut()
+            .flat_map(|segment| Arc::make_mut(segment).iter_mut())
+    }
+
+    /// Take every entry out, leaving any clone with its own.
+    pub(crate) fn drain(&mut self) -> Vec<(K, V)> {
+        std::mem::take(self).into_iter().collect()
+    }
+
+    /// Make room for `additional` more entries spread evenly over the segments.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let per_segment = additional.div_ceil(SEGMENTS);
+        for segment in &mut self.segments {
+            Arc::make_mut(segment).reserve(per_segment);
+        }
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        for segment in &mut self.segments {
+            if let Some(segment) = Arc::get_mut(segment) {
+                segment.shrink_to_fit();
+            }
+        }
+    }
+}
+
+/// Moves the entries of segments nobody else holds and clones those of shared ones.
+impl<K, V> IntoIterator for CowMap<K, V>
+where
+    K: Clone,
+    V: Clone,
+{
+    type Item = (K, V);
+    type IntoIter = std::iter::FlatMap<
+        std::vec::IntoIter<Arc<HashMap<K, V>>>,
+        std::collections::hash_map::IntoIter<K, V>,
+        fn(Arc<HashMap<K, V>>) -> std::collections::hash_map::IntoIter<K, V>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments
+            .into_vec()
+            .into_iter()
+            .flat_map(|segment| Arc::unwrap_or_clone(segment).into_iter())
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for CowMap<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        let mut map = Self::default();
+        for (key, val) in entries {
+            map.insert(key, val);
+        }
+        map
+    }
+}
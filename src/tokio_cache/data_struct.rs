@@ -1,16 +1,115 @@
+use std::collections::HashMap;
+use std::future;
+use std::hash::Hash;
+use std::time::{Duration, SystemTime};
+
 use tokio::time::Instant;
 
+use crate::tokio_cache::cow::CowMap;
+use crate::tokio_cache::removal::RemovalReason;
+use crate::tokio_cache::runtime::Runtime;
+
+/// How often actors drop expired entries and evict those over capacity.
+pub(crate) const TICK: Duration = Duration::from_millis(100);
+
+/// When the wall clock reads `at` by the actor's clock, now if it already has. Taken by the actor
+/// as it applies a write, so time spent in the queue doesn't shift the expiry.
+pub(crate) fn instant_at(at: SystemTime) -> Instant {
+    Instant::now() + at.duration_since(SystemTime::now()).unwrap_or_default()
+}
+
+/// When an actor's earliest TTL runs out, so TTLs shorter than a `TICK` are honoured on time.
+/// It may be earlier than any entry left, which only costs a needless sweep, but never later.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct NextExpiration(Option<Instant>);
+
+impl NextExpiration {
+    /// The lowest deadline of an actor's expiry index.
+    pub(crate) fn at(next: Option<Instant>) -> Self {
+        Self(next)
+    }
+
+    pub(crate) fn is_due(&self, now: Instant) -> bool {
+        self.0.is_some_and(|next| next <= now)
+    }
+
+    /// Resolves once it is due by the clock of `runtime`, never if nothing expires.
+    pub(crate) async fn elapsed(self, runtime: &dyn Runtime) {
+        match self.0 {
+            Some(next) => runtime.sleep_until(next.into_std()).await,
+            None => future::pending().await,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct HashSetState {
-    pub expiration: Option<Instant>,
-    pub call_cnt: u64,
-    pub last_accessed: Instant,
+pub(crate) struct HashSetState {
+    pub(crate) expiration: Option<Instant>,
+    pub(crate) call_cnt: u64,
+    pub(crate) last_accessed: Instant,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct ValueWithState<V> {
-    pub val: V,
-    pub expiration: Option<Instant>,
-    pub call_cnt: u64,
-    pub last_accessed: Instant,
+pub(crate) struct ValueWithState<V> {
+    pub(crate) val: V,
+    pub(crate) expiration: Option<Instant>,
+    pub(crate) call_cnt: u64,
+    pub(crate) last_accessed: Instant,
+    /// Never evicted nor expired while set, see `HashMapCache::pin`.
+    pub(crate) pinned: bool,
+    /// Goes up with every new value, see `HashMapCache::get_versioned`.
+    pub(crate) version: u64,
+    /// Dropped once unused for this long, see `InsertOptions::tti`.
+    pub(crate) tti: Option<Duration>,
+    /// How long loading the value took, see `HashMapCache::get_with_refresh`.
+    pub(crate) refresh_cost: Duration,
+}
+
+impl<V> ValueWithState<V> {
+    /// When the entry runs out, by its TTL or by going unused for its time to idle, whichever
+    /// comes first.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        let idle_deadline = self.tti.map(|tti| self.last_accessed + tti);
+        match (self.expiration, idle_deadline) {
+            (Some(expiration), Some(idle_deadline)) => Some(expiration.min(idle_deadline)),
+            (expiration, idle_deadline) => expiration.or(idle_deadline),
+        }
+    }
+
+    /// When the entry runs out unless it is pinned, see `deadline`.
+    pub(crate) fn unpinned_deadline(&self) -> Option<Instant> {
+        self.deadline().filter(|_| !self.pinned)
+    }
+
+    /// Why the entry has run out by `now`, if it has.
+    pub(crate) fn expired(&self, now: Instant) -> Option<RemovalReason> {
+        if self.expiration.is_some_and(|exp| exp <= now) {
+            Some(RemovalReason::TtlElapsed)
+        } else if self.tti.is_some_and(|tti| self.last_accessed + tti <= now) {
+            Some(RemovalReason::IdleTimeout)
+        } else {
+            None
+        }
+    }
 }
+
+/// Every entry of a hash map actor with its state, which is also how the actor stores them, so
+/// taking one is cheap. Never handed outside the crate, since the expiration instants only mean
+/// something to the actor's clock.
+pub(crate) type HashMapSnapshot<K, V> = CowMap<K, ValueWithState<V>>;
+
+impl<K, V> CowMap<K, ValueWithState<V>>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// The values without their state, built outside the actor from a snapshot it handed out.
+    pub(crate) fn into_vals(self) -> HashMap<K, V> {
+        self.into_iter()
+            .map(|(key, val_with_state)| (key, val_with_state.val))
+            .collect()
+    }
+}
+
+/// Every value of a hash set actor with its state.
+pub(crate) type HashSetSnapshot<V> = HashMap<V, HashSetState>;
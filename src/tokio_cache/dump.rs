@@ -0,0 +1,353 @@
+//! Cache contents serialized by `dump` and read back by `load`. An `Instant` means nothing outside
+//! the process that took it, so expirations are stored as the TTL that was left.
+//!
+//! A snapshot is little-endian binary:
+//!
+//! ```text
+//! magic "TACS" | version u16 | min_version u16 | header_len u32 | header
+//! header:  policy u8 (0 None, 1 LFU, 2 LRU) | capacity u64 | n_entries u64 | ...
+//! entries: n_entries × (record_len u32 | record)
+//! record:  has_ttl u8 | ttl_nanos u64 | call_cnt u64 | item_len u32 | item (JSON) | ...
+//! ```
+//!
+//! Later versions may only append fields to the header and to records, which older readers skip
+//! using the lengths. `min_version` is the oldest version that can still read a snapshot, and is
+//! only raised for changes older readers would get wrong.
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::option::ExpirationPolicy;
+use crate::tokio_cache::router::{Crc16Router, HashRouter, WeightedRouter};
+
+const MAGIC: &[u8; 4] = b"TACS";
+/// Version written by this crate, which reads every version up to it.
+const VERSION: u16 = 1;
+/// Oldest version that can read what this crate writes.
+const MIN_VERSION: u16 = 1;
+
+/// What a snapshot says about itself, readable without decoding its entries.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotHeader {
+    pub version: u16,
+    /// Policy of the cache the snapshot was taken from.
+    pub expiration_policy: ExpirationPolicy,
+    pub n_entries: u64,
+}
+
+impl SnapshotHeader {
+    pub fn read(bytes: &[u8]) -> Result<Self, TokioActorCacheError> {
+        Self::read_from(&mut Reader(bytes))
+    }
+
+    fn read_from(reader: &mut Reader<'_>) -> Result<Self, TokioActorCacheError> {
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a snapshot"));
+        }
+        let version = reader.u16()?;
+        let min_version = reader.u16()?;
+        if min_version > VERSION {
+            return Err(invalid(format!(
+                "snapshot version {version} needs a reader of version {min_version} or later, this one is version {VERSION}"
+            )));
+        }
+        let header_len = reader.u32()? as usize;
+        let mut header = Reader(reader.take(header_len)?);
+        let expiration_policy = match (header.u8()?, header.u64()?) {
+            (0, _) => ExpirationPolicy::None,
+            (1, capacity) => ExpirationPolicy::LFU(capacity as usize),
+            (2, capacity) => ExpirationPolicy::LRU(capacity as usize),
+            (tag, _) => return Err(invalid(format!("unknown expiration policy {tag}"))),
+        };
+        let n_entries = header.u64()?;
+        Ok(Self {
+            version,
+            expiration_policy,
+            n_entries,
+        })
+    }
+}
+
+/// What `HashMapCacheCluster::snapshot_all` saved, written next to the node snapshots as
+/// `manifest.json` once all of them are.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterManifest {
+    /// How many nodes keys were routed over.
+    pub n_node: u64,
+    /// The router keys were routed by, `None` when `KeyRouter::spec` doesn't describe it.
+    #[serde(default)]
+    pub router: Option<RouterSpec>,
+    pub nodes: Vec<NodeSnapshot>,
+}
+
+/// A router of the crate, as a cluster manifest records it, see `KeyRouter::spec`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RouterSpec {
+    Crc16,
+    Hash {
+        seed: u64,
+    },
+    /// Node weights that were set by node id, the rest being 1.
+    Weighted {
+        seed: u64,
+        weights: Vec<(u64, u32)>,
+    },
+}
+
+/// Routers `restore` can build again for the keys of a snapshot to route as they did when it was
+/// taken. A router `KeyRouter::spec` doesn't describe can return itself for `None`.
+pub trait RestoreRouter: Sized {
+    /// The router `spec` describes, `None` if it isn't one of this type.
+    fn restore(spec: Option<&RouterSpec>) -> Option<Self>;
+}
+
+impl RestoreRouter for Crc16Router {
+    fn restore(spec: Option<&RouterSpec>) -> Option<Self> {
+        matches!(spec, Some(RouterSpec::Crc16)).then_some(Crc16Router)
+    }
+}
+
+impl RestoreRouter for HashRouter {
+    fn restore(spec: Option<&RouterSpec>) -> Option<Self> {
+        match spec {
+            Some(RouterSpec::Hash { seed }) => Some(HashRouter::with_seed(*seed)),
+            _ => None,
+        }
+    }
+}
+
+impl RestoreRouter for WeightedRouter {
+    fn restore(spec: Option<&RouterSpec>) -> Option<Self> {
+        let Some(RouterSpec::Weighted { seed, weights }) = spec else {
+            return None;
+        };
+        let router = WeightedRouter::with_seed(*seed);
+        for &(id, weight) in weights {
+            router.set_weight(id, weight);
+        }
+        Some(router)
+    }
+}
+
+/// Where one node was saved. The node held the keys its router sent to `id` out of `n_node`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeSnapshot {
+    pub id: u64,
+    /// File name within the snapshot directory.
+    pub file: String,
+    pub n_entries: u64,
+}
+
+impl ClusterManifest {
+    pub const FILE: &str = "manifest.json";
+
+    pub async fn read(dir: impl AsRef<Path>) -> Result<Self, TokioActorCacheError> {
+        let path = dir.as_ref().join(Self::FILE);
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|err| TokioActorCacheError::io(&path, err))?;
+        serde_json::from_slice(&bytes).map_err(|err| invalid(err.to_string()))
+    }
+
+    pub(crate) async fn write(&self, dir: &Path) -> Result<(), TokioActorCacheError> {
+        let path = dir.join(Self::FILE);
+        let bytes = serde_json::to_vec_pretty(self).map_err(|err| invalid(err.to_string()))?;
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|err| TokioActorCacheError::io(&path, err))
+    }
+}
+
+/// One entry of a snapshot: a key-val pair for `HashMapCache`, a val otherwise.
+#[derive(Debug)]
+pub(crate) struct Dumped<T> {
+    pub(crate) item: T,
+    ttl: Option<Duration>,
+    pub(crate) call_cnt: u64,
+}
+
+impl<T> Dumped<T> {
+    /// `None` when the entry has expired but hasn't been swept yet.
+    pub(crate) fn new(
+        item: T,
+        expiration: Option<Instant>,
+        call_cnt: u64,
+        now: Instant,
+    ) -> Option<Self> {
+        let ttl = match expiration {
+            Some(expiration) => Some(expiration.checked_duration_since(now)?),
+            None => None,
+        };
+        Some(Self {
+            item,
+            ttl,
+            call_cnt,
+        })
+    }
+
+    pub(crate) fn expiration(&self, now: Instant) -> Option<Instant> {
+        self.ttl.map(|ttl| now + ttl)
+    }
+}
+
+pub(crate) fn encode<T: Serialize>(
+    expiration_policy: ExpirationPolicy,
+    entries: &[Dumped<T>],
+) -> Result<Vec<u8>, TokioActorCacheError> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&MIN_VERSION.to_le_bytes());
+
+    let (tag, capacity) = match expiration_policy {
+        ExpirationPolicy::None => (0u8, 0),
+        ExpirationPolicy::LFU(capacity) => (1, capacity as u64),
+        ExpirationPolicy::LRU(capacity) => (2, capacity as u64),
+    };
+    let mut header = vec![tag];
+    header.extend_from_slice(&capacity.to_le_bytes());
+    header.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    push_framed(&mut bytes, &header);
+
+    let mut record = Vec::new();
+    for entry in entries {
+        record.clear();
+        let ttl_nanos = entry
+            .ttl
+            .map_or(0, |ttl| u64::try_from(ttl.as_nanos()).unwrap_or(u64::MAX));
+        record.push(u8::from(entry.ttl.is_some()));
+        record.extend_from_slice(&ttl_nanos.to_le_bytes());
+        record.extend_from_slice(&entry.call_cnt.to_le_bytes());
+        let item = serde_json::to_vec(&entry.item).map_err(|err| TokioActorCacheError::Dump {
+            reason: err.to_string(),
+        })?;
+        push_framed(&mut record, &item);
+        push_framed(&mut bytes, &record);
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn decode<T: DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<Vec<Dumped<T>>, TokioActorCacheError> {
+    let mut reader = Reader(bytes);
+    let header = SnapshotHeader::read_from(&mut reader)?;
+    let mut entries = Vec::new();
+    for _ in 0..header.n_entries {
+        let record_len = reader.u32()? as usize;
+        let mut record = Reader(reader.take(record_len)?);
+        let has_ttl = record.u8()? != 0;
+        let ttl_nanos = record.u64()?;
+        let call_cnt = record.u64()?;
+        let item_len = record.u32()? as usize;
+        let item = serde_json::from_slice(record.take(item_len)?).map_err(|err| {
+            TokioActorCacheError::Dump {
+                reason: err.to_string(),
+            }
+        })?;
+        entries.push(Dumped {
+            item,
+            ttl: has_ttl.then(|| Duration::from_nanos(ttl_nanos)),
+            call_cnt,
+        });
+    }
+    Ok(entries)
+}
+
+fn push_framed(bytes: &mut Vec<u8>, frame: &[u8]) {
+    bytes.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(frame);
+}
+
+fn invalid(reason: impl Into<String>) -> TokioActorCacheError {
+    TokioActorCacheError::Dump {
+        reason: reason.into(),
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], TokioActorCacheError> {
+        if self.0.len() < n {
+            return Err(invalid("snapshot is truncated"));
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, TokioActorCacheError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, TokioActorCacheError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, TokioActorCacheError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, TokioActorCacheError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// One line of `HashMapCache::export_json`, kept readable so fixtures can be written by hand.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct JsonLine<K, V> {
+    pub(crate) key: K,
+    pub(crate) val: V,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) ttl_ms: Option<u64>,
+}
+
+impl<K, V> JsonLine<K, V> {
+    /// `None` when the entry has expired but hasn't been swept yet.
+    pub(crate) fn new(key: K, val: V, expiration: Option<Instant>, now: Instant) -> Option<Self> {
+        let ttl_ms = match expiration {
+            Some(expiration) => Some(expiration.checked_duration_since(now)?.as_millis() as u64),
+            None => None,
+        };
+        Some(Self { key, val, ttl_ms })
+    }
+
+    pub(crate) fn ttl(&self) -> Option<Duration> {
+        self.ttl_ms.map(Duration::from_millis)
+    }
+}
+
+pub(crate) fn encode_lines<K: Serialize, V: Serialize>(
+    lines: &[JsonLine<K, V>],
+) -> Result<Vec<u8>, TokioActorCacheError> {
+    let mut bytes = Vec::new();
+    for line in lines {
+        serde_json::to_writer(&mut bytes, line).map_err(|err| TokioActorCacheError::Dump {
+            reason: err.to_string(),
+        })?;
+        bytes.push(b'\n');
+    }
+    Ok(bytes)
+}
+
+/// Blank lines are skipped; errors name the line they were found on.
+pub(crate) fn decode_lines<K: DeserializeOwned, V: DeserializeOwned>(
+    text: &str,
+) -> Result<Vec<JsonLine<K, V>>, TokioActorCacheError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|err| TokioActorCacheError::Dump {
+                reason: format!("line {}: {err}", i + 1),
+            })
+        })
+        .collect()
+}
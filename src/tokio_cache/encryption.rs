@@ -0,0 +1,155 @@
+//! Encryption of snapshots at rest with AES-256-GCM.
+//!
+//! An encrypted snapshot wraps a plain one, little-endian:
+//!
+//! ```text
+//! magic "TACE" | version u16 | key_id u32 | nonce [u8; 12] | ciphertext with tag
+//! ```
+//!
+//! The header is authenticated along with the snapshot. Its key id lets a `Keyring` that holds
+//! the keys in use pick the one a snapshot was sealed with, so keys can be rotated while older
+//! snapshots are still around.
+
+use std::fmt;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::tokio_cache::error::TokioActorCacheError;
+
+const MAGIC: &[u8; 4] = b"TACE";
+const VERSION: u16 = 1;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 2 + 4 + NONCE_LEN;
+
+/// A 256-bit AES key, named by an id that sealed snapshots record.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: u32,
+    cipher: Aes256Gcm,
+}
+
+impl EncryptionKey {
+    pub fn new(id: u32, key: [u8; 32]) -> Self {
+        Self {
+            id,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        }
+    }
+
+    /// A random key from the operating system's generator.
+    pub fn generate(id: u32) -> Self {
+        Self {
+            id,
+            cipher: Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng)),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The keys snapshots are sealed and opened with: the current one seals, and any of them opens
+/// what was sealed with it. To rotate, make the new key current and keep the old one until no
+/// snapshot needs it, or `reseal` those that do.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    current: EncryptionKey,
+    previous: Vec<EncryptionKey>,
+}
+
+impl Keyring {
+    pub fn new(current: EncryptionKey) -> Self {
+        Self {
+            current,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Keep opening snapshots sealed with `key`.
+    pub fn with_previous(mut self, key: EncryptionKey) -> Self {
+        self.previous.push(key);
+        self
+    }
+
+    pub fn current(&self) -> &EncryptionKey {
+        &self.current
+    }
+
+    /// Encrypt `snapshot` with the current key.
+    pub fn seal(&self, snapshot: &[u8]) -> Result<Vec<u8>, TokioActorCacheError> {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let mut sealed = Vec::with_capacity(HEADER_LEN + snapshot.len() + 16);
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&VERSION.to_le_bytes());
+        sealed.extend_from_slice(&self.current.id.to_le_bytes());
+        sealed.extend_from_slice(&nonce);
+        let ciphertext = self
+            .current
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: snapshot,
+                    aad: &sealed,
+                },
+            )
+            .map_err(|_| invalid("cannot encrypt snapshot"))?;
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt what `seal` encrypted, with whichever key it names.
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, TokioActorCacheError> {
+        let key_id = key_id(sealed)?;
+        let key = std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.id == key_id)
+            .ok_or_else(|| invalid(format!("no key with id {key_id}")))?;
+        let (header, ciphertext) = sealed.split_at(HEADER_LEN);
+        let nonce = Nonce::from_slice(&header[HEADER_LEN - NONCE_LEN..]);
+        key.cipher
+            .decrypt(
+                nonce,
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| invalid(format!("snapshot doesn't match key {key_id}")))
+    }
+
+    /// Seal again with the current key what an older key sealed.
+    pub fn reseal(&self, sealed: &[u8]) -> Result<Vec<u8>, TokioActorCacheError> {
+        self.seal(&self.open(sealed)?)
+    }
+}
+
+/// The id of the key `sealed` was encrypted with.
+pub fn key_id(sealed: &[u8]) -> Result<u32, TokioActorCacheError> {
+    if sealed.len() < HEADER_LEN || &sealed[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not an encrypted snapshot"));
+    }
+    let version = u16::from_le_bytes(sealed[4..6].try_into().unwrap());
+    if version > VERSION {
+        return Err(invalid(format!(
+            "encrypted snapshot version {version} is newer than this reader's {VERSION}"
+        )));
+    }
+    Ok(u32::from_le_bytes(sealed[6..10].try_into().unwrap()))
+}
+
+fn invalid(reason: impl Into<String>) -> TokioActorCacheError {
+    TokioActorCacheError::Encryption {
+        reason: reason.into(),
+    }
+}
@@ -1,13 +1,91 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum TokioActorCacheError {
-    #[error("node cannot be found")]
-    NodeNotExists,
-    #[error("unknown data store error")]
-    InconsistentLen,
-    #[error("unknown data store error")]
-    Receive,
-    #[error("unknown data store error")]
-    Send,
+    #[error("node {id} cannot be found")]
+    NodeNotExists { id: u64 },
+    #[error("inconsistent argument lengths: expected {expected}, got {got}")]
+    InconsistentLen { expected: usize, got: usize },
+    #[error("command channel is full ({len} of {capacity} queued)")]
+    ChannelFull { len: usize, capacity: usize },
+    #[error("cache actor is no longer running")]
+    ActorGone,
+    #[error("no response from cache actor after {after:?}")]
+    Timeout { after: Duration },
+    #[error("cluster has {got} nodes, expected {expected}")]
+    ClusterSizeMismatch { expected: usize, got: usize },
+    #[error("cluster snapshot was routed by another router than the one restoring it")]
+    RouterMismatch,
+    #[error("cache is a read-only replica")]
+    ReadOnlyReplica,
+    #[error("cannot dump or load cache contents: {reason}")]
+    Dump { reason: String },
+    #[error("cannot access {path}: {reason}")]
+    Io { path: PathBuf, reason: String },
+    #[error("cannot decompress cached value: {reason}")]
+    Decompress { reason: String },
+    #[error("cannot encrypt or decrypt snapshot: {reason}")]
+    Encryption { reason: String },
+    #[error("cache {name} is registered with other key or value types")]
+    CacheTypeMismatch { name: String },
+    #[error("cache is at its hard capacity of {capacity} entries")]
+    CapacityExceeded { capacity: usize },
+    #[error("operation was cancelled")]
+    Cancelled,
+    #[error("cache actor shed the command under load")]
+    Shed,
+    #[error("transaction aborted: {reason}")]
+    TransactionAborted { reason: String },
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+/// Why a cache can't be built as configured.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("an LFU or LRU cache needs a capacity of at least 1")]
+    ZeroCapacity,
+    #[error("capacity {capacity} is over the maximum of {max}")]
+    CapacityTooLarge { capacity: usize, max: usize },
+    #[error("a bounded cache needs a buffer of at least 1")]
+    ZeroBuffer,
+    #[error("a cluster needs at least one node")]
+    NoNodes,
+}
+
+impl ConfigError {
+    pub(crate) fn check_buffer(buffer: usize) -> Result<(), Self> {
+        match buffer {
+            0 => Err(ConfigError::ZeroBuffer),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_n_node(n_node: u64) -> Result<(), Self> {
+        match n_node {
+            0 => Err(ConfigError::NoNodes),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TokioActorCacheError {
+    #[cfg(feature = "serde")]
+    pub(crate) fn io(path: &std::path::Path, err: std::io::Error) -> Self {
+        TokioActorCacheError::Io {
+            path: path.to_path_buf(),
+            reason: err.to_string(),
+        }
+    }
+
+    /// Returns the first length in `lens` that differs from `expected`, as an `InconsistentLen` error.
+    pub(crate) fn check_len(expected: usize, lens: &[usize]) -> Result<(), Self> {
+        match lens.iter().find(|&&got| got != expected) {
+            Some(&got) => Err(TokioActorCacheError::InconsistentLen { expected, got }),
+            None => Ok(()),
+        }
+    }
 }
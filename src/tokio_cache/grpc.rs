@@ -0,0 +1,243 @@
+//! A gRPC front-end, so a `HashMapCacheCluster` can run as a standalone cache node. The service is
+//! generated by `build.rs` from the messages below; `CacheServiceClient` talks to it.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use bytes::Bytes;
+use prost::Message;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status};
+
+use crate::tokio_cache::channel::{self, CacheReceiver, SendMode};
+use crate::tokio_cache::cmd::HashMapCmd;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm_cluster::HashMapCacheCluster;
+use crate::tokio_cache::option::InsertPolicy;
+use crate::tokio_cache::router::{HashRouter, KeyRouter};
+
+pub use cache_service_client::CacheServiceClient;
+pub use cache_service_server::{CacheService, CacheServiceServer};
+
+include!(concat!(env!("OUT_DIR"), "/tokio_cache.CacheService.rs"));
+
+/// Events a subscriber may fall behind by before its node waits for it.
+const SUBSCRIBE_BUFFER: usize = 1024;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetRequest {
+    #[prost(bytes = "bytes", tag = "1")]
+    pub key: Bytes,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetResponse {
+    #[prost(bytes = "bytes", optional, tag = "1")]
+    pub val: Option<Bytes>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetRequest {
+    #[prost(bytes = "bytes", tag = "1")]
+    pub key: Bytes,
+    #[prost(bytes = "bytes", tag = "2")]
+    pub val: Bytes,
+    /// Time to live in milliseconds; the entry never expires without one.
+    #[prost(uint64, optional, tag = "3")]
+    pub ttl_ms: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SetResponse {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MGetRequest {
+    #[prost(bytes = "bytes", repeated, tag = "1")]
+    pub keys: Vec<Bytes>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MGetResponse {
+    /// One per requested key, in order.
+    #[prost(message, repeated, tag = "1")]
+    pub vals: Vec<Value>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RemoveRequest {
+    #[prost(bytes = "bytes", repeated, tag = "1")]
+    pub keys: Vec<Bytes>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RemoveResponse {
+    /// The removed value of each requested key, in order.
+    #[prost(message, repeated, tag = "1")]
+    pub vals: Vec<Value>,
+}
+
+/// A value that may be missing, since protobuf has no optional repeated elements.
+#[derive(Clone, PartialEq, Message)]
+pub struct Value {
+    #[prost(bytes = "bytes", optional, tag = "1")]
+    pub val: Option<Bytes>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SubscribeRequest {
+    /// Keys to watch; every key when empty.
+    #[prost(bytes = "bytes", repeated, tag = "1")]
+    pub keys: Vec<Bytes>,
+}
+
+/// `key` was set to `val`, or removed when `val` is missing.
+#[derive(Clone, PartialEq, Message)]
+pub struct Event {
+    #[prost(bytes = "bytes", tag = "1")]
+    pub key: Bytes,
+    #[prost(bytes = "bytes", optional, tag = "2")]
+    pub val: Option<Bytes>,
+}
+
+/// Serves `CacheService` from a cluster; pass `into_service()` to a tonic server.
+#[derive(Debug, Clone)]
+pub struct CacheNode<C, R = HashRouter> {
+    cluster: HashMapCacheCluster<Bytes, Bytes, C, R>,
+}
+
+impl<C, R> CacheNode<C, R>
+where
+    C: Clone + Send + Sync + 'static,
+    R: KeyRouter<Bytes> + Send + Sync + 'static,
+{
+    pub fn new(cluster: HashMapCacheCluster<Bytes, Bytes, C, R>) -> Self {
+        Self { cluster }
+    }
+
+    pub fn into_service(self) -> CacheServiceServer<Self> {
+        CacheServiceServer::new(self)
+    }
+}
+
+#[tonic::async_trait]
+impl<C, R> CacheService for CacheNode<C, R>
+where
+    C: Clone + Send + Sync + 'static,
+    R: KeyRouter<Bytes> + Send + Sync + 'static,
+{
+    async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        let val = self.cluster.get(request.into_inner().key).await?;
+        Ok(Response::new(GetResponse { val }))
+    }
+
+    async fn set(&self, request: Request<SetRequest>) -> Result<Response<SetResponse>, Status> {
+        let SetRequest { key, val, ttl_ms } = request.into_inner();
+        let ex = ttl_ms.map(Duration::from_millis);
+        self.cluster
+            .insert(key, val, ex, InsertPolicy::Always)
+            .await?;
+        Ok(Response::new(SetResponse {}))
+    }
+
+    async fn m_get(&self, request: Request<MGetRequest>) -> Result<Response<MGetResponse>, Status> {
+        let vals = self.cluster.mget(&request.into_inner().keys).await?;
+        let vals = vals.into_iter().map(|val| Value { val }).collect();
+        Ok(Response::new(MGetResponse { vals }))
+    }
+
+    async fn remove(
+        &self,
+        request: Request<RemoveRequest>,
+    ) -> Result<Response<RemoveResponse>, Status> {
+        let vals = self.cluster.remove(&request.into_inner().keys).await?;
+        let vals = vals.into_iter().map(|val| Value { val }).collect();
+        Ok(Response::new(RemoveResponse { vals }))
+    }
+
+    type SubscribeStream = ReceiverStream<Result<Event, Status>>;
+
+    /// Stream every write to the requested keys, starting with their current values. The
+    /// subscription follows each node the way a replica does, and ends when the client hangs up.
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let keys: HashSet<Bytes> = request.into_inner().keys.into_iter().collect();
+        let (events_tx, events_rx) = mpsc::channel(SUBSCRIBE_BUFFER);
+        for node in self.cluster.nodes.values() {
+            let (changes_tx, changes_rx) = channel::unbounded();
+            tokio::spawn(forward_changes(changes_rx, keys.clone(), events_tx.clone()));
+            let add_replica_cmd = HashMapCmd::AddReplica {
+                replica: changes_tx,
+            };
+            node.tx.send(add_replica_cmd, SendMode::Wait).await?;
+        }
+        Ok(Response::new(ReceiverStream::new(events_rx)))
+    }
+}
+
+/// Turn the writes a node forwards to its replicas into events. Dropping `rx` once the subscriber
+/// is gone lets the node prune it like any other vanished replica.
+async fn forward_changes(
+    mut rx: CacheReceiver<HashMapCmd<Bytes, Bytes>>,
+    keys: HashSet<Bytes>,
+    events_tx: mpsc::Sender<Result<Event, Status>>,
+) {
+    while let Some(cmd) = rx.recv().await {
+        let events: Vec<Event> = match cmd {
+            HashMapCmd::Sync { hm, .. } => hm
+                .into_iter()
+                .map(|(key, val)| Event::set(key, val.val))
+                .collect(),
+            HashMapCmd::Put { key, val, .. } => vec![Event::set(key, val.val)],
+            HashMapCmd::Delta { puts, deletes, .. } => puts
+                .into_iter()
+                .map(|(key, val)| Event::set(key, val.val))
+                .chain(deletes.into_iter().map(Event::removed))
+                .collect(),
+            HashMapCmd::Delete { keys, .. } => keys.into_iter().map(Event::removed).collect(),
+            _ => continue,
+        };
+        for event in events {
+            if !keys.is_empty() && !keys.contains(&event.key) {
+                continue;
+            }
+            if events_tx.send(Ok(event)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl Event {
+    fn set(key: Bytes, val: Bytes) -> Self {
+        Self {
+            key,
+            val: Some(val),
+        }
+    }
+
+    fn removed(key: Bytes) -> Self {
+        Self { key, val: None }
+    }
+}
+
+impl From<TokioActorCacheError> for Status {
+    fn from(err: TokioActorCacheError) -> Self {
+        let code = match err {
+            TokioActorCacheError::ChannelFull { .. } | TokioActorCacheError::Shed => {
+                Code::ResourceExhausted
+            }
+            TokioActorCacheError::ActorGone | TokioActorCacheError::NodeNotExists { .. } => {
+                Code::Unavailable
+            }
+            TokioActorCacheError::Timeout { .. } => Code::DeadlineExceeded,
+            TokioActorCacheError::ReadOnlyReplica => Code::FailedPrecondition,
+            TokioActorCacheError::TransactionAborted { .. } => Code::Aborted,
+            TokioActorCacheError::Config(_) => Code::InvalidArgument,
+            _ => Code::Internal,
+        };
+        Status::new(code, err.to_string())
+    }
+}
@@ -0,0 +1,223 @@
+//! Tracking which cluster nodes still answer.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, PoisonError, RwLock, Weak};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinSet;
+use tokio::time;
+
+use crate::tokio_cache::channel::{CacheSender, SendMode};
+use crate::tokio_cache::error::TokioActorCacheError;
+
+/// How many pings in a row a node may leave unanswered before it is marked down, so a node that
+/// is merely slow to answer once, e.g. behind a full channel, stays up. One whose actor is gone
+/// is marked down right away.
+const MAX_MISSED_PINGS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeStatus {
+    Up,
+    /// The node's actor is gone or didn't answer the last few pings in time.
+    Down,
+}
+
+/// The ids of the nodes marked down by their pings, shared by every clone of a cluster.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NodeHealth {
+    down: Arc<RwLock<HashSet<u64>>>,
+}
+
+impl NodeHealth {
+    pub(crate) fn is_down(&self, id: u64) -> bool {
+        self.down
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&id)
+    }
+
+    pub(crate) fn status(&self, ids: impl Iterator<Item = u64>) -> HashMap<u64, NodeStatus> {
+        let down = self.down.read().unwrap_or_else(PoisonError::into_inner);
+        ids.map(|id| {
+            let status = if down.contains(&id) {
+                NodeStatus::Down
+            } else {
+                NodeStatus::Up
+            };
+            (id, status)
+        })
+        .collect()
+    }
+
+    /// Node `id` of `nodes`, or while it is down the first of its `standbys` still running, and
+    /// `ActorGone` without one. Writes a standby takes meanwhile stay with it: they are left
+    /// behind, and shadowed by the node's stale values, should the node come back up.
+    pub(crate) fn route<'a, N: Clone>(
+        &self,
+        id: u64,
+        nodes: &'a HashMap<u64, N>,
+        standbys: &Standbys<N>,
+        is_running: impl Fn(&N) -> bool,
+    ) -> Result<Cow<'a, N>, TokioActorCacheError> {
+        let node = nodes
+            .get(&id)
+            .ok_or(TokioActorCacheError::NodeNotExists { id })?;
+        if !self.is_down(id) {
+            return Ok(Cow::Borrowed(node));
+        }
+        standbys
+            .find(id, is_running)
+            .map(Cow::Owned)
+            .ok_or(TokioActorCacheError::ActorGone)
+    }
+
+    /// Ping every node at once each `every` with the command built by `ping`, until the cluster is
+    /// dropped.
+    pub(crate) fn monitor<T: Send + 'static>(
+        &self,
+        nodes: Vec<(u64, CacheSender<T>)>,
+        every: Duration,
+        ping: fn(oneshot::Sender<bool>) -> T,
+    ) {
+        tokio::spawn(run_monitor(Arc::downgrade(&self.down), nodes, every, ping));
+    }
+}
+
+/// The nodes of the clusters replicating a cluster, by the id of the node each follows, for a node
+/// that is down to be stood in for, and those the cluster itself stands in with for the cluster
+/// it replicates, to withdraw once it stops. Shared by every clone of a cluster.
+#[derive(Debug)]
+pub(crate) struct Standbys<N> {
+    by_node: Arc<RwLock<HashMap<u64, Vec<N>>>>,
+    of_master: Arc<RwLock<Option<Standbys<N>>>>,
+}
+
+impl<N> Default for Standbys<N> {
+    fn default() -> Self {
+        Self {
+            by_node: Arc::default(),
+            of_master: Arc::default(),
+        }
+    }
+}
+
+impl<N> Clone for Standbys<N> {
+    fn clone(&self) -> Self {
+        Self {
+            by_node: self.by_node.clone(),
+            of_master: self.of_master.clone(),
+        }
+    }
+}
+
+impl<N: Clone> Standbys<N> {
+    /// Stand in with `nodes`, by the id of the node each replicates, for those of the cluster
+    /// whose standbys `master` are, and no longer for the cluster this one replicated before.
+    pub(crate) fn follow(
+        &self,
+        master: &Self,
+        nodes: impl IntoIterator<Item = (u64, N)>,
+        same_node: impl Fn(&N, &N) -> bool,
+    ) {
+        let mut of_master = self
+            .of_master
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let nodes = nodes.into_iter().collect::<Vec<(u64, N)>>();
+        if let Some(old) = of_master.take() {
+            old.withdraw(&nodes, &same_node);
+        }
+        let mut by_node = master
+            .by_node
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (id, node) in &nodes {
+            let standbys = by_node.entry(*id).or_default();
+            if !standbys.iter().any(|standby| same_node(standby, node)) {
+                standbys.push(node.clone());
+            }
+        }
+        *of_master = Some(master.clone());
+    }
+
+    /// Stop standing in with `nodes` for the cluster this one replicates.
+    pub(crate) fn unfollow(&self, nodes: &[(u64, N)], same_node: impl Fn(&N, &N) -> bool) {
+        let of_master = self
+            .of_master
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(master) = of_master {
+            master.withdraw(nodes, &same_node);
+        }
+    }
+
+    fn withdraw(&self, nodes: &[(u64, N)], same_node: &impl Fn(&N, &N) -> bool) {
+        let mut by_node = self.by_node.write().unwrap_or_else(PoisonError::into_inner);
+        for (id, node) in nodes {
+            if let Some(standbys) = by_node.get_mut(id) {
+                standbys.retain(|standby| !same_node(standby, node));
+            }
+        }
+    }
+
+    /// The first standby of node `id` that `is_running`.
+    fn find(&self, id: u64, is_running: impl Fn(&N) -> bool) -> Option<N> {
+        let by_node = self.by_node.read().unwrap_or_else(PoisonError::into_inner);
+        by_node
+            .get(&id)?
+            .iter()
+            .find(|node| is_running(node))
+            .cloned()
+    }
+}
+
+async fn run_monitor<T: Send + 'static>(
+    down: Weak<RwLock<HashSet<u64>>>,
+    nodes: Vec<(u64, CacheSender<T>)>,
+    every: Duration,
+    ping: fn(oneshot::Sender<bool>) -> T,
+) {
+    let mut n_missed = HashMap::<u64, u32>::new();
+    let mut ticker = time::interval(every);
+    loop {
+        ticker.tick().await;
+        let mut pings = JoinSet::new();
+        for (id, tx) in &nodes {
+            let (id, tx) = (*id, tx.clone());
+            pings.spawn(async move {
+                let answer = time::timeout(every, tx.request(SendMode::Wait, ping)).await;
+                (id, answer)
+            });
+        }
+        while let Some(joined) = pings.join_next().await {
+            let Ok((id, answer)) = joined else {
+                continue;
+            };
+            let is_down = match answer {
+                Ok(Ok(_)) => {
+                    n_missed.remove(&id);
+                    false
+                }
+                // The actor is gone.
+                Ok(Err(_)) => true,
+                Err(_) => {
+                    let n_missed = n_missed.entry(id).or_default();
+                    *n_missed += 1;
+                    *n_missed >= MAX_MISSED_PINGS
+                }
+            };
+            let Some(down) = down.upgrade() else {
+                return;
+            };
+            let mut down = down.write().unwrap_or_else(PoisonError::into_inner);
+            if is_down {
+                down.insert(id);
+            } else if !n_missed.contains_key(&id) {
+                down.remove(&id);
+            }
+        }
+    }
+}
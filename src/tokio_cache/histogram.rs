@@ -0,0 +1,72 @@
+//! How the remaining TTLs of a cache's entries are spread, to see mass expirations coming.
+
+use std::time::Duration;
+
+/// Buckets of a `TtlHistogram`: one under a second, one per doubling up to 2^22 seconds (about
+/// 48 days), and one for everything longer.
+const BUCKETS: usize = 24;
+
+/// Entries by remaining TTL. Bucket `i` counts the entries with less than `bound(i)` left but at
+/// least `bound(i - 1)`, so a spike in one bucket is a wave of expirations due within its bounds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TtlHistogram {
+    buckets: [usize; BUCKETS],
+    persistent: usize,
+}
+
+impl Default for TtlHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; BUCKETS],
+            persistent: 0,
+        }
+    }
+}
+
+impl TtlHistogram {
+    /// The exclusive upper bound of bucket `i`, `None` for the last one.
+    pub fn bound(i: usize) -> Option<Duration> {
+        (i + 1 < BUCKETS).then(|| Duration::from_secs(1 << i))
+    }
+
+    /// Every bucket with its upper bound, shortest first.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, usize)> + '_ {
+        self.buckets
+            .iter()
+            .enumerate()
+            .map(|(i, &entries)| (Self::bound(i), entries))
+    }
+
+    /// Entries without a TTL.
+    pub fn persistent(&self) -> usize {
+        self.persistent
+    }
+
+    /// Entries with a TTL.
+    pub fn expiring(&self) -> usize {
+        self.buckets.iter().sum()
+    }
+
+    /// Add the entries of `other`, e.g. of another shard.
+    pub fn merge(&mut self, other: &Self) {
+        for (bucket, entries) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += entries;
+        }
+        self.persistent += other.persistent;
+    }
+
+    /// Count an entry with `ttl` left, `None` if it doesn't expire.
+    pub(crate) fn record(&mut self, ttl: Option<Duration>) {
+        let Some(ttl) = ttl else {
+            self.persistent += 1;
+            return;
+        };
+        let secs = ttl.as_secs();
+        let i = if secs == 0 {
+            0
+        } else {
+            (secs.ilog2() as usize + 1).min(BUCKETS - 1)
+        };
+        self.buckets[i] += 1;
+    }
+}
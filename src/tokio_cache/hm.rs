@@ -0,0 +1,3074 @@
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crate::tokio_cache::bytes_cache::ByteBudget;
+use crate::tokio_cache::cancel::{CANCEL_CHECK_EVERY, CancelFlag, InFlight, OpId};
+use crate::tokio_cache::channel::{CacheReceiver, CacheSender, Replies, SendMode, reply};
+use crate::tokio_cache::cmd::{EntriesScan, HashMapCmd, SnapshotAccess, ValueRead, ValueUpdate};
+use crate::tokio_cache::compute::{lowest_ranked, lowest_ranked_sorted};
+use crate::tokio_cache::data_struct::{
+    HashMapSnapshot, NextExpiration, TICK, ValueWithState, instant_at,
+};
+#[cfg(feature = "serde")]
+use crate::tokio_cache::dump::{self, Dumped, JsonLine};
+#[cfg(feature = "encryption")]
+use crate::tokio_cache::encryption::Keyring;
+use crate::tokio_cache::error::{ConfigError, TokioActorCacheError};
+use crate::tokio_cache::histogram::TtlHistogram;
+use crate::tokio_cache::index::{EvictionIndex, RankIndex};
+use crate::tokio_cache::ingestion::IngestionLimit;
+use crate::tokio_cache::invalidation::Subscribers;
+use crate::tokio_cache::jitter::{Rng, TtlJitter};
+use crate::tokio_cache::namespace::NamespaceIndex;
+use crate::tokio_cache::ops::{cache_ops, mode_pairs};
+use crate::tokio_cache::option::{
+    CacheConfig, DroppedReceiver, ExpirationPolicy, InsertEntry, InsertOptions, InsertPolicy,
+    ReplicaWrites, ShedPolicy, TtlOnWrite,
+};
+use crate::tokio_cache::pipeline::{HashMapPipeline, HashMapReply};
+use crate::tokio_cache::registry::CacheUsage;
+use crate::tokio_cache::removal::{Eviction, Removal, RemovalListeners, RemovalReason};
+use crate::tokio_cache::replication::{
+    ChangeLog, Digest, LocalWrites, Replicas, ReplicationCheck, ReplicationInfo, SyncStats,
+    diverged_keys, next_cache_id,
+};
+use crate::tokio_cache::runtime::{Runtime, TokioRuntime};
+use crate::tokio_cache::snapshot::{VersionedSnapshot, Versions};
+use crate::tokio_cache::trace;
+use crate::tokio_cache::txn::{Prepared, PreparedTxns};
+use crate::tokio_cache::warmup::{BULK_LOAD_CHUNK, WARMUP_BATCH, Warmup};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tokio_stream::{Stream, StreamExt};
+
+#[derive(Debug, Clone)]
+pub struct HashMapCache<K, V, C> {
+    pub(crate) tx: CacheSender<HashMapCmd<K, V>>,
+    // Priority lane for point reads, see `with_read_lane`.
+    reads: Option<CacheSender<HashMapCmd<K, V>>>,
+    channel: PhantomData<C>,
+    id: u64,
+    replicating: Arc<AtomicBool>,
+    replica_writes: ReplicaWrites,
+    // Shared with the actor, see `with_forward_writes`.
+    forward_writes: Arc<AtomicBool>,
+    // Shared with the actor, see `with_hard_capacity`; `usize::MAX` without one.
+    hard_capacity: Arc<AtomicUsize>,
+    // Shared with the actor, see `with_cleanup_budget`; `usize::MAX` without one.
+    cleanup_budget: Arc<AtomicUsize>,
+    // Shared with the actor, see `with_ingestion_limit`.
+    ingestion: IngestionLimit,
+    // Shared with the actor, see `with_dropped_receiver`.
+    replies: Replies,
+    in_flight: InFlight,
+    ttl_jitter: TtlJitter,
+    warmup: Warmup,
+}
+
+impl<K, V, C> HashMapCache<K, V, C>
+where
+    K: Clone,
+    V: Clone,
+{
+    cache_ops! {
+        HashMapCmd;
+
+        tx fn replica_count / try_replica_count() -> usize => ReplicaCount;
+        tx fn is_replica / try_is_replica() -> bool => IsReplica;
+        tx fn replication_info / try_replication_info() -> ReplicationInfo => ReplicationInfo;
+        /// Read-your-writes for replicas: while `enabled`, `get` and `mget` of keys written to the
+        /// replica directly are answered by the master until the master writes them again, and
+        /// every read is until the master's full state has arrived after `replicate`. Writes
+        /// through the master handle are seen by later replica reads once `insert_sync`, or any
+        /// write that replies, has returned, as long as the replica has no read lane.
+        tx fn read_your_writes / try_read_your_writes(enabled: bool) => ReadYourWrites { enabled };
+        read fn ttl / try_ttl(keys: &[K]) -> Vec<Option<Duration>> => Ttl { keys: keys.to_vec() };
+        /// How many writes the cache has applied, the version the next `snapshot` would have.
+        tx fn version / try_version() -> u64 => Version;
+        /// The `n` most used keys with their call counts, most used first. Reading them doesn't
+        /// count as a use.
+        tx fn hot_keys / try_hot_keys(n: usize) -> Vec<(K, u64)> => HotKeys { n };
+        /// The `n` keys unused for longest with how long they have been idle, idlest first.
+        tx fn idle_keys / try_idle_keys(n: usize) -> Vec<(K, Duration)> => IdleKeys { n };
+        /// How the remaining TTLs of the entries are spread, to see mass expirations coming.
+        tx fn ttl_histogram / try_ttl_histogram() -> TtlHistogram => TtlHistogram;
+        /// How many entries expire within `within` from now, e.g. to forecast the load a wave of
+        /// expirations will put on the backend.
+        tx fn expiring_within / try_expiring_within(within: Duration) -> usize => ExpiringWithin {
+            within,
+        };
+        write fn clear / try_clear() => Clear;
+        /// Resolves once the actor has handled every command queued before it, e.g. to wait for
+        /// a batch of fire-and-forget writes without acknowledging each of them.
+        tx fn barrier / try_barrier() -> () => Barrier;
+        /// How many entries the cache holds and roughly how much memory they take.
+        tx fn usage / try_usage() -> CacheUsage => Usage;
+        /// Keep `keys` cached until `unpin`, however the expiration policy ranks them and whatever
+        /// their TTL, though `remove` and `clear` still drop them. An overwrite keeps an entry
+        /// pinned. Returns, for each key, whether it is cached and so pinned.
+        write fn pin / try_pin(keys: &[K]) -> Vec<bool> => Pin {
+            keys: keys.to_vec(),
+            pinned: true,
+        };
+        /// Let `keys` be evicted and expire again; a TTL that ran out meanwhile expires them right
+        /// away. Returns, for each key, whether it is cached.
+        write fn unpin / try_unpin(keys: &[K]) -> Vec<bool> => Pin {
+            keys: keys.to_vec(),
+            pinned: false,
+        };
+        tx fn policy / try_policy() -> ExpirationPolicy => Policy;
+        /// Evict by `policy` from now on, keeping the entries cached. Entries over a smaller
+        /// capacity are evicted right away, as far as the cleanup budget allows.
+        tx fn set_policy / try_set_policy(policy: ExpirationPolicy) -> () => SetPolicy { policy };
+        /// Change the capacity of the expiration policy but not how it evicts, e.g. to give memory
+        /// back under pressure. Returns whether there was a capacity to change, which
+        /// `ExpirationPolicy::None` hasn't.
+        tx fn set_capacity / try_set_capacity(capacity: usize) -> bool => SetCapacity {
+            capacity,
+        };
+        /// Evict entries by the expiration policy, least recently used first without one, until at
+        /// most `target` are left, then hand the memory they took back. Returns how many were
+        /// evicted.
+        write fn shrink / try_shrink(target: usize) -> usize => Shrink { target };
+        write fn remove / try_remove(keys: &[K]) -> Vec<Option<V>> => Remove {
+            keys: keys.to_vec(),
+        };
+        read fn contains_key / try_contains_key(keys: &[K]) -> Vec<bool> => ContainsKey {
+            keys: keys.to_vec(),
+        };
+        read fn mget / try_mget(keys: &[K]) -> Vec<Option<V>> => MGet { keys: keys.to_vec() };
+        /// Insert every entry in one command, each with its own TTL and policy.
+        write fn minsert_entries / try_minsert_entries(entries: &[InsertEntry<K, V>]) => MInsert {
+            entries: entries.to_vec(),
+        };
+        read fn get / try_get(key: K) -> Option<V> => Get { key };
+        /// The value of `key` with its version, which goes up with every new value, for
+        /// `insert_if_version`.
+        read fn get_versioned / try_get_versioned(key: K) -> Option<(V, u64)> => GetVersioned {
+            key,
+        };
+        /// Let `key` live for `ex` from now, or for good with `None`, like Redis `EXPIRE` and
+        /// `PERSIST`. Returns whether `key` was cached.
+        write fn expire / try_expire(key: K, ex: Option<Duration>) -> bool => Expire { key, ex };
+        /// Let `key` live until the wall clock reads `at`, like Redis `EXPIREAT`, or expire it
+        /// right away if it already has. Returns whether `key` was cached.
+        write fn expire_at / try_expire_at(key: K, at: SystemTime) -> bool => ExpireAt { key, at };
+        write fn insert / try_insert(
+            key: K,
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) => Insert { key, val, ex, policy };
+    }
+
+    mode_pairs! {
+        /// Insert with Redis `SET`-style `options`. Returns the previous value when `options.get`
+        /// is set, `None` otherwise.
+        fn set / try_set(key: K, val: V, options: InsertOptions)
+            -> Result<Option<V>, TokioActorCacheError> => set_with;
+        /// Insert `val` only if `key` is still at `expected_version` from `get_versioned`, or
+        /// isn't cached with `0`, for optimistic concurrency without locking the key. A TTL is
+        /// kept as `with_ttl_on_write` says for a write without one. Returns the new version,
+        /// `None` when the entry has moved on.
+        fn insert_if_version / try_insert_if_version(key: K, val: V, expected_version: u64)
+            -> Result<Option<u64>, TokioActorCacheError> => insert_if_version_with;
+        /// Like `insert`, but resolves once the actor has applied the write rather than once it
+        /// is queued, so reads sent afterwards see it, even over the read lane. Fails with
+        /// `CapacityExceeded` when the write is rejected, see `with_hard_capacity`.
+        fn insert_sync / try_insert_sync(
+            key: K,
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) -> Result<(), TokioActorCacheError> => insert_sync_with;
+    }
+
+    /// Like `get`, but the read can be called off by `cancel` with the returned id, or by dropping
+    /// the future, e.g. along with the request that asked for it. A cancelled read resolves with
+    /// `TokioActorCacheError::Cancelled` once the actor gets to it.
+    pub fn get_cancellable(
+        &self,
+        key: K,
+    ) -> (
+        OpId,
+        impl Future<Output = Result<Option<V>, TokioActorCacheError>> + use<K, V, C>,
+    ) {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.cancellable(HashMapCmd::Get { key, resp_tx }, resp_rx)
+    }
+
+    /// Like `mget`, cancellable like `get_cancellable`. The actor stops gathering the values as
+    /// soon as it sees the cancellation, even partway through the keys.
+    pub fn mget_cancellable(
+        &self,
+        keys: &[K],
+    ) -> (
+        OpId,
+        impl Future<Output = Result<Vec<Option<V>>, TokioActorCacheError>> + use<K, V, C>,
+    ) {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let keys = keys.to_vec();
+        self.cancellable(HashMapCmd::MGet { keys, resp_tx }, resp_rx)
+    }
+
+    /// Call off the operation `op` of any handle of this cache. Returns whether it was still in
+    /// flight.
+    pub fn cancel(&self, op: OpId) -> bool {
+        self.in_flight.cancel(op)
+    }
+
+    fn cancellable<R>(
+        &self,
+        cmd: HashMapCmd<K, V>,
+        resp_rx: oneshot::Receiver<R>,
+    ) -> (
+        OpId,
+        impl Future<Output = Result<R, TokioActorCacheError>> + use<K, V, C, R>,
+    ) {
+        let tx = self.reads().clone();
+        let (op, cancel, guard) = self.in_flight.start();
+        let cmd = HashMapCmd::Cancellable {
+            cancel: cancel.clone(),
+            cmd: Box::new(cmd),
+        };
+        let fut = async move {
+            // Cancels the command if this future is dropped before it is answered.
+            let _guard = guard;
+            tx.send(cmd, SendMode::Wait).await?;
+            tx.wait_reply(resp_rx).await.map_err(|err| match err {
+                TokioActorCacheError::ActorGone | TokioActorCacheError::Shed if cancel.is_set() => {
+                    TokioActorCacheError::Cancelled
+                }
+                err => err,
+            })
+        };
+        (op, fut)
+    }
+
+    pub async fn try_stop_replicating(&self) -> Result<(), TokioActorCacheError> {
+        self.stop_replicating_with(SendMode::Try).await
+    }
+
+    pub async fn try_replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
+        self.replicate_with(master, SendMode::Try).await
+    }
+
+    pub async fn try_promote_to_master(&self) -> Result<(), TokioActorCacheError> {
+        self.promote_to_master_with(SendMode::Try).await
+    }
+
+    pub async fn try_failover(old: &Self, new: &Self) -> Result<usize, TokioActorCacheError> {
+        Self::failover_with(old, new, SendMode::Try).await
+    }
+
+    /// Make `replica` follow this cache; a master fans its writes out to all of its replicas.
+    pub async fn try_add_replica(&self, replica: &Self) -> Result<(), TokioActorCacheError> {
+        replica.replicate_with(self, SendMode::Try).await
+    }
+
+    pub async fn try_removals(
+        &self,
+    ) -> Result<mpsc::UnboundedReceiver<Removal<K, V>>, TokioActorCacheError> {
+        self.removals_with(SendMode::Try).await
+    }
+
+    pub async fn try_minsert(
+        &self,
+        keys: &[K],
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        let entries = InsertEntry::zip(keys, vals, ex, policy)?;
+        self.try_minsert_entries(&entries).await
+    }
+
+    pub async fn try_update_value<F>(&self, key: K, f: F) -> Result<bool, TokioActorCacheError>
+    where
+        F: FnOnce(&mut V) + Send + 'static,
+    {
+        self.check_writable()?;
+        let update = ValueUpdate(Box::new(f));
+        self.tx
+            .request(SendMode::Try, |resp_tx| HashMapCmd::Update {
+                key,
+                update,
+                resp_tx,
+            })
+            .await
+    }
+
+    pub async fn try_get_borrowed<Q>(&self, key: &Q) -> Result<Option<V>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.get_borrowed_with(key, SendMode::Try).await
+    }
+
+    pub async fn try_contains_key_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<bool>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.contains_key_borrowed_with(keys, SendMode::Try).await
+    }
+
+    pub async fn try_remove_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.remove_borrowed_with(keys, SendMode::Try).await
+    }
+
+    pub async fn stop_replicating(&self) -> Result<(), TokioActorCacheError> {
+        self.stop_replicating_with(SendMode::Wait).await
+    }
+
+    pub async fn replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
+        self.replicate_with(master, SendMode::Wait).await
+    }
+
+    /// Stop replicating and take writes again, even if the old master is gone.
+    pub async fn promote_to_master(&self) -> Result<(), TokioActorCacheError> {
+        self.promote_to_master_with(SendMode::Wait).await
+    }
+
+    /// Promote `new` and re-point the other replicas of `old` to it. Returns how many were moved.
+    pub async fn failover(old: &Self, new: &Self) -> Result<usize, TokioActorCacheError> {
+        Self::failover_with(old, new, SendMode::Wait).await
+    }
+
+    /// Make `replica` follow this cache; a master fans its writes out to all of its replicas.
+    pub async fn add_replica(&self, replica: &Self) -> Result<(), TokioActorCacheError> {
+        replica.replicate_with(self, SendMode::Wait).await
+    }
+
+    /// Report every entry that leaves the cache from now on, with why, e.g. to write evicted
+    /// entries back to a store but not deleted ones. The channel is unbounded so a slow listener
+    /// can't hold up the cache; drop the receiver to stop listening. Replicas report nothing, as
+    /// their master decides what leaves.
+    pub async fn removals(
+        &self,
+    ) -> Result<mpsc::UnboundedReceiver<Removal<K, V>>, TokioActorCacheError> {
+        self.removals_with(SendMode::Wait).await
+    }
+
+    pub async fn minsert(
+        &self,
+        keys: &[K],
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        let entries = InsertEntry::zip(keys, vals, ex, policy)?;
+        self.minsert_entries(&entries).await
+    }
+
+    /// Run `f` on the value of `key` inside the actor, so large values are changed in place
+    /// instead of being cloned out and written back. Returns whether `key` was cached.
+    pub async fn update_value<F>(&self, key: K, f: F) -> Result<bool, TokioActorCacheError>
+    where
+        F: FnOnce(&mut V) + Send + 'static,
+    {
+        self.check_writable()?;
+        let update = ValueUpdate(Box::new(f));
+        self.tx
+            .request(SendMode::Wait, |resp_tx| HashMapCmd::Update {
+                key,
+                update,
+                resp_tx,
+            })
+            .await
+    }
+
+    pub async fn try_read_value<R, F>(
+        &self,
+        key: K,
+        f: F,
+    ) -> Result<Option<R>, TokioActorCacheError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&V) -> R + Send + 'static,
+    {
+        self.read_value_with(key, f, SendMode::Try).await
+    }
+
+    /// Run `f` on the value of `key` inside the actor and return what it gives back, so a part of
+    /// a large value is read without cloning the rest. Counts as a use of `key`.
+    pub async fn read_value<R, F>(&self, key: K, f: F) -> Result<Option<R>, TokioActorCacheError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&V) -> R + Send + 'static,
+    {
+        self.read_value_with(key, f, SendMode::Wait).await
+    }
+
+    /// The value of `key`, or on a miss what `load` gives, cached for `ex`. A hit is loaded
+    /// again ahead of its expiry with a chance that rises as the expiry nears and with how long
+    /// the last load took, XFetch style, so a hot key is refreshed by one caller rather than
+    /// missed by all of them at once. Errors of `load` are returned and not cached.
+    pub async fn get_with_refresh<F, Fut, E>(
+        &self,
+        key: K,
+        ex: Option<Duration>,
+        load: F,
+    ) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+        E: From<TokioActorCacheError>,
+    {
+        let cached = self
+            .reads()
+            .request(SendMode::Wait, |resp_tx| HashMapCmd::GetForRefresh {
+                key: key.clone(),
+                resp_tx,
+            })
+            .await?;
+        if let Some((val, false)) = cached {
+            return Ok(val);
+        }
+        self.check_writable()?;
+        let started = Instant::now();
+        let val = load().await?;
+        let cost = started.elapsed();
+        self.tx
+            .send(
+                HashMapCmd::Refresh {
+                    key,
+                    val: val.clone(),
+                    ex,
+                    cost,
+                },
+                SendMode::Wait,
+            )
+            .await?;
+        Ok(val)
+    }
+
+    async fn insert_sync_with(
+        &self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let options = InsertOptions {
+            ex,
+            exat: None,
+            tti: None,
+            policy,
+            get: false,
+        };
+        self.set_with(key, val, options, mode).await?;
+        Ok(())
+    }
+
+    async fn set_with(
+        &self,
+        key: K,
+        val: V,
+        options: InsertOptions,
+        mode: SendMode,
+    ) -> Result<Option<V>, TokioActorCacheError> {
+        self.check_writable()?;
+        self.tx
+            .request(mode, |resp_tx| HashMapCmd::Set {
+                key,
+                val,
+                options,
+                resp_tx,
+            })
+            .await?
+    }
+
+    async fn insert_if_version_with(
+        &self,
+        key: K,
+        val: V,
+        expected_version: u64,
+        mode: SendMode,
+    ) -> Result<Option<u64>, TokioActorCacheError> {
+        self.check_writable()?;
+        self.tx
+            .request(mode, |resp_tx| HashMapCmd::InsertIfVersion {
+                key,
+                val,
+                expected_version,
+                resp_tx,
+            })
+            .await?
+    }
+
+    pub(crate) async fn read_value_with<R, F>(
+        &self,
+        key: K,
+        f: F,
+        mode: SendMode,
+    ) -> Result<Option<R>, TokioActorCacheError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&V) -> R + Send + 'static,
+    {
+        self.reads()
+            .request(mode, |resp_tx| HashMapCmd::Read {
+                key,
+                read: ValueRead(Box::new(move |val| reply(resp_tx, val.map(f)))),
+            })
+            .await
+    }
+
+    /// `get` by a borrowed form of the key, e.g. `&str` for a `String`-keyed cache. The key is
+    /// turned into a `K` once, as it crosses to the actor, rather than by the caller ahead of the
+    /// call; the actor then serves it exactly as it does `get`.
+    pub async fn get_borrowed<Q>(&self, key: &Q) -> Result<Option<V>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.get_borrowed_with(key, SendMode::Wait).await
+    }
+
+    /// `contains_key` by borrowed forms of the keys, see `get_borrowed`.
+    pub async fn contains_key_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<bool>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.contains_key_borrowed_with(keys, SendMode::Wait).await
+    }
+
+    /// `remove` by borrowed forms of the keys, see `get_borrowed`.
+    pub async fn remove_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.remove_borrowed_with(keys, SendMode::Wait).await
+    }
+
+    async fn get_borrowed_with<Q>(
+        &self,
+        key: &Q,
+        mode: SendMode,
+    ) -> Result<Option<V>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.reads()
+            .request(mode, |resp_tx| HashMapCmd::Get {
+                key: key.to_owned(),
+                resp_tx,
+            })
+            .await
+    }
+
+    async fn contains_key_borrowed_with<Q>(
+        &self,
+        keys: &[&Q],
+        mode: SendMode,
+    ) -> Result<Vec<bool>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.reads()
+            .request(mode, |resp_tx| HashMapCmd::ContainsKey {
+                keys: owned_keys(keys),
+                resp_tx,
+            })
+            .await
+    }
+
+    async fn remove_borrowed_with<Q>(
+        &self,
+        keys: &[&Q],
+        mode: SendMode,
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.check_writable()?;
+        self.tx
+            .request(mode, |resp_tx| HashMapCmd::Remove {
+                keys: owned_keys(keys),
+                resp_tx,
+            })
+            .await
+    }
+
+    /// Insert every entry of `stream` and return how many there were. Entries reach the actor in
+    /// batches, each waiting for room in the channel, so a fast stream can't flood it.
+    pub async fn warm_from<S>(&self, stream: S) -> Result<usize, TokioActorCacheError>
+    where
+        S: Stream<Item = (K, V, Option<Duration>)>,
+    {
+        let _warming = self.warmup.start();
+        let mut stream = pin!(stream);
+        let mut n_entries = 0;
+        let mut batch = Vec::with_capacity(WARMUP_BATCH);
+        while let Some((key, val, ex)) = stream.next().await {
+            batch.push(InsertEntry {
+                key,
+                val,
+                ex,
+                tti: None,
+                policy: InsertPolicy::Always,
+            });
+            if batch.len() == WARMUP_BATCH {
+                n_entries += self.warm_batch(&mut batch).await?;
+            }
+        }
+        if !batch.is_empty() {
+            n_entries += self.warm_batch(&mut batch).await?;
+        }
+        Ok(n_entries)
+    }
+
+    /// Fill the cache with `entries`, e.g. on startup, and return how many there were once all of
+    /// them are stored. Unlike `minsert`, they reach the actor in large chunks, always overwrite,
+    /// aren't reported to removal listeners when they do, and the capacity is enforced once at
+    /// the end rather than after each of them. Counts as a warmup for `ready`.
+    pub async fn bulk_load<I>(&self, entries: I) -> Result<usize, TokioActorCacheError>
+    where
+        I: IntoIterator<Item = (K, V, Option<Duration>)>,
+    {
+        self.check_writable()?;
+        let _warming = self.warmup.start();
+        let mut entries = entries.into_iter().peekable();
+        let mut n_entries = 0;
+        loop {
+            let chunk = entries.by_ref().take(BULK_LOAD_CHUNK).collect::<Vec<_>>();
+            n_entries += chunk.len();
+            if entries.peek().is_none() {
+                self.tx
+                    .request(SendMode::Wait, |resp_tx| HashMapCmd::BulkLoad {
+                        entries: chunk,
+                        resp_tx: Some(resp_tx),
+                    })
+                    .await?;
+                return Ok(n_entries);
+            }
+            let bulk_load_cmd = HashMapCmd::BulkLoad {
+                entries: chunk,
+                resp_tx: None,
+            };
+            self.tx.send(bulk_load_cmd, SendMode::Wait).await?;
+        }
+    }
+
+    /// Wait until no `warm_from` is running on any handle of this cache. Returns at once when
+    /// none was started, so start the warmup before handing out handles that wait on it.
+    pub async fn ready(&self) {
+        self.warmup.ready().await;
+    }
+
+    /// Start a pipeline whose commands reach the actor as a single message.
+    pub fn pipeline(&self) -> HashMapPipeline<'_, K, V, C> {
+        HashMapPipeline::new(self)
+    }
+
+    /// Identifies this cache in `ReplicationInfo::master_id`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Number of commands queued for the actor, e.g. to shed load before `try_*` calls fail
+    /// with `ChannelFull`.
+    pub fn queue_depth(&self) -> usize {
+        let reads = self.reads.as_ref().map_or(0, CacheSender::queue_depth);
+        self.tx.queue_depth() + reads
+    }
+
+    /// Fail requests with `TokioActorCacheError::Timeout` when the actor hasn't answered within `after`.
+    pub fn with_timeout(mut self, after: Duration) -> Self {
+        self.tx.set_timeout(Some(after));
+        if let Some(reads) = &mut self.reads {
+            reads.set_timeout(Some(after));
+        }
+        self
+    }
+
+    /// See `with_read_lane`.
+    pub(crate) fn with_read_lane_channel(
+        mut self,
+        mut reads: CacheSender<HashMapCmd<K, V>>,
+        rx: CacheReceiver<HashMapCmd<K, V>>,
+    ) -> Self {
+        // Reads stay on the main channel if the actor can't be told about the new one.
+        if self.tx.send_now(HashMapCmd::ReadLane { rx }).is_ok() {
+            reads.set_timeout(self.tx.timeout());
+            self.reads = Some(reads);
+        }
+        self
+    }
+
+    pub(crate) fn reads(&self) -> &CacheSender<HashMapCmd<K, V>> {
+        self.reads.as_ref().unwrap_or(&self.tx)
+    }
+
+    /// Choose whether direct writes are accepted while this cache is replicating.
+    pub fn with_replica_writes(mut self, replica_writes: ReplicaWrites) -> Self {
+        self.replica_writes = replica_writes;
+        self
+    }
+
+    /// With `forward`, direct writes to this cache while it is replicating are sent on to its
+    /// master, which applies them and replicates them back, so a single handle reads locally but
+    /// writes to the master, even when `ReplicaWrites::Reject` is set. Replies such as `remove`'s
+    /// come from the master. A write the master can't take right away is applied locally instead.
+    /// Applies to every handle.
+    pub fn with_forward_writes(self, forward: bool) -> Self {
+        self.forward_writes.store(forward, Ordering::Release);
+        self
+    }
+
+    /// Reject writes of new keys once `capacity` entries are cached, with
+    /// `TokioActorCacheError::CapacityExceeded` for `set` and `insert_sync`, while writes without
+    /// a reply, like `insert`, are dropped. The capacity of the expiration policy becomes a soft
+    /// limit: writes no longer evict right away, the next tick evicts down to it. Caches that act
+    /// as quotas use `ExpirationPolicy::None` to never evict. `bulk_load` isn't held to it.
+    /// Applies to every handle.
+    pub fn with_hard_capacity(self, capacity: usize) -> Self {
+        self.hard_capacity.store(capacity, Ordering::Release);
+        self
+    }
+
+    /// Remove at most `max_entries` expired or evicted entries at a time, and look at no more
+    /// deadlines than that for them, so a mass expiration or a capacity shrink doesn't hold up
+    /// commands until it is done. The rest are carried over to
+    /// the next tick and to the commands that follow, each of which removes up to `max_entries`
+    /// more first. Meanwhile `get`, `mget`, `contains_key` and `ttl` already treat the expired ones
+    /// as gone, while other reads may still see them. Applies to every handle.
+    pub fn with_cleanup_budget(self, max_entries: usize) -> Self {
+        self.cleanup_budget
+            .store(max_entries.max(1), Ordering::Release);
+        self
+    }
+
+    /// Handle at most `max_per_tick` commands each tick, about every 100ms, so one caller flooding
+    /// the channel can't push everyone's latency up without bound. Commands past the limit wait
+    /// for the next tick, and each tick the actor sheds what is queued beyond `max_per_tick`, as
+    /// `shed` says. Callers of shed requests get `TokioActorCacheError::Shed`. Applies to every
+    /// handle.
+    pub fn with_ingestion_limit(self, max_per_tick: usize, shed: ShedPolicy) -> Self {
+        self.ingestion.set(max_per_tick, shed);
+        self
+    }
+
+    /// Apply the settings of `config` that apply to a running cache.
+    pub(crate) fn with_config(self, config: &CacheConfig) -> Self {
+        let cache = self.with_ttl_jitter(config.ttl_jitter);
+        match config.ingestion_limit {
+            Some((max_per_tick, shed)) => cache.with_ingestion_limit(max_per_tick, shed),
+            None => cache,
+        }
+    }
+
+    /// How many commands the actor has shed, see `with_ingestion_limit`.
+    pub fn shed_commands(&self) -> u64 {
+        self.ingestion.n_shed()
+    }
+
+    /// Choose what the actor does about callers that stopped waiting for its reply, e.g. count
+    /// them or skip reads nobody waits for anymore. Applies to every handle.
+    pub fn with_dropped_receiver(self, on_dropped: DroppedReceiver) -> Self {
+        self.replies.set_on_dropped(on_dropped);
+        self
+    }
+
+    /// How many callers had stopped waiting by the time the actor answered them, counting reads
+    /// it skipped, since `with_dropped_receiver` asked for them to be counted.
+    pub fn dropped_receivers(&self) -> u64 {
+        self.replies.dropped()
+    }
+
+    /// Spread TTLs by up to `± fraction` of their length, e.g. `0.1` for ±10%, so entries written
+    /// together with the same TTL don't all expire on the same tick.
+    pub fn with_ttl_jitter(self, fraction: f64) -> Self {
+        self.ttl_jitter.set(fraction);
+        self
+    }
+
+    /// Choose what writes to a cached key do to its TTL, e.g. `TtlOnWrite::Extend` to renew
+    /// sessions by their TTL on each write. Applies to `insert`, `set` and `minsert`, from the
+    /// next write on, for every handle.
+    pub fn with_ttl_on_write(self, on_write: TtlOnWrite) -> Self {
+        self.ttl_jitter.set_on_write(on_write);
+        self
+    }
+
+    async fn removals_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<mpsc::UnboundedReceiver<Removal<K, V>>, TokioActorCacheError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.tx
+            .send(HashMapCmd::ListenRemovals { tx }, mode)
+            .await?;
+        Ok(rx)
+    }
+
+    async fn warm_batch(
+        &self,
+        batch: &mut Vec<InsertEntry<K, V>>,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.check_writable()?;
+        let entries = std::mem::replace(batch, Vec::with_capacity(WARMUP_BATCH));
+        let n_entries = entries.len();
+        self.tx
+            .send(HashMapCmd::MInsert { entries }, SendMode::Wait)
+            .await?;
+        Ok(n_entries)
+    }
+
+    pub(crate) fn check_writable(&self) -> Result<(), TokioActorCacheError> {
+        if self.replica_writes == ReplicaWrites::Reject
+            && self.replicating.load(Ordering::Acquire)
+            && !self.forward_writes.load(Ordering::Acquire)
+        {
+            return Err(TokioActorCacheError::ReadOnlyReplica);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn stop_replicating_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let master = self
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::StopReplicating { resp_tx })
+            .await?;
+        self.replicating.store(false, Ordering::Release);
+        if let Some(master) = master {
+            let remove_replica_cmd = HashMapCmd::RemoveReplica {
+                replica: self.tx.clone(),
+            };
+            // A master that is gone has no replica left to drop.
+            match master.send(remove_replica_cmd, mode).await {
+                Ok(()) | Err(TokioActorCacheError::ActorGone) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn promote_to_master_with(&self, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        let master = self
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::StopReplicating { resp_tx })
+            .await?;
+        self.replicating.store(false, Ordering::Release);
+        if let Some(master) = master {
+            let remove_replica_cmd = HashMapCmd::RemoveReplica {
+                replica: self.tx.clone(),
+            };
+            // Best effort: the old master may be gone, which is usually why a replica gets promoted.
+            let _ = master.send(remove_replica_cmd, mode).await;
+        }
+        Ok(())
+    }
+
+    async fn failover_with(
+        old: &Self,
+        new: &Self,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        new.promote_to_master_with(mode).await?;
+        old.tx
+            .request(mode, |resp_tx| HashMapCmd::Failover {
+                new_master: new.tx.clone(),
+                new_master_id: new.id,
+                resp_tx,
+            })
+            .await
+    }
+
+    /// Detach from the current master, then register with `master`, which sends its full state and
+    /// forwards every later write.
+    pub(crate) async fn replicate_with(
+        &self,
+        master: &Self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        // A cache replicating itself would forward its writes back to itself forever.
+        if self.tx.same_channel(&master.tx) {
+            return Ok(());
+        }
+        self.stop_replicating_with(mode).await?;
+        let replicate_cmd = HashMapCmd::Replicate {
+            master: master.tx.clone(),
+            master_id: master.id,
+        };
+        self.tx.send(replicate_cmd, mode).await?;
+        self.replicating.store(true, Ordering::Release);
+        let add_replica_cmd = HashMapCmd::AddReplica {
+            replica: self.tx.clone(),
+        };
+        master.tx.send(add_replica_cmd, mode).await
+    }
+
+    pub(crate) fn spawn(
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<HashMapCmd<K, V>>,
+        rx: CacheReceiver<HashMapCmd<K, V>>,
+    ) -> Result<Self, ConfigError>
+    where
+        K: Debug + Eq + Hash + Send + Sync + 'static,
+        V: Debug + Send + Sync + 'static,
+    {
+        Self::spawn_on(Arc::new(TokioRuntime), expiration_policy, tx, rx)
+    }
+
+    /// Like `spawn`, but runs the actor on `runtime` and ticks by its clock.
+    pub(crate) fn spawn_on(
+        runtime: Arc<dyn Runtime>,
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<HashMapCmd<K, V>>,
+        rx: CacheReceiver<HashMapCmd<K, V>>,
+    ) -> Result<Self, ConfigError>
+    where
+        K: Debug + Eq + Hash + Send + Sync + 'static,
+        V: Debug + Send + Sync + 'static,
+    {
+        expiration_policy.validate()?;
+        let (cache, actor) = Self::start(expiration_policy, tx);
+        runtime.spawn(Box::pin(actor.run(rx, runtime.clone())));
+        Ok(cache)
+    }
+
+    /// Like `spawn`, but runs the actor on the current `LocalSet`, so neither the keys nor the
+    /// values need to be `Send`.
+    pub(crate) fn spawn_local(
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<HashMapCmd<K, V>>,
+        rx: CacheReceiver<HashMapCmd<K, V>>,
+    ) -> Result<Self, ConfigError>
+    where
+        K: Debug + Eq + Hash + 'static,
+        V: Debug + 'static,
+    {
+        expiration_policy.validate()?;
+        let (cache, actor) = Self::start(expiration_policy, tx);
+        tokio::task::spawn_local(actor.run(rx, Arc::new(TokioRuntime)));
+        Ok(cache)
+    }
+
+    /// A handle and the actor it talks to, yet to be spawned.
+    fn start(
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<HashMapCmd<K, V>>,
+    ) -> (Self, HashMapActor<K, V>) {
+        let hm = HashMapSnapshot::with_capacity(expiration_policy.preallocated());
+        let id = next_cache_id();
+        let ttl_jitter = TtlJitter::new(id);
+        let forward_writes = Arc::new(AtomicBool::new(false));
+        let hard_capacity = Arc::new(AtomicUsize::new(usize::MAX));
+        let cleanup_budget = Arc::new(AtomicUsize::new(usize::MAX));
+        let ingestion = IngestionLimit::default();
+        let replies = Replies::default();
+        let actor = HashMapActor {
+            hm,
+            expiration_policy,
+            replica_of: None,
+            sync_stats: SyncStats::default(),
+            changes: ChangeLog::default(),
+            replicas: Replicas::default(),
+            local_writes: LocalWrites::default(),
+            forward_writes: forward_writes.clone(),
+            hard_capacity: hard_capacity.clone(),
+            cleanup_budget: cleanup_budget.clone(),
+            ingestion: ingestion.clone(),
+            n_handled: 0,
+            namespaces: None,
+            byte_budget: None,
+            prepared: PreparedTxns::default(),
+            removals: RemovalListeners::default(),
+            subscribers: Subscribers::default(),
+            versions: Versions::default(),
+            replies: replies.clone(),
+            cancel: None,
+            ttl_jitter: ttl_jitter.clone(),
+            rng: Rng::new(id),
+            expiry_index: RankIndex::default(),
+            expiry_backlog: false,
+            eviction_index: EvictionIndex::default(),
+            entry_version: 0,
+        };
+
+        let cache = Self {
+            tx,
+            reads: None,
+            channel: PhantomData,
+            id,
+            replicating: Arc::new(AtomicBool::new(false)),
+            replica_writes: ReplicaWrites::default(),
+            forward_writes,
+            hard_capacity,
+            cleanup_budget,
+            ingestion,
+            replies,
+            in_flight: InFlight::default(),
+            ttl_jitter,
+            warmup: Warmup::default(),
+        };
+        (cache, actor)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, C> HashMapCache<K, V, C>
+where
+    K: Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// Serialize the live entries with their remaining TTLs and call counts, for `load` to
+    /// bring back, e.g. after a restart or in another process.
+    pub async fn try_dump(&self) -> Result<Vec<u8>, TokioActorCacheError> {
+        self.dump_with(SendMode::Try).await
+    }
+
+    /// Add what `dump` serialized, overwriting entries that are already cached.
+    pub async fn try_load(&self, bytes: &[u8]) -> Result<(), TokioActorCacheError> {
+        self.load_with(bytes, SendMode::Try).await
+    }
+
+    pub async fn dump(&self) -> Result<Vec<u8>, TokioActorCacheError> {
+        self.dump_with(SendMode::Wait).await
+    }
+
+    pub async fn load(&self, bytes: &[u8]) -> Result<(), TokioActorCacheError> {
+        self.load_with(bytes, SendMode::Wait).await
+    }
+
+    /// Write the live entries to `path` as JSON lines, `{"key":..,"val":..,"ttl_ms":..}`, to
+    /// inspect them or seed another cache with `import_json`. Returns how many were written.
+    pub async fn try_export_json(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.export_json_with(path.as_ref(), SendMode::Try).await
+    }
+
+    /// Insert the entries of a JSON lines file as written by `export_json`; `ttl_ms` may be left
+    /// out. Returns how many were inserted.
+    pub async fn try_import_json(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.import_json_with(path.as_ref(), SendMode::Try).await
+    }
+
+    pub async fn export_json(&self, path: impl AsRef<Path>) -> Result<usize, TokioActorCacheError> {
+        self.export_json_with(path.as_ref(), SendMode::Wait).await
+    }
+
+    pub async fn import_json(&self, path: impl AsRef<Path>) -> Result<usize, TokioActorCacheError> {
+        self.import_json_with(path.as_ref(), SendMode::Wait).await
+    }
+
+    pub(crate) async fn dump_with(&self, mode: SendMode) -> Result<Vec<u8>, TokioActorCacheError> {
+        let (expiration_policy, hm) = self
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::Dump { resp_tx })
+            .await?;
+        let now = Instant::now();
+        let entries = hm
+            .into_iter()
+            .filter_map(|(key, val_with_state)| {
+                Dumped::new(
+                    (key, val_with_state.val),
+                    val_with_state.expiration,
+                    val_with_state.call_cnt,
+                    now,
+                )
+            })
+            .collect::<Vec<Dumped<(K, V)>>>();
+        dump::encode(expiration_policy, &entries)
+    }
+
+    pub(crate) async fn load_with(
+        &self,
+        bytes: &[u8],
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        self.check_writable()?;
+        let now = Instant::now();
+        let entries = dump::decode::<(K, V)>(bytes)?
+            .into_iter()
+            .map(|entry| {
+                let expiration = entry.expiration(now);
+                let (key, val) = entry.item;
+                let val_with_state = ValueWithState {
+                    val,
+                    expiration,
+                    call_cnt: entry.call_cnt,
+                    last_accessed: now,
+                    pinned: false,
+                    tti: None,
+                    refresh_cost: Duration::ZERO,
+                    // Set by the actor.
+                    version: 0,
+                };
+                (key, val_with_state)
+            })
+            .collect::<Vec<(K, ValueWithState<V>)>>();
+        self.tx.send(HashMapCmd::Load { entries }, mode).await
+    }
+
+    async fn export_json_with(
+        &self,
+        path: &Path,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        let hm = self
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::Snapshot { resp_tx })
+            .await?;
+        let now = Instant::now();
+        let lines = hm
+            .into_iter()
+            .filter_map(|(key, val_with_state)| {
+                JsonLine::new(key, val_with_state.val, val_with_state.expiration, now)
+            })
+            .collect::<Vec<JsonLine<K, V>>>();
+        let bytes = dump::encode_lines(&lines)?;
+        tokio::fs::write(path, bytes)
+            .await
+            .map_err(|err| TokioActorCacheError::io(path, err))?;
+        Ok(lines.len())
+    }
+
+    async fn import_json_with(
+        &self,
+        path: &Path,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.check_writable()?;
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|err| TokioActorCacheError::io(path, err))?;
+        let entries = dump::decode_lines::<K, V>(&text)?
+            .into_iter()
+            .map(|line| InsertEntry {
+                ex: line.ttl(),
+                tti: None,
+                key: line.key,
+                val: line.val,
+                policy: InsertPolicy::Always,
+            })
+            .collect::<Vec<InsertEntry<K, V>>>();
+        let n_entries = entries.len();
+        self.tx.send(HashMapCmd::MInsert { entries }, mode).await?;
+        Ok(n_entries)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<K, V, C> HashMapCache<K, V, C>
+where
+    K: Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// `dump`, encrypted with the current key of `keyring`.
+    pub async fn try_dump_encrypted(
+        &self,
+        keyring: &Keyring,
+    ) -> Result<Vec<u8>, TokioActorCacheError> {
+        keyring.seal(&self.dump_with(SendMode::Try).await?)
+    }
+
+    /// `load` what `dump_encrypted` wrote with any of the keys of `keyring`.
+    pub async fn try_load_encrypted(
+        &self,
+        bytes: &[u8],
+        keyring: &Keyring,
+    ) -> Result<(), TokioActorCacheError> {
+        self.load_with(&keyring.open(bytes)?, SendMode::Try).await
+    }
+
+    pub async fn dump_encrypted(&self, keyring: &Keyring) -> Result<Vec<u8>, TokioActorCacheError> {
+        keyring.seal(&self.dump_with(SendMode::Wait).await?)
+    }
+
+    pub async fn load_encrypted(
+        &self,
+        bytes: &[u8],
+        keyring: &Keyring,
+    ) -> Result<(), TokioActorCacheError> {
+        self.load_with(&keyring.open(bytes)?, SendMode::Wait).await
+    }
+}
+
+impl<K, V, C> HashMapCache<K, V, C>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Hash + Send + Sync + 'static,
+{
+    mode_pairs! {
+        /// Compare this master's entries with those of `replica`, first by checksums of buckets of
+        /// the key space, then key by key within the buckets that differ, to detect drift between
+        /// syncs. Writes still on their way to the replica show up as divergence, so check while
+        /// writes are quiet, or check again to confirm.
+        fn verify_replication / try_verify_replication(replica: &Self)
+            -> Result<ReplicationCheck<K>, TokioActorCacheError> => verify_replication_with;
+    }
+
+    async fn verify_replication_with(
+        &self,
+        replica: &Self,
+        mode: SendMode,
+    ) -> Result<ReplicationCheck<K>, TokioActorCacheError> {
+        let (master_digest, replica_digest) = tokio::try_join!(
+            self.scan_with(|entries| Digest::of(entries), mode),
+            replica.scan_with(|entries| Digest::of(entries), mode),
+        )?;
+        let buckets = Arc::new(master_digest.diverged_buckets(&replica_digest));
+        let diverged = if buckets.is_empty() {
+            Vec::new()
+        } else {
+            let replica_buckets = buckets.clone();
+            let (master_hashes, replica_hashes) = tokio::try_join!(
+                self.scan_with(move |entries| entry_hashes(entries, &buckets), mode),
+                replica.scan_with(move |entries| entry_hashes(entries, &replica_buckets), mode),
+            )?;
+            diverged_keys(master_hashes, replica_hashes)
+        };
+        Ok(ReplicationCheck {
+            master_entries: master_digest.entries,
+            replica_entries: replica_digest.entries,
+            diverged,
+        })
+    }
+
+    /// Run `scan` over the entries in the actor and return what it computes.
+    async fn scan_with<R, F>(&self, scan: F, mode: SendMode) -> Result<R, TokioActorCacheError>
+    where
+        R: Send + 'static,
+        F: for<'a> FnOnce(&mut dyn Iterator<Item = (&'a K, &'a V)>) -> R + Send + 'static,
+    {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let scan = EntriesScan(Box::new(move |entries| reply(resp_tx, scan(entries))));
+        self.tx.send(HashMapCmd::Scan { scan }, mode).await?;
+        self.tx.wait_reply(resp_rx).await
+    }
+}
+
+impl<K, V, C> HashMapCache<K, V, C>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    mode_pairs! {
+        /// Every live entry, counting as a use of each. The actor shares its entries rather than
+        /// copying them, which happens here instead, out of its way.
+        fn get_all / try_get_all() -> Result<HashMap<K, V>, TokioActorCacheError> => get_all_with;
+    }
+
+    pub async fn try_transaction<F>(
+        &self,
+        build: F,
+    ) -> Result<Vec<HashMapReply<K, V>>, TokioActorCacheError>
+    where
+        F: FnOnce(&mut HashMapPipeline<'_, K, V, C>),
+    {
+        let mut txn = self.pipeline();
+        build(&mut txn);
+        txn.try_execute().await
+    }
+
+    /// Apply the commands `build` queues as one transaction: the actor handles them all in a
+    /// single turn, so no other command to this cache, from any handle or over the read lane,
+    /// lands in between, and reads among them see the writes queued before them. Returns their
+    /// answers in order, like `HashMapPipeline::execute`.
+    pub async fn transaction<F>(
+        &self,
+        build: F,
+    ) -> Result<Vec<HashMapReply<K, V>>, TokioActorCacheError>
+    where
+        F: FnOnce(&mut HashMapPipeline<'_, K, V, C>),
+    {
+        let mut txn = self.pipeline();
+        build(&mut txn);
+        txn.execute().await
+    }
+
+    pub(crate) async fn get_all_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<HashMap<K, V>, TokioActorCacheError> {
+        let hm = self
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::GetAll { resp_tx })
+            .await?;
+        Ok(hm.into_vals())
+    }
+}
+
+impl<K, V, C> HashMapCache<K, V, C>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    mode_pairs! {
+        /// Every live entry, shared with the actor and other readers rather than copied for each
+        /// call: the actor copies its entries again only after a write. Meant for code that
+        /// iterates over the whole cache; taking it doesn't count as a use of the entries.
+        fn snapshot / try_snapshot() -> Result<VersionedSnapshot<K, V>, TokioActorCacheError>
+            => snapshot_with;
+    }
+
+    async fn snapshot_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<VersionedSnapshot<K, V>, TokioActorCacheError> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let access = SnapshotAccess::<K, V>(Box::new(move |versions, hm| {
+            let snapshot = versions.snapshot(|| {
+                hm.iter()
+                    .map(|(key, val_with_state)| (key.clone(), val_with_state.val.clone()))
+                    .collect()
+            });
+            reply(resp_tx, snapshot);
+        }));
+        self.tx
+            .send(HashMapCmd::VersionedSnapshot { access }, mode)
+            .await?;
+        self.tx.wait_reply(resp_rx).await
+    }
+}
+
+/// The entry hash of each key in `buckets`.
+fn entry_hashes<'a, K, V>(
+    entries: &mut dyn Iterator<Item = (&'a K, &'a V)>,
+    buckets: &HashSet<usize>,
+) -> HashMap<K, u64>
+where
+    K: Clone + Eq + Hash + 'a,
+    V: Hash + 'a,
+{
+    entries
+        .filter(|(key, _)| buckets.contains(&Digest::bucket_of(*key)))
+        .map(|(key, val)| (key.clone(), Digest::entry_hash(key, val)))
+        .collect()
+}
+
+/// Field-level operations on caches of hashes, like the Redis `H*` commands. Only the fields
+/// involved travel through the actor, not the whole hash.
+impl<K, F, FV, C> HashMapCache<K, HashMap<F, FV>, C>
+where
+    K: Clone,
+    F: Clone + Eq + Hash + Send + 'static,
+    FV: Clone + Send + 'static,
+{
+    mode_pairs! {
+        /// Set `field` of the hash at `key` to `val`, caching a new hash without a TTL when `key`
+        /// isn't cached. Returns the previous value of `field`.
+        fn hset / try_hset(key: K, field: F, val: FV)
+            -> Result<Option<FV>, TokioActorCacheError> => hset_with;
+        /// The value of `field` in the hash at `key`. Counts as a use of `key`.
+        fn hget / try_hget(key: K, field: F)
+            -> Result<Option<FV>, TokioActorCacheError> => hget_with;
+        /// Remove `fields` from the hash at `key`. Returns how many of them were there; the hash
+        /// stays cached even once empty.
+        fn hdel / try_hdel(key: K, fields: &[F])
+            -> Result<usize, TokioActorCacheError> => hdel_with;
+    }
+
+    pub async fn try_hgetall(
+        &self,
+        key: K,
+    ) -> Result<Option<HashMap<F, FV>>, TokioActorCacheError> {
+        self.try_get(key).await
+    }
+
+    /// The whole hash at `key`, the same as `get`.
+    pub async fn hgetall(&self, key: K) -> Result<Option<HashMap<F, FV>>, TokioActorCacheError> {
+        self.get(key).await
+    }
+
+    async fn hset_with(
+        &self,
+        key: K,
+        field: F,
+        val: FV,
+        mode: SendMode,
+    ) -> Result<Option<FV>, TokioActorCacheError> {
+        self.check_writable()?;
+        self.tx
+            .request(mode, |resp_tx| HashMapCmd::Upsert {
+                key,
+                default: HashMap::new(),
+                update: ValueUpdate(Box::new(move |hash: &mut HashMap<F, FV>| {
+                    reply(resp_tx, hash.insert(field, val))
+                })),
+            })
+            .await
+    }
+
+    async fn hget_with(
+        &self,
+        key: K,
+        field: F,
+        mode: SendMode,
+    ) -> Result<Option<FV>, TokioActorCacheError> {
+        let val = self
+            .read_value_with(key, move |hash| hash.get(&field).cloned(), mode)
+            .await?;
+        Ok(val.flatten())
+    }
+
+    async fn hdel_with(
+        &self,
+        key: K,
+        fields: &[F],
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.check_writable()?;
+        let fields = fields.to_vec();
+        let (n_tx, n_rx) = oneshot::channel();
+        let update = ValueUpdate(Box::new(move |hash: &mut HashMap<F, FV>| {
+            let n_removed = fields
+                .iter()
+                .filter(|field| hash.remove(field).is_some())
+                .count();
+            reply(n_tx, n_removed);
+        }));
+        self.tx
+            .request(mode, |resp_tx| HashMapCmd::Update {
+                key,
+                update,
+                resp_tx,
+            })
+            .await?;
+        // The update is dropped unrun when `key` isn't cached.
+        Ok(n_rx.await.unwrap_or(0))
+    }
+}
+
+/// Negative caching on caches of `Option`s, where `None` records that the source of the values
+/// has nothing for a key, so repeated lookups of keys it doesn't have don't all reach it.
+impl<K, V, C> HashMapCache<K, Option<V>, C>
+where
+    K: Clone,
+    V: Clone,
+{
+    mode_pairs! {
+        /// Record for `ttl` that the source has nothing for `key`.
+        fn insert_negative / try_insert_negative(key: K, ttl: Duration)
+            -> Result<(), TokioActorCacheError> => insert_negative_with;
+    }
+
+    /// The value of `key`, or on a miss what `load` finds, cached for `ex`. When `load` finds
+    /// nothing, that is cached for `negative_ttl` instead, usually shorter, and later calls return
+    /// `None` without loading until it runs out. Errors of `load` are returned and not cached. A
+    /// value written while `load` runs is kept over the loaded one.
+    pub async fn get_or_insert_with<F, Fut, E>(
+        &self,
+        key: K,
+        ex: Option<Duration>,
+        negative_ttl: Duration,
+        load: F,
+    ) -> Result<Option<V>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<V>, E>>,
+        E: From<TokioActorCacheError>,
+    {
+        if let Some(cached) = self.get(key.clone()).await? {
+            return Ok(cached);
+        }
+        let loaded = load().await?;
+        let ex = match loaded {
+            Some(_) => ex,
+            None => Some(negative_ttl),
+        };
+        self.insert(key, loaded.clone(), ex, InsertPolicy::IfAbsent)
+            .await?;
+        Ok(loaded)
+    }
+
+    async fn insert_negative_with(
+        &self,
+        key: K,
+        ttl: Duration,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        match mode {
+            SendMode::Try => {
+                self.try_insert(key, None, Some(ttl), InsertPolicy::Always)
+                    .await
+            }
+            SendMode::Wait => {
+                self.insert(key, None, Some(ttl), InsertPolicy::Always)
+                    .await
+            }
+        }
+    }
+}
+
+struct HashMapActor<K, V> {
+    hm: HashMapSnapshot<K, V>,
+    expiration_policy: ExpirationPolicy,
+    replica_of: Option<CacheSender<HashMapCmd<K, V>>>,
+    sync_stats: SyncStats,
+    changes: ChangeLog<K>,
+    replicas: Replicas<HashMapCmd<K, V>>,
+    local_writes: LocalWrites<K>,
+    forward_writes: Arc<AtomicBool>,
+    hard_capacity: Arc<AtomicUsize>,
+    cleanup_budget: Arc<AtomicUsize>,
+    ingestion: IngestionLimit,
+    // Commands handled since the last tick, see `IngestionLimit`.
+    n_handled: usize,
+    namespaces: Option<Box<dyn NamespaceIndex<K>>>,
+    byte_budget: Option<ByteBudget<V>>,
+    prepared: PreparedTxns<K, V>,
+    removals: RemovalListeners<K, V>,
+    subscribers: Subscribers<K>,
+    versions: Versions,
+    replies: Replies,
+    // The cancellation of the command being handled, if it can be cancelled.
+    cancel: Option<CancelFlag>,
+    ttl_jitter: TtlJitter,
+    rng: Rng,
+    // The unpinned entries with a TTL or time to idle by when they run out.
+    expiry_index: RankIndex<Instant, K>,
+    // Set while expired entries are left over from a pass that ran out of its cleanup budget.
+    expiry_backlog: bool,
+    eviction_index: EvictionIndex<K>,
+    // The last version handed to an entry, see `next_entry_version`.
+    entry_version: u64,
+}
+
+impl<K, V> HashMapActor<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    async fn run(mut self, mut rx: CacheReceiver<HashMapCmd<K, V>>, runtime: Arc<dyn Runtime>) {
+        let mut ticker = runtime.interval(TICK);
+        let mut reads = None;
+        loop {
+            // Past the ingestion limit, commands wait for the next tick.
+            let has_room = self.ingestion.has_room(self.n_handled);
+            tokio::select! {
+                biased;
+
+                // Serve the read lane first.
+                command = recv_read(&mut reads), if reads.is_some() && has_room => {
+                    match command {
+                        Some(cmd) => self.ingest(cmd, &mut reads),
+                        None => reads = None,
+                    }
+                }
+
+                _ = ticker.next() => {
+                    self.tick();
+                    self.n_handled = 0;
+                    self.shed_backlog(&mut rx, &mut reads);
+                }
+
+                // Expire short TTLs without waiting for the next tick. A backlog is left to the
+                // ticks and the commands in between, or it would hold them up after all.
+                _ = self.next_expiration().elapsed(&*runtime),
+                    if self.replica_of.is_none() && !self.expiry_backlog =>
+                {
+                    self.expire_due();
+                }
+
+                // Handle commands.
+                command = rx.recv(), if has_room => {
+                    match command {
+                        Some(cmd) => self.ingest(cmd, &mut reads),
+                        // Every handle is gone.
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle a command taken off the channel, counting it against the ingestion limit.
+    fn ingest(
+        &mut self,
+        cmd: HashMapCmd<K, V>,
+        reads: &mut Option<CacheReceiver<HashMapCmd<K, V>>>,
+    ) {
+        match cmd {
+            HashMapCmd::ReadLane { rx } => *reads = Some(rx),
+            cmd => {
+                self.n_handled += 1;
+                self.handle(cmd);
+            }
+        }
+    }
+
+    /// Shed the commands queued beyond what the ingestion limit lets through this tick, oldest
+    /// first, as the shed policy says. Those the policy keeps are handled right away.
+    fn shed_backlog(
+        &mut self,
+        rx: &mut CacheReceiver<HashMapCmd<K, V>>,
+        reads: &mut Option<CacheReceiver<HashMapCmd<K, V>>>,
+    ) {
+        let n_queued = rx.len() + reads.as_ref().map_or(0, CacheReceiver::len);
+        let mut excess = n_queued.saturating_sub(self.ingestion.max_per_tick());
+        if excess == 0 {
+            return;
+        }
+        let shed_policy = self.ingestion.shed_policy();
+        if shed_policy == ShedPolicy::OldestReads {
+            while excess > 0
+                && let Some(_cmd) = reads.as_mut().and_then(CacheReceiver::try_recv)
+            {
+                self.ingestion.note_shed();
+                excess -= 1;
+            }
+        }
+        while excess > 0
+            && let Some(cmd) = rx.try_recv()
+        {
+            excess -= 1;
+            let shed = match shed_policy {
+                ShedPolicy::OldestReads => cmd.is_read(),
+                ShedPolicy::WritesFirst => cmd.is_write(),
+            };
+            if shed {
+                self.ingestion.note_shed();
+            } else {
+                self.ingest(cmd, reads);
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        // Drop transactions whose commit never came.
+        self.prepared.expire(Instant::now());
+
+        // Drop replicas that are gone and bring new or lagging ones up to date.
+        self.replicas.prune();
+        self.sync_replicas();
+
+        // Replicas follow the expirations and evictions of their master.
+        if self.replica_of.is_some() {
+            return;
+        }
+
+        self.expire_due();
+        self.evict();
+    }
+
+    /// Drop the entries whose TTL has run out, taking only those due off the expiry index and no
+    /// more than the cleanup budget allows, stale ones included. Those left over keep the next
+    /// expiration due and are picked up where this pass stopped. Entries unused for their time to
+    /// idle go the same way.
+    fn expire_due(&mut self) {
+        let now = Instant::now();
+        let budget = self.cleanup_budget.load(Ordering::Acquire);
+        let mut due = HashMap::new();
+        let mut n_examined = 0;
+        while n_examined < budget
+            && self.next_expiration().is_due(now)
+            && let Some((_, key)) = self.expiry_index.pop_lowest()
+        {
+            n_examined += 1;
+            let Some(val_with_state) = self
+                .hm
+                .get(&key)
+                .filter(|val_with_state| !val_with_state.pinned && !due.contains_key(&key))
+            else {
+                continue;
+            };
+            match val_with_state.expired(now) {
+                Some(reason) => {
+                    due.insert(key, reason);
+                }
+                // Used or given a later TTL since it was pushed.
+                None => {
+                    if let Some(deadline) = val_with_state.deadline() {
+                        self.expiry_index.push(deadline, key);
+                    }
+                }
+            }
+        }
+        self.expiry_backlog = self.next_expiration().is_due(now);
+        let (idle_keys, expired_keys) =
+            due.into_iter()
+                .partition::<Vec<(K, RemovalReason)>, _>(|(_, reason)| {
+                    *reason == RemovalReason::IdleTimeout
+                });
+        if !expired_keys.is_empty() {
+            trace::debug_event!(expired = expired_keys.len(), "expired entries");
+            let expired_keys = expired_keys.into_iter().map(|(key, _)| key).collect();
+            self.delete(expired_keys, Some(RemovalReason::TtlElapsed));
+        }
+        if !idle_keys.is_empty() {
+            trace::debug_event!(expired = idle_keys.len(), "expired idle entries");
+            let idle_keys = idle_keys.into_iter().map(|(key, _)| key).collect();
+            self.delete(idle_keys, Some(RemovalReason::IdleTimeout));
+        }
+    }
+
+    /// When the earliest entry on the expiry index runs out.
+    fn next_expiration(&self) -> NextExpiration {
+        NextExpiration::at(self.expiry_index.lowest())
+    }
+
+    /// Note when the entry of `key` runs out, now that it was stored, unpinned or given a new
+    /// TTL.
+    fn index_for_expiry(&mut self, deadline: Instant, key: K) {
+        self.expiry_index.push(deadline, key);
+        if self.expiry_index.is_bloated(self.hm.len()) {
+            self.refill_expiry_index();
+        }
+    }
+
+    /// Put every unpinned entry with a deadline back on the expiry index, dropping what went
+    /// stale.
+    fn refill_expiry_index(&mut self) {
+        self.expiry_index.clear();
+        for (key, val_with_state) in self.hm.iter() {
+            if let Some(deadline) = val_with_state.unpinned_deadline() {
+                self.expiry_index.push(deadline, key.clone());
+            }
+        }
+    }
+
+    /// Invalidate cache according to expiration policy.
+    fn evict(&mut self) {
+        let (capacity, eviction) = match self.expiration_policy {
+            ExpirationPolicy::LFU(capacity) => (capacity, Eviction::Lfu),
+            ExpirationPolicy::LRU(capacity) => (capacity, Eviction::Lru),
+            ExpirationPolicy::None => return,
+        };
+        // Leave what is over the cleanup budget to the next tick or write.
+        let budget = self.cleanup_budget.load(Ordering::Acquire);
+        let capacity = capacity.max(self.hm.len().saturating_sub(budget));
+        self.evict_down_to(capacity, eviction);
+    }
+
+    /// How to pick entries to evict beyond the capacity of the expiration policy, e.g. to shrink.
+    fn eviction(&self) -> Eviction {
+        match self.expiration_policy {
+            ExpirationPolicy::LFU(_) => Eviction::Lfu,
+            ExpirationPolicy::LRU(_) | ExpirationPolicy::None => Eviction::Lru,
+        }
+    }
+
+    /// Evict the entries of the namespace of `key` that `eviction` ranks lowest until it is
+    /// within its quota again.
+    fn evict_over_quota(&mut self, key: &K) {
+        let Some(namespaces) = &self.namespaces else {
+            return;
+        };
+        let n_exceed = namespaces.excess(key);
+        if n_exceed == 0 {
+            return;
+        }
+        let eviction = self.eviction();
+        let in_namespace = self.hm.iter().filter(|(other, val_with_state)| {
+            !val_with_state.pinned && namespaces.same_namespace(other, key)
+        });
+        let evicted_keys = match eviction {
+            Eviction::Lfu => lowest_ranked(
+                in_namespace.map(|(key, val_with_state)| (key, val_with_state.call_cnt)),
+                n_exceed,
+            ),
+            Eviction::Lru => lowest_ranked(
+                in_namespace.map(|(key, val_with_state)| (key, val_with_state.last_accessed)),
+                n_exceed,
+            ),
+        };
+        let evicted_keys = evicted_keys.into_iter().cloned().collect::<Vec<K>>();
+        trace::debug_event!(evicted = evicted_keys.len(), "evicted entries over quota");
+        self.delete(evicted_keys, Some(RemovalReason::Evicted(eviction)));
+    }
+
+    /// Evict the entries `eviction` ranks lowest until the values take no more bytes than the
+    /// byte budget allows.
+    fn evict_over_bytes(&mut self) {
+        let Some(mut n_excess_bytes) = self.byte_budget.as_ref().map(ByteBudget::excess) else {
+            return;
+        };
+        let eviction = self.eviction();
+        let mut evicted_keys = HashSet::new();
+        while n_excess_bytes > 0
+            && let Some(key) = self.pop_evictable(eviction, &evicted_keys)
+        {
+            if let (Some(byte_budget), Some(val_with_state)) =
+                (&self.byte_budget, self.hm.get(&key))
+            {
+                n_excess_bytes =
+                    n_excess_bytes.saturating_sub(byte_budget.weigh(&val_with_state.val));
+            }
+            evicted_keys.insert(key);
+        }
+        if evicted_keys.is_empty() {
+            return;
+        }
+        trace::debug_event!(
+            evicted = evicted_keys.len(),
+            "evicted entries over byte budget"
+        );
+        self.delete(
+            evicted_keys.into_iter().collect(),
+            Some(RemovalReason::Evicted(eviction)),
+        );
+    }
+
+    /// Evict the entries `eviction` ranks lowest until at most `capacity` are left, taking them
+    /// off the eviction index in O(log n) each. Pinned entries are never picked, so more may be
+    /// left. Returns how many were evicted.
+    fn evict_down_to(&mut self, capacity: usize, eviction: Eviction) -> usize {
+        let n_exceed = self.hm.len().saturating_sub(capacity);
+        let mut evicted_keys = HashSet::new();
+        while evicted_keys.len() < n_exceed
+            && let Some(key) = self.pop_evictable(eviction, &evicted_keys)
+        {
+            evicted_keys.insert(key);
+        }
+        if evicted_keys.is_empty() {
+            return 0;
+        }
+        let n_evicted = evicted_keys.len();
+        trace::debug_event!(evicted = n_evicted, "evicted entries over capacity");
+        self.delete(
+            evicted_keys.into_iter().collect(),
+            Some(RemovalReason::Evicted(eviction)),
+        );
+        n_evicted
+    }
+
+    /// Take the unpinned entry `eviction` ranks lowest, other than those already `picked`, off
+    /// the eviction index, filling it first if it isn't kept for `eviction` yet.
+    fn pop_evictable(&mut self, eviction: Eviction, picked: &HashSet<K>) -> Option<K> {
+        if self.eviction_index.keep(eviction) {
+            self.refill_eviction_index();
+        }
+        let hm = &self.hm;
+        self.eviction_index.pop_current(|key| {
+            hm.get(key)
+                .filter(|val_with_state| !val_with_state.pinned && !picked.contains(key))
+                .map(|val_with_state| (val_with_state.call_cnt, val_with_state.last_accessed))
+        })
+    }
+
+    /// Put every unpinned entry back on the eviction index, dropping what went stale.
+    fn refill_eviction_index(&mut self) {
+        self.eviction_index.clear();
+        let unpinned = self
+            .hm
+            .iter()
+            .filter(|(_, val_with_state)| !val_with_state.pinned);
+        for (key, val_with_state) in unpinned {
+            if let Some(rank) = self
+                .eviction_index
+                .rank(val_with_state.call_cnt, val_with_state.last_accessed)
+            {
+                self.eviction_index.push(rank, key.clone());
+            }
+        }
+    }
+
+    /// Note where the unpinned entry of `key` ranks for eviction, now that it was stored or
+    /// unpinned.
+    fn index_for_eviction(&mut self, key: K) {
+        let Some(val_with_state) = self
+            .hm
+            .get(&key)
+            .filter(|val_with_state| !val_with_state.pinned)
+        else {
+            return;
+        };
+        if let Some(rank) = self
+            .eviction_index
+            .rank(val_with_state.call_cnt, val_with_state.last_accessed)
+        {
+            self.eviction_index.push(rank, key);
+        }
+        if self.eviction_index.is_bloated(self.hm.len()) {
+            self.refill_eviction_index();
+        }
+    }
+
+    fn handle(&mut self, cmd: HashMapCmd<K, V>) {
+        trace::command_span!(cmd);
+
+        if self.replies.skip(cmd.is_abandoned()) {
+            return;
+        }
+        // Never serve an entry past its TTL, even when its timer hasn't fired yet.
+        if self.replica_of.is_none() && self.next_expiration().is_due(Instant::now()) {
+            self.expire_due();
+        }
+        if self.replica_of.is_some() {
+            self.note_local_write(&cmd);
+        }
+        let Some(cmd) = self.forward_write(cmd) else {
+            return;
+        };
+
+        match cmd {
+            HashMapCmd::Batch { cmds } => {
+                for cmd in cmds {
+                    self.handle(cmd);
+                }
+            }
+            HashMapCmd::Cancellable { cancel, cmd } => {
+                if cancel.is_set() {
+                    trace::debug_event!("skipped a cancelled command");
+                    return;
+                }
+                self.cancel = Some(cancel);
+                self.handle(*cmd);
+                self.cancel = None;
+            }
+            HashMapCmd::StopReplicating { resp_tx } => {
+                self.sync_stats.stop();
+                self.local_writes.reset();
+                self.replies.send(resp_tx, self.replica_of.take());
+            }
+            HashMapCmd::IsReplica { resp_tx } => {
+                let is_replica = self.replica_of.is_some();
+
+                self.replies.send(resp_tx, is_replica);
+            }
+            HashMapCmd::ReplicationInfo { resp_tx } => {
+                let info = self.sync_stats.info(self.replicas.len());
+
+                self.replies.send(resp_tx, info);
+            }
+            HashMapCmd::Replicate { master, master_id } => {
+                self.replica_of = Some(master);
+                self.sync_stats.start(master_id);
+                self.local_writes.record_all();
+            }
+            HashMapCmd::AddReplica { replica } => {
+                self.replicas.add(replica);
+                self.sync_replicas();
+            }
+            HashMapCmd::RemoveReplica { replica } => {
+                self.replicas.remove(&replica);
+            }
+            HashMapCmd::ReplicaCount { resp_tx } => {
+                self.replies.send(resp_tx, self.replicas.len());
+            }
+            HashMapCmd::Failover {
+                new_master,
+                new_master_id,
+                resp_tx,
+            } => {
+                let mut moved = 0;
+                for replica in self.replicas.drain() {
+                    if replica.same_channel(&new_master) {
+                        continue;
+                    }
+                    let replicate_cmd = HashMapCmd::Replicate {
+                        master: new_master.clone(),
+                        master_id: new_master_id,
+                    };
+                    if replica.send_now(replicate_cmd).is_err() {
+                        continue;
+                    }
+                    if new_master
+                        .send_now(HashMapCmd::AddReplica { replica })
+                        .is_ok()
+                    {
+                        moved += 1;
+                    }
+                }
+
+                self.replies.send(resp_tx, moved);
+            }
+            // Writes forwarded by the master are dropped once replication has stopped.
+            HashMapCmd::Sync { hm, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, hm.len());
+                    self.hm = hm;
+                    self.versions.bump();
+                    self.subscribers.changed_all();
+                    // Filled again from the new entries should this replica ever evict.
+                    self.eviction_index = EvictionIndex::default();
+                    self.refill_expiry_index();
+                    if let Some(namespaces) = &mut self.namespaces {
+                        namespaces.clear();
+                        for key in self.hm.keys() {
+                            namespaces.added(key);
+                        }
+                    }
+                    if let Some(byte_budget) = &mut self.byte_budget {
+                        byte_budget.clear();
+                        for val_with_state in self.hm.values() {
+                            byte_budget.added(&val_with_state.val);
+                        }
+                    }
+                    self.local_writes.reset();
+                    self.changes.reset();
+                    self.replicas
+                        .forward(self.changes.seq(), |sent_at| HashMapCmd::Sync {
+                            sent_at,
+                            hm: self.hm.clone(),
+                        });
+                }
+            }
+            HashMapCmd::Put { key, val, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, 1);
+                    self.local_writes.synced(&key);
+                    self.put(key, val);
+                }
+            }
+            HashMapCmd::Delete { keys, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, keys.len());
+                    for key in &keys {
+                        self.local_writes.synced(key);
+                    }
+                    self.delete(keys, None);
+                }
+            }
+            HashMapCmd::Delta {
+                puts,
+                deletes,
+                sent_at,
+            } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, puts.len() + deletes.len());
+                    for (key, val_with_state) in puts {
+                        self.local_writes.synced(&key);
+                        self.put(key, val_with_state);
+                    }
+                    for key in &deletes {
+                        self.local_writes.synced(key);
+                    }
+                    self.delete(deletes, None);
+                }
+            }
+            #[cfg(feature = "serde")]
+            HashMapCmd::Dump { resp_tx } => {
+                self.replies
+                    .send(resp_tx, (self.expiration_policy, self.hm.clone()));
+            }
+            #[cfg(feature = "serde")]
+            HashMapCmd::Load { entries } => {
+                for (key, mut val_with_state) in entries {
+                    let old = self
+                        .hm
+                        .get(&key)
+                        .map(|val_with_state| val_with_state.version);
+                    val_with_state.version = self.next_entry_version(old);
+                    self.put(key, val_with_state);
+                }
+                if self.replica_of.is_none() {
+                    self.evict();
+                    self.evict_over_bytes();
+                }
+            }
+            HashMapCmd::Snapshot { resp_tx } => {
+                let val = self.hm.clone();
+
+                self.replies.send(resp_tx, val);
+            }
+            HashMapCmd::VersionedSnapshot { access } => {
+                (access.0)(&mut self.versions, &self.hm);
+            }
+            HashMapCmd::Version { resp_tx } => {
+                self.replies.send(resp_tx, self.versions.version());
+            }
+            HashMapCmd::Ttl { keys, resp_tx } => {
+                let ttl = keys
+                    .iter()
+                    .map(|key| {
+                        // Get 'val_with_state' by 'key'.
+                        self.live_mut(key).and_then(|val_with_state| {
+                            // incr 'call_cnt' by 1 and update 'last_accessed'.
+                            val_with_state.call_cnt += 1;
+                            val_with_state.last_accessed = Instant::now();
+
+                            // Get ttl from 'val_with_state'.
+                            val_with_state
+                                .expiration
+                                .and_then(|ex| ex.checked_duration_since(Instant::now()))
+                        })
+                    })
+                    .collect::<Vec<Option<Duration>>>();
+
+                self.replies.send(resp_tx, ttl);
+            }
+            HashMapCmd::GetAll { resp_tx } => {
+                for (_, val_with_state) in self.hm.iter_mut() {
+                    val_with_state.call_cnt += 1;
+                    val_with_state.last_accessed = Instant::now();
+                }
+
+                self.replies.send(resp_tx, self.hm.clone());
+            }
+            HashMapCmd::HotKeys { n, resp_tx } => {
+                let hot_keys = lowest_ranked_sorted(
+                    self.hm
+                        .iter()
+                        .map(|(key, val_with_state)| (key, Reverse(val_with_state.call_cnt))),
+                    n,
+                )
+                .into_iter()
+                .map(|(key, Reverse(call_cnt))| (key.clone(), call_cnt))
+                .collect::<Vec<(K, u64)>>();
+
+                self.replies.send(resp_tx, hot_keys);
+            }
+            HashMapCmd::IdleKeys { n, resp_tx } => {
+                let now = Instant::now();
+                let idle_keys = lowest_ranked_sorted(
+                    self.hm
+                        .iter()
+                        .map(|(key, val_with_state)| (key, val_with_state.last_accessed)),
+                    n,
+                )
+                .into_iter()
+                .map(|(key, last_accessed)| {
+                    (key.clone(), now.saturating_duration_since(last_accessed))
+                })
+                .collect::<Vec<(K, Duration)>>();
+
+                self.replies.send(resp_tx, idle_keys);
+            }
+            HashMapCmd::TtlHistogram { resp_tx } => {
+                let now = Instant::now();
+                let mut histogram = TtlHistogram::default();
+                for val_with_state in self.hm.values() {
+                    histogram.record(
+                        val_with_state
+                            .expiration
+                            .map(|expiration| expiration.saturating_duration_since(now)),
+                    );
+                }
+
+                self.replies.send(resp_tx, histogram);
+            }
+            HashMapCmd::ExpiringWithin { within, resp_tx } => {
+                let deadline = Instant::now() + within;
+                let n_expiring = self
+                    .hm
+                    .values()
+                    .filter(|val_with_state| {
+                        !val_with_state.pinned
+                            && val_with_state
+                                .deadline()
+                                .is_some_and(|expiration| expiration <= deadline)
+                    })
+                    .count();
+
+                self.replies.send(resp_tx, n_expiring);
+            }
+            HashMapCmd::Barrier { resp_tx } => {
+                self.replies.send(resp_tx, ());
+            }
+            HashMapCmd::Usage { resp_tx } => {
+                let usage = CacheUsage::of_table(
+                    self.hm.len(),
+                    self.hm.capacity(),
+                    size_of::<(K, ValueWithState<V>)>(),
+                );
+
+                self.replies.send(resp_tx, usage);
+            }
+            HashMapCmd::Policy { resp_tx } => {
+                self.replies.send(resp_tx, self.expiration_policy);
+            }
+            HashMapCmd::SetPolicy { policy, resp_tx } => {
+                self.expiration_policy = policy;
+                // Replicas follow the evictions of their master.
+                if self.replica_of.is_none() {
+                    self.evict();
+                }
+
+                self.replies.send(resp_tx, ());
+            }
+            HashMapCmd::SetCapacity { capacity, resp_tx } => {
+                let policy = self.expiration_policy.with_capacity(capacity);
+                if let Some(policy) = policy {
+                    self.expiration_policy = policy;
+                    if self.replica_of.is_none() {
+                        self.evict();
+                    }
+                }
+
+                self.replies.send(resp_tx, policy.is_some());
+            }
+            HashMapCmd::Shrink { target, resp_tx } => {
+                let n_evicted = self.evict_down_to(target, self.eviction());
+                self.hm.shrink_to_fit();
+
+                self.replies.send(resp_tx, n_evicted);
+            }
+            HashMapCmd::Clear => {
+                if self.is_listened() {
+                    let removed = self.hm.drain();
+                    for (key, val_with_state) in removed {
+                        self.removals
+                            .notify(key, val_with_state.val, RemovalReason::ManualRemove);
+                    }
+                } else {
+                    self.hm.clear();
+                }
+                self.expiry_index.clear();
+                self.eviction_index.clear();
+                self.versions.bump();
+                self.subscribers.changed_all();
+                if let Some(namespaces) = &mut self.namespaces {
+                    namespaces.clear();
+                }
+                if let Some(byte_budget) = &mut self.byte_budget {
+                    byte_budget.clear();
+                }
+                self.changes.reset();
+                self.replicas
+                    .forward(self.changes.seq(), |sent_at| HashMapCmd::Sync {
+                        sent_at,
+                        hm: HashMapSnapshot::default(),
+                    });
+            }
+            HashMapCmd::Remove { keys, resp_tx } => {
+                let vals = self.remove(keys);
+                self.replies.send(resp_tx, vals);
+            }
+            HashMapCmd::RemoveWhere { filter, resp_tx } => {
+                let keys = self
+                    .hm
+                    .keys()
+                    .filter(|key| (filter.0)(key))
+                    .cloned()
+                    .collect::<Vec<K>>();
+                let n_removed = keys.len();
+                self.delete(keys, Some(RemovalReason::ManualRemove));
+
+                self.replies.send(resp_tx, n_removed);
+            }
+            HashMapCmd::Namespaces { access } => {
+                let is_new = self.namespaces.is_none();
+                (access.0)(&mut self.namespaces);
+                if is_new && let Some(namespaces) = &mut self.namespaces {
+                    for key in self.hm.keys() {
+                        namespaces.added(key);
+                    }
+                }
+            }
+            HashMapCmd::ByteBudget { access } => {
+                let is_new = self.byte_budget.is_none();
+                (access.0)(&mut self.byte_budget);
+                if is_new && let Some(byte_budget) = &mut self.byte_budget {
+                    for val_with_state in self.hm.values() {
+                        byte_budget.added(&val_with_state.val);
+                    }
+                }
+                // Replicas follow the evictions of their master.
+                if self.replica_of.is_none() {
+                    self.evict_over_bytes();
+                }
+            }
+            HashMapCmd::ContainsKey { keys, resp_tx } => {
+                let is_contains_keys = keys
+                    .iter()
+                    .map(|key| {
+                        // Incr 'call_cnt' by 1 and update 'last_accessed'.
+                        match self.live_mut(key) {
+                            Some(val_with_state) => {
+                                val_with_state.call_cnt += 1;
+                                val_with_state.last_accessed = Instant::now();
+                                true
+                            }
+                            None => false,
+                        }
+                    })
+                    .collect::<Vec<bool>>();
+
+                self.replies.send(resp_tx, is_contains_keys);
+            }
+            HashMapCmd::MGet { keys, resp_tx } => {
+                let cmd = HashMapCmd::MGet { keys, resp_tx };
+                let Some(HashMapCmd::MGet { keys, resp_tx }) = self.read_from_master(cmd) else {
+                    return;
+                };
+                let mut vals = Vec::with_capacity(keys.len());
+                for keys in keys.chunks(CANCEL_CHECK_EVERY) {
+                    if self.is_cancelled() {
+                        trace::debug_event!("cancelled a read partway through");
+                        return;
+                    }
+                    vals.extend(keys.iter().map(|key| self.get(key)));
+                }
+
+                self.replies.send(resp_tx, vals);
+            }
+            HashMapCmd::MInsert { entries } => self.minsert(entries),
+            HashMapCmd::Migrate { key, val, resp_tx } => {
+                let res = self.migrate(key, val);
+                self.replies.send(resp_tx, res);
+            }
+            HashMapCmd::PrepareTxn {
+                txn,
+                inserts,
+                removes,
+                resp_tx,
+            } => {
+                let prepared = Prepared::new(inserts, removes);
+                let res = self.check_prepared(&prepared);
+                if res.is_ok() {
+                    self.prepared.insert(txn, prepared);
+                }
+                self.replies.send(resp_tx, res);
+            }
+            HashMapCmd::CommitTxn { txn, resp_tx } => {
+                let res = match self.prepared.take(txn) {
+                    Some(prepared) => {
+                        let vals = self.remove(prepared.removes);
+                        self.minsert(prepared.inserts);
+                        Ok(vals)
+                    }
+                    None => Err(TokioActorCacheError::TransactionAborted {
+                        reason: "its writes were no longer prepared".to_string(),
+                    }),
+                };
+                self.replies.send(resp_tx, res);
+            }
+            HashMapCmd::AbortTxn { txn } => {
+                self.prepared.take(txn);
+            }
+            HashMapCmd::BulkLoad { entries, resp_tx } => {
+                self.hm.reserve(entries.len());
+                let last_accessed = Instant::now();
+                for (key, val, ex) in entries {
+                    let old = self
+                        .hm
+                        .get(&key)
+                        .map(|val_with_state| val_with_state.version);
+                    let val_with_state = ValueWithState {
+                        val,
+                        expiration: self.ttl_jitter.expiration(ex),
+                        call_cnt: 0,
+                        last_accessed,
+                        pinned: false,
+                        tti: None,
+                        refresh_cost: Duration::ZERO,
+                        version: self.next_entry_version(old),
+                    };
+                    self.put(key, val_with_state);
+                }
+                if let Some(resp_tx) = resp_tx {
+                    if self.replica_of.is_none() {
+                        self.evict();
+                        self.evict_over_bytes();
+                    }
+
+                    self.replies.send(resp_tx, ());
+                }
+            }
+            HashMapCmd::Get { key, resp_tx } => {
+                let cmd = HashMapCmd::Get { key, resp_tx };
+                let Some(HashMapCmd::Get { key, resp_tx }) = self.read_from_master(cmd) else {
+                    return;
+                };
+                let val = self.get(&key);
+
+                self.replies.send(resp_tx, val);
+            }
+            HashMapCmd::Insert {
+                key,
+                val,
+                ex,
+                policy,
+            } => {
+                let _ = self.insert(key, val, ex, None, None, policy);
+            }
+            HashMapCmd::GetForRefresh { key, resp_tx } => {
+                let now = Instant::now();
+                // XFetch: refresh once `now` plus the cost of loading, scaled by an exponentially
+                // distributed factor, reaches the expiry, so the odds rise as it nears.
+                let factor = -(1.0 - self.rng.unit()).ln();
+                let res = self.live_mut(&key).map(|val_with_state| {
+                    val_with_state.call_cnt += 1;
+                    val_with_state.last_accessed = now;
+                    let early = val_with_state.refresh_cost.mul_f64(factor);
+                    let refresh = val_with_state
+                        .expiration
+                        .is_some_and(|expiration| now + early >= expiration);
+                    (val_with_state.val.clone(), refresh)
+                });
+
+                self.replies.send(resp_tx, res);
+            }
+            HashMapCmd::Refresh { key, val, ex, cost } => {
+                if self
+                    .insert(key.clone(), val, ex, None, None, InsertPolicy::Always)
+                    .is_ok()
+                    && let Some(val_with_state) = self.hm.get_mut(&key)
+                {
+                    val_with_state.refresh_cost = cost;
+                }
+            }
+            HashMapCmd::GetVersioned { key, resp_tx } => {
+                let val = self.get(&key);
+                let version = self
+                    .hm
+                    .get(&key)
+                    .map(|val_with_state| val_with_state.version);
+
+                self.replies.send(resp_tx, val.zip(version));
+            }
+            HashMapCmd::InsertIfVersion {
+                key,
+                val,
+                expected_version,
+                resp_tx,
+            } => {
+                let version = self
+                    .live(&key)
+                    .map_or(0, |val_with_state| val_with_state.version);
+                let res = if version == expected_version {
+                    self.insert(key, val, None, None, None, InsertPolicy::Always)
+                        .map(|()| Some(self.entry_version))
+                } else {
+                    Ok(None)
+                };
+
+                self.replies.send(resp_tx, res);
+            }
+            HashMapCmd::Set {
+                key,
+                val,
+                options,
+                resp_tx,
+            } => {
+                let old = if options.get { self.get(&key) } else { None };
+                let res = self
+                    .insert(
+                        key,
+                        val,
+                        options.ex,
+                        options.exat,
+                        options.tti,
+                        options.policy,
+                    )
+                    .map(|()| old);
+
+                self.replies.send(resp_tx, res);
+            }
+            HashMapCmd::Update {
+                key,
+                update,
+                resp_tx,
+            } => {
+                let is_updated = self.update(key, update);
+
+                self.replies.send(resp_tx, is_updated);
+            }
+            HashMapCmd::Read { key, read } => {
+                let val = self.hm.get_mut(&key).map(|val_with_state| {
+                    val_with_state.call_cnt += 1;
+                    val_with_state.last_accessed = Instant::now();
+                    &val_with_state.val
+                });
+                (read.0)(val);
+            }
+            HashMapCmd::Upsert {
+                key,
+                default,
+                update,
+            } => {
+                self.upsert(key, default, update);
+            }
+            HashMapCmd::Expire { key, ex, resp_tx } => {
+                let is_expired = self.expire(key, ex.map(|ex| Instant::now() + ex));
+
+                self.replies.send(resp_tx, is_expired);
+            }
+            HashMapCmd::ExpireAt { key, at, resp_tx } => {
+                let is_expired = self.expire(key, Some(instant_at(at)));
+
+                self.replies.send(resp_tx, is_expired);
+            }
+            HashMapCmd::Pin {
+                keys,
+                pinned,
+                resp_tx,
+            } => {
+                let is_cached = keys
+                    .into_iter()
+                    .map(|key| self.pin(key, pinned))
+                    .collect::<Vec<bool>>();
+
+                self.replies.send(resp_tx, is_cached);
+            }
+            HashMapCmd::Scan { scan } => {
+                let mut entries = self
+                    .hm
+                    .iter()
+                    .map(|(key, val_with_state)| (key, &val_with_state.val));
+                (scan.0)(&mut entries);
+            }
+            // Taken over by `run`.
+            HashMapCmd::ReadLane { .. } => (),
+            HashMapCmd::ReadYourWrites { enabled } => {
+                self.local_writes.set_enabled(enabled);
+                if self.replica_of.is_some() {
+                    self.local_writes.record_all();
+                }
+            }
+            HashMapCmd::ListenRemovals { tx } => {
+                self.removals.add(tx);
+            }
+            HashMapCmd::Subscribe { tx } => {
+                self.subscribers.add(tx);
+            }
+        }
+    }
+
+    /// Note the keys a direct write to this replica changes, see `LocalWrites`.
+    fn note_local_write(&mut self, cmd: &HashMapCmd<K, V>) {
+        match cmd {
+            HashMapCmd::Batch { cmds } => {
+                for cmd in cmds {
+                    self.note_local_write(cmd);
+                }
+            }
+            HashMapCmd::Cancellable { cmd, .. } => self.note_local_write(cmd),
+            HashMapCmd::Insert { key, .. }
+            | HashMapCmd::Set { key, .. }
+            | HashMapCmd::Migrate { key, .. }
+            | HashMapCmd::Refresh { key, .. }
+            | HashMapCmd::InsertIfVersion { key, .. }
+            | HashMapCmd::Update { key, .. }
+            | HashMapCmd::Upsert { key, .. }
+            | HashMapCmd::Expire { key, .. }
+            | HashMapCmd::ExpireAt { key, .. } => self.local_writes.record(key),
+            HashMapCmd::Pin { keys, .. } => {
+                for key in keys {
+                    self.local_writes.record(key);
+                }
+            }
+            HashMapCmd::MInsert { entries } => {
+                for entry in entries {
+                    self.local_writes.record(&entry.key);
+                }
+            }
+            HashMapCmd::PrepareTxn {
+                inserts, removes, ..
+            } => {
+                for entry in inserts {
+                    self.local_writes.record(&entry.key);
+                }
+                for key in removes {
+                    self.local_writes.record(key);
+                }
+            }
+            HashMapCmd::BulkLoad { entries, .. } => {
+                for (key, _, _) in entries {
+                    self.local_writes.record(key);
+                }
+            }
+            HashMapCmd::Remove { keys, .. } => {
+                for key in keys {
+                    self.local_writes.record(key);
+                }
+            }
+            #[cfg(feature = "serde")]
+            HashMapCmd::Load { .. } => self.local_writes.record_all(),
+            HashMapCmd::Clear | HashMapCmd::Shrink { .. } | HashMapCmd::RemoveWhere { .. } => {
+                self.local_writes.record_all()
+            }
+            _ => (),
+        }
+    }
+
+    /// Hand a direct write to the master when this replica forwards its writes. Returns the
+    /// command when it is to be applied locally, including when the master can't take it right
+    /// away.
+    fn forward_write(&self, cmd: HashMapCmd<K, V>) -> Option<HashMapCmd<K, V>> {
+        match &self.replica_of {
+            Some(master) if cmd.is_write() && self.forward_writes.load(Ordering::Acquire) => {
+                let cmd = master.hand_over(cmd).err();
+                if cmd.is_some() {
+                    trace::warn_event!("master can't take a forwarded write, applying it locally");
+                }
+                cmd
+            }
+            _ => Some(cmd),
+        }
+    }
+
+    /// Whether the command being handled has been cancelled since the actor picked it up.
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelFlag::is_set)
+    }
+
+    /// Hand a `Get` or `MGet` of keys this replica may be behind on to the master, which answers
+    /// the caller itself. Returns the command when it is to be served locally, including when the
+    /// master can't take it right away.
+    fn read_from_master(&self, cmd: HashMapCmd<K, V>) -> Option<HashMapCmd<K, V>> {
+        let is_behind = match &cmd {
+            HashMapCmd::Get { key, .. } => self.local_writes.covers(key),
+            HashMapCmd::MGet { keys, .. } => keys.iter().any(|key| self.local_writes.covers(key)),
+            _ => false,
+        };
+        match &self.replica_of {
+            Some(master) if is_behind => master.hand_over(cmd).err(),
+            _ => Some(cmd),
+        }
+    }
+
+    fn update(&mut self, key: K, update: ValueUpdate<V>) -> bool {
+        let Some(old) = self
+            .hm
+            .get(&key)
+            .map(|val_with_state| val_with_state.version)
+        else {
+            return false;
+        };
+        let version = self.next_entry_version(Some(old));
+        let Some(val_with_state) = self.hm.get_mut(&key) else {
+            return false;
+        };
+        if let Some(byte_budget) = &mut self.byte_budget {
+            byte_budget.removed(&val_with_state.val);
+        }
+        (update.0)(&mut val_with_state.val);
+        if let Some(byte_budget) = &mut self.byte_budget {
+            byte_budget.added(&val_with_state.val);
+        }
+        val_with_state.version = version;
+        val_with_state.call_cnt += 1;
+        val_with_state.last_accessed = Instant::now();
+
+        // Only clone the value when there are replicas to send it to.
+        if self.replicas.is_empty() {
+            self.versions.bump();
+            self.subscribers.changed(&key);
+        } else {
+            let val_with_state = val_with_state.clone();
+            self.put(key, val_with_state);
+        }
+        if self.replica_of.is_none() {
+            self.evict_over_bytes();
+        }
+        true
+    }
+
+    fn upsert(&mut self, key: K, default: V, update: ValueUpdate<V>) {
+        if self.hm.contains_key(&key) {
+            self.update(key, update);
+            return;
+        }
+        let mut val = default;
+        (update.0)(&mut val);
+        let _ = self.insert(key, val, None, None, None, InsertPolicy::Always);
+    }
+
+    fn expire(&mut self, key: K, expiration: Option<Instant>) -> bool {
+        let Some(val_with_state) = self.hm.get_mut(&key) else {
+            return false;
+        };
+        val_with_state.expiration = expiration;
+
+        if self.replicas.is_empty() {
+            self.versions.bump();
+            // Storing it again below notes the new deadline all the same.
+            if let Some(deadline) = val_with_state.unpinned_deadline() {
+                self.index_for_expiry(deadline, key);
+            }
+        } else {
+            let val_with_state = val_with_state.clone();
+            self.put(key, val_with_state);
+        }
+        true
+    }
+
+    /// The version of a new value of an entry whose last one was `old`: above it and above every
+    /// version handed out by this actor, so an entry's versions only go up, even when it was
+    /// removed in between or this replica has taken over from its master.
+    fn next_entry_version(&mut self, old: Option<u64>) -> u64 {
+        self.entry_version = self.entry_version.max(old.unwrap_or(0)) + 1;
+        self.entry_version
+    }
+
+    /// Pin or unpin `key`. Returns whether it is cached.
+    fn pin(&mut self, key: K, pinned: bool) -> bool {
+        if self.live(&key).is_none() {
+            return false;
+        }
+        let Some(val_with_state) = self.hm.get_mut(&key) else {
+            return false;
+        };
+        if val_with_state.pinned == pinned {
+            return true;
+        }
+        val_with_state.pinned = pinned;
+
+        if self.replicas.is_empty() {
+            self.versions.bump();
+            // Storing it again below notes it as expiring and evictable all the same. A TTL that
+            // ran out while pinned runs out on the next sweep.
+            if let Some(deadline) = val_with_state.unpinned_deadline() {
+                self.index_for_expiry(deadline, key.clone());
+            }
+            if !pinned && self.eviction_index.is_kept() {
+                self.index_for_eviction(key);
+            }
+        } else {
+            let val_with_state = val_with_state.clone();
+            self.put(key, val_with_state);
+        }
+        true
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.live_mut(key).map(|val_with_state| {
+            val_with_state.call_cnt += 1;
+            val_with_state.last_accessed = Instant::now();
+            val_with_state.val.clone()
+        })
+    }
+
+    /// The entry of `key`, `None` once its TTL or time to idle has run out even if its removal
+    /// was carried over, see `with_cleanup_budget`.
+    fn live(&self, key: &K) -> Option<&ValueWithState<V>> {
+        self.hm.get(key).filter(|val_with_state| {
+            !self.expiry_backlog
+                || val_with_state.pinned
+                || val_with_state.expired(Instant::now()).is_none()
+        })
+    }
+
+    fn live_mut(&mut self, key: &K) -> Option<&mut ValueWithState<V>> {
+        self.live(key)?;
+        self.hm.get_mut(key)
+    }
+
+    /// Store `val` under `key` as `policy` allows. Fails when `key` is new and the hard capacity
+    /// is reached.
+    fn insert(
+        &mut self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        exat: Option<SystemTime>,
+        tti: Option<Duration>,
+        policy: InsertPolicy,
+    ) -> Result<(), TokioActorCacheError> {
+        let last_accessed = Instant::now();
+
+        // An overwrite keeps the entry pinned, and how long loading it takes.
+        let existing = self.live(&key).map(|val_with_state| {
+            (
+                val_with_state.expiration,
+                val_with_state.call_cnt,
+                val_with_state.refresh_cost,
+                val_with_state.pinned,
+            )
+        });
+        let old_version = self
+            .hm
+            .get(&key)
+            .map(|val_with_state| val_with_state.version);
+        if !policy.allows(existing.is_some()) {
+            return Ok(());
+        }
+        let hard_capacity = self.hard_capacity.load(Ordering::Acquire);
+        if existing.is_none() && self.hm.len() >= hard_capacity {
+            return Err(TokioActorCacheError::CapacityExceeded {
+                capacity: hard_capacity,
+            });
+        }
+        let expiration = match exat {
+            Some(at) => Some(instant_at(at)),
+            None => self
+                .ttl_jitter
+                .expiration_on_write(ex, existing.map(|(expiration, ..)| expiration)),
+        };
+        let call_cnt = existing.map_or(0, |(_, call_cnt, ..)| call_cnt + 1);
+        let val_with_state = ValueWithState {
+            val,
+            expiration,
+            call_cnt,
+            last_accessed,
+            pinned: existing.is_some_and(|(.., pinned)| pinned),
+            version: self.next_entry_version(old_version),
+            tti,
+            refresh_cost: existing.map_or(Duration::ZERO, |(.., refresh_cost, _)| refresh_cost),
+        };
+        let replaced_key = self.is_listened().then(|| key.clone());
+        let quota_key = self.namespaces.is_some().then(|| key.clone());
+        if let Some(old) = self.put(key, val_with_state)
+            && let Some(key) = replaced_key
+        {
+            // Without a live entry, what was replaced is one whose expiry was carried over.
+            let reason = match existing {
+                Some(_) => RemovalReason::Replaced,
+                None => old
+                    .expired(last_accessed)
+                    .unwrap_or(RemovalReason::TtlElapsed),
+            };
+            self.removals.notify(key, old.val, reason);
+        }
+
+        // Enforce the capacity right away rather than at the next tick, so a write burst can't
+        // take the cache over it, unless it is a soft limit under a hard one. Replicas follow the
+        // evictions of their master.
+        if self.replica_of.is_none() && hard_capacity == usize::MAX {
+            self.evict();
+        }
+        if self.replica_of.is_none()
+            && let Some(key) = quota_key
+        {
+            self.evict_over_quota(&key);
+        }
+        if self.replica_of.is_none() {
+            self.evict_over_bytes();
+        }
+        Ok(())
+    }
+
+    /// Send lagging replicas what changed since they fell behind, or the full state when the
+    /// change log no longer reaches back that far.
+    fn sync_replicas(&mut self) {
+        let seq = self.changes.seq();
+        self.replicas.sync(seq, |synced_seq, sent_at| {
+            match synced_seq.and_then(|synced_seq| self.changes.since(synced_seq)) {
+                Some(changed) => {
+                    let mut puts = Vec::new();
+                    let mut deletes = Vec::new();
+                    for key in changed {
+                        match self.hm.get(&key) {
+                            Some(val_with_state) => puts.push((key, val_with_state.clone())),
+                            None => deletes.push(key),
+                        }
+                    }
+                    HashMapCmd::Delta {
+                        sent_at,
+                        puts,
+                        deletes,
+                    }
+                }
+                None => HashMapCmd::Sync {
+                    sent_at,
+                    hm: self.hm.clone(),
+                },
+            }
+        });
+    }
+
+    /// Whether removals need reporting. Replicas report nothing, their master does.
+    fn is_listened(&self) -> bool {
+        self.replica_of.is_none() && !self.removals.is_empty()
+    }
+
+    /// Store `val_with_state` and forward it to the replicas. Returns the entry it replaced.
+    fn put(&mut self, key: K, val_with_state: ValueWithState<V>) -> Option<ValueWithState<V>> {
+        if !self.replicas.is_empty() {
+            self.changes.record(key.clone());
+        }
+        self.replicas
+            .forward(self.changes.seq(), |sent_at| HashMapCmd::Put {
+                sent_at,
+                key: key.clone(),
+                val: val_with_state.clone(),
+            });
+        let expiring = val_with_state
+            .unpinned_deadline()
+            .map(|deadline| (deadline, key.clone()));
+        if let Some(namespaces) = &mut self.namespaces
+            && !self.hm.contains_key(&key)
+        {
+            namespaces.added(&key);
+        }
+        if let Some(byte_budget) = &mut self.byte_budget {
+            byte_budget.added(&val_with_state.val);
+        }
+        self.versions.bump();
+        self.subscribers.changed(&key);
+        let indexed_key = self.eviction_index.is_kept().then(|| key.clone());
+        let replaced = self.hm.insert(key, val_with_state);
+        if let (Some(byte_budget), Some(replaced)) = (&mut self.byte_budget, &replaced) {
+            byte_budget.removed(&replaced.val);
+        }
+        if let Some((deadline, key)) = expiring {
+            self.index_for_expiry(deadline, key);
+        }
+        if let Some(key) = indexed_key {
+            self.index_for_eviction(key);
+        }
+        replaced
+    }
+
+    /// Remove `key` without telling anyone but the namespace index, the version and the
+    /// subscribers.
+    fn take(&mut self, key: &K) -> Option<ValueWithState<V>> {
+        let removed = self.hm.remove(key);
+        if let Some(val_with_state) = &removed {
+            self.versions.bump();
+            self.subscribers.changed(key);
+            if let Some(namespaces) = &mut self.namespaces {
+                namespaces.removed(key);
+            }
+            if let Some(byte_budget) = &mut self.byte_budget {
+                byte_budget.removed(&val_with_state.val);
+            }
+        }
+        removed
+    }
+
+    /// Remove `keys` as asked by a caller. Returns their values.
+    fn remove(&mut self, keys: Vec<K>) -> Vec<Option<V>> {
+        let vals = keys
+            .iter()
+            .map(|key| self.take(key).map(|val_with_state| val_with_state.val))
+            .collect::<Vec<Option<V>>>();
+        if self.is_listened() {
+            for (key, val) in keys.iter().zip(&vals) {
+                if let Some(val) = val {
+                    self.removals
+                        .notify(key.clone(), val.clone(), RemovalReason::ManualRemove);
+                }
+            }
+        }
+        // Already removed, this only forwards the removal to the replicas.
+        self.delete(keys, None);
+        vals
+    }
+
+    fn minsert(&mut self, entries: Vec<InsertEntry<K, V>>) {
+        // Writes without a reply that are over the hard capacity are dropped.
+        for entry in entries {
+            let _ = self.insert(
+                entry.key,
+                entry.val,
+                entry.ex,
+                None,
+                entry.tti,
+                entry.policy,
+            );
+        }
+    }
+
+    /// Store an entry moved from another node as it was there, pinned, counted and versioned,
+    /// unless a live one was written here since.
+    fn migrate(&mut self, key: K, val: ValueWithState<V>) -> Result<(), TokioActorCacheError> {
+        if self.live(&key).is_some() {
+            return Ok(());
+        }
+        let hard_capacity = self.hard_capacity.load(Ordering::Acquire);
+        if self.hm.len() >= hard_capacity {
+            return Err(TokioActorCacheError::CapacityExceeded {
+                capacity: hard_capacity,
+            });
+        }
+        // Later writes still get a newer version than the one moved here.
+        self.entry_version = self.entry_version.max(val.version);
+        let replaced_key = self.is_listened().then(|| key.clone());
+        if let Some(old) = self.put(key, val)
+            && let Some(key) = replaced_key
+        {
+            self.removals
+                .notify(key, old.val, RemovalReason::TtlElapsed);
+        }
+        if self.replica_of.is_none() {
+            self.evict();
+            self.evict_over_bytes();
+        }
+        Ok(())
+    }
+
+    /// Whether the writes of `prepared` can be committed as things stand: no other transaction
+    /// holds their keys, and the keys they add fit under the hard capacity.
+    fn check_prepared(&self, prepared: &Prepared<K, V>) -> Result<(), TokioActorCacheError> {
+        if self.prepared.conflicts(prepared) {
+            return Err(TokioActorCacheError::TransactionAborted {
+                reason: "another transaction holds some of its keys".to_string(),
+            });
+        }
+        let hard_capacity = self.hard_capacity.load(Ordering::Acquire);
+        let n_added = prepared
+            .inserts
+            .iter()
+            .filter(|entry| self.live(&entry.key).is_none())
+            .count();
+        let n_removed = prepared
+            .removes
+            .iter()
+            .filter(|key| self.live(key).is_some())
+            .count();
+        if (self.hm.len() + n_added).saturating_sub(n_removed) > hard_capacity {
+            return Err(TokioActorCacheError::CapacityExceeded {
+                capacity: hard_capacity,
+            });
+        }
+        Ok(())
+    }
+
+    /// Remove `keys`, reporting them to removal listeners with `reason` if there is one, and have
+    /// the replicas do the same.
+    fn delete(&mut self, keys: Vec<K>, reason: Option<RemovalReason>) {
+        let reason = reason.filter(|_| self.is_listened());
+        for key in &keys {
+            let removed = self.take(key);
+            if let (Some(val_with_state), Some(reason)) = (removed, reason) {
+                self.removals
+                    .notify(key.clone(), val_with_state.val, reason);
+            }
+            if !self.replicas.is_empty() {
+                self.changes.record(key.clone());
+            }
+        }
+        self.replicas
+            .forward(self.changes.seq(), |sent_at| HashMapCmd::Delete {
+                sent_at,
+                keys: keys.clone(),
+            });
+    }
+}
+
+/// Keys cross the channel to the actor, so borrowed keys are turned into owned ones once here.
+pub(crate) fn owned_keys<K, Q>(keys: &[&Q]) -> Vec<K>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + ToOwned<Owned = K>,
+{
+    keys.iter().map(|key| (*key).to_owned()).collect()
+}
+
+async fn recv_read<T>(reads: &mut Option<CacheReceiver<T>>) -> Option<T> {
+    match reads {
+        Some(rx) => rx.recv().await,
+        None => None,
+    }
+}
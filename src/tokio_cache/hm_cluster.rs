@@ -0,0 +1,648 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, de::DeserializeOwned};
+
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+use crate::tokio_cache::channel::SendMode;
+use crate::tokio_cache::cmd::HashMapCmd;
+#[cfg(feature = "serde")]
+use crate::tokio_cache::dump::{ClusterManifest, NodeSnapshot, RestoreRouter, SnapshotHeader};
+#[cfg(feature = "encryption")]
+use crate::tokio_cache::encryption::Keyring;
+#[cfg(feature = "serde")]
+use crate::tokio_cache::error::ConfigError;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::health::{NodeHealth, NodeStatus, Standbys};
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::ops::{cluster_ops, mode_pairs};
+#[cfg(feature = "serde")]
+use crate::tokio_cache::option::ExpirationPolicy;
+use crate::tokio_cache::option::{InsertEntry, InsertPolicy};
+use crate::tokio_cache::rebalance::RebalanceProgress;
+use crate::tokio_cache::router::{HashRouter, KeyRouter, WeightedRouter};
+use crate::tokio_cache::txn::next_txn_id;
+
+#[derive(Debug, Clone)]
+pub struct HashMapCacheCluster<K, V, C, R = HashRouter> {
+    pub nodes: HashMap<u64, HashMapCache<K, V, C>>,
+    pub(crate) router: R,
+    pub(crate) health: NodeHealth,
+    pub(crate) standbys: Standbys<HashMapCache<K, V, C>>,
+}
+
+impl<K, V, C, R> HashMapCacheCluster<K, V, C, R>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    C: Clone,
+    R: KeyRouter<K>,
+{
+    mode_pairs! {
+        /// The remaining time to live of each of `keys`, paired with it; `None` for entries without
+        /// one or that don't exist.
+        fn ttl / try_ttl(keys: &[K]) -> Result<Vec<(K, Option<Duration>)>, TokioActorCacheError>
+            => ttl_with;
+        fn get_all / try_get_all() -> Result<HashMap<K, V>, TokioActorCacheError> => get_all_with;
+        /// Like `mget`, but asks the nodes concurrently and fails only the keys of a node that
+        /// errors. Results are in the order of `keys`.
+        fn mget_ordered / try_mget_ordered(
+            keys: &[K],
+        ) -> Vec<Result<Option<V>, TokioActorCacheError>> => mget_ordered_with;
+        fn minsert / try_minsert(
+            keys: &[K],
+            vals: &[V],
+            ex: &[Option<Duration>],
+            policy: &[InsertPolicy],
+        ) -> Result<(), TokioActorCacheError> => minsert_with;
+        /// Insert every entry, sending one command per node.
+        fn minsert_entries / try_minsert_entries(
+            entries: &[InsertEntry<K, V>],
+        ) -> Result<(), TokioActorCacheError> => minsert_entries_with;
+        /// Insert `inserts` and remove `removes` together across the nodes, e.g. to move a value
+        /// from a key on one node to a key on another, by two-phase commit: every node involved
+        /// first stages its writes, and only once all have are they applied, each node's in one
+        /// go. If a node can't stage them, because it is down, another transaction holds some of
+        /// the keys, or they would go over its hard capacity, none are applied and the error is
+        /// returned. Best effort: a node failing between the two phases still leaves the others
+        /// applied. Writes outside transactions aren't held back by them. Returns the removed
+        /// values in the order of `removes`.
+        fn transact / try_transact(
+            inserts: &[InsertEntry<K, V>],
+            removes: &[K],
+        ) -> Result<Vec<Option<V>>, TokioActorCacheError> => transact_with;
+        /// Make every node replicate the node with the same id in `master`, which must have as many
+        /// nodes as this cluster so that keys route to matching nodes. Until `stop_replicating`,
+        /// `master` sends what routes to a node its health check has down to the node here.
+        fn replicate / try_replicate(master: &Self) -> Result<(), TokioActorCacheError>
+            => replicate_with;
+        fn stop_replicating / try_stop_replicating() -> Result<(), TokioActorCacheError>
+            => stop_replicating_with;
+    }
+
+    cluster_ops! {
+        HashMapCmd;
+
+        routed fn get / try_get(key: K) -> Option<V> => Get { key };
+        routed fn insert / try_insert(
+            key: K,
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) => Insert { key, val, ex, policy };
+        each fn remove / try_remove(keys: &[K]) -> Vec<Option<V>> => Remove { keys };
+        each fn contains_key / try_contains_key(keys: &[K]) -> Vec<bool> => ContainsKey { keys };
+        each fn mget / try_mget(keys: &[K]) -> Vec<Option<V>> => MGet { keys };
+        every fn clear / try_clear() => Clear;
+        /// `barrier` on every node.
+        every fn barrier / try_barrier() -> () => Barrier;
+    }
+
+    /// Move every entry that sits on a node other than the one its key routes to now, e.g. after
+    /// `with_router`. Runs in a background task whose progress is published on the returned
+    /// channel.
+    pub fn rebalance(&self) -> watch::Receiver<RebalanceProgress>
+    where
+        C: Clone + Send + Sync + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        let (progress_tx, progress_rx) = watch::channel(RebalanceProgress::default());
+        let cluster = self.clone();
+        tokio::spawn(async move {
+            let res = cluster.rebalance_with(&progress_tx).await;
+            progress_tx.send_modify(|progress| progress.finished = Some(res));
+        });
+        progress_rx
+    }
+
+    /// Apply `with_timeout` to every node of the cluster.
+    pub fn with_timeout(mut self, after: Duration) -> Self {
+        for node in self.nodes.values_mut() {
+            node.tx.set_timeout(Some(after));
+        }
+        self
+    }
+
+    /// Ping every node each `every`, and until a node that stops answering does again, send its
+    /// keys to the matching node of a cluster replicating this one, see `replicate`, rather
+    /// than wait on it. Without one they fail fast with `ActorGone`, and reads across every node
+    /// skip it.
+    pub fn with_health_check(self, every: Duration) -> Self {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(id, node)| (*id, node.tx.clone()))
+            .collect();
+        self.health
+            .monitor(nodes, every, |resp_tx| HashMapCmd::IsReplica { resp_tx });
+        self
+    }
+
+    /// The status of every node as of its last health check; all nodes are `Up` without
+    /// `with_health_check`.
+    pub fn cluster_status(&self) -> HashMap<u64, NodeStatus> {
+        self.health.status(self.nodes.keys().copied())
+    }
+
+    async fn replicate_with(
+        &self,
+        master: &Self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        if master.nodes.len() != self.nodes.len() {
+            return Err(TokioActorCacheError::ClusterSizeMismatch {
+                expected: master.nodes.len(),
+                got: self.nodes.len(),
+            });
+        }
+        for (id, node) in &self.nodes {
+            let master_node = master
+                .nodes
+                .get(id)
+                .ok_or(TokioActorCacheError::NodeNotExists { id: *id })?;
+            node.replicate_with(master_node, mode).await?;
+        }
+        self.standbys
+            .follow(&master.standbys, self.paired_nodes(), Self::same_node);
+
+        Ok(())
+    }
+
+    /// Every node by its id, which is also that of the node it replicates.
+    fn paired_nodes(&self) -> Vec<(u64, HashMapCache<K, V, C>)> {
+        self.nodes
+            .iter()
+            .map(|(id, node)| (*id, node.clone()))
+            .collect()
+    }
+
+    fn same_node(a: &HashMapCache<K, V, C>, b: &HashMapCache<K, V, C>) -> bool {
+        a.tx.same_channel(&b.tx)
+    }
+
+    async fn rebalance_with(
+        &self,
+        progress: &watch::Sender<RebalanceProgress>,
+    ) -> Result<(), TokioActorCacheError> {
+        // Find every misplaced entry first so that `remaining` is known before anything moves.
+        let mut misplaced = Vec::new();
+        for (id, node) in &self.nodes {
+            if self.health.is_down(*id) {
+                continue;
+            }
+            let hm = node
+                .tx
+                .request(SendMode::Wait, |resp_tx| HashMapCmd::Snapshot { resp_tx })
+                .await?;
+            misplaced.extend(
+                hm.into_iter()
+                    .filter(|(key, _)| self.node_id(key) != *id)
+                    .map(|(key, val)| (*id, key, val)),
+            );
+        }
+        progress.send_modify(|progress| progress.remaining = misplaced.len());
+
+        for (id, key, val) in misplaced {
+            let target = self.get_node(&key)?;
+            // Only drop the entry from where it was once the new owner holds it, which keeps a
+            // value written to it since the scan.
+            let moved = key.clone();
+            target
+                .tx
+                .request(SendMode::Wait, |resp_tx| HashMapCmd::Migrate {
+                    key: moved,
+                    val,
+                    resp_tx,
+                })
+                .await??;
+            let source = self
+                .nodes
+                .get(&id)
+                .ok_or(TokioActorCacheError::NodeNotExists { id })?;
+            source
+                .tx
+                .request(SendMode::Wait, |resp_tx| HashMapCmd::Remove {
+                    keys: vec![key],
+                    resp_tx,
+                })
+                .await?;
+            progress.send_modify(RebalanceProgress::record_move);
+        }
+
+        Ok(())
+    }
+
+    async fn stop_replicating_with(&self, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        for node in self.nodes.values() {
+            node.stop_replicating_with(mode).await?;
+        }
+        self.standbys
+            .unfollow(&self.paired_nodes(), Self::same_node);
+
+        Ok(())
+    }
+
+    async fn ttl_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<(K, Option<Duration>)>, TokioActorCacheError> {
+        let mut res = Vec::new();
+        for key in keys.iter().cloned() {
+            let node = self.get_node(&key)?;
+            let ttl = node
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::Ttl {
+                    keys: vec![key.clone()],
+                    resp_tx,
+                })
+                .await?;
+            // Pair each answer with its input rather than relying on the node's answer length.
+            res.push((key, ttl.into_iter().next().flatten()));
+        }
+
+        Ok(res)
+    }
+
+    async fn get_all_with(&self, mode: SendMode) -> Result<HashMap<K, V>, TokioActorCacheError> {
+        let mut res = HashMap::new();
+        for node in self.live_nodes() {
+            let hm = node
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::GetAll { resp_tx })
+                .await?;
+            res.extend(hm.into_vals());
+        }
+
+        Ok(res)
+    }
+
+    async fn mget_ordered_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Vec<Result<Option<V>, TokioActorCacheError>> {
+        // Keys whose request task never reports back keep this error.
+        let mut res = vec![Err(TokioActorCacheError::ActorGone); keys.len()];
+
+        // One request per node, remembering where each answer goes.
+        let mut batches = HashMap::<u64, (Vec<usize>, Vec<K>)>::new();
+        for (i, key) in keys.iter().enumerate() {
+            let batch = batches.entry(self.node_id(key)).or_default();
+            batch.0.push(i);
+            batch.1.push(key.clone());
+        }
+        let mut requests = JoinSet::new();
+        for (id, (indices, keys)) in batches {
+            let node = match self.node(id) {
+                Ok(node) => node,
+                Err(e) => {
+                    for i in indices {
+                        res[i] = Err(e.clone());
+                    }
+                    continue;
+                }
+            };
+            let tx = node.tx.clone();
+            requests.spawn(async move {
+                let vals = tx
+                    .request(mode, |resp_tx| HashMapCmd::MGet { keys, resp_tx })
+                    .await;
+                (indices, vals)
+            });
+        }
+
+        while let Some(joined) = requests.join_next().await {
+            let Ok((indices, vals)) = joined else {
+                continue;
+            };
+            match vals {
+                Ok(vals) => {
+                    for (i, val) in indices.into_iter().zip(vals) {
+                        res[i] = Ok(val);
+                    }
+                }
+                Err(e) => {
+                    for i in indices {
+                        res[i] = Err(e.clone());
+                    }
+                }
+            }
+        }
+
+        res
+    }
+
+    async fn minsert_with(
+        &self,
+        keys: &[K],
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let entries = InsertEntry::zip(keys, vals, ex, policy)?;
+        self.minsert_entries_with(&entries, mode).await
+    }
+
+    async fn minsert_entries_with(
+        &self,
+        entries: &[InsertEntry<K, V>],
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        // One command per node.
+        let mut batches = HashMap::<u64, Vec<_>>::new();
+        for entry in entries {
+            let batch = batches.entry(self.node_id(&entry.key)).or_default();
+            batch.push(entry.clone());
+        }
+        for (id, entries) in batches {
+            let node = self.node(id)?;
+            node.tx.send(HashMapCmd::MInsert { entries }, mode).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn transact_with(
+        &self,
+        inserts: &[InsertEntry<K, V>],
+        removes: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        // The writes to each node, and where its removed values go.
+        let mut parts = HashMap::<u64, (Vec<InsertEntry<K, V>>, Vec<usize>, Vec<K>)>::new();
+        for entry in inserts {
+            let part = parts.entry(self.node_id(&entry.key)).or_default();
+            part.0.push(entry.clone());
+        }
+        for (i, key) in removes.iter().enumerate() {
+            let part = parts.entry(self.node_id(key)).or_default();
+            part.1.push(i);
+            part.2.push(key.clone());
+        }
+        let parts = parts
+            .into_iter()
+            .map(|(id, part)| {
+                let node = self.node(id)?;
+                node.check_writable()?;
+                Ok((node, part))
+            })
+            .collect::<Result<Vec<_>, TokioActorCacheError>>()?;
+
+        let txn = next_txn_id();
+        for (n_prepared, (node, (inserts, _, removes))) in parts.iter().enumerate() {
+            let prepared = node
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::PrepareTxn {
+                    txn,
+                    inserts: inserts.clone(),
+                    removes: removes.clone(),
+                    resp_tx,
+                })
+                .await;
+            if let Err(err) = prepared.and_then(|prepared| prepared) {
+                // Nodes that miss the abort drop their writes once `PREPARED_FOR` is up.
+                for (node, _) in &parts[..n_prepared] {
+                    let _ = node.tx.send(HashMapCmd::AbortTxn { txn }, mode).await;
+                }
+                return Err(err);
+            }
+        }
+
+        let mut res = vec![None; removes.len()];
+        for (node, (_, indices, _)) in parts {
+            let vals = node
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::CommitTxn { txn, resp_tx })
+                .await??;
+            for (i, val) in indices.into_iter().zip(vals) {
+                res[i] = val;
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Every node, with a standby in place of each the health check has down, and without
+    /// those that have none.
+    fn live_nodes(&self) -> impl Iterator<Item = Cow<'_, HashMapCache<K, V, C>>> {
+        self.nodes.keys().filter_map(|id| self.node(*id).ok())
+    }
+
+    fn node_id(&self, key: &K) -> u64 {
+        let n_node = self.nodes.len() as u64;
+        self.router.route(key, n_node)
+    }
+
+    fn get_node(&self, key: &K) -> Result<Cow<'_, HashMapCache<K, V, C>>, TokioActorCacheError> {
+        self.node(self.node_id(key))
+    }
+
+    /// Node `id`, or while the health check has it down a standby, see `NodeHealth::route`.
+    fn node(&self, id: u64) -> Result<Cow<'_, HashMapCache<K, V, C>>, TokioActorCacheError> {
+        self.health
+            .route(id, &self.nodes, &self.standbys, |node| !node.tx.is_closed())
+    }
+}
+
+impl<K, V, C, S> HashMapCacheCluster<K, V, C, WeightedRouter<S>>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    C: Clone + Send + Sync + 'static,
+    S: BuildHasher + Clone + Send + Sync + 'static,
+{
+    /// Give node `id` `weight` shares of the keys, e.g. in proportion to its capacity, and
+    /// `rebalance` the entries that now route elsewhere, which only move to or from that node.
+    pub fn set_node_weight(
+        &self,
+        id: u64,
+        weight: u32,
+    ) -> Result<watch::Receiver<RebalanceProgress>, TokioActorCacheError> {
+        if !self.nodes.contains_key(&id) {
+            return Err(TokioActorCacheError::NodeNotExists { id });
+        }
+        self.router.set_weight(id, weight);
+        Ok(self.rebalance())
+    }
+}
+
+impl<K, V, C, R> HashMapCacheCluster<K, V, C, R> {
+    /// Route keys to nodes with `router` instead of the default `HashRouter`.
+    pub fn with_router<R2: KeyRouter<K>>(self, router: R2) -> HashMapCacheCluster<K, V, C, R2> {
+        HashMapCacheCluster {
+            nodes: self.nodes,
+            router,
+            health: self.health,
+            standbys: self.standbys,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, C, R> HashMapCacheCluster<K, V, C, R>
+where
+    K: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    V: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+    C: Clone + Send + Sync + 'static,
+    R: KeyRouter<K>,
+{
+    /// Dump every node at once into a file of its own in `dir`, created if missing, then write
+    /// the `ClusterManifest` of them, for `restore` to rebuild the cluster from. A directory
+    /// without a manifest holds no complete snapshot.
+    pub async fn try_snapshot_all(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<ClusterManifest, TokioActorCacheError> {
+        self.snapshot_all_with(dir.as_ref(), Ok, SendMode::Try)
+            .await
+    }
+
+    pub async fn snapshot_all(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<ClusterManifest, TokioActorCacheError> {
+        self.snapshot_all_with(dir.as_ref(), Ok, SendMode::Wait)
+            .await
+    }
+
+    /// `snapshot_all`, each node's file encrypted with the current key of `keyring`. The
+    /// manifest is left in the clear.
+    #[cfg(feature = "encryption")]
+    pub async fn try_snapshot_all_encrypted(
+        &self,
+        dir: impl AsRef<Path>,
+        keyring: &Keyring,
+    ) -> Result<ClusterManifest, TokioActorCacheError> {
+        let keyring = keyring.clone();
+        self.snapshot_all_with(
+            dir.as_ref(),
+            move |bytes| keyring.seal(&bytes),
+            SendMode::Try,
+        )
+        .await
+    }
+
+    #[cfg(feature = "encryption")]
+    pub async fn snapshot_all_encrypted(
+        &self,
+        dir: impl AsRef<Path>,
+        keyring: &Keyring,
+    ) -> Result<ClusterManifest, TokioActorCacheError> {
+        let keyring = keyring.clone();
+        self.snapshot_all_with(
+            dir.as_ref(),
+            move |bytes| keyring.seal(&bytes),
+            SendMode::Wait,
+        )
+        .await
+    }
+
+    /// Each node's dump goes through `seal` before it is written.
+    async fn snapshot_all_with<S>(
+        &self,
+        dir: &Path,
+        seal: S,
+        mode: SendMode,
+    ) -> Result<ClusterManifest, TokioActorCacheError>
+    where
+        S: Fn(Vec<u8>) -> Result<Vec<u8>, TokioActorCacheError> + Clone + Send + 'static,
+    {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|err| TokioActorCacheError::io(dir, err))?;
+
+        let mut dumps = JoinSet::new();
+        for (id, node) in &self.nodes {
+            let (id, node) = (*id, node.clone());
+            let file = format!("node-{id}.tacs");
+            let path = dir.join(&file);
+            let seal = seal.clone();
+            dumps.spawn(async move {
+                let bytes = node.dump_with(mode).await?;
+                let n_entries = SnapshotHeader::read(&bytes)?.n_entries;
+                tokio::fs::write(&path, seal(bytes)?)
+                    .await
+                    .map_err(|err| TokioActorCacheError::io(&path, err))?;
+                Ok::<_, TokioActorCacheError>(NodeSnapshot {
+                    id,
+                    file,
+                    n_entries,
+                })
+            });
+        }
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        while let Some(joined) = dumps.join_next().await {
+            nodes.push(joined.map_err(|_| TokioActorCacheError::ActorGone)??);
+        }
+        nodes.sort_unstable_by_key(|node| node.id);
+
+        let manifest = ClusterManifest {
+            n_node: self.nodes.len() as u64,
+            router: self.router.spec(),
+            nodes,
+        };
+        manifest.write(dir).await?;
+        Ok(manifest)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, C, R> HashMapCacheCluster<K, V, C, R>
+where
+    K: Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+    R: RestoreRouter,
+{
+    /// Rebuild the cluster `snapshot_all` saved to `dir`, each node made by `make` with the
+    /// expiration policy it was saved with, after `open` undoes the `seal` it was saved with.
+    /// Fails with `RouterMismatch` unless `R` can be built as the router saved.
+    pub(crate) async fn restore_with<O, F, Fut>(
+        dir: &Path,
+        open: O,
+        make: F,
+    ) -> Result<Self, TokioActorCacheError>
+    where
+        O: Fn(Vec<u8>) -> Result<Vec<u8>, TokioActorCacheError>,
+        F: Fn(ExpirationPolicy) -> Fut,
+        Fut: Future<Output = Result<HashMapCache<K, V, C>, ConfigError>>,
+    {
+        let manifest = ClusterManifest::read(dir).await?;
+        if manifest.nodes.len() as u64 != manifest.n_node {
+            return Err(TokioActorCacheError::ClusterSizeMismatch {
+                expected: manifest.n_node as usize,
+                got: manifest.nodes.len(),
+            });
+        }
+        let router =
+            R::restore(manifest.router.as_ref()).ok_or(TokioActorCacheError::RouterMismatch)?;
+
+        let mut nodes = HashMap::with_capacity(manifest.nodes.len());
+        for node in &manifest.nodes {
+            let path = dir.join(&node.file);
+            let bytes = tokio::fs::read(&path)
+                .await
+                .map_err(|err| TokioActorCacheError::io(&path, err))?;
+            let bytes = open(bytes)?;
+            let header = SnapshotHeader::read(&bytes)?;
+            let hm_cache = make(header.expiration_policy).await?;
+            hm_cache.load_with(&bytes, SendMode::Wait).await?;
+            nodes.insert(node.id, hm_cache);
+        }
+        Ok(Self {
+            nodes,
+            router,
+            health: NodeHealth::default(),
+            standbys: Standbys::default(),
+        })
+    }
+}
@@ -0,0 +1,1133 @@
+#[cfg(feature = "serde")]
+use serde::{Serialize, de::DeserializeOwned};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+use crate::tokio_cache::channel::{self, CacheReceiver, CacheSender, SendMode, reply};
+use crate::tokio_cache::cmd::{HashMapCmd, HashSetCmd};
+use crate::tokio_cache::compute::lowest_ranked_sorted;
+use crate::tokio_cache::data_struct::{HashSetState, NextExpiration, TICK};
+#[cfg(feature = "serde")]
+use crate::tokio_cache::dump::{self, Dumped};
+use crate::tokio_cache::error::{ConfigError, TokioActorCacheError};
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::index::{EvictionIndex, RankIndex};
+use crate::tokio_cache::jitter::{Rng, TtlJitter};
+use crate::tokio_cache::ops::cache_ops;
+use crate::tokio_cache::option::{ExpirationPolicy, InsertPolicy, ReplicaWrites};
+use crate::tokio_cache::projection::Projection;
+use crate::tokio_cache::removal::Eviction;
+use crate::tokio_cache::replication::{
+    ChangeLog, Replicas, ReplicationInfo, SyncStats, next_cache_id,
+};
+use crate::tokio_cache::runtime::{Runtime, TokioRuntime};
+use crate::tokio_cache::trace;
+
+#[derive(Debug, Clone)]
+pub struct HashSetCache<V, C> {
+    pub(crate) tx: CacheSender<HashSetCmd<V>>,
+    channel: PhantomData<C>,
+    id: u64,
+    replicating: Arc<AtomicBool>,
+    replica_writes: ReplicaWrites,
+    ttl_jitter: TtlJitter,
+    // Shared with the actor, see `with_cleanup_budget`; `usize::MAX` without one.
+    cleanup_budget: Arc<AtomicUsize>,
+}
+
+impl<V, C> HashSetCache<V, C>
+where
+    V: Clone,
+{
+    cache_ops! {
+        HashSetCmd;
+
+        tx fn replica_count / try_replica_count() -> usize => ReplicaCount;
+        tx fn is_replica / try_is_replica() -> bool => IsReplica;
+        tx fn replication_info / try_replication_info() -> ReplicationInfo => ReplicationInfo;
+        tx fn ttl / try_ttl(vals: &[V]) -> Vec<Option<Duration>> => Ttl { vals: vals.to_vec() };
+        tx fn get_all / try_get_all() -> HashSet<V> => GetAll;
+        /// The `n` most used vals with their call counts, most used first. Reading them doesn't
+        /// count as a use.
+        tx fn hot_vals / try_hot_vals(n: usize) -> Vec<(V, u64)> => HotVals { n };
+        /// The `n` vals unused for longest with how long they have been idle, idlest first.
+        tx fn idle_vals / try_idle_vals(n: usize) -> Vec<(V, Duration)> => IdleVals { n };
+        /// Up to `n` distinct vals picked at random, like Redis `SRANDMEMBER`; fewer when the set
+        /// is smaller. Each pick counts as a use.
+        tx fn srandmember / try_srandmember(n: usize) -> Vec<V> => RandMember { n };
+        /// Remove and return up to `n` distinct vals picked at random, like Redis `SPOP`.
+        write fn spop / try_spop(n: usize) -> Vec<V> => Pop { n };
+        write fn clear / try_clear() => Clear;
+        /// Resolves once the actor has handled every command queued before it, e.g. to wait for
+        /// a batch of fire-and-forget writes without acknowledging each of them.
+        tx fn barrier / try_barrier() -> () => Barrier;
+        write fn remove / try_remove(vals: &[V]) -> Vec<bool> => Remove { vals: vals.to_vec() };
+        tx fn contains / try_contains(vals: &[V]) -> Vec<bool> => Contains {
+            vals: vals.to_vec(),
+        };
+        write fn insert / try_insert(val: V, ex: Option<Duration>, policy: InsertPolicy) => Insert {
+            val,
+            ex,
+            policy,
+        };
+        /// Like `insert`, but resolves once the actor has applied the write rather than once it
+        /// is queued.
+        write fn insert_sync / try_insert_sync(
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) -> () => InsertSync { val, ex, policy };
+    }
+
+    pub async fn try_stop_replicating(&self) -> Result<(), TokioActorCacheError> {
+        self.stop_replicating_with(SendMode::Try).await
+    }
+
+    pub async fn try_replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
+        self.replicate_with(master, SendMode::Try).await
+    }
+
+    pub async fn try_promote_to_master(&self) -> Result<(), TokioActorCacheError> {
+        self.promote_to_master_with(SendMode::Try).await
+    }
+
+    pub async fn try_failover(old: &Self, new: &Self) -> Result<usize, TokioActorCacheError> {
+        Self::failover_with(old, new, SendMode::Try).await
+    }
+
+    /// Make `replica` follow this cache; a master fans its writes out to all of its replicas.
+    pub async fn try_add_replica(&self, replica: &Self) -> Result<(), TokioActorCacheError> {
+        replica.replicate_with(self, SendMode::Try).await
+    }
+
+    pub async fn try_minsert(
+        &self,
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        self.check_writable()?;
+        let minsert_cmd = Self::minsert_cmd(vals, ex, policy)?;
+        self.tx.send(minsert_cmd, SendMode::Try).await
+    }
+
+    pub async fn stop_replicating(&self) -> Result<(), TokioActorCacheError> {
+        self.stop_replicating_with(SendMode::Wait).await
+    }
+
+    pub async fn replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
+        self.replicate_with(master, SendMode::Wait).await
+    }
+
+    /// Stop replicating and take writes again, even if the old master is gone.
+    pub async fn promote_to_master(&self) -> Result<(), TokioActorCacheError> {
+        self.promote_to_master_with(SendMode::Wait).await
+    }
+
+    /// Promote `new` and re-point the other replicas of `old` to it. Returns how many were moved.
+    pub async fn failover(old: &Self, new: &Self) -> Result<usize, TokioActorCacheError> {
+        Self::failover_with(old, new, SendMode::Wait).await
+    }
+
+    /// Make `replica` follow this cache; a master fans its writes out to all of its replicas.
+    pub async fn add_replica(&self, replica: &Self) -> Result<(), TokioActorCacheError> {
+        replica.replicate_with(self, SendMode::Wait).await
+    }
+
+    pub async fn minsert(
+        &self,
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        self.check_writable()?;
+        let minsert_cmd = Self::minsert_cmd(vals, ex, policy)?;
+        self.tx.send(minsert_cmd, SendMode::Wait).await
+    }
+
+    /// Follow `master` by keeping the set equal to `project` applied to each of its entries, e.g.
+    /// `|key, _| key.clone()` to mirror its key set.
+    pub async fn replicate_from<K, MV, MC, F>(
+        &self,
+        master: &HashMapCache<K, MV, MC>,
+        project: F,
+    ) -> Result<(), TokioActorCacheError>
+    where
+        K: Clone + Eq + Hash + Send + Sync + 'static,
+        MV: Clone + Send + Sync + 'static,
+        V: Eq + Hash + Send + 'static,
+        F: Fn(&K, &MV) -> V + Send + 'static,
+    {
+        let (bridge_tx, bridge_rx) = channel::unbounded();
+        tokio::spawn(Projection::new(self.tx.clone(), project).run(bridge_rx));
+        let add_replica_cmd = HashMapCmd::AddReplica { replica: bridge_tx };
+        master.tx.send(add_replica_cmd, SendMode::Wait).await
+    }
+
+    /// Identifies this cache in `ReplicationInfo::master_id`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Number of commands queued for the actor, e.g. to shed load before `try_*` calls fail
+    /// with `ChannelFull`.
+    pub fn queue_depth(&self) -> usize {
+        self.tx.queue_depth()
+    }
+
+    /// Fail requests with `TokioActorCacheError::Timeout` when the actor hasn't answered within `after`.
+    pub fn with_timeout(mut self, after: Duration) -> Self {
+        self.tx.set_timeout(Some(after));
+        self
+    }
+
+    /// Choose whether direct writes are accepted while this cache is replicating.
+    pub fn with_replica_writes(mut self, replica_writes: ReplicaWrites) -> Self {
+        self.replica_writes = replica_writes;
+        self
+    }
+
+    /// Spread TTLs by up to `± fraction` of their length, e.g. `0.1` for ±10%, so entries written
+    /// together with the same TTL don't all expire on the same tick.
+    pub fn with_ttl_jitter(self, fraction: f64) -> Self {
+        self.ttl_jitter.set(fraction);
+        self
+    }
+
+    /// Remove at most `max_vals` expired or evicted vals at a time, and look at no more deadlines
+    /// than that for them, so a mass expiration doesn't hold up commands until it is done. The
+    /// rest are carried over to the next tick and to the commands that follow, each of which
+    /// removes up to `max_vals` more first. Meanwhile `contains`, `ttl` and `insert` already
+    /// treat the expired ones as gone, while other reads may still see them. Applies to every
+    /// handle.
+    pub fn with_cleanup_budget(self, max_vals: usize) -> Self {
+        self.cleanup_budget
+            .store(max_vals.max(1), Ordering::Release);
+        self
+    }
+
+    fn minsert_cmd(
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<HashSetCmd<V>, TokioActorCacheError> {
+        TokioActorCacheError::check_len(vals.len(), &[ex.len(), policy.len()])?;
+
+        Ok(HashSetCmd::MInsert {
+            vals: vals.to_vec(),
+            ex: ex.to_vec(),
+            policy: policy.to_vec(),
+        })
+    }
+
+    fn check_writable(&self) -> Result<(), TokioActorCacheError> {
+        if self.replica_writes == ReplicaWrites::Reject && self.replicating.load(Ordering::Acquire)
+        {
+            return Err(TokioActorCacheError::ReadOnlyReplica);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn stop_replicating_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let master = self
+            .tx
+            .request(mode, |resp_tx| HashSetCmd::StopReplicating { resp_tx })
+            .await?;
+        self.replicating.store(false, Ordering::Release);
+        if let Some(master) = master {
+            let remove_replica_cmd = HashSetCmd::RemoveReplica {
+                replica: self.tx.clone(),
+            };
+            // A master that is gone has no replica left to drop.
+            match master.send(remove_replica_cmd, mode).await {
+                Ok(()) | Err(TokioActorCacheError::ActorGone) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn promote_to_master_with(&self, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        let master = self
+            .tx
+            .request(mode, |resp_tx| HashSetCmd::StopReplicating { resp_tx })
+            .await?;
+        self.replicating.store(false, Ordering::Release);
+        if let Some(master) = master {
+            let remove_replica_cmd = HashSetCmd::RemoveReplica {
+                replica: self.tx.clone(),
+            };
+            // Best effort: the old master may be gone, which is usually why a replica gets promoted.
+            let _ = master.send(remove_replica_cmd, mode).await;
+        }
+        Ok(())
+    }
+
+    async fn failover_with(
+        old: &Self,
+        new: &Self,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        new.promote_to_master_with(mode).await?;
+        old.tx
+            .request(mode, |resp_tx| HashSetCmd::Failover {
+                new_master: new.tx.clone(),
+                new_master_id: new.id,
+                resp_tx,
+            })
+            .await
+    }
+
+    /// Detach from the current master, then register with `master`, which sends its full state and
+    /// forwards every later write.
+    pub(crate) async fn replicate_with(
+        &self,
+        master: &Self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        // A cache replicating itself would forward its writes back to itself forever.
+        if self.tx.same_channel(&master.tx) {
+            return Ok(());
+        }
+        self.stop_replicating_with(mode).await?;
+        let replicate_cmd = HashSetCmd::Replicate {
+            master: master.tx.clone(),
+            master_id: master.id,
+        };
+        self.tx.send(replicate_cmd, mode).await?;
+        self.replicating.store(true, Ordering::Release);
+        let add_replica_cmd = HashSetCmd::AddReplica {
+            replica: self.tx.clone(),
+        };
+        master.tx.send(add_replica_cmd, mode).await
+    }
+
+    pub(crate) fn spawn(
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<HashSetCmd<V>>,
+        rx: CacheReceiver<HashSetCmd<V>>,
+    ) -> Result<Self, ConfigError>
+    where
+        V: Debug + Eq + Hash + Send + 'static,
+    {
+        Self::spawn_on(Arc::new(TokioRuntime), expiration_policy, tx, rx)
+    }
+
+    /// Like `spawn`, but runs the actor on `runtime` and ticks by its clock.
+    pub(crate) fn spawn_on(
+        runtime: Arc<dyn Runtime>,
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<HashSetCmd<V>>,
+        rx: CacheReceiver<HashSetCmd<V>>,
+    ) -> Result<Self, ConfigError>
+    where
+        V: Debug + Eq + Hash + Send + 'static,
+    {
+        expiration_policy.validate()?;
+        let hm = HashMap::with_capacity(expiration_policy.preallocated());
+        let id = next_cache_id();
+        let ttl_jitter = TtlJitter::new(id);
+        let cleanup_budget = Arc::new(AtomicUsize::new(usize::MAX));
+        let actor = HashSetActor {
+            hm,
+            expiration_policy,
+            replica_of: None,
+            sync_stats: SyncStats::default(),
+            changes: ChangeLog::default(),
+            replicas: Replicas::default(),
+            ttl_jitter: ttl_jitter.clone(),
+            rng: Rng::new(id),
+            expiry_index: RankIndex::default(),
+            expiry_backlog: false,
+            eviction_index: EvictionIndex::default(),
+            cleanup_budget: cleanup_budget.clone(),
+        };
+        runtime.spawn(Box::pin(actor.run(rx, runtime.clone())));
+
+        Ok(Self {
+            tx,
+            channel: PhantomData,
+            id,
+            replicating: Arc::new(AtomicBool::new(false)),
+            replica_writes: ReplicaWrites::default(),
+            ttl_jitter,
+            cleanup_budget,
+        })
+    }
+}
+
+/// How `combine` folds sets together.
+#[derive(Debug, Clone, Copy)]
+enum SetOp {
+    Union,
+    Intersect,
+    Difference,
+}
+
+impl<V, C> HashSetCache<V, C>
+where
+    V: Clone + Eq + Hash,
+{
+    pub async fn try_union_with(&self, other: &Self) -> Result<HashSet<V>, TokioActorCacheError> {
+        Self::combine(&[self, other], SetOp::Union, SendMode::Try).await
+    }
+
+    pub async fn try_intersect(&self, other: &Self) -> Result<HashSet<V>, TokioActorCacheError> {
+        Self::combine(&[self, other], SetOp::Intersect, SendMode::Try).await
+    }
+
+    pub async fn try_difference(&self, other: &Self) -> Result<HashSet<V>, TokioActorCacheError> {
+        Self::combine(&[self, other], SetOp::Difference, SendMode::Try).await
+    }
+
+    pub async fn try_store_union(&self, sets: &[&Self]) -> Result<usize, TokioActorCacheError> {
+        self.store_with(sets, SetOp::Union, SendMode::Try).await
+    }
+
+    pub async fn try_store_intersect(&self, sets: &[&Self]) -> Result<usize, TokioActorCacheError> {
+        self.store_with(sets, SetOp::Intersect, SendMode::Try).await
+    }
+
+    pub async fn try_store_difference(
+        &self,
+        sets: &[&Self],
+    ) -> Result<usize, TokioActorCacheError> {
+        self.store_with(sets, SetOp::Difference, SendMode::Try)
+            .await
+    }
+
+    /// The vals in either set, like Redis `SUNION`. Reading them doesn't count as a use.
+    pub async fn union_with(&self, other: &Self) -> Result<HashSet<V>, TokioActorCacheError> {
+        Self::combine(&[self, other], SetOp::Union, SendMode::Wait).await
+    }
+
+    /// The vals in both sets, like Redis `SINTER`.
+    pub async fn intersect(&self, other: &Self) -> Result<HashSet<V>, TokioActorCacheError> {
+        Self::combine(&[self, other], SetOp::Intersect, SendMode::Wait).await
+    }
+
+    /// The vals in this set but not in `other`, like Redis `SDIFF`.
+    pub async fn difference(&self, other: &Self) -> Result<HashSet<V>, TokioActorCacheError> {
+        Self::combine(&[self, other], SetOp::Difference, SendMode::Wait).await
+    }
+
+    /// Replace the vals of this cache with the union of `sets`, which may include this cache,
+    /// like Redis `SUNIONSTORE`. The stored vals have no TTL. Returns how many there are.
+    pub async fn store_union(&self, sets: &[&Self]) -> Result<usize, TokioActorCacheError> {
+        self.store_with(sets, SetOp::Union, SendMode::Wait).await
+    }
+
+    /// `store_union` with the vals in every one of `sets`, like Redis `SINTERSTORE`.
+    pub async fn store_intersect(&self, sets: &[&Self]) -> Result<usize, TokioActorCacheError> {
+        self.store_with(sets, SetOp::Intersect, SendMode::Wait)
+            .await
+    }
+
+    /// `store_union` with the vals of the first of `sets` that are in none of the others, like
+    /// Redis `SDIFFSTORE`.
+    pub async fn store_difference(&self, sets: &[&Self]) -> Result<usize, TokioActorCacheError> {
+        self.store_with(sets, SetOp::Difference, SendMode::Wait)
+            .await
+    }
+
+    /// `op` over the live vals of `sets`, from a snapshot of each so their usage stats are left
+    /// alone.
+    async fn combine(
+        sets: &[&Self],
+        op: SetOp,
+        mode: SendMode,
+    ) -> Result<HashSet<V>, TokioActorCacheError> {
+        let mut sets = sets.iter();
+        let Some(first) = sets.next() else {
+            return Ok(HashSet::new());
+        };
+        let mut res = first.live_vals(mode).await?;
+        for set in sets {
+            let vals = set.live_vals(mode).await?;
+            match op {
+                SetOp::Union => res.extend(vals),
+                SetOp::Intersect => res.retain(|val| vals.contains(val)),
+                SetOp::Difference => res.retain(|val| !vals.contains(val)),
+            }
+        }
+        Ok(res)
+    }
+
+    async fn store_with(
+        &self,
+        sets: &[&Self],
+        op: SetOp,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.check_writable()?;
+        let vals = Self::combine(sets, op, mode).await?;
+        let n_val = vals.len();
+        self.tx.send(HashSetCmd::Store { vals }, mode).await?;
+        Ok(n_val)
+    }
+
+    /// The vals that haven't expired, even if the actor hasn't dropped them yet.
+    async fn live_vals(&self, mode: SendMode) -> Result<HashSet<V>, TokioActorCacheError> {
+        let hm = self
+            .tx
+            .request(mode, |resp_tx| HashSetCmd::Snapshot { resp_tx })
+            .await?;
+        let now = Instant::now();
+        Ok(hm
+            .into_iter()
+            .filter(|(_, state)| state.expiration.is_none_or(|exp| exp > now))
+            .map(|(val, _)| val)
+            .collect())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<V, C> HashSetCache<V, C>
+where
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// Serialize the live vals with their remaining TTLs and call counts, for `load` to
+    /// bring back, e.g. after a restart or in another process.
+    pub async fn try_dump(&self) -> Result<Vec<u8>, TokioActorCacheError> {
+        self.dump_with(SendMode::Try).await
+    }
+
+    /// Add what `dump` serialized, overwriting entries that are already cached.
+    pub async fn try_load(&self, bytes: &[u8]) -> Result<(), TokioActorCacheError> {
+        self.load_with(bytes, SendMode::Try).await
+    }
+
+    pub async fn dump(&self) -> Result<Vec<u8>, TokioActorCacheError> {
+        self.dump_with(SendMode::Wait).await
+    }
+
+    pub async fn load(&self, bytes: &[u8]) -> Result<(), TokioActorCacheError> {
+        self.load_with(bytes, SendMode::Wait).await
+    }
+
+    async fn dump_with(&self, mode: SendMode) -> Result<Vec<u8>, TokioActorCacheError> {
+        let (expiration_policy, hm) = self
+            .tx
+            .request(mode, |resp_tx| HashSetCmd::Dump { resp_tx })
+            .await?;
+        let now = Instant::now();
+        let vals = hm
+            .into_iter()
+            .filter_map(|(val, state)| Dumped::new(val, state.expiration, state.call_cnt, now))
+            .collect::<Vec<Dumped<V>>>();
+        dump::encode(expiration_policy, &vals)
+    }
+
+    async fn load_with(&self, bytes: &[u8], mode: SendMode) -> Result<(), TokioActorCacheError> {
+        self.check_writable()?;
+        let now = Instant::now();
+        let entries = dump::decode::<V>(bytes)?
+            .into_iter()
+            .map(|entry| {
+                let state = HashSetState {
+                    expiration: entry.expiration(now),
+                    call_cnt: entry.call_cnt,
+                    last_accessed: now,
+                };
+                (entry.item, state)
+            })
+            .collect::<Vec<(V, HashSetState)>>();
+        self.tx.send(HashSetCmd::Load { entries }, mode).await
+    }
+}
+
+struct HashSetActor<V> {
+    hm: HashMap<V, HashSetState>,
+    expiration_policy: ExpirationPolicy,
+    replica_of: Option<CacheSender<HashSetCmd<V>>>,
+    sync_stats: SyncStats,
+    changes: ChangeLog<V>,
+    replicas: Replicas<HashSetCmd<V>>,
+    ttl_jitter: TtlJitter,
+    rng: Rng,
+    // The vals with a TTL by when it runs out.
+    expiry_index: RankIndex<Instant, V>,
+    // Set while expired vals are left over from a pass that ran out of its cleanup budget.
+    expiry_backlog: bool,
+    eviction_index: EvictionIndex<V>,
+    cleanup_budget: Arc<AtomicUsize>,
+}
+
+impl<V> HashSetActor<V>
+where
+    V: Clone + Eq + Hash,
+{
+    async fn run(mut self, mut rx: CacheReceiver<HashSetCmd<V>>, runtime: Arc<dyn Runtime>) {
+        let mut ticker = runtime.interval(TICK);
+        loop {
+            tokio::select! {
+                _ = ticker.next() => self.tick(),
+
+                // Expire short TTLs without waiting for the next tick. A backlog is left to the
+                // ticks and the commands in between, or it would hold them up after all.
+                _ = self.next_expiration().elapsed(&*runtime),
+                    if self.replica_of.is_none() && !self.expiry_backlog =>
+                {
+                    self.expire_due();
+                }
+
+                // Handle commands.
+                command = rx.recv() => {
+                    match command {
+                        Some(cmd) => self.handle(cmd),
+                        // Every handle is gone.
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        // Drop replicas that are gone and bring new or lagging ones up to date.
+        self.replicas.prune();
+        self.sync_replicas();
+
+        // Replicas follow the expirations and evictions of their master.
+        if self.replica_of.is_some() {
+            return;
+        }
+
+        self.expire_due();
+        self.evict();
+    }
+
+    /// Drop the vals whose TTL has run out, taking only those due off the expiry index and no
+    /// more than the cleanup budget allows, stale ones included. Those left over keep the next
+    /// expiration due and are picked up where this pass stopped.
+    fn expire_due(&mut self) {
+        let now = Instant::now();
+        let budget = self.cleanup_budget.load(Ordering::Acquire);
+        let mut expired_vals = HashSet::new();
+        let mut n_examined = 0;
+        while n_examined < budget
+            && self.next_expiration().is_due(now)
+            && let Some((_, val)) = self.expiry_index.pop_lowest()
+        {
+            n_examined += 1;
+            match self.hm.get(&val).and_then(|state| state.expiration) {
+                Some(exp) if exp <= now => {
+                    expired_vals.insert(val);
+                }
+                // Given a later TTL since it was pushed.
+                Some(exp) => self.expiry_index.push(exp, val),
+                None => {}
+            }
+        }
+        self.expiry_backlog = self.next_expiration().is_due(now);
+        if !expired_vals.is_empty() {
+            trace::debug_event!(expired = expired_vals.len(), "expired entries");
+            self.delete(expired_vals.into_iter().collect());
+        }
+    }
+
+    /// The state of `val`, `None` once its TTL has run out even if its removal was carried
+    /// over, see `with_cleanup_budget`.
+    fn live_mut(&mut self, val: &V) -> Option<&mut HashSetState> {
+        let expiry_backlog = self.expiry_backlog;
+        let now = Instant::now();
+        self.hm
+            .get_mut(val)
+            .filter(|state| !expiry_backlog || state.expiration.is_none_or(|exp| exp > now))
+    }
+
+    /// When the earliest val on the expiry index runs out.
+    fn next_expiration(&self) -> NextExpiration {
+        NextExpiration::at(self.expiry_index.lowest())
+    }
+
+    /// Put every val with a TTL back on the expiry index, dropping what went stale.
+    fn refill_expiry_index(&mut self) {
+        self.expiry_index.clear();
+        for (val, state) in &self.hm {
+            if let Some(exp) = state.expiration {
+                self.expiry_index.push(exp, val.clone());
+            }
+        }
+    }
+
+    /// Invalidate cache according to expiration policy, taking the vals over capacity off the
+    /// eviction index.
+    fn evict(&mut self) {
+        let (capacity, eviction) = match self.expiration_policy {
+            ExpirationPolicy::LFU(capacity) => (capacity, Eviction::Lfu),
+            ExpirationPolicy::LRU(capacity) => (capacity, Eviction::Lru),
+            ExpirationPolicy::None => return,
+        };
+        if self.eviction_index.keep(eviction) {
+            self.refill_eviction_index();
+        }
+        // Leave what is over the cleanup budget to the next tick or write.
+        let budget = self.cleanup_budget.load(Ordering::Acquire);
+        let n_exceed = self.hm.len().saturating_sub(capacity).min(budget);
+        let mut evicted_vals = HashSet::new();
+        while evicted_vals.len() < n_exceed
+            && let Some(val) = self.eviction_index.pop_current(|val| {
+                self.hm
+                    .get(val)
+                    .filter(|_| !evicted_vals.contains(val))
+                    .map(|state| (state.call_cnt, state.last_accessed))
+            })
+        {
+            evicted_vals.insert(val);
+        }
+        if evicted_vals.is_empty() {
+            return;
+        }
+        trace::debug_event!(
+            evicted = evicted_vals.len(),
+            "evicted entries over capacity"
+        );
+        self.delete(evicted_vals.into_iter().collect());
+    }
+
+    /// Put every val back on the eviction index, dropping what went stale.
+    fn refill_eviction_index(&mut self) {
+        self.eviction_index.clear();
+        for (val, state) in &self.hm {
+            if let Some(rank) = self
+                .eviction_index
+                .rank(state.call_cnt, state.last_accessed)
+            {
+                self.eviction_index.push(rank, val.clone());
+            }
+        }
+    }
+
+    fn handle(&mut self, cmd: HashSetCmd<V>) {
+        trace::command_span!(cmd);
+
+        // Never serve a val past its TTL, even when its timer hasn't fired yet.
+        if self.replica_of.is_none() && self.next_expiration().is_due(Instant::now()) {
+            self.expire_due();
+        }
+
+        match cmd {
+            HashSetCmd::StopReplicating { resp_tx } => {
+                self.sync_stats.stop();
+                reply(resp_tx, self.replica_of.take());
+            }
+            HashSetCmd::IsReplica { resp_tx } => {
+                let is_replica = self.replica_of.is_some();
+
+                reply(resp_tx, is_replica);
+            }
+            HashSetCmd::ReplicationInfo { resp_tx } => {
+                let info = self.sync_stats.info(self.replicas.len());
+
+                reply(resp_tx, info);
+            }
+            HashSetCmd::Replicate { master, master_id } => {
+                self.replica_of = Some(master);
+                self.sync_stats.start(master_id);
+            }
+            HashSetCmd::AddReplica { replica } => {
+                self.replicas.add(replica);
+                self.sync_replicas();
+            }
+            HashSetCmd::RemoveReplica { replica } => {
+                self.replicas.remove(&replica);
+            }
+            HashSetCmd::ReplicaCount { resp_tx } => {
+                reply(resp_tx, self.replicas.len());
+            }
+            HashSetCmd::Failover {
+                new_master,
+                new_master_id,
+                resp_tx,
+            } => {
+                let mut moved = 0;
+                for replica in self.replicas.drain() {
+                    if replica.same_channel(&new_master) {
+                        continue;
+                    }
+                    let replicate_cmd = HashSetCmd::Replicate {
+                        master: new_master.clone(),
+                        master_id: new_master_id,
+                    };
+                    if replica.send_now(replicate_cmd).is_err() {
+                        continue;
+                    }
+                    if new_master
+                        .send_now(HashSetCmd::AddReplica { replica })
+                        .is_ok()
+                    {
+                        moved += 1;
+                    }
+                }
+
+                reply(resp_tx, moved);
+            }
+            // Writes forwarded by the master are dropped once replication has stopped.
+            HashSetCmd::Sync { hm, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, hm.len());
+                    self.hm = hm;
+                    self.refill_expiry_index();
+                    self.eviction_index = EvictionIndex::default();
+                    self.changes.reset();
+                    self.replicas
+                        .forward(self.changes.seq(), |sent_at| HashSetCmd::Sync {
+                            sent_at,
+                            hm: self.hm.clone(),
+                        });
+                }
+            }
+            HashSetCmd::Put {
+                val,
+                state,
+                sent_at,
+            } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, 1);
+                    self.put(val, state);
+                }
+            }
+            HashSetCmd::Delete { vals, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, vals.len());
+                    self.delete(vals);
+                }
+            }
+            HashSetCmd::Delta {
+                puts,
+                deletes,
+                sent_at,
+            } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, puts.len() + deletes.len());
+                    for (val, state) in puts {
+                        self.put(val, state);
+                    }
+                    self.delete(deletes);
+                }
+            }
+            #[cfg(feature = "serde")]
+            HashSetCmd::Dump { resp_tx } => {
+                reply(resp_tx, (self.expiration_policy, self.hm.clone()));
+            }
+            #[cfg(feature = "serde")]
+            HashSetCmd::Load { entries } => {
+                for (val, state) in entries {
+                    self.put(val, state);
+                }
+                if self.replica_of.is_none() {
+                    self.evict();
+                }
+            }
+            HashSetCmd::Snapshot { resp_tx } => {
+                let val = self.hm.clone();
+
+                reply(resp_tx, val);
+            }
+            HashSetCmd::Ttl { vals, resp_tx } => {
+                let ttl = vals
+                    .iter()
+                    .map(|val| {
+                        self.live_mut(val).and_then(|state| {
+                            state.call_cnt += 1;
+                            state.last_accessed = Instant::now();
+                            state
+                                .expiration
+                                .and_then(|ex| ex.checked_duration_since(Instant::now()))
+                        })
+                    })
+                    .collect::<Vec<Option<Duration>>>();
+
+                reply(resp_tx, ttl);
+            }
+            HashSetCmd::GetAll { resp_tx } => {
+                let now = Instant::now();
+                let vals = self
+                    .hm
+                    .iter_mut()
+                    .map(|(val, state)| {
+                        state.call_cnt += 1;
+                        state.last_accessed = now;
+                        val.clone()
+                    })
+                    .collect::<HashSet<V>>();
+
+                reply(resp_tx, vals);
+            }
+            HashSetCmd::HotVals { n, resp_tx } => {
+                let hot_vals = lowest_ranked_sorted(
+                    self.hm
+                        .iter()
+                        .map(|(val, state)| (val, Reverse(state.call_cnt))),
+                    n,
+                )
+                .into_iter()
+                .map(|(val, Reverse(call_cnt))| (val.clone(), call_cnt))
+                .collect::<Vec<(V, u64)>>();
+
+                reply(resp_tx, hot_vals);
+            }
+            HashSetCmd::IdleVals { n, resp_tx } => {
+                let now = Instant::now();
+                let idle_vals = lowest_ranked_sorted(
+                    self.hm
+                        .iter()
+                        .map(|(val, state)| (val, state.last_accessed)),
+                    n,
+                )
+                .into_iter()
+                .map(|(val, last_accessed)| {
+                    (val.clone(), now.saturating_duration_since(last_accessed))
+                })
+                .collect::<Vec<(V, Duration)>>();
+
+                reply(resp_tx, idle_vals);
+            }
+            HashSetCmd::RandMember { n, resp_tx } => {
+                let now = Instant::now();
+                let vals = self.sample(n);
+                for val in &vals {
+                    if let Some(state) = self.hm.get_mut(val) {
+                        state.call_cnt += 1;
+                        state.last_accessed = now;
+                    }
+                }
+
+                reply(resp_tx, vals);
+            }
+            HashSetCmd::Pop { n, resp_tx } => {
+                let vals = self.sample(n);
+                self.delete(vals.clone());
+
+                reply(resp_tx, vals);
+            }
+            HashSetCmd::Barrier { resp_tx } => {
+                reply(resp_tx, ());
+            }
+            HashSetCmd::Clear => {
+                self.hm.clear();
+                self.expiry_index.clear();
+                self.eviction_index.clear();
+                self.changes.reset();
+                self.replicas
+                    .forward(self.changes.seq(), |sent_at| HashSetCmd::Sync {
+                        sent_at,
+                        hm: HashMap::new(),
+                    });
+            }
+            HashSetCmd::Remove { vals, resp_tx } => {
+                let is_remove = vals
+                    .iter()
+                    .map(|val| self.hm.remove(val).is_some())
+                    .collect::<Vec<bool>>();
+                self.delete(vals);
+
+                reply(resp_tx, is_remove);
+            }
+            HashSetCmd::Contains { vals, resp_tx } => {
+                let is_contains_vals = vals
+                    .iter()
+                    .map(|val| {
+                        // Get 'state' with 'val'.
+                        match self.live_mut(val) {
+                            Some(state) => {
+                                // incr 'call_cnt' by 1 and update 'last_accessed'.
+                                state.call_cnt += 1;
+                                state.last_accessed = Instant::now();
+                                true
+                            }
+                            None => false,
+                        }
+                    })
+                    .collect::<Vec<bool>>();
+
+                reply(resp_tx, is_contains_vals);
+            }
+            HashSetCmd::MInsert { vals, ex, policy } => {
+                for ((val, ex), policy) in vals.into_iter().zip(ex).zip(policy) {
+                    self.insert(val, ex, policy);
+                }
+            }
+            HashSetCmd::Migrate {
+                val,
+                state,
+                resp_tx,
+            } => {
+                if !self.hm.contains_key(&val) {
+                    self.put(val, state);
+                    if self.replica_of.is_none() {
+                        self.evict();
+                    }
+                }
+
+                reply(resp_tx, ());
+            }
+            HashSetCmd::Insert { val, ex, policy } => {
+                self.insert(val, ex, policy);
+            }
+            HashSetCmd::InsertSync {
+                val,
+                ex,
+                policy,
+                resp_tx,
+            } => {
+                self.insert(val, ex, policy);
+
+                reply(resp_tx, ());
+            }
+            HashSetCmd::Store { vals } => {
+                let now = Instant::now();
+                self.hm = vals
+                    .into_iter()
+                    .map(|val| {
+                        let state = HashSetState {
+                            expiration: None,
+                            call_cnt: 0,
+                            last_accessed: now,
+                        };
+                        (val, state)
+                    })
+                    .collect();
+                // Stored without TTLs.
+                self.expiry_index.clear();
+                self.eviction_index = EvictionIndex::default();
+                self.changes.reset();
+                self.replicas
+                    .forward(self.changes.seq(), |sent_at| HashSetCmd::Sync {
+                        sent_at,
+                        hm: self.hm.clone(),
+                    });
+                if self.replica_of.is_none() {
+                    self.evict();
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, val: V, ex: Option<Duration>, policy: InsertPolicy) {
+        let expiration = self.ttl_jitter.expiration(ex);
+        let last_accessed = Instant::now();
+
+        let existing = self.live_mut(&val);
+        if !policy.allows(existing.is_some()) {
+            return;
+        }
+        let call_cnt = existing.map_or(0, |state| state.call_cnt + 1);
+        let state = HashSetState {
+            expiration,
+            call_cnt,
+            last_accessed,
+        };
+        self.put(val, state);
+
+        // Evict now rather than at the next tick; replicas follow the evictions of their master.
+        if self.replica_of.is_none() {
+            self.evict();
+        }
+    }
+
+    /// Up to `n` distinct vals, each as likely as any other to be picked.
+    fn sample(&mut self, n: usize) -> Vec<V> {
+        if n == 0 {
+            return Vec::new();
+        }
+        // Reservoir sampling, in one pass without copying the set.
+        let mut picked = Vec::with_capacity(n.min(self.hm.len()));
+        for (i, val) in self.hm.keys().enumerate() {
+            if i < n {
+                picked.push(val.clone());
+            } else {
+                let j = self.rng.below(i + 1);
+                if j < n {
+                    picked[j] = val.clone();
+                }
+            }
+        }
+        picked
+    }
+
+    /// Send lagging replicas what changed since they fell behind, or the full state when the
+    /// change log no longer reaches back that far.
+    fn sync_replicas(&mut self) {
+        let seq = self.changes.seq();
+        self.replicas.sync(seq, |synced_seq, sent_at| {
+            match synced_seq.and_then(|synced_seq| self.changes.since(synced_seq)) {
+                Some(changed) => {
+                    let mut puts = Vec::new();
+                    let mut deletes = Vec::new();
+                    for val in changed {
+                        match self.hm.get(&val) {
+                            Some(state) => puts.push((val, state.clone())),
+                            None => deletes.push(val),
+                        }
+                    }
+                    HashSetCmd::Delta {
+                        sent_at,
+                        puts,
+                        deletes,
+                    }
+                }
+                None => HashSetCmd::Sync {
+                    sent_at,
+                    hm: self.hm.clone(),
+                },
+            }
+        });
+    }
+
+    /// Store `val` and forward it to the replicas.
+    fn put(&mut self, val: V, state: HashSetState) {
+        if !self.replicas.is_empty() {
+            self.changes.record(val.clone());
+        }
+        self.replicas
+            .forward(self.changes.seq(), |sent_at| HashSetCmd::Put {
+                sent_at,
+                val: val.clone(),
+                state: state.clone(),
+            });
+        if let Some(exp) = state.expiration {
+            self.expiry_index.push(exp, val.clone());
+        }
+        if let Some(rank) = self
+            .eviction_index
+            .rank(state.call_cnt, state.last_accessed)
+        {
+            self.eviction_index.push(rank, val.clone());
+        }
+        self.hm.insert(val, state);
+        if self.expiry_index.is_bloated(self.hm.len()) {
+            self.refill_expiry_index();
+        }
+        if self.eviction_index.is_bloated(self.hm.len()) {
+            self.refill_eviction_index();
+        }
+    }
+
+    /// Remove `vals` and have the replicas do the same.
+    fn delete(&mut self, vals: Vec<V>) {
+        for val in &vals {
+            self.hm.remove(val);
+            if !self.replicas.is_empty() {
+                self.changes.record(val.clone());
+            }
+        }
+        self.replicas
+            .forward(self.changes.seq(), |sent_at| HashSetCmd::Delete {
+                sent_at,
+                vals: vals.clone(),
+            });
+    }
+}
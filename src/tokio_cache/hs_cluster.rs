@@ -0,0 +1,325 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash};
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::tokio_cache::channel::SendMode;
+use crate::tokio_cache::cmd::HashSetCmd;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::health::{NodeHealth, NodeStatus, Standbys};
+use crate::tokio_cache::hs::HashSetCache;
+use crate::tokio_cache::ops::{cluster_ops, mode_pairs};
+use crate::tokio_cache::option::InsertPolicy;
+use crate::tokio_cache::rebalance::RebalanceProgress;
+use crate::tokio_cache::router::{HashRouter, KeyRouter, WeightedRouter};
+
+#[derive(Debug, Clone)]
+pub struct HashSetCacheCluster<V, C, R = HashRouter> {
+    pub nodes: HashMap<u64, HashSetCache<V, C>>,
+    pub(crate) router: R,
+    pub(crate) health: NodeHealth,
+    pub(crate) standbys: Standbys<HashSetCache<V, C>>,
+}
+
+impl<V, C, R> HashSetCacheCluster<V, C, R>
+where
+    V: Clone + Debug + Eq + Hash + Send + 'static,
+    C: Clone,
+    R: KeyRouter<V>,
+{
+    mode_pairs! {
+        /// The remaining time to live of each of `vals`, paired with it; `None` for entries without
+        /// one or that don't exist.
+        fn ttl / try_ttl(vals: &[V]) -> Result<Vec<(V, Option<Duration>)>, TokioActorCacheError>
+            => ttl_with;
+        fn get_all / try_get_all() -> Result<HashSet<V>, TokioActorCacheError> => get_all_with;
+        fn minsert / try_minsert(
+            vals: &[V],
+            ex: &[Option<Duration>],
+            policy: &[InsertPolicy],
+        ) -> Result<(), TokioActorCacheError> => minsert_with;
+        /// Make every node replicate the node with the same id in `master`, which must have as many
+        /// nodes as this cluster so that keys route to matching nodes. Until `stop_replicating`,
+        /// `master` sends what routes to a node its health check has down to the node here.
+        fn replicate / try_replicate(master: &Self) -> Result<(), TokioActorCacheError>
+            => replicate_with;
+        fn stop_replicating / try_stop_replicating() -> Result<(), TokioActorCacheError>
+            => stop_replicating_with;
+    }
+
+    cluster_ops! {
+        HashSetCmd;
+
+        routed fn insert / try_insert(
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) => Insert { val, ex, policy };
+        each fn remove / try_remove(vals: &[V]) -> Vec<bool> => Remove { vals };
+        each fn contains / try_contains(vals: &[V]) -> Vec<bool> => Contains { vals };
+        every fn clear / try_clear() => Clear;
+        /// `barrier` on every node.
+        every fn barrier / try_barrier() -> () => Barrier;
+    }
+
+    /// Move every entry that sits on a node other than the one its key routes to now, e.g. after
+    /// `with_router`. Runs in a background task whose progress is published on the returned
+    /// channel.
+    pub fn rebalance(&self) -> watch::Receiver<RebalanceProgress>
+    where
+        C: Clone + Send + Sync + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        let (progress_tx, progress_rx) = watch::channel(RebalanceProgress::default());
+        let cluster = self.clone();
+        tokio::spawn(async move {
+            let res = cluster.rebalance_with(&progress_tx).await;
+            progress_tx.send_modify(|progress| progress.finished = Some(res));
+        });
+        progress_rx
+    }
+
+    /// Apply `with_timeout` to every node of the cluster.
+    pub fn with_timeout(mut self, after: Duration) -> Self {
+        for node in self.nodes.values_mut() {
+            node.tx.set_timeout(Some(after));
+        }
+        self
+    }
+
+    /// Ping every node each `every`, and until a node that stops answering does again, send its
+    /// values to the matching node of a cluster replicating this one, see `replicate`, rather
+    /// than wait on it. Without one they fail fast with `ActorGone`, and reads across every node
+    /// skip it.
+    pub fn with_health_check(self, every: Duration) -> Self {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(id, node)| (*id, node.tx.clone()))
+            .collect();
+        self.health
+            .monitor(nodes, every, |resp_tx| HashSetCmd::IsReplica { resp_tx });
+        self
+    }
+
+    /// The status of every node as of its last health check; all nodes are `Up` without
+    /// `with_health_check`.
+    pub fn cluster_status(&self) -> HashMap<u64, NodeStatus> {
+        self.health.status(self.nodes.keys().copied())
+    }
+
+    async fn replicate_with(
+        &self,
+        master: &Self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        if master.nodes.len() != self.nodes.len() {
+            return Err(TokioActorCacheError::ClusterSizeMismatch {
+                expected: master.nodes.len(),
+                got: self.nodes.len(),
+            });
+        }
+        for (id, node) in &self.nodes {
+            let master_node = master
+                .nodes
+                .get(id)
+                .ok_or(TokioActorCacheError::NodeNotExists { id: *id })?;
+            node.replicate_with(master_node, mode).await?;
+        }
+        self.standbys
+            .follow(&master.standbys, self.paired_nodes(), Self::same_node);
+
+        Ok(())
+    }
+
+    /// Every node by its id, which is also that of the node it replicates.
+    fn paired_nodes(&self) -> Vec<(u64, HashSetCache<V, C>)> {
+        self.nodes
+            .iter()
+            .map(|(id, node)| (*id, node.clone()))
+            .collect()
+    }
+
+    fn same_node(a: &HashSetCache<V, C>, b: &HashSetCache<V, C>) -> bool {
+        a.tx.same_channel(&b.tx)
+    }
+
+    async fn rebalance_with(
+        &self,
+        progress: &watch::Sender<RebalanceProgress>,
+    ) -> Result<(), TokioActorCacheError> {
+        // Find every misplaced entry first so that `remaining` is known before anything moves.
+        let mut misplaced = Vec::new();
+        for (id, node) in &self.nodes {
+            if self.health.is_down(*id) {
+                continue;
+            }
+            let hs = node
+                .tx
+                .request(SendMode::Wait, |resp_tx| HashSetCmd::Snapshot { resp_tx })
+                .await?;
+            misplaced.extend(
+                hs.into_iter()
+                    .filter(|(val, _)| self.node_id(val) != *id)
+                    .map(|(val, state)| (*id, val, state)),
+            );
+        }
+        progress.send_modify(|progress| progress.remaining = misplaced.len());
+
+        for (id, val, state) in misplaced {
+            let target = self.get_node(&val)?;
+            // Only drop the value from where it was once the new owner holds it.
+            let moved = val.clone();
+            target
+                .tx
+                .request(SendMode::Wait, |resp_tx| HashSetCmd::Migrate {
+                    val: moved,
+                    state,
+                    resp_tx,
+                })
+                .await?;
+            let source = self
+                .nodes
+                .get(&id)
+                .ok_or(TokioActorCacheError::NodeNotExists { id })?;
+            source
+                .tx
+                .request(SendMode::Wait, |resp_tx| HashSetCmd::Remove {
+                    vals: vec![val],
+                    resp_tx,
+                })
+                .await?;
+            progress.send_modify(RebalanceProgress::record_move);
+        }
+
+        Ok(())
+    }
+
+    async fn stop_replicating_with(&self, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        for node in self.nodes.values() {
+            node.stop_replicating_with(mode).await?;
+        }
+        self.standbys
+            .unfollow(&self.paired_nodes(), Self::same_node);
+
+        Ok(())
+    }
+
+    async fn ttl_with(
+        &self,
+        vals: &[V],
+        mode: SendMode,
+    ) -> Result<Vec<(V, Option<Duration>)>, TokioActorCacheError> {
+        let mut res = Vec::new();
+        for val in vals.iter().cloned() {
+            let node = self.get_node(&val)?;
+            let ttl = node
+                .tx
+                .request(mode, |resp_tx| HashSetCmd::Ttl {
+                    vals: vec![val.clone()],
+                    resp_tx,
+                })
+                .await?;
+            // Pair each answer with its input rather than relying on the node's answer length.
+            res.push((val, ttl.into_iter().next().flatten()));
+        }
+
+        Ok(res)
+    }
+
+    async fn get_all_with(&self, mode: SendMode) -> Result<HashSet<V>, TokioActorCacheError> {
+        let mut res = HashSet::new();
+        for node in self.live_nodes() {
+            let vals = node
+                .tx
+                .request(mode, |resp_tx| HashSetCmd::GetAll { resp_tx })
+                .await?;
+            res.extend(vals);
+        }
+
+        Ok(res)
+    }
+
+    async fn minsert_with(
+        &self,
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        TokioActorCacheError::check_len(vals.len(), &[ex.len(), policy.len()])?;
+
+        // One command per node, each value keeping its own `ex` and `policy`.
+        let mut batches = HashMap::<u64, (Vec<_>, Vec<_>, Vec<_>)>::new();
+        for ((val, ex), policy) in vals.iter().zip(ex).zip(policy) {
+            let batch = batches.entry(self.node_id(val)).or_default();
+            batch.0.push(val.clone());
+            batch.1.push(*ex);
+            batch.2.push(*policy);
+        }
+        for (id, (vals, ex, policy)) in batches {
+            let node = self.node(id)?;
+            let minsert_cmd = HashSetCmd::MInsert { vals, ex, policy };
+            node.tx.send(minsert_cmd, mode).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Every node, with a standby in place of each the health check has down, and without
+    /// those that have none.
+    fn live_nodes(&self) -> impl Iterator<Item = Cow<'_, HashSetCache<V, C>>> {
+        self.nodes.keys().filter_map(|id| self.node(*id).ok())
+    }
+
+    fn node_id(&self, val: &V) -> u64 {
+        let n_node = self.nodes.len() as u64;
+        self.router.route(val, n_node)
+    }
+
+    fn get_node(&self, val: &V) -> Result<Cow<'_, HashSetCache<V, C>>, TokioActorCacheError> {
+        self.node(self.node_id(val))
+    }
+
+    /// Node `id`, or while the health check has it down a standby, see `NodeHealth::route`.
+    fn node(&self, id: u64) -> Result<Cow<'_, HashSetCache<V, C>>, TokioActorCacheError> {
+        self.health
+            .route(id, &self.nodes, &self.standbys, |node| !node.tx.is_closed())
+    }
+}
+
+impl<V, C, S> HashSetCacheCluster<V, C, WeightedRouter<S>>
+where
+    V: Clone + Debug + Eq + Hash + Send + 'static,
+    C: Clone + Send + Sync + 'static,
+    S: BuildHasher + Clone + Send + Sync + 'static,
+{
+    /// Give node `id` `weight` shares of the values, e.g. in proportion to its capacity, and
+    /// `rebalance` the entries that now route elsewhere, which only move to or from that node.
+    pub fn set_node_weight(
+        &self,
+        id: u64,
+        weight: u32,
+    ) -> Result<watch::Receiver<RebalanceProgress>, TokioActorCacheError> {
+        if !self.nodes.contains_key(&id) {
+            return Err(TokioActorCacheError::NodeNotExists { id });
+        }
+        self.router.set_weight(id, weight);
+        Ok(self.rebalance())
+    }
+}
+
+impl<V, C, R> HashSetCacheCluster<V, C, R> {
+    /// Route keys to nodes with `router` instead of the default `HashRouter`.
+    pub fn with_router<R2: KeyRouter<V>>(self, router: R2) -> HashSetCacheCluster<V, C, R2> {
+        HashSetCacheCluster {
+            nodes: self.nodes,
+            router,
+            health: self.health,
+            standbys: self.standbys,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+use tokio::time::Instant;
+
+use crate::tokio_cache::removal::Eviction;
+
+/// How many stale items an index may hold beyond two per live entry before it is rebuilt.
+const STALE_SLACK: usize = 64;
+
+/// Items of an actor's entries ordered by a rank that only goes up while the entry is cached, such
+/// as when it was last used, so the lowest come out in O(log n) without looking at the rest.
+///
+/// Kept lazily: a rank going up leaves the index alone, and an item that comes out ranking higher
+/// by then goes back in at its new rank. Items of removed entries are dropped as they come out.
+/// So an entry only needs pushing when it is stored and when its rank may have gone down.
+#[derive(Debug)]
+pub(crate) struct RankIndex<R, T> {
+    ranked: BTreeMap<(R, u64), T>,
+    seq: u64,
+}
+
+impl<R, T> Default for RankIndex<R, T> {
+    fn default() -> Self {
+        Self {
+            ranked: BTreeMap::new(),
+            seq: 0,
+        }
+    }
+}
+
+impl<R: Copy + Ord, T> RankIndex<R, T> {
+    pub(crate) fn push(&mut self, rank: R, item: T) {
+        self.seq += 1;
+        self.ranked.insert((rank, self.seq), item);
+    }
+
+    /// The lowest rank pushed, which may be that of a stale item.
+    pub(crate) fn lowest(&self) -> Option<R> {
+        self.ranked.first_key_value().map(|(&(rank, _), _)| rank)
+    }
+
+    /// Take out the item of the lowest rank pushed, stale or not.
+    pub(crate) fn pop_lowest(&mut self) -> Option<(R, T)> {
+        self.ranked
+            .pop_first()
+            .map(|((rank, _), item)| (rank, item))
+    }
+
+    /// Take out the lowest item whose entry still ranks as it was pushed. `rank_of` gives the
+    /// current rank of an item's entry, `None` for those that are gone or not to be taken.
+    pub(crate) fn pop_current(&mut self, mut rank_of: impl FnMut(&T) -> Option<R>) -> Option<T> {
+        while let Some((rank, item)) = self.pop_lowest() {
+            match rank_of(&item) {
+                None => {}
+                Some(current) if current > rank => self.push(current, item),
+                Some(_) => return Some(item),
+            }
+        }
+        None
+    }
+
+    /// Whether stale items have piled up enough, next to `n_entries` live ones, to be worth
+    /// rebuilding from the entries, which then takes O(1) per push on average.
+    pub(crate) fn is_bloated(&self, n_entries: usize) -> bool {
+        self.ranked.len() > 2 * n_entries + STALE_SLACK
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.ranked.clear();
+    }
+}
+
+/// Where an entry ranks for eviction, by call count under LFU and by last use under LRU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum EvictionRank {
+    Calls(u64),
+    Accessed(Instant),
+}
+
+impl EvictionRank {
+    fn new(eviction: Eviction, call_cnt: u64, last_accessed: Instant) -> Self {
+        match eviction {
+            Eviction::Lfu => Self::Calls(call_cnt),
+            Eviction::Lru => Self::Accessed(last_accessed),
+        }
+    }
+}
+
+/// The entries of an actor in the order `eviction` evicts them. Only kept once something is
+/// evicted, so caches that never evict pay nothing for it, and rebuilt when the policy changes.
+#[derive(Debug)]
+pub(crate) struct EvictionIndex<T> {
+    eviction: Option<Eviction>,
+    ranked: RankIndex<EvictionRank, T>,
+}
+
+impl<T> Default for EvictionIndex<T> {
+    fn default() -> Self {
+        Self {
+            eviction: None,
+            ranked: RankIndex::default(),
+        }
+    }
+}
+
+impl<T> EvictionIndex<T> {
+    pub(crate) fn is_kept(&self) -> bool {
+        self.eviction.is_some()
+    }
+
+    /// Where an entry ranks, `None` while the index isn't kept.
+    pub(crate) fn rank(&self, call_cnt: u64, last_accessed: Instant) -> Option<EvictionRank> {
+        self.eviction
+            .map(|eviction| EvictionRank::new(eviction, call_cnt, last_accessed))
+    }
+
+    /// Note an entry stored at `rank`, see `rank`.
+    pub(crate) fn push(&mut self, rank: EvictionRank, item: T) {
+        self.ranked.push(rank, item);
+    }
+
+    /// Start ranking by `eviction`, if not already. Returns whether the index was reset and has to
+    /// be refilled with every entry.
+    pub(crate) fn keep(&mut self, eviction: Eviction) -> bool {
+        if self.eviction == Some(eviction) {
+            return false;
+        }
+        self.eviction = Some(eviction);
+        self.ranked.clear();
+        true
+    }
+
+    /// Empty the index, still ranking as before, for it to be refilled.
+    pub(crate) fn clear(&mut self) {
+        self.ranked.clear();
+    }
+
+    pub(crate) fn is_bloated(&self, n_entries: usize) -> bool {
+        self.eviction.is_some() && self.ranked.is_bloated(n_entries)
+    }
+
+    /// Take out the entry to evict next, see `RankIndex::pop_current`. `state_of` gives the call
+    /// count and last use of an item's entry, `None` for those that are gone or pinned.
+    pub(crate) fn pop_current(
+        &mut self,
+        mut state_of: impl FnMut(&T) -> Option<(u64, Instant)>,
+    ) -> Option<T> {
+        let eviction = self.eviction?;
+        self.ranked.pop_current(|item| {
+            state_of(item).map(|(call_cnt, last_accessed)| {
+                EvictionRank::new(eviction, call_cnt, last_accessed)
+            })
+        })
+    }
+}
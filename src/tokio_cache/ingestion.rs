@@ -0,0 +1,64 @@
+//! Bounding how many commands an actor handles per tick, so one caller flooding its channel
+//! can't hold everyone else up for long.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering};
+
+use crate::tokio_cache::option::ShedPolicy;
+use crate::tokio_cache::trace;
+
+/// The limit of a `HashMapCache` actor, shared between the handles and the actor so
+/// `with_ingestion_limit` takes effect from the next tick, and how many commands it has shed.
+#[derive(Debug, Clone)]
+pub(crate) struct IngestionLimit {
+    // `usize::MAX` without a limit.
+    max_per_tick: Arc<AtomicUsize>,
+    shed_policy: Arc<AtomicU8>,
+    shed: Arc<AtomicU64>,
+}
+
+impl Default for IngestionLimit {
+    fn default() -> Self {
+        Self {
+            max_per_tick: Arc::new(AtomicUsize::new(usize::MAX)),
+            shed_policy: Arc::new(AtomicU8::new(ShedPolicy::default() as u8)),
+            shed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl IngestionLimit {
+    pub(crate) fn set(&self, max_per_tick: usize, shed_policy: ShedPolicy) {
+        self.shed_policy.store(shed_policy as u8, Ordering::Relaxed);
+        self.max_per_tick
+            .store(max_per_tick.max(1), Ordering::Release);
+    }
+
+    pub(crate) fn max_per_tick(&self) -> usize {
+        self.max_per_tick.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn shed_policy(&self) -> ShedPolicy {
+        match self.shed_policy.load(Ordering::Relaxed) {
+            1 => ShedPolicy::WritesFirst,
+            _ => ShedPolicy::OldestReads,
+        }
+    }
+
+    /// Whether `n_handled` commands leave room for another this tick.
+    pub(crate) fn has_room(&self, n_handled: usize) -> bool {
+        n_handled < self.max_per_tick()
+    }
+
+    /// Note a command shed. Dropping it drops the sender of its answer too, which tells its
+    /// caller.
+    pub(crate) fn note_shed(&self) {
+        trace::debug_event!("shed a command under load");
+        self.shed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// How many commands were shed.
+    pub(crate) fn n_shed(&self) -> u64 {
+        self.shed.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,51 @@
+//! Notifications of the keys whose value a cache changed, for copies kept outside of it.
+
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Keys whose cached value may no longer be the one a copy holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Invalidation<K> {
+    /// `key` was written or removed.
+    Key(K),
+    /// Every key may have changed, as when the cache is cleared or synced from its master.
+    All,
+}
+
+/// The channels invalidations are sent on. Unlike replicas, subscribers are sent no values and
+/// are neither synced, counted nor moved by a failover.
+#[derive(Debug)]
+pub(crate) struct Subscribers<K> {
+    subscribers: Vec<UnboundedSender<Invalidation<K>>>,
+}
+
+impl<K> Default for Subscribers<K> {
+    fn default() -> Self {
+        Self {
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+impl<K: Clone> Subscribers<K> {
+    pub(crate) fn add(&mut self, tx: UnboundedSender<Invalidation<K>>) {
+        self.subscribers.push(tx);
+    }
+
+    /// Tell every subscriber that `key` changed, forgetting subscribers that are gone.
+    pub(crate) fn changed(&mut self, key: &K) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        self.notify(Invalidation::Key(key.clone()));
+    }
+
+    /// Tell every subscriber that any key may have changed.
+    pub(crate) fn changed_all(&mut self) {
+        self.notify(Invalidation::All);
+    }
+
+    fn notify(&mut self, invalidation: Invalidation<K>) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(invalidation.clone()).is_ok());
+    }
+}
@@ -0,0 +1,113 @@
+//! TTL jitter, so entries written together with the same TTL don't all expire on the same tick,
+//! and the random numbers behind it.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::tokio_cache::option::TtlOnWrite;
+
+/// Spreads each TTL uniformly within `± fraction` of its length. The fraction is shared between
+/// the handles and the actor, so `with_ttl_jitter` takes effect for the writes that follow it.
+/// So is what overwrites do to TTLs, set by `with_ttl_on_write`.
+#[derive(Debug, Clone)]
+pub(crate) struct TtlJitter {
+    fraction: Arc<AtomicU64>,
+    on_write: Arc<AtomicU8>,
+    rng: Rng,
+}
+
+impl TtlJitter {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            fraction: Arc::new(AtomicU64::new(0f64.to_bits())),
+            on_write: Arc::new(AtomicU8::new(TtlOnWrite::Reset as u8)),
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub(crate) fn set_on_write(&self, on_write: TtlOnWrite) {
+        self.on_write.store(on_write as u8, Ordering::Relaxed);
+    }
+
+    /// When an entry written now with TTL `ex` expires, given the expiration `old` of the entry
+    /// it overwrites, if any.
+    pub(crate) fn expiration_on_write(
+        &mut self,
+        ex: Option<Duration>,
+        old: Option<Option<Instant>>,
+    ) -> Option<Instant> {
+        let on_write = match self.on_write.load(Ordering::Relaxed) {
+            1 => TtlOnWrite::Keep,
+            2 => TtlOnWrite::Extend,
+            _ => TtlOnWrite::Reset,
+        };
+        match (on_write, old) {
+            (TtlOnWrite::Reset, _) | (_, None) => self.expiration(ex),
+            (TtlOnWrite::Keep, Some(old)) => old,
+            (TtlOnWrite::Extend, Some(old)) => match (old, ex) {
+                (Some(old), Some(ex)) => Some(old + self.jitter(ex)),
+                (old, _) => old,
+            },
+        }
+    }
+
+    /// `fraction` is clamped to `0.0..=1.0`; `0.1` means ±10%.
+    pub(crate) fn set(&self, fraction: f64) {
+        let fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, 1.0)
+        };
+        self.fraction.store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    /// When an entry written now with TTL `ex` expires.
+    pub(crate) fn expiration(&mut self, ex: Option<Duration>) -> Option<Instant> {
+        ex.map(|ex| Instant::now() + self.jitter(ex))
+    }
+
+    fn jitter(&mut self, ex: Duration) -> Duration {
+        let fraction = f64::from_bits(self.fraction.load(Ordering::Relaxed));
+        if fraction == 0.0 {
+            return ex;
+        }
+        // Uniform in [-1, 1).
+        let unit = self.rng.unit() * 2.0 - 1.0;
+        ex.mul_f64(1.0 + fraction * unit)
+    }
+}
+
+/// Xorshift, cheap and good enough to spread TTLs and sample vals, not for anything secret.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            // Keep the state non-zero whatever the seed.
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `0..n`, up to a bias that is negligible for cache sizes; `n` must not be 0.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
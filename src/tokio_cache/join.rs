@@ -0,0 +1,45 @@
+//! Reads from several caches at once, whatever their key and value types, awaited together.
+//!
+//! ```ignore
+//! let (users, sessions) = join::mget2(&users, &user_ids, &sessions, &session_ids).await?;
+//! ```
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+
+/// `mget` of `keys_a` from `a` and of `keys_b` from `b`, sent to both actors at once. Fails with
+/// the first error either gives.
+pub async fn mget2<KA, VA, CA, KB, VB, CB>(
+    a: &HashMapCache<KA, VA, CA>,
+    keys_a: &[KA],
+    b: &HashMapCache<KB, VB, CB>,
+    keys_b: &[KB],
+) -> Result<(Vec<Option<VA>>, Vec<Option<VB>>), TokioActorCacheError>
+where
+    KA: Clone,
+    VA: Clone,
+    KB: Clone,
+    VB: Clone,
+{
+    tokio::try_join!(a.mget(keys_a), b.mget(keys_b))
+}
+
+/// `mget2` over three caches.
+pub async fn mget3<KA, VA, CA, KB, VB, CB, KC, VC, CC>(
+    a: &HashMapCache<KA, VA, CA>,
+    keys_a: &[KA],
+    b: &HashMapCache<KB, VB, CB>,
+    keys_b: &[KB],
+    c: &HashMapCache<KC, VC, CC>,
+    keys_c: &[KC],
+) -> Result<(Vec<Option<VA>>, Vec<Option<VB>>, Vec<Option<VC>>), TokioActorCacheError>
+where
+    KA: Clone,
+    VA: Clone,
+    KB: Clone,
+    VB: Clone,
+    KC: Clone,
+    VC: Clone,
+{
+    tokio::try_join!(a.mget(keys_a), b.mget(keys_b), c.mget(keys_c))
+}
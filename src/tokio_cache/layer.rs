@@ -0,0 +1,196 @@
+//! Tower middleware that caches the responses of an inner service in a `HashMapCache`.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use tower::{Layer, Service};
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::option::InsertPolicy;
+use crate::tokio_cache::trace;
+
+/// A response that can be stored as bytes.
+pub trait Cacheable: Sized {
+    /// The bytes to cache, or `None` for responses that shouldn't be cached, such as errors.
+    fn to_bytes(&self) -> Option<Bytes>;
+
+    fn from_bytes(bytes: Bytes) -> Self;
+}
+
+impl Cacheable for Bytes {
+    fn to_bytes(&self) -> Option<Bytes> {
+        Some(self.clone())
+    }
+
+    fn from_bytes(bytes: Bytes) -> Self {
+        bytes
+    }
+}
+
+impl Cacheable for Vec<u8> {
+    fn to_bytes(&self) -> Option<Bytes> {
+        Some(Bytes::copy_from_slice(self))
+    }
+
+    fn from_bytes(bytes: Bytes) -> Self {
+        bytes.to_vec()
+    }
+}
+
+/// Caches responses under the key `key` extracts from each request; requests it returns `None`
+/// for go straight to the inner service.
+///
+/// Responses are fresh for `ttl`. With `stale_while_revalidate`, a response stays cached for that
+/// much longer, during which it is still served while one background request refreshes it.
+#[derive(Debug, Clone)]
+pub struct CacheLayer<K, C, F> {
+    cache: HashMapCache<K, Bytes, C>,
+    key: F,
+    ttl: Duration,
+    stale_while_revalidate: Duration,
+}
+
+impl<K, C, F> CacheLayer<K, C, F> {
+    pub fn new(cache: HashMapCache<K, Bytes, C>, key: F, ttl: Duration) -> Self {
+        Self {
+            cache,
+            key,
+            ttl,
+            stale_while_revalidate: Duration::ZERO,
+        }
+    }
+
+    pub fn stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.stale_while_revalidate = window;
+        self
+    }
+}
+
+impl<S, K: Clone, C: Clone, F: Clone> Layer<S> for CacheLayer<K, C, F> {
+    type Service = CachedService<S, K, C, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CachedService {
+            inner,
+            layer: self.clone(),
+            revalidating: Arc::default(),
+        }
+    }
+}
+
+/// The service built by `CacheLayer`.
+#[derive(Debug, Clone)]
+pub struct CachedService<S, K, C, F> {
+    inner: S,
+    layer: CacheLayer<K, C, F>,
+    // Keys being refreshed in the background, so a stale entry is refreshed only once.
+    revalidating: Arc<Mutex<HashSet<K>>>,
+}
+
+impl<S, Req, K, C, F> Service<Req> for CachedService<S, K, C, F>
+where
+    S: Service<Req> + Clone + Send + 'static,
+    S::Response: Cacheable + Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+    Req: Send + 'static,
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    C: Clone + Send + Sync + 'static,
+    F: Fn(&Req) -> Option<K> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        // Take the service that was polled ready and leave a fresh clone behind.
+        let inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, inner);
+        let Some(key) = (self.layer.key)(&req) else {
+            return Box::pin(inner.call(req));
+        };
+        let cache = self.layer.cache.clone();
+        let ex = self.layer.ttl + self.layer.stale_while_revalidate;
+        let stale_while_revalidate = self.layer.stale_while_revalidate;
+        let revalidating = self.revalidating.clone();
+
+        Box::pin(async move {
+            // Serve from the inner service if the cache can't answer.
+            match lookup(&cache, key.clone()).await {
+                Ok(Some((bytes, ttl))) => {
+                    let is_stale = ttl.is_some_and(|ttl| ttl <= stale_while_revalidate);
+                    if is_stale && start_revalidating(&revalidating, &key) {
+                        tokio::spawn(async move {
+                            if let Ok(resp) = inner.call(req).await {
+                                store(&cache, key.clone(), resp.to_bytes(), ex).await;
+                            }
+                            revalidating
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .remove(&key);
+                        });
+                    }
+                    return Ok(S::Response::from_bytes(bytes));
+                }
+                Ok(None) => (),
+                Err(_err) => {
+                    trace::warn_event!(error = %_err, "cache lookup failed, calling inner service");
+                }
+            }
+            let resp = inner.call(req).await?;
+            store(&cache, key, resp.to_bytes(), ex).await;
+            Ok(resp)
+        })
+    }
+}
+
+/// The cached bytes of `key` and their remaining time to live.
+async fn lookup<K, C>(
+    cache: &HashMapCache<K, Bytes, C>,
+    key: K,
+) -> Result<Option<(Bytes, Option<Duration>)>, TokioActorCacheError>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+{
+    let Some(bytes) = cache.get(key.clone()).await? else {
+        return Ok(None);
+    };
+    let ttl = cache.ttl(&[key]).await?.pop().flatten();
+    Ok(Some((bytes, ttl)))
+}
+
+/// Cache `bytes`, if the response was cacheable.
+async fn store<K, C>(cache: &HashMapCache<K, Bytes, C>, key: K, bytes: Option<Bytes>, ex: Duration)
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+{
+    let Some(bytes) = bytes else {
+        return;
+    };
+    if let Err(_err) = cache
+        .insert(key, bytes, Some(ex), InsertPolicy::Always)
+        .await
+    {
+        trace::warn_event!(error = %_err, "cannot cache response");
+    }
+}
+
+/// Returns whether the caller should refresh `key`, i.e. nobody else is doing so.
+fn start_revalidating<K: Clone + Eq + Hash>(revalidating: &Mutex<HashSet<K>>, key: &K) -> bool {
+    revalidating
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(key.clone())
+}
@@ -0,0 +1,25 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::tokio_cache::channel::{self, Local};
+use crate::tokio_cache::error::ConfigError;
+use crate::tokio_cache::option::ExpirationPolicy;
+
+/// A `HashMapCache` whose actor runs on the current thread's `LocalSet` rather than on the
+/// multi-threaded runtime, so its keys and values needn't be `Send`, e.g. `Rc`s, and it runs
+/// where there is only one thread, such as wasm32. Operations that hand values to other threads,
+/// like `snapshot`, still need them to be `Send`.
+pub type HashMapCache<K, V> = crate::tokio_cache::hm::HashMapCache<K, V, Local>;
+
+impl<K, V> HashMapCache<K, V>
+where
+    K: Debug + Clone + Eq + Hash + 'static,
+    V: Debug + Clone + 'static,
+{
+    /// Start the actor on the current `LocalSet`. Panics outside of one, like
+    /// `tokio::task::spawn_local`.
+    pub async fn new(expiration_policy: ExpirationPolicy) -> Result<Self, ConfigError> {
+        let (tx, rx) = channel::unbounded();
+        Self::spawn_local(expiration_policy, tx, rx)
+    }
+}
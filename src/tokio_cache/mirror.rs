@@ -0,0 +1,169 @@
+//! Mirroring a whole cluster onto a standby cluster, e.g. to warm the green side of a blue/green
+//! deploy.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::tokio_cache::channel::{self, CacheReceiver, CacheSender, SendMode};
+use crate::tokio_cache::cmd::HashMapCmd;
+use crate::tokio_cache::data_struct::ValueWithState;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm_cluster::HashMapCacheCluster;
+use crate::tokio_cache::option::{InsertEntry, InsertPolicy};
+use crate::tokio_cache::router::KeyRouter;
+use crate::tokio_cache::trace;
+
+/// Writes a primary node may queue for the replicator before it falls behind and gets resynced.
+const BUFFER: usize = 1024;
+
+/// Follows every node of a primary cluster like a replica would, and applies what each of them
+/// sends to a standby cluster, routing the keys by the standby's own router. The clusters may
+/// differ in size and router, unlike with `HashMapCacheCluster::replicate`, and the standby nodes
+/// stay masters, so they keep their own expirations and replicas.
+///
+/// Entries arrive with their remaining TTL but without their call counts. Mirroring stops on
+/// `stop` or once dropped.
+#[derive(Debug)]
+pub struct ClusterReplicator<K, V> {
+    links: Vec<Link<K, V>>,
+    tasks: JoinSet<()>,
+}
+
+/// A primary node and the sender it feeds the replicator through.
+#[derive(Debug)]
+struct Link<K, V> {
+    node: CacheSender<HashMapCmd<K, V>>,
+    tx: CacheSender<HashMapCmd<K, V>>,
+}
+
+impl<K, V> ClusterReplicator<K, V>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+{
+    /// Start mirroring `primary` onto `standby`. Each primary node first sends its full state,
+    /// then every write it applies.
+    pub async fn start<C, R, C2, R2>(
+        primary: &HashMapCacheCluster<K, V, C, R>,
+        standby: &HashMapCacheCluster<K, V, C2, R2>,
+    ) -> Result<Self, TokioActorCacheError>
+    where
+        C2: Clone + Send + Sync + 'static,
+        R2: KeyRouter<K> + Clone + Send + Sync + 'static,
+    {
+        let mut replicator = Self {
+            links: Vec::with_capacity(primary.nodes.len()),
+            tasks: JoinSet::new(),
+        };
+        for node in primary.nodes.values() {
+            let (tx, rx) = channel::bounded(BUFFER);
+            replicator.tasks.spawn(mirror_node(rx, standby.clone()));
+            let add_replica_cmd = HashMapCmd::AddReplica {
+                replica: tx.clone(),
+            };
+            node.tx.send(add_replica_cmd, SendMode::Wait).await?;
+            replicator.links.push(Link {
+                node: node.tx.clone(),
+                tx,
+            });
+        }
+        Ok(replicator)
+    }
+
+    /// Whether every primary node is still being mirrored; a node stops once its actor is gone.
+    pub fn is_running(&self) -> bool {
+        self.links.iter().all(|link| !link.node.is_closed())
+    }
+
+    /// Stop mirroring. The standby keeps what it has.
+    pub async fn stop(mut self) {
+        for Link { node, tx } in self.links.drain(..) {
+            // Best effort: a primary node that is gone has nothing left to send anyway.
+            let _ = node
+                .send(HashMapCmd::RemoveReplica { replica: tx }, SendMode::Wait)
+                .await;
+        }
+        self.tasks.shutdown().await;
+    }
+}
+
+/// Apply what a primary node sends its replicas to `standby` until the node stops sending.
+async fn mirror_node<K, V, C, R>(
+    mut rx: CacheReceiver<HashMapCmd<K, V>>,
+    standby: HashMapCacheCluster<K, V, C, R>,
+) where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    C: Clone,
+    R: KeyRouter<K>,
+{
+    // Keys mirrored from this node, so a full sync can remove the ones it no longer has.
+    let mut mirrored = HashSet::new();
+    while let Some(cmd) = rx.recv().await {
+        let (puts, deletes) = match cmd {
+            HashMapCmd::Put { key, val, .. } => (vec![(key, val)], Vec::new()),
+            HashMapCmd::Delete { keys, .. } => (Vec::new(), keys),
+            HashMapCmd::Delta { puts, deletes, .. } => (puts, deletes),
+            HashMapCmd::Sync { hm, .. } => {
+                let deletes = mirrored
+                    .iter()
+                    .filter(|key| !hm.contains_key(*key))
+                    .cloned()
+                    .collect();
+                (hm.into_iter().collect(), deletes)
+            }
+            // Nothing else is sent to replicas but a failover, which the primary handles.
+            _ => continue,
+        };
+        for key in &deletes {
+            mirrored.remove(key);
+        }
+        mirrored.extend(puts.iter().map(|(key, _)| key.clone()));
+        if let Err(_err) = apply(&standby, puts, deletes).await {
+            trace::warn_event!(err = %_err, "cannot mirror to the standby cluster");
+        }
+    }
+}
+
+async fn apply<K, V, C, R>(
+    standby: &HashMapCacheCluster<K, V, C, R>,
+    puts: Vec<(K, ValueWithState<V>)>,
+    mut deletes: Vec<K>,
+) -> Result<(), TokioActorCacheError>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    C: Clone,
+    R: KeyRouter<K>,
+{
+    let now = Instant::now();
+    let mut entries = Vec::with_capacity(puts.len());
+    for (key, val_with_state) in puts {
+        let ex = val_with_state
+            .expiration
+            .map(|expiration| expiration.saturating_duration_since(now));
+        // Expired on its way here; the primary is about to delete it too.
+        if ex.is_some_and(|ex| ex.is_zero()) {
+            deletes.push(key);
+            continue;
+        }
+        entries.push(InsertEntry {
+            key,
+            val: val_with_state.val,
+            ex,
+            tti: val_with_state.tti,
+            policy: InsertPolicy::Always,
+        });
+    }
+    if !entries.is_empty() {
+        standby.minsert_entries(&entries).await?;
+    }
+    if !deletes.is_empty() {
+        standby.remove(&deletes).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,280 @@
+//! Multi-tenant caches: keys prefixed with a tenant id, with entry counts and quotas kept per
+//! tenant by the actor.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::tokio_cache::channel::{SendMode, reply};
+use crate::tokio_cache::cmd::{HashMapCmd, KeyFilter, NamespaceAccess};
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::ops::mode_pairs;
+use crate::tokio_cache::option::InsertPolicy;
+
+/// The per-namespace bookkeeping a `HashMapCache` actor keeps once a `NamespacedCache` wraps it,
+/// behind a trait so the actor doesn't need to know the tenant type.
+pub(crate) trait NamespaceIndex<K>: Send {
+    /// `key` was not cached and now is.
+    fn added(&mut self, key: &K);
+    /// `key` was cached and no longer is.
+    fn removed(&mut self, key: &K);
+    fn clear(&mut self);
+    /// How many entries the namespace of `key` holds over its quota.
+    fn excess(&self, key: &K) -> usize;
+    fn same_namespace(&self, a: &K, b: &K) -> bool;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Entry counts and quotas by tenant, for keys `(tenant, key)`.
+#[derive(Debug)]
+struct NamespaceTable<T> {
+    counts: HashMap<T, usize>,
+    quotas: HashMap<T, usize>,
+    default_quota: Option<usize>,
+}
+
+impl<T> Default for NamespaceTable<T> {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::new(),
+            quotas: HashMap::new(),
+            default_quota: None,
+        }
+    }
+}
+
+impl<T> NamespaceTable<T>
+where
+    T: Eq + Hash,
+{
+    fn quota(&self, tenant: &T) -> Option<usize> {
+        self.quotas.get(tenant).copied().or(self.default_quota)
+    }
+}
+
+impl<T, K> NamespaceIndex<(T, K)> for NamespaceTable<T>
+where
+    T: Clone + Eq + Hash + Send + 'static,
+{
+    fn added(&mut self, (tenant, _): &(T, K)) {
+        *self.counts.entry(tenant.clone()).or_default() += 1;
+    }
+
+    fn removed(&mut self, (tenant, _): &(T, K)) {
+        if let Some(count) = self.counts.get_mut(tenant) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(tenant);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    fn excess(&self, (tenant, _): &(T, K)) -> usize {
+        let count = self.counts.get(tenant).copied().unwrap_or(0);
+        self.quota(tenant)
+            .map_or(0, |quota| count.saturating_sub(quota))
+    }
+
+    fn same_namespace(&self, (a, _): &(T, K), (b, _): &(T, K)) -> bool {
+        a == b
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A `HashMapCache` shared by tenants, whose keys are `(tenant, key)`. The actor counts the
+/// entries of each tenant and, when a write takes a tenant over its quota, evicts that tenant's
+/// entries by the expiration policy, least recently used first without one, so one tenant can't
+/// crowd the others out. The cache-wide capacity still applies on top.
+#[derive(Debug, Clone)]
+pub struct NamespacedCache<T, K, V, C> {
+    cache: HashMapCache<(T, K), V, C>,
+}
+
+impl<T, K, V, C> NamespacedCache<T, K, V, C>
+where
+    T: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Send + Sync + 'static,
+{
+    /// Share `cache` between tenants, counting the entries it already holds.
+    pub async fn new(cache: HashMapCache<(T, K), V, C>) -> Result<Self, TokioActorCacheError> {
+        let namespaced = Self { cache };
+        namespaced.access_with(|_| (), SendMode::Wait).await?;
+        Ok(namespaced)
+    }
+
+    /// The cache underneath, whose keys carry their tenant.
+    pub fn cache(&self) -> &HashMapCache<(T, K), V, C> {
+        &self.cache
+    }
+
+    mode_pairs! {
+        fn get / try_get(tenant: T, key: K) -> Result<Option<V>, TokioActorCacheError> => get_with;
+        fn insert / try_insert(
+            tenant: T,
+            key: K,
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) -> Result<(), TokioActorCacheError> => insert_with;
+        fn remove / try_remove(tenant: T, keys: &[K])
+            -> Result<Vec<Option<V>>, TokioActorCacheError> => remove_with;
+        /// Remove every entry of `tenant`. Returns how many there were.
+        fn clear_namespace / try_clear_namespace(tenant: T)
+            -> Result<usize, TokioActorCacheError> => clear_namespace_with;
+        /// How many entries `tenant` holds.
+        fn namespace_len / try_namespace_len(tenant: T)
+            -> Result<usize, TokioActorCacheError> => namespace_len_with;
+        /// How many entries each tenant holds, leaving out those with none.
+        fn namespace_counts / try_namespace_counts()
+            -> Result<HashMap<T, usize>, TokioActorCacheError> => namespace_counts_with;
+        /// Cap `tenant` at `max_entries`, or lift its own cap with `None` so the default applies.
+        /// A tenant already over a lowered quota is evicted down to it on its next write.
+        fn set_quota / try_set_quota(tenant: T, max_entries: Option<usize>)
+            -> Result<(), TokioActorCacheError> => set_quota_with;
+        /// The quota of tenants without one of their own, none by default.
+        fn set_default_quota / try_set_default_quota(max_entries: Option<usize>)
+            -> Result<(), TokioActorCacheError> => set_default_quota_with;
+    }
+
+    async fn get_with(
+        &self,
+        tenant: T,
+        key: K,
+        mode: SendMode,
+    ) -> Result<Option<V>, TokioActorCacheError> {
+        match mode {
+            SendMode::Try => self.cache.try_get((tenant, key)).await,
+            SendMode::Wait => self.cache.get((tenant, key)).await,
+        }
+    }
+
+    async fn insert_with(
+        &self,
+        tenant: T,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let key = (tenant, key);
+        match mode {
+            SendMode::Try => self.cache.try_insert(key, val, ex, policy).await,
+            SendMode::Wait => self.cache.insert(key, val, ex, policy).await,
+        }
+    }
+
+    async fn remove_with(
+        &self,
+        tenant: T,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        let keys = keys
+            .iter()
+            .map(|key| (tenant.clone(), key.clone()))
+            .collect::<Vec<_>>();
+        match mode {
+            SendMode::Try => self.cache.try_remove(&keys).await,
+            SendMode::Wait => self.cache.remove(&keys).await,
+        }
+    }
+
+    async fn clear_namespace_with(
+        &self,
+        tenant: T,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.cache.check_writable()?;
+        let filter = KeyFilter(Box::new(move |(key_tenant, _): &(T, K)| {
+            *key_tenant == tenant
+        }));
+        self.cache
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::RemoveWhere { filter, resp_tx })
+            .await
+    }
+
+    async fn namespace_len_with(
+        &self,
+        tenant: T,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        self.access_with(
+            move |table| table.counts.get(&tenant).copied().unwrap_or(0),
+            mode,
+        )
+        .await
+    }
+
+    async fn namespace_counts_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<HashMap<T, usize>, TokioActorCacheError> {
+        self.access_with(|table| table.counts.clone(), mode).await
+    }
+
+    async fn set_quota_with(
+        &self,
+        tenant: T,
+        max_entries: Option<usize>,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        self.access_with(
+            move |table| match max_entries {
+                Some(max_entries) => {
+                    table.quotas.insert(tenant, max_entries);
+                }
+                None => {
+                    table.quotas.remove(&tenant);
+                }
+            },
+            mode,
+        )
+        .await
+    }
+
+    async fn set_default_quota_with(
+        &self,
+        max_entries: Option<usize>,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        self.access_with(move |table| table.default_quota = max_entries, mode)
+            .await
+    }
+
+    /// Run `f` on the actor's `NamespaceTable`, setting one up first if there is none yet.
+    async fn access_with<R, F>(&self, f: F, mode: SendMode) -> Result<R, TokioActorCacheError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut NamespaceTable<T>) -> R + Send + 'static,
+    {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let access = NamespaceAccess(Box::new(move |index| {
+            let index = index.get_or_insert_with(|| Box::new(NamespaceTable::<T>::default()));
+            // Only a `NamespacedCache` sets an index up, and its tenant type is fixed by the key
+            // type, so this is the table for `T`.
+            if let Some(table) = index.as_any_mut().downcast_mut::<NamespaceTable<T>>() {
+                reply(resp_tx, f(table));
+            }
+        }));
+        self.cache
+            .tx
+            .send(HashMapCmd::Namespaces { access }, mode)
+            .await?;
+        self.cache.tx.wait_reply(resp_rx).await
+    }
+}
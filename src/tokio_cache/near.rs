@@ -0,0 +1,200 @@
+//! A small in-process LRU in front of a shared `HashMapCache`.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::time::Duration;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::tokio_cache::channel::SendMode;
+use crate::tokio_cache::cmd::HashMapCmd;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::index::RankIndex;
+use crate::tokio_cache::invalidation::Invalidation;
+use crate::tokio_cache::option::{ExpirationPolicy, InsertPolicy};
+
+/// Serves repeated reads from a local copy and drops that copy whenever the authoritative cache
+/// reports a write to the key.
+#[derive(Debug, Clone)]
+pub struct NearCache<K, V, C> {
+    remote: HashMapCache<K, V, C>,
+    local: Arc<Mutex<Local<K, V>>>,
+}
+
+impl<K, V, C> NearCache<K, V, C>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Debug + Send + Sync + 'static,
+{
+    /// Put a local LRU of `capacity` entries in front of `remote`.
+    pub async fn new(
+        remote: HashMapCache<K, V, C>,
+        capacity: usize,
+    ) -> Result<Self, TokioActorCacheError> {
+        let local = Arc::new(Mutex::new(Local::new(capacity)));
+
+        // Subscribed rather than added as a replica, so `remote` neither counts us nor hands us
+        // to a new master on failover.
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(invalidate(Arc::downgrade(&local), rx));
+        remote
+            .tx
+            .send(HashMapCmd::Subscribe { tx }, SendMode::Wait)
+            .await?;
+
+        Ok(Self { remote, local })
+    }
+
+    pub fn remote(&self) -> &HashMapCache<K, V, C> {
+        &self.remote
+    }
+
+    /// Number of entries currently held locally.
+    pub fn local_len(&self) -> usize {
+        self.local().entries.len()
+    }
+
+    pub async fn get(&self, key: K) -> Result<Option<V>, TokioActorCacheError> {
+        let generation = {
+            let mut local = self.local();
+            if let Some(val) = local.get(&key) {
+                return Ok(Some(val));
+            }
+            local.generation
+        };
+
+        let val = self.remote.get(key.clone()).await?;
+
+        // Skip caching if anything was invalidated while the remote read was in flight.
+        if let Some(val) = &val {
+            let mut local = self.local();
+            if local.generation == generation {
+                local.insert(key, val.clone());
+            }
+        }
+        Ok(val)
+    }
+
+    pub async fn insert(
+        &self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+    ) -> Result<(), TokioActorCacheError> {
+        self.local().invalidate(&key);
+        self.remote.insert(key, val, ex, policy).await
+    }
+
+    pub async fn remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        {
+            let mut local = self.local();
+            for key in keys {
+                local.invalidate(key);
+            }
+        }
+        self.remote.remove(keys).await
+    }
+
+    fn local(&self) -> MutexGuard<'_, Local<K, V>> {
+        self.local.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+async fn invalidate<K, V>(
+    local: Weak<Mutex<Local<K, V>>>,
+    mut rx: UnboundedReceiver<Invalidation<K>>,
+) where
+    K: Eq + Hash,
+{
+    while let Some(invalidation) = rx.recv().await {
+        // Stop once every handle to the near cache is gone.
+        let Some(local) = local.upgrade() else {
+            break;
+        };
+        let mut local = local.lock().unwrap_or_else(PoisonError::into_inner);
+        match invalidation {
+            Invalidation::Key(key) => local.invalidate(&key),
+            Invalidation::All => local.clear(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Local<K, V> {
+    capacity: usize,
+    // Value and the tick it was last read at.
+    entries: HashMap<K, (V, u64)>,
+    // Keys by the tick they were last read at, least recently used first.
+    lru_index: RankIndex<u64, K>,
+    clock: u64,
+    // Bumped by every invalidation.
+    generation: u64,
+}
+
+impl<K, V> Local<K, V>
+where
+    K: Eq + Hash,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::with_capacity(capacity.min(ExpirationPolicy::MAX_PREALLOCATED)),
+            lru_index: RankIndex::default(),
+            clock: 0,
+            generation: 0,
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.clock += 1;
+        let (val, last_accessed) = self.entries.get_mut(key)?;
+        *last_accessed = self.clock;
+        Some(val.clone())
+    }
+
+    fn insert(&mut self, key: K, val: V)
+    where
+        K: Clone,
+    {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&key)
+            && self.entries.len() >= self.capacity
+            && let Some(lru_key) = self.lru_index.pop_current(|key| {
+                self.entries
+                    .get(key)
+                    .map(|(_val, last_accessed)| *last_accessed)
+            })
+        {
+            self.entries.remove(&lru_key);
+        }
+        self.clock += 1;
+        self.lru_index.push(self.clock, key.clone());
+        self.entries.insert(key, (val, self.clock));
+        if self.lru_index.is_bloated(self.entries.len()) {
+            self.lru_index.clear();
+            for (key, (_val, last_accessed)) in &self.entries {
+                self.lru_index.push(*last_accessed, key.clone());
+            }
+        }
+    }
+
+    fn invalidate(&mut self, key: &K) {
+        self.generation += 1;
+        self.entries.remove(key);
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+        self.lru_index.clear();
+    }
+}
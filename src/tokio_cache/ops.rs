@@ -0,0 +1,236 @@
+//! Cache operations defined once and expanded into their client methods.
+//!
+//! `cache_ops!` generates the `try_`/waiting method pairs of a single cache, and `cluster_ops!`
+//! those of a cluster for operations that fan out in one of its usual shapes. Neither generates
+//! the command variants: each actor matches on its own enum in one place, so those are still
+//! written out in `cmd.rs`, where `HashMapCmd::kind` classes each one as a read, a write or
+//! neither, rather than split into a read and a write enum. Cluster operations of other shapes,
+//! such as batched writes, merged reads and replication, still fan out by hand behind
+//! `mode_pairs!`.
+
+/// Expands each operation into `try_$name`, which fails with `ChannelFull` instead of waiting for
+/// room in a bounded channel, and `$name`, which waits. An operation is sent on one of three lanes:
+///
+/// - `read`: `self.reads()`, the priority read lane for point reads where the cache has one.
+/// - `write`: `self.tx`, after `self.check_writable()` refuses writes to read-only replicas.
+/// - `tx`: `self.tx` as is.
+///
+/// An operation with `-> T` waits for the `T` the actor replies on `resp_tx`, one without returns
+/// once the command is queued. Fields are built from the arguments, e.g. `keys: keys.to_vec()`,
+/// or taken as is when only named:
+///
+/// ```ignore
+/// cache_ops! {
+///     HashMapCmd;
+///
+///     /// Doc comments go on both methods.
+///     read fn mget / try_mget(keys: &[K]) -> Vec<Option<V>> => MGet { keys: keys.to_vec() };
+///     write fn clear / try_clear() => Clear;
+/// }
+/// ```
+macro_rules! cache_ops {
+    (
+        $cmd:ident;
+        $(
+            $(#[$attr:meta])*
+            $lane:ident fn $name:ident / $try_name:ident($($arg:ident: $arg_ty:ty),* $(,)?)
+                $(-> $ret:ty)? => $variant:ident $({ $($field:ident $(: $val:expr)?),* $(,)? })?;
+        )*
+    ) => {
+        $(
+            $(#[$attr])*
+            pub async fn $try_name(
+                &self,
+                $($arg: $arg_ty),*
+            ) -> Result<
+                cache_ops!(@ret $($ret)?),
+                $crate::tokio_cache::error::TokioActorCacheError,
+            > {
+                cache_ops!(
+                    @send self, $lane, $crate::tokio_cache::channel::SendMode::Try,
+                    $cmd::$variant { $($($field $(: $val)?,)*)? } $(-> $ret)?
+                )
+            }
+
+            $(#[$attr])*
+            pub async fn $name(
+                &self,
+                $($arg: $arg_ty),*
+            ) -> Result<
+                cache_ops!(@ret $($ret)?),
+                $crate::tokio_cache::error::TokioActorCacheError,
+            > {
+                cache_ops!(
+                    @send self, $lane, $crate::tokio_cache::channel::SendMode::Wait,
+                    $cmd::$variant { $($($field $(: $val)?,)*)? } $(-> $ret)?
+                )
+            }
+        )*
+    };
+
+    (@ret) => { () };
+    (@ret $ret:ty) => { $ret };
+
+    (
+        @send $this:ident, $lane:ident, $mode:expr,
+        $cmd:ident::$variant:ident { $($fields:tt)* } -> $ret:ty
+    ) => {
+        cache_ops!(@lane $this, $lane)
+            .request($mode, |resp_tx| $cmd::$variant { $($fields)* resp_tx })
+            .await
+    };
+    (
+        @send $this:ident, $lane:ident, $mode:expr,
+        $cmd:ident::$variant:ident { $($fields:tt)* }
+    ) => {
+        cache_ops!(@lane $this, $lane)
+            .send($cmd::$variant { $($fields)* }, $mode)
+            .await
+    };
+
+    (@lane $this:ident, read) => { $this.reads() };
+    (@lane $this:ident, write) => {{
+        $this.check_writable()?;
+        &$this.tx
+    }};
+    (@lane $this:ident, tx) => { &$this.tx };
+}
+
+/// Expands each operation of a cluster into `try_$name` and `$name` as `cache_ops!` does for a
+/// single cache, sending to the nodes in one of three ways:
+///
+/// - `routed`: to the node the first argument routes to, e.g. the key of `get`.
+/// - `each`: to the node each item of the first argument, a slice, routes to, one command per
+///   item, with the replies collected in the order of the items. The other fields are copied
+///   into every command.
+/// - `every`: to every live node.
+///
+/// Fields are named after the arguments they are taken from, and a node the health check has down
+/// is replaced by its standby as `get_node` and `live_nodes` do:
+///
+/// ```ignore
+/// cluster_ops! {
+///     HashMapCmd;
+///
+///     routed fn get / try_get(key: K) -> Option<V> => Get { key };
+///     each fn mget / try_mget(keys: &[K]) -> Vec<Option<V>> => MGet { keys };
+///     every fn clear / try_clear() => Clear;
+/// }
+/// ```
+macro_rules! cluster_ops {
+    (
+        $cmd:ident;
+        $(
+            $(#[$attr:meta])*
+            $fan:ident fn $name:ident / $try_name:ident($($arg:ident: $arg_ty:ty),* $(,)?)
+                $(-> $ret:ty)? => $variant:ident $({ $($field:ident),* $(,)? })?;
+        )*
+    ) => {
+        $(
+            $(#[$attr])*
+            pub async fn $try_name(
+                &self,
+                $($arg: $arg_ty),*
+            ) -> Result<
+                $crate::tokio_cache::ops::cache_ops!(@ret $($ret)?),
+                $crate::tokio_cache::error::TokioActorCacheError,
+            > {
+                cluster_ops!(
+                    @fan self, $fan, $crate::tokio_cache::channel::SendMode::Try,
+                    $cmd::$variant { $($($field),*)? } $(-> $ret)?
+                )
+            }
+
+            $(#[$attr])*
+            pub async fn $name(
+                &self,
+                $($arg: $arg_ty),*
+            ) -> Result<
+                $crate::tokio_cache::ops::cache_ops!(@ret $($ret)?),
+                $crate::tokio_cache::error::TokioActorCacheError,
+            > {
+                cluster_ops!(
+                    @fan self, $fan, $crate::tokio_cache::channel::SendMode::Wait,
+                    $cmd::$variant { $($($field),*)? } $(-> $ret)?
+                )
+            }
+        )*
+    };
+
+    (
+        @fan $this:ident, routed, $mode:expr,
+        $cmd:ident::$variant:ident { $first:ident $(, $field:ident)* } $(-> $ret:ty)?
+    ) => {{
+        let node = $this.get_node(&$first)?;
+        cluster_ops!(@send node, $mode, $cmd::$variant { $first, $($field,)* } $(-> $ret)?)
+    }};
+    (
+        @fan $this:ident, each, $mode:expr,
+        $cmd:ident::$variant:ident { $first:ident $(, $field:ident)* } -> $ret:ty
+    ) => {{
+        let mut res = Vec::new();
+        for item in $first.iter().cloned() {
+            let node = $this.get_node(&item)?;
+            let replies = node
+                .tx
+                .request($mode, |resp_tx| $cmd::$variant {
+                    $first: vec![item],
+                    $($field,)*
+                    resp_tx,
+                })
+                .await?;
+            res.extend(replies);
+        }
+
+        Ok(res)
+    }};
+    (
+        @fan $this:ident, every, $mode:expr,
+        $cmd:ident::$variant:ident {} $(-> $ret:ty)?
+    ) => {{
+        for node in $this.live_nodes() {
+            cluster_ops!(@send node, $mode, $cmd::$variant {} $(-> $ret)?)?;
+        }
+
+        Ok(())
+    }};
+
+    (@send $node:ident, $mode:expr, $cmd:ident::$variant:ident { $($fields:tt)* } -> $ret:ty) => {
+        $node
+            .tx
+            .request($mode, |resp_tx| $cmd::$variant { $($fields)* resp_tx })
+            .await
+    };
+    (@send $node:ident, $mode:expr, $cmd:ident::$variant:ident { $($fields:tt)* }) => {
+        $node.tx.send($cmd::$variant { $($fields)* }, $mode).await
+    };
+}
+
+/// Expands each `fn $name / $try_name(args) -> T => $with;` into `try_$name` and `$name`, which
+/// call `self.$with(args, mode)` with `SendMode::Try` and `SendMode::Wait`, for handles like the
+/// clusters whose operations fan out over several actors.
+macro_rules! mode_pairs {
+    (
+        $(
+            $(#[$attr:meta])*
+            fn $name:ident / $try_name:ident($($arg:ident: $arg_ty:ty),* $(,)?) -> $ret:ty
+                => $with:ident;
+        )*
+    ) => {
+        $(
+            $(#[$attr])*
+            pub async fn $try_name(&self, $($arg: $arg_ty),*) -> $ret {
+                self.$with($($arg,)* $crate::tokio_cache::channel::SendMode::Try).await
+            }
+
+            $(#[$attr])*
+            pub async fn $name(&self, $($arg: $arg_ty),*) -> $ret {
+                self.$with($($arg,)* $crate::tokio_cache::channel::SendMode::Wait).await
+            }
+        )*
+    };
+}
+
+pub(crate) use cache_ops;
+pub(crate) use cluster_ops;
+pub(crate) use mode_pairs;
@@ -1,6 +1,337 @@
-#[derive(Clone, Copy)]
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use crate::tokio_cache::error::{ConfigError, TokioActorCacheError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExpirationPolicy {
     LFU(usize),
     LRU(usize),
     None,
-}
\ No newline at end of file
+}
+
+impl ExpirationPolicy {
+    /// The largest capacity an LFU or LRU policy may have, past what any machine could hold, so
+    /// that a larger one is taken for a mistake.
+    pub const MAX_CAPACITY: usize = u32::MAX as usize;
+
+    /// The most entries a cache allocates room for up front, however large its capacity; it
+    /// grows past that as entries come.
+    pub(crate) const MAX_PREALLOCATED: usize = 4096;
+
+    /// Whether a cache can keep as many entries as the policy says.
+    pub fn validate(self) -> Result<(), ConfigError> {
+        match self.capacity() {
+            Some(0) => Err(ConfigError::ZeroCapacity),
+            Some(capacity) if capacity > Self::MAX_CAPACITY => Err(ConfigError::CapacityTooLarge {
+                capacity,
+                max: Self::MAX_CAPACITY,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// How many entries a cache under the policy allocates room for when it starts.
+    pub(crate) fn preallocated(self) -> usize {
+        self.capacity()
+            .map_or(0, |capacity| capacity.min(Self::MAX_PREALLOCATED))
+    }
+
+    /// How many entries the policy keeps, `None` if it doesn't evict.
+    pub fn capacity(self) -> Option<usize> {
+        match self {
+            ExpirationPolicy::LFU(capacity) | ExpirationPolicy::LRU(capacity) => Some(capacity),
+            ExpirationPolicy::None => None,
+        }
+    }
+
+    /// The same policy keeping `capacity` entries, `None` if it doesn't evict.
+    pub(crate) fn with_capacity(self, capacity: usize) -> Option<Self> {
+        match self {
+            ExpirationPolicy::LFU(_) => Some(ExpirationPolicy::LFU(capacity)),
+            ExpirationPolicy::LRU(_) => Some(ExpirationPolicy::LRU(capacity)),
+            ExpirationPolicy::None => None,
+        }
+    }
+}
+
+/// Whether a write goes ahead, depending on whether the entry is already cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InsertPolicy {
+    /// Insert, overwriting any existing entry.
+    #[default]
+    Always,
+    /// Only insert entries that aren't cached yet, like Redis `NX`.
+    IfAbsent,
+    /// Only overwrite entries that are already cached, like Redis `XX`.
+    IfPresent,
+}
+
+impl InsertPolicy {
+    pub(crate) fn allows(self, present: bool) -> bool {
+        match self {
+            InsertPolicy::Always => true,
+            InsertPolicy::IfAbsent => !present,
+            InsertPolicy::IfPresent => present,
+        }
+    }
+}
+
+/// What overwriting a cached key does to its TTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TtlOnWrite {
+    /// Expire after the TTL of the write, or never without one.
+    #[default]
+    Reset,
+    /// Keep the expiration the key had, like Redis `SET ... KEEPTTL`.
+    Keep,
+    /// Push the expiration the key had back by the TTL of the write. A key without a TTL keeps
+    /// living for good, and so does one written without a TTL.
+    Extend,
+}
+
+/// What a `HashMapCache` actor sheds of a backlog it can't get through within a tick, see
+/// `HashMapCache::with_ingestion_limit`. Shed commands fail with `TokioActorCacheError::Shed`,
+/// or are dropped when nobody waits for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShedPolicy {
+    /// Reject the oldest queued reads, whose callers have waited the longest and are the likeliest
+    /// to have given up. Writes are never shed.
+    #[default]
+    OldestReads,
+    /// Reject queued writes first, so reads keep being served, for caches whose writes can be
+    /// redone, e.g. by loading the value again.
+    WritesFirst,
+}
+
+/// What a `HashMapCache` actor does about a caller that stopped waiting for its reply, e.g.
+/// because its request timed out or its future was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DroppedReceiver {
+    /// Log it.
+    #[default]
+    Log,
+    /// Also count it, see `HashMapCache::dropped_receivers`.
+    Count,
+    /// Count it, and skip reads whose caller is already gone when the actor gets to them rather
+    /// than build an answer nobody gets, e.g. a large `get_all`. Writes are applied all the same.
+    Skip,
+}
+
+/// An end of a `VecCache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VecEnd {
+    /// Where the oldest pushes are.
+    #[default]
+    Front,
+    Back,
+}
+
+/// Which occurrences of a value `VecCache::remove` deletes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RemovePolicy {
+    /// Every occurrence, like Redis `LREM` with a count of 0.
+    #[default]
+    All,
+    /// The first occurrence only, like Redis `LREM` with a count of 1.
+    First,
+}
+
+/// Redis `SET`-style options for `HashMapCache::set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InsertOptions {
+    pub ex: Option<Duration>,
+    /// Expire at this wall-clock time instead of after `ex`, like Redis `EXAT`, e.g. for a token
+    /// whose expiry a server hands out. Not spread by `with_ttl_jitter`.
+    pub exat: Option<SystemTime>,
+    /// Drop the entry once it goes unused for this long, even if `ex` hasn't run out yet, like
+    /// a session timeout. Reads and writes of the entry start the window over.
+    pub tti: Option<Duration>,
+    pub policy: InsertPolicy,
+    /// Return the value the key held before the write, like Redis `GET`. It is returned even
+    /// when `policy` skips the write.
+    pub get: bool,
+}
+
+/// One write of `HashMapCache::minsert_entries`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertEntry<K, V> {
+    pub key: K,
+    pub val: V,
+    pub ex: Option<Duration>,
+    /// See `InsertOptions::tti`.
+    pub tti: Option<Duration>,
+    pub policy: InsertPolicy,
+}
+
+impl<K, V> InsertEntry<K, V> {
+    /// An entry without a TTL, written with `InsertPolicy::Always`.
+    pub fn new(key: K, val: V) -> Self {
+        Self {
+            key,
+            val,
+            ex: None,
+            tti: None,
+            policy: InsertPolicy::Always,
+        }
+    }
+
+    pub fn with_ex(mut self, ex: Duration) -> Self {
+        self.ex = Some(ex);
+        self
+    }
+
+    pub fn with_tti(mut self, tti: Duration) -> Self {
+        self.tti = Some(tti);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: InsertPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Bundle the parallel slices taken by `minsert`.
+    pub(crate) fn zip(
+        keys: &[K],
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<Vec<Self>, TokioActorCacheError>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        TokioActorCacheError::check_len(keys.len(), &[vals.len(), ex.len(), policy.len()])?;
+
+        Ok(keys
+            .iter()
+            .zip(vals)
+            .zip(ex)
+            .zip(policy)
+            .map(|(((key, val), ex), policy)| Self {
+                key: key.clone(),
+                val: val.clone(),
+                ex: *ex,
+                tti: None,
+                policy: *policy,
+            })
+            .collect())
+    }
+}
+
+/// What a cache does with direct writes while it is replicating a master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplicaWrites {
+    /// Apply them; the master overwrites the keys it writes afterwards.
+    #[default]
+    Allow,
+    /// Fail them with `TokioActorCacheError::ReadOnlyReplica`.
+    Reject,
+}
+
+/// How to build a single cache, e.g. one made by `CacheRegistry::get_or_create`.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+    pub expiration_policy: ExpirationPolicy,
+    /// Channel capacity of a bounded cache; unbounded caches ignore it.
+    pub buffer: usize,
+    /// See `HashMapCache::with_ttl_jitter`.
+    pub ttl_jitter: f64,
+    /// Commands handled per tick and what to shed past them, see
+    /// `HashMapCache::with_ingestion_limit`. No limit by default.
+    pub ingestion_limit: Option<(usize, ShedPolicy)>,
+}
+
+impl CacheConfig {
+    const DEFAULT_BUFFER: usize = 32;
+
+    pub fn new(expiration_policy: ExpirationPolicy) -> Self {
+        Self {
+            expiration_policy,
+            buffer: Self::DEFAULT_BUFFER,
+            ttl_jitter: 0.0,
+            ingestion_limit: None,
+        }
+    }
+
+    pub fn with_buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer;
+        self
+    }
+
+    pub fn with_ttl_jitter(mut self, fraction: f64) -> Self {
+        self.ttl_jitter = fraction;
+        self
+    }
+
+    pub fn with_ingestion_limit(mut self, max_per_tick: usize, shed: ShedPolicy) -> Self {
+        self.ingestion_limit = Some((max_per_tick, shed));
+        self
+    }
+}
+
+/// How to build the nodes of a cluster, with optional overrides for individual nodes.
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    pub n_node: u64,
+    pub expiration_policy: ExpirationPolicy,
+    /// Channel capacity of each node of a bounded cluster; unbounded clusters ignore it.
+    pub buffer: usize,
+    /// TTL jitter of every node, see `HashMapCache::with_ttl_jitter`.
+    pub ttl_jitter: f64,
+    /// Overrides keyed by node id.
+    pub nodes: HashMap<u64, NodeConfig>,
+}
+
+impl ClusterConfig {
+    const DEFAULT_BUFFER: usize = 32;
+
+    pub fn new(expiration_policy: ExpirationPolicy, n_node: u64) -> Self {
+        Self {
+            n_node,
+            expiration_policy,
+            buffer: Self::DEFAULT_BUFFER,
+            ttl_jitter: 0.0,
+            nodes: HashMap::new(),
+        }
+    }
+
+    pub fn with_buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer;
+        self
+    }
+
+    pub fn with_ttl_jitter(mut self, fraction: f64) -> Self {
+        self.ttl_jitter = fraction;
+        self
+    }
+
+    /// Build node `id` with `node`'s settings wherever they are set.
+    pub fn with_node(mut self, id: u64, node: NodeConfig) -> Self {
+        self.nodes.insert(id, node);
+        self
+    }
+
+    pub(crate) fn expiration_policy(&self, id: u64) -> ExpirationPolicy {
+        self.nodes
+            .get(&id)
+            .and_then(|node| node.expiration_policy)
+            .unwrap_or(self.expiration_policy)
+    }
+
+    pub(crate) fn buffer(&self, id: u64) -> usize {
+        self.nodes
+            .get(&id)
+            .and_then(|node| node.buffer)
+            .unwrap_or(self.buffer)
+    }
+}
+
+/// Settings of a single cluster node; `None` falls back to the cluster-wide value. The capacity of
+/// an LFU/LRU node is part of its `expiration_policy`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeConfig {
+    pub expiration_policy: Option<ExpirationPolicy>,
+    pub buffer: Option<usize>,
+}
@@ -0,0 +1,186 @@
+//! Pipelines queue several commands and hand them to the actor as one `Batch` message.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::tokio_cache::channel::SendMode;
+use crate::tokio_cache::cmd::HashMapCmd;
+use crate::tokio_cache::data_struct::HashMapSnapshot;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::option::InsertPolicy;
+
+/// The answer to one command of a `HashMapPipeline`, in the order the commands were queued.
+#[derive(Debug, Clone)]
+pub enum HashMapReply<K, V> {
+    /// The command has no response (insert, clear).
+    Done,
+    Get(Option<V>),
+    MGet(Vec<Option<V>>),
+    Remove(Vec<Option<V>>),
+    ContainsKey(Vec<bool>),
+    Ttl(Vec<Option<Duration>>),
+    GetAll(HashMap<K, V>),
+}
+
+#[derive(Debug)]
+enum Pending<K, V> {
+    Done,
+    Get(oneshot::Receiver<Option<V>>),
+    MGet(oneshot::Receiver<Vec<Option<V>>>),
+    Remove(oneshot::Receiver<Vec<Option<V>>>),
+    ContainsKey(oneshot::Receiver<Vec<bool>>),
+    Ttl(oneshot::Receiver<Vec<Option<Duration>>>),
+    GetAll(oneshot::Receiver<HashMapSnapshot<K, V>>),
+}
+
+#[derive(Debug)]
+pub struct HashMapPipeline<'a, K, V, C> {
+    cache: &'a HashMapCache<K, V, C>,
+    cmds: Vec<HashMapCmd<K, V>>,
+    pending: Vec<Pending<K, V>>,
+    has_writes: bool,
+}
+
+impl<'a, K, V, C> HashMapPipeline<'a, K, V, C>
+where
+    K: Clone,
+    V: Clone,
+{
+    pub(crate) fn new(cache: &'a HashMapCache<K, V, C>) -> Self {
+        Self {
+            cache,
+            cmds: Vec::new(),
+            pending: Vec::new(),
+            has_writes: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cmds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cmds.is_empty()
+    }
+
+    pub fn insert(
+        &mut self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+    ) -> &mut Self {
+        self.cmds.push(HashMapCmd::Insert {
+            key,
+            val,
+            ex,
+            policy,
+        });
+        self.has_writes = true;
+        self.pending.push(Pending::Done);
+        self
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.cmds.push(HashMapCmd::Clear);
+        self.has_writes = true;
+        self.pending.push(Pending::Done);
+        self
+    }
+
+    pub fn get(&mut self, key: K) -> &mut Self {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.cmds.push(HashMapCmd::Get { key, resp_tx });
+        self.pending.push(Pending::Get(resp_rx));
+        self
+    }
+
+    pub fn mget(&mut self, keys: Vec<K>) -> &mut Self {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.cmds.push(HashMapCmd::MGet { keys, resp_tx });
+        self.pending.push(Pending::MGet(resp_rx));
+        self
+    }
+
+    pub fn remove(&mut self, keys: Vec<K>) -> &mut Self {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.cmds.push(HashMapCmd::Remove { keys, resp_tx });
+        self.has_writes = true;
+        self.pending.push(Pending::Remove(resp_rx));
+        self
+    }
+
+    pub fn contains_key(&mut self, keys: Vec<K>) -> &mut Self {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.cmds.push(HashMapCmd::ContainsKey { keys, resp_tx });
+        self.pending.push(Pending::ContainsKey(resp_rx));
+        self
+    }
+
+    pub fn ttl(&mut self, keys: Vec<K>) -> &mut Self {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.cmds.push(HashMapCmd::Ttl { keys, resp_tx });
+        self.pending.push(Pending::Ttl(resp_rx));
+        self
+    }
+
+    pub fn get_all(&mut self) -> &mut Self {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.cmds.push(HashMapCmd::GetAll { resp_tx });
+        self.pending.push(Pending::GetAll(resp_rx));
+        self
+    }
+}
+
+impl<K, V, C> HashMapPipeline<'_, K, V, C>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    pub async fn try_execute(self) -> Result<Vec<HashMapReply<K, V>>, TokioActorCacheError> {
+        self.execute_with(SendMode::Try).await
+    }
+
+    pub async fn execute(self) -> Result<Vec<HashMapReply<K, V>>, TokioActorCacheError> {
+        self.execute_with(SendMode::Wait).await
+    }
+
+    async fn execute_with(
+        self,
+        mode: SendMode,
+    ) -> Result<Vec<HashMapReply<K, V>>, TokioActorCacheError> {
+        if self.cmds.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.has_writes {
+            self.cache.check_writable()?;
+        }
+
+        let tx = &self.cache.tx;
+        tx.send(HashMapCmd::Batch { cmds: self.cmds }, mode).await?;
+
+        let mut replies = Vec::with_capacity(self.pending.len());
+        for pending in self.pending {
+            let reply = match pending {
+                Pending::Done => HashMapReply::Done,
+                Pending::Get(resp_rx) => HashMapReply::Get(tx.wait_reply(resp_rx).await?),
+                Pending::MGet(resp_rx) => HashMapReply::MGet(tx.wait_reply(resp_rx).await?),
+                Pending::Remove(resp_rx) => HashMapReply::Remove(tx.wait_reply(resp_rx).await?),
+                Pending::ContainsKey(resp_rx) => {
+                    HashMapReply::ContainsKey(tx.wait_reply(resp_rx).await?)
+                }
+                Pending::Ttl(resp_rx) => HashMapReply::Ttl(tx.wait_reply(resp_rx).await?),
+                Pending::GetAll(resp_rx) => {
+                    HashMapReply::GetAll(tx.wait_reply(resp_rx).await?.into_vals())
+                }
+            };
+            replies.push(reply);
+        }
+
+        Ok(replies)
+    }
+}
@@ -0,0 +1,186 @@
+//! Growing and shrinking the capacities of registered caches with the memory the process takes, a
+//! poor man's `maxmemory` across many caches.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::AbortHandle;
+use tokio::time;
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::registry::CacheRegistry;
+use crate::tokio_cache::trace;
+
+/// Where a `CapacityController` reads how much memory is taken.
+#[derive(Clone)]
+pub enum MemoryGauge {
+    /// The resident set size of the process, from `/proc/self/status`. Unknown off Linux, where
+    /// the controller leaves the capacities alone.
+    ProcessRss,
+    /// Bytes as the caller measures them, e.g. from allocator statistics, `None` when unknown.
+    Custom(Arc<dyn Fn() -> Option<usize> + Send + Sync>),
+}
+
+impl fmt::Debug for MemoryGauge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryGauge::ProcessRss => f.write_str("ProcessRss"),
+            MemoryGauge::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl MemoryGauge {
+    pub fn custom<F>(read: F) -> Self
+    where
+        F: Fn() -> Option<usize> + Send + Sync + 'static,
+    {
+        MemoryGauge::Custom(Arc::new(read))
+    }
+
+    /// Bytes taken, `None` when the gauge can't tell.
+    pub fn read(&self) -> Option<usize> {
+        match self {
+            MemoryGauge::ProcessRss => process_rss(),
+            MemoryGauge::Custom(read) => read(),
+        }
+    }
+}
+
+/// `VmRSS` of `/proc/self/status`, which is given in kB.
+fn process_rss() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(kb * 1024)
+}
+
+/// The capacities a `CapacityController` keeps a cache within.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityBounds {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl CapacityBounds {
+    pub fn new(min: usize, max: usize) -> Self {
+        Self { min, max }
+    }
+}
+
+/// When a `CapacityController` steps in and how far it goes. Between the two watermarks it leaves
+/// the capacities as they are, so they don't swing back and forth around a single threshold.
+#[derive(Debug, Clone)]
+pub struct PressureConfig {
+    /// Bytes taken above which every capacity shrinks a step.
+    pub high_watermark: usize,
+    /// Bytes taken below which every capacity grows a step.
+    pub low_watermark: usize,
+    /// The share of its capacity a cache shrinks or grows by each step, `0.1` by default.
+    pub step: f64,
+    /// How often the gauge is read, every second by default.
+    pub every: Duration,
+    /// The bounds of caches without bounds of their own, any capacity from 1 by default.
+    pub default_bounds: CapacityBounds,
+    /// Bounds by cache name.
+    pub bounds: BTreeMap<String, CapacityBounds>,
+}
+
+impl PressureConfig {
+    pub fn new(low_watermark: usize, high_watermark: usize) -> Self {
+        Self {
+            high_watermark,
+            low_watermark,
+            step: 0.1,
+            every: Duration::from_secs(1),
+            default_bounds: CapacityBounds::new(1, usize::MAX),
+            bounds: BTreeMap::new(),
+        }
+    }
+
+    /// Keep the cache registered as `name` within `bounds`.
+    pub fn with_bounds(mut self, name: impl Into<String>, bounds: CapacityBounds) -> Self {
+        self.bounds.insert(name.into(), bounds);
+        self
+    }
+
+    fn bounds(&self, name: &str) -> CapacityBounds {
+        self.bounds
+            .get(name)
+            .copied()
+            .unwrap_or(self.default_bounds)
+    }
+}
+
+/// Which way the capacities go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pressure {
+    High,
+    Low,
+}
+
+/// A task that reads a `MemoryGauge` and steps the capacities of every cache in a
+/// `CacheRegistry` down while memory is scarce and back up once it isn't, through `set_capacity`.
+/// Caches that don't evict are left alone. The task stops once the controller is dropped.
+#[derive(Debug)]
+pub struct CapacityController {
+    task: AbortHandle,
+}
+
+impl CapacityController {
+    pub fn spawn(registry: CacheRegistry, gauge: MemoryGauge, config: PressureConfig) -> Self {
+        let task = tokio::spawn(async move {
+            let mut ticker = time::interval(config.every);
+            loop {
+                ticker.tick().await;
+                if let Err(_err) = Self::adjust(&registry, &gauge, &config).await {
+                    trace::warn_event!(error = %_err, "failed to adjust cache capacities");
+                }
+            }
+        });
+        Self {
+            task: task.abort_handle(),
+        }
+    }
+
+    /// Read `gauge` once and step the capacities as `config` says, as the task does every
+    /// `config.every`. Returns how many caches changed capacity.
+    pub async fn adjust(
+        registry: &CacheRegistry,
+        gauge: &MemoryGauge,
+        config: &PressureConfig,
+    ) -> Result<usize, TokioActorCacheError> {
+        let pressure = match gauge.read() {
+            Some(bytes) if bytes > config.high_watermark => Pressure::High,
+            Some(bytes) if bytes < config.low_watermark => Pressure::Low,
+            _ => return Ok(0),
+        };
+        let mut n_changed = 0;
+        for (name, cache) in registry.snapshot() {
+            let Some(capacity) = cache.capacity().await? else {
+                continue;
+            };
+            let bounds = config.bounds(&name);
+            let step = ((capacity as f64 * config.step).ceil() as usize).max(1);
+            let target = match pressure {
+                Pressure::High => capacity.saturating_sub(step),
+                Pressure::Low => capacity.saturating_add(step),
+            }
+            .clamp(bounds.min, bounds.max.max(bounds.min));
+            if target != capacity {
+                trace::debug_event!(cache = %name, capacity = target, "adjusting cache capacity");
+                cache.set_capacity(target).await?;
+                n_changed += 1;
+            }
+        }
+        Ok(n_changed)
+    }
+}
+
+impl Drop for CapacityController {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
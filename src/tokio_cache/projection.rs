@@ -0,0 +1,133 @@
+//! Keeps a `HashSetCache` in step with a projection of a `HashMapCache`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::tokio_cache::channel::{CacheReceiver, CacheSender, SendMode};
+use crate::tokio_cache::cmd::{HashMapCmd, HashSetCmd};
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::option::InsertPolicy;
+
+/// Registered as a replica of the map, it turns the writes the map forwards into writes on the set.
+pub(crate) struct Projection<K, V, T, F> {
+    project: F,
+    target: CacheSender<HashSetCmd<T>>,
+    // Several keys may project to the same value, which stays in the set until all of them are gone.
+    projected: HashMap<K, T>,
+    counts: HashMap<T, usize>,
+    _val: PhantomData<fn(&V)>,
+}
+
+impl<K, V, T, F> Projection<K, V, T, F>
+where
+    K: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+    F: Fn(&K, &V) -> T,
+{
+    pub(crate) fn new(target: CacheSender<HashSetCmd<T>>, project: F) -> Self {
+        Self {
+            project,
+            target,
+            projected: HashMap::new(),
+            counts: HashMap::new(),
+            _val: PhantomData,
+        }
+    }
+
+    /// Runs until the set or the map goes away.
+    pub(crate) async fn run(mut self, mut rx: CacheReceiver<HashMapCmd<K, V>>) {
+        while let Some(cmd) = rx.recv().await {
+            if self.apply(cmd).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn apply(&mut self, cmd: HashMapCmd<K, V>) -> Result<(), TokioActorCacheError> {
+        let (added, removed) = match cmd {
+            HashMapCmd::Sync { hm, .. } => {
+                self.projected.clear();
+                self.counts.clear();
+                self.target.send(HashSetCmd::Clear, SendMode::Wait).await?;
+                let added = hm
+                    .iter()
+                    .filter_map(|(key, val_with_state)| self.link(key.clone(), &val_with_state.val))
+                    .collect();
+                (added, Vec::new())
+            }
+            HashMapCmd::Put { key, val, .. } => self.replace(vec![(key, val.val)]),
+            HashMapCmd::Delta { puts, deletes, .. } => {
+                let (added, mut removed) = self.replace(
+                    puts.into_iter()
+                        .map(|(key, val_with_state)| (key, val_with_state.val))
+                        .collect(),
+                );
+                removed.extend(deletes.iter().filter_map(|key| self.unlink(key)));
+                (added, removed)
+            }
+            HashMapCmd::Delete { keys, .. } => {
+                let removed = keys.iter().filter_map(|key| self.unlink(key)).collect();
+                (Vec::new(), removed)
+            }
+            _ => return Ok(()),
+        };
+
+        if !removed.is_empty() {
+            self.target
+                .request(SendMode::Wait, |resp_tx| HashSetCmd::Remove {
+                    vals: removed,
+                    resp_tx,
+                })
+                .await?;
+        }
+        if !added.is_empty() {
+            let n = added.len();
+            let minsert_cmd = HashSetCmd::MInsert {
+                vals: added,
+                ex: vec![None; n],
+                policy: vec![InsertPolicy::Always; n],
+            };
+            self.target.send(minsert_cmd, SendMode::Wait).await?;
+        }
+        Ok(())
+    }
+
+    /// Point each key at its new projection, returning the values that appeared and disappeared.
+    fn replace(&mut self, entries: Vec<(K, V)>) -> (Vec<T>, Vec<T>) {
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+        for (key, val) in entries {
+            let old = self.unlink(&key);
+            let new = self.link(key, &val);
+            if let Some(old) = old
+                && new.as_ref() != Some(&old)
+            {
+                removed.push(old);
+            }
+            added.extend(new);
+        }
+        (added, removed)
+    }
+
+    /// Returns the projection of `key` if no other key projects to it.
+    fn link(&mut self, key: K, val: &V) -> Option<T> {
+        let projected = (self.project)(&key, val);
+        self.projected.insert(key, projected.clone());
+        let count = self.counts.entry(projected.clone()).or_insert(0);
+        *count += 1;
+        (*count == 1).then_some(projected)
+    }
+
+    /// Returns the projection of `key` if it was the last key projecting to it.
+    fn unlink(&mut self, key: &K) -> Option<T> {
+        let projected = self.projected.remove(key)?;
+        let count = self.counts.get_mut(&projected)?;
+        *count -= 1;
+        if *count > 0 {
+            return None;
+        }
+        self.counts.remove(&projected);
+        Some(projected)
+    }
+}
@@ -0,0 +1,25 @@
+//! Reporting how far a cluster rebalance has got.
+
+use crate::tokio_cache::error::TokioActorCacheError;
+
+/// Progress of a cluster rebalance, published through a `watch` channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RebalanceProgress {
+    /// Entries moved to the node that owns them now.
+    pub moved: usize,
+    /// Misplaced entries that still have to move.
+    pub remaining: usize,
+    /// Set once the rebalance has stopped, holding the error that stopped it early if any.
+    pub finished: Option<Result<(), TokioActorCacheError>>,
+}
+
+impl RebalanceProgress {
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    pub(crate) fn record_move(&mut self) {
+        self.moved += 1;
+        self.remaining -= 1;
+    }
+}
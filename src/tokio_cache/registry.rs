@@ -0,0 +1,305 @@
+//! A process-wide directory of caches by name, to look caches up where their handles weren't
+//! passed, see how much memory they take together and free some of it in an emergency.
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use crate::tokio_cache::error::{ConfigError, TokioActorCacheError};
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::option::CacheConfig;
+use crate::tokio_cache::{bounded, unbounded};
+
+pub type RegistryFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, TokioActorCacheError>> + Send + 'a>>;
+
+/// How many entries a cache holds and roughly how much memory they take.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub entries: usize,
+    /// The size of the cache's table, not counting what keys and values own on the heap, e.g. the
+    /// contents of a `String`.
+    pub bytes: usize,
+}
+
+impl CacheUsage {
+    /// The usage of a hash table with room for `capacity` entries of `entry_size` bytes, plus the
+    /// control byte each slot takes.
+    pub(crate) fn of_table(entries: usize, capacity: usize, entry_size: usize) -> Self {
+        Self {
+            entries,
+            bytes: capacity * (entry_size + 1),
+        }
+    }
+}
+
+/// What the registry needs from a cache.
+pub trait RegisteredCache: Send + Sync + 'static {
+    fn usage(&self) -> RegistryFuture<'_, CacheUsage>;
+
+    fn clear(&self) -> RegistryFuture<'_, ()>;
+
+    /// Evict down to `target` entries. Returns how many were evicted.
+    fn shrink(&self, target: usize) -> RegistryFuture<'_, usize>;
+
+    /// The capacity of the expiration policy, `None` if the cache doesn't evict.
+    fn capacity(&self) -> RegistryFuture<'_, Option<usize>>;
+
+    /// Change the capacity of the expiration policy. Returns whether it has one.
+    fn set_capacity(&self, capacity: usize) -> RegistryFuture<'_, bool>;
+}
+
+impl<K, V, C> RegisteredCache for HashMapCache<K, V, C>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    C: Send + Sync + 'static,
+{
+    fn usage(&self) -> RegistryFuture<'_, CacheUsage> {
+        Box::pin(self.usage())
+    }
+
+    /// Clears, then hands back the memory the entries took.
+    fn clear(&self) -> RegistryFuture<'_, ()> {
+        Box::pin(async move {
+            self.clear().await?;
+            self.shrink(0).await?;
+            Ok(())
+        })
+    }
+
+    fn shrink(&self, target: usize) -> RegistryFuture<'_, usize> {
+        Box::pin(self.shrink(target))
+    }
+
+    fn capacity(&self) -> RegistryFuture<'_, Option<usize>> {
+        Box::pin(async move { Ok(self.policy().await?.capacity()) })
+    }
+
+    fn set_capacity(&self, capacity: usize) -> RegistryFuture<'_, bool> {
+        Box::pin(self.set_capacity(capacity))
+    }
+}
+
+/// The usage of every registered cache, and their total.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryStats {
+    pub entries: usize,
+    pub bytes: usize,
+    /// By cache name.
+    pub caches: BTreeMap<String, CacheUsage>,
+}
+
+/// A registered cache, both as itself for lookups and as what the registry needs.
+struct Registered {
+    cache: Arc<dyn RegisteredCache>,
+    handle: Box<dyn Any + Send + Sync>,
+}
+
+/// Caches by name. Clones share their caches; `global()` is the one for the whole process.
+#[derive(Clone, Default)]
+pub struct CacheRegistry {
+    caches: Arc<RwLock<BTreeMap<String, Registered>>>,
+}
+
+impl CacheRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn global() -> &'static CacheRegistry {
+        static GLOBAL: OnceLock<CacheRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(CacheRegistry::new)
+    }
+
+    /// Add `cache` as `name`, replacing any cache registered with the same name.
+    pub fn register<T>(&self, name: impl Into<String>, cache: T)
+    where
+        T: RegisteredCache + Clone,
+    {
+        self.caches
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.into(), Registered::new(cache));
+    }
+
+    /// The cache registered as `name`, if there is one of type `T`, e.g.
+    /// `registry.get::<HashMapCache<String, Session>>("sessions")`.
+    pub fn get<T>(&self, name: &str) -> Option<T>
+    where
+        T: Clone + 'static,
+    {
+        self.caches
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .and_then(|registered| registered.handle.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// The bounded `HashMapCache` registered as `name`, created from `config` and registered
+    /// first if there is none, so every part of a program asking for `name` shares one cache.
+    /// Fails with `CacheTypeMismatch` if `name` is taken by a cache of another type, and with
+    /// `Config` if it has to create one and `config` is invalid.
+    pub async fn get_or_create<K, V>(
+        &self,
+        name: &str,
+        config: CacheConfig,
+    ) -> Result<bounded::hm::HashMapCache<K, V>, TokioActorCacheError>
+    where
+        K: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+        V: Debug + Clone + Send + Sync + 'static,
+    {
+        self.get_or_create_with(name, async {
+            bounded::hm::HashMapCache::<K, V>::new(config.expiration_policy, config.buffer)
+                .await
+                .map(|cache| cache.with_config(&config))
+        })
+        .await
+    }
+
+    /// `get_or_create` for an unbounded `HashMapCache`, which ignores `config.buffer`.
+    pub async fn get_or_create_unbounded<K, V>(
+        &self,
+        name: &str,
+        config: CacheConfig,
+    ) -> Result<unbounded::hm::HashMapCache<K, V>, TokioActorCacheError>
+    where
+        K: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+        V: Debug + Clone + Send + Sync + 'static,
+    {
+        self.get_or_create_with(name, async {
+            unbounded::hm::HashMapCache::<K, V>::new(config.expiration_policy)
+                .await
+                .map(|cache| cache.with_config(&config))
+        })
+        .await
+    }
+
+    async fn get_or_create_with<T>(
+        &self,
+        name: &str,
+        make: impl Future<Output = Result<T, ConfigError>>,
+    ) -> Result<T, TokioActorCacheError>
+    where
+        T: RegisteredCache + Clone,
+    {
+        if let Some(cache) = self.lookup(name)? {
+            return Ok(cache);
+        }
+        let cache = make.await?;
+        // Another caller may have registered `name` while the cache was made; theirs wins, and
+        // the cache made here stops once dropped.
+        let mut caches = self.caches.write().unwrap_or_else(PoisonError::into_inner);
+        let registered = caches
+            .entry(name.to_string())
+            .or_insert_with(|| Registered::new(cache));
+        registered
+            .handle
+            .downcast_ref::<T>()
+            .cloned()
+            .ok_or_else(|| mismatch(name))
+    }
+
+    /// `get`, failing if `name` holds a cache of another type.
+    fn lookup<T>(&self, name: &str) -> Result<Option<T>, TokioActorCacheError>
+    where
+        T: Clone + 'static,
+    {
+        let caches = self.caches.read().unwrap_or_else(PoisonError::into_inner);
+        let Some(registered) = caches.get(name) else {
+            return Ok(None);
+        };
+        match registered.handle.downcast_ref::<T>() {
+            Some(cache) => Ok(Some(cache.clone())),
+            None => Err(mismatch(name)),
+        }
+    }
+
+    /// Returns whether a cache was registered as `name`.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.caches
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(name)
+            .is_some()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.caches
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    pub async fn stats(&self) -> Result<RegistryStats, TokioActorCacheError> {
+        let mut stats = RegistryStats::default();
+        for (name, cache) in self.snapshot() {
+            let usage = cache.usage().await?;
+            stats.entries += usage.entries;
+            stats.bytes += usage.bytes;
+            stats.caches.insert(name, usage);
+        }
+        Ok(stats)
+    }
+
+    /// Clear every cache.
+    pub async fn clear(&self) -> Result<(), TokioActorCacheError> {
+        for (_, cache) in self.snapshot() {
+            cache.clear().await?;
+        }
+        Ok(())
+    }
+
+    /// Shrink every cache by the same share of its entries, so that together they take about
+    /// `target_bytes`. Returns how many entries were evicted.
+    pub async fn shrink(&self, target_bytes: usize) -> Result<usize, TokioActorCacheError> {
+        let stats = self.stats().await?;
+        if stats.bytes <= target_bytes {
+            return Ok(0);
+        }
+        let mut n_evicted = 0;
+        for (name, cache) in self.snapshot() {
+            let Some(usage) = stats.caches.get(&name) else {
+                continue;
+            };
+            let target = usage.entries as u128 * target_bytes as u128 / stats.bytes as u128;
+            n_evicted += cache.shrink(target as usize).await?;
+        }
+        Ok(n_evicted)
+    }
+
+    /// The registered caches, so none of them is awaited with the lock held.
+    pub(crate) fn snapshot(&self) -> Vec<(String, Arc<dyn RegisteredCache>)> {
+        self.caches
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(name, registered)| (name.clone(), registered.cache.clone()))
+            .collect()
+    }
+}
+
+impl Registered {
+    fn new<T>(cache: T) -> Self
+    where
+        T: RegisteredCache + Clone,
+    {
+        Self {
+            cache: Arc::new(cache.clone()),
+            handle: Box::new(cache),
+        }
+    }
+}
+
+fn mismatch(name: &str) -> TokioActorCacheError {
+    TokioActorCacheError::CacheTypeMismatch {
+        name: name.to_string(),
+    }
+}
@@ -0,0 +1,68 @@
+//! Notifications of entries leaving a cache, and why.
+
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Why an entry left the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RemovalReason {
+    /// Its time to live ran out.
+    TtlElapsed,
+    /// It went unused for longer than its time to idle.
+    IdleTimeout,
+    /// It was evicted to keep the cache within capacity.
+    Evicted(Eviction),
+    /// A write overwrote it; the removal carries the old value.
+    Replaced,
+    /// It was removed by `remove` or `clear`.
+    ManualRemove,
+}
+
+/// The expiration policy that picked an evicted entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Eviction {
+    Lru,
+    Lfu,
+}
+
+/// An entry that left the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal<K, V> {
+    pub key: K,
+    pub val: V,
+    pub reason: RemovalReason,
+}
+
+/// The channels removals are reported on.
+#[derive(Debug)]
+pub(crate) struct RemovalListeners<K, V> {
+    listeners: Vec<UnboundedSender<Removal<K, V>>>,
+}
+
+impl<K, V> Default for RemovalListeners<K, V> {
+    fn default() -> Self {
+        Self {
+            listeners: Vec::new(),
+        }
+    }
+}
+
+impl<K, V> RemovalListeners<K, V>
+where
+    K: Clone,
+    V: Clone,
+{
+    pub(crate) fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, tx: UnboundedSender<Removal<K, V>>) {
+        self.listeners.push(tx);
+    }
+
+    /// Tell every listener that `key` left the cache, forgetting listeners that are gone.
+    pub(crate) fn notify(&mut self, key: K, val: V, reason: RemovalReason) {
+        let removal = Removal { key, val, reason };
+        self.listeners
+            .retain(|listener| listener.send(removal.clone()).is_ok());
+    }
+}
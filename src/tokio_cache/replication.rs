@@ -0,0 +1,394 @@
+//! Replication bookkeeping: the master's list of replicas and the replica's view of its master.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::tokio_cache::channel::CacheSender;
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::trace;
+
+/// Identifies a cache actor, so replicas can tell which master they follow.
+pub(crate) fn next_cache_id() -> u64 {
+    static NEXT_CACHE_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_CACHE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Master,
+    Replica,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationInfo {
+    pub role: Role,
+    /// `id()` of the cache being replicated.
+    pub master_id: Option<u64>,
+    /// Number of replicas following this cache.
+    pub replicas: usize,
+    /// When the last write from the master was applied.
+    pub last_sync: Option<Instant>,
+    /// Entries applied from the master since replication started.
+    pub entries_synced: u64,
+    /// How long the last write from the master waited before it was applied.
+    pub lag: Option<Duration>,
+}
+
+/// What `verify_replication` found comparing a master with one of its replicas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationCheck<K> {
+    pub master_entries: usize,
+    pub replica_entries: usize,
+    /// Keys cached on only one side, or with different values.
+    pub diverged: Vec<K>,
+}
+
+impl<K> ReplicationCheck<K> {
+    pub fn is_consistent(&self) -> bool {
+        self.diverged.is_empty()
+    }
+}
+
+/// Checksums of a cache's entries, one per bucket of the key space, so two caches are compared by
+/// exchanging a few numbers and a divergence is narrowed down to the keys of the buckets that
+/// differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Digest {
+    pub(crate) entries: usize,
+    buckets: Vec<u64>,
+}
+
+impl Digest {
+    const N_BUCKET: usize = 64;
+
+    pub(crate) fn of<'a, K, V>(entries: impl Iterator<Item = (&'a K, &'a V)>) -> Self
+    where
+        K: Hash + 'a,
+        V: Hash + 'a,
+    {
+        let mut digest = Self {
+            entries: 0,
+            buckets: vec![0; Self::N_BUCKET],
+        };
+        for (key, val) in entries {
+            digest.entries += 1;
+            // Summed, so the order the entries are visited in doesn't matter.
+            let bucket = &mut digest.buckets[Self::bucket_of(key)];
+            *bucket = bucket.wrapping_add(Self::entry_hash(key, val));
+        }
+        digest
+    }
+
+    pub(crate) fn bucket_of<K: Hash>(key: &K) -> usize {
+        (hash_of(key) % Self::N_BUCKET as u64) as usize
+    }
+
+    pub(crate) fn entry_hash<K: Hash, V: Hash>(key: &K, val: &V) -> u64 {
+        hash_of(&(key, val))
+    }
+
+    /// The buckets whose entries differ between the two caches.
+    pub(crate) fn diverged_buckets(&self, other: &Self) -> HashSet<usize> {
+        self.buckets
+            .iter()
+            .zip(&other.buckets)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(bucket, _)| bucket)
+            .collect()
+    }
+}
+
+fn hash_of<T: Hash>(val: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    val.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The keys whose entry hashes differ between `master` and `replica`.
+pub(crate) fn diverged_keys<K>(master: HashMap<K, u64>, mut replica: HashMap<K, u64>) -> Vec<K>
+where
+    K: Eq + Hash,
+{
+    let mut diverged = Vec::new();
+    for (key, entry_hash) in master {
+        if replica.remove(&key) != Some(entry_hash) {
+            diverged.push(key);
+        }
+    }
+    diverged.extend(replica.into_keys());
+    diverged
+}
+
+/// Replica-side state of the link to the master.
+#[derive(Debug, Default)]
+pub(crate) struct SyncStats {
+    master_id: Option<u64>,
+    last_sync: Option<Instant>,
+    entries_synced: u64,
+    lag: Option<Duration>,
+}
+
+impl SyncStats {
+    pub(crate) fn start(&mut self, master_id: u64) {
+        *self = Self {
+            master_id: Some(master_id),
+            ..Self::default()
+        };
+    }
+
+    pub(crate) fn stop(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Note that `entries` entries sent by the master at `sent_at` were applied.
+    pub(crate) fn record(&mut self, sent_at: Instant, entries: usize) {
+        let now = Instant::now();
+        self.last_sync = Some(now);
+        self.entries_synced += entries as u64;
+        self.lag = Some(now.saturating_duration_since(sent_at));
+    }
+
+    pub(crate) fn info(&self, replicas: usize) -> ReplicationInfo {
+        let role = match self.master_id {
+            Some(_) => Role::Replica,
+            None => Role::Master,
+        };
+        ReplicationInfo {
+            role,
+            master_id: self.master_id,
+            replicas,
+            last_sync: self.last_sync,
+            entries_synced: self.entries_synced,
+            lag: self.lag,
+        }
+    }
+}
+
+/// Replica-side record of the keys whose local copy may differ from the master's, so that with
+/// read-your-writes on, reads of them are answered by the master: keys written to the replica
+/// directly until the master next writes them, and every key until the master's full state has
+/// arrived.
+#[derive(Debug)]
+pub(crate) struct LocalWrites<K> {
+    enabled: bool,
+    keys: HashSet<K>,
+    all: bool,
+}
+
+impl<K> Default for LocalWrites<K> {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keys: HashSet::new(),
+            all: false,
+        }
+    }
+}
+
+impl<K> LocalWrites<K>
+where
+    K: Clone + Eq + Hash,
+{
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.reset();
+        }
+    }
+
+    pub(crate) fn record(&mut self, key: &K) {
+        if self.enabled && !self.all {
+            self.keys.insert(key.clone());
+        }
+    }
+
+    /// Record a change to every key, such as a clear, or replication starting.
+    pub(crate) fn record_all(&mut self) {
+        if self.enabled {
+            self.all = true;
+            self.keys.clear();
+        }
+    }
+
+    /// The master's copy of `key` was applied.
+    pub(crate) fn synced(&mut self, key: &K) {
+        self.keys.remove(key);
+    }
+
+    /// The master's full state was applied, or replication stopped.
+    pub(crate) fn reset(&mut self) {
+        self.all = false;
+        self.keys.clear();
+    }
+
+    pub(crate) fn covers(&self, key: &K) -> bool {
+        self.enabled && (self.all || self.keys.contains(key))
+    }
+}
+
+/// Master-side record of which keys changed recently, so lagging replicas can be sent only those.
+#[derive(Debug)]
+pub(crate) struct ChangeLog<K> {
+    seq: u64,
+    // Every change with a sequence number from `covered_from` on is in `log`.
+    covered_from: u64,
+    log: VecDeque<(u64, K)>,
+}
+
+impl<K> Default for ChangeLog<K> {
+    fn default() -> Self {
+        Self {
+            seq: 0,
+            covered_from: 1,
+            log: VecDeque::new(),
+        }
+    }
+}
+
+impl<K> ChangeLog<K>
+where
+    K: Clone + Eq + Hash,
+{
+    const MAX_LEN: usize = 1024;
+
+    pub(crate) fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub(crate) fn record(&mut self, key: K) {
+        self.seq += 1;
+        self.log.push_back((self.seq, key));
+        if self.log.len() > Self::MAX_LEN
+            && let Some((seq, _)) = self.log.pop_front()
+        {
+            self.covered_from = seq + 1;
+        }
+    }
+
+    /// Record a change that can't be expressed per key, such as a clear.
+    pub(crate) fn reset(&mut self) {
+        self.seq += 1;
+        self.log.clear();
+        self.covered_from = self.seq + 1;
+    }
+
+    /// The keys changed after `seq`, or `None` if the log no longer reaches back that far.
+    pub(crate) fn since(&self, seq: u64) -> Option<Vec<K>> {
+        if seq + 1 < self.covered_from {
+            return None;
+        }
+        let mut seen = HashSet::new();
+        let keys = self
+            .log
+            .iter()
+            .filter(|(change_seq, key)| *change_seq > seq && seen.insert(key))
+            .map(|(_, key)| key.clone())
+            .collect();
+        Some(keys)
+    }
+}
+
+#[derive(Debug)]
+struct Replica<T> {
+    tx: CacheSender<T>,
+    // Set when the replica missed a forwarded write and has to be brought up to date again.
+    needs_sync: bool,
+    // Sequence number of the last change delivered, `None` until the first full sync.
+    synced_seq: Option<u64>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Replicas<T> {
+    replicas: Vec<Replica<T>>,
+}
+
+impl<T> Default for Replicas<T> {
+    fn default() -> Self {
+        Self {
+            replicas: Vec::new(),
+        }
+    }
+}
+
+impl<T> Replicas<T> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.replicas.is_empty()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Remove every replica, handing back their senders.
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = CacheSender<T>> + '_ {
+        self.replicas.drain(..).map(|replica| replica.tx)
+    }
+
+    /// Forget replicas whose actor has stopped.
+    pub(crate) fn prune(&mut self) {
+        self.replicas.retain(|replica| !replica.tx.is_closed());
+    }
+
+    /// Register `tx`; it gets the full state on the next `sync`.
+    pub(crate) fn add(&mut self, tx: CacheSender<T>) {
+        self.remove(&tx);
+        self.replicas.push(Replica {
+            tx,
+            needs_sync: true,
+            synced_seq: None,
+        });
+    }
+
+    pub(crate) fn remove(&mut self, tx: &CacheSender<T>) {
+        self.replicas.retain(|replica| !replica.tx.same_channel(tx));
+    }
+
+    /// Forward change `seq` to every replica that is in sync.
+    pub(crate) fn forward(&mut self, seq: u64, build: impl Fn(Instant) -> T) {
+        let sent_at = Instant::now();
+        self.replicas.retain_mut(|replica| {
+            if replica.needs_sync {
+                return true;
+            }
+            Self::deliver(replica, seq, build(sent_at))
+        });
+    }
+
+    /// Bring replicas that are out of sync up to `seq`; `build` gets the last sequence number each
+    /// of them has seen.
+    pub(crate) fn sync(&mut self, seq: u64, build: impl Fn(Option<u64>, Instant) -> T) {
+        let sent_at = Instant::now();
+        self.replicas.retain_mut(|replica| {
+            if !replica.needs_sync {
+                return true;
+            }
+            replica.needs_sync = false;
+            let cmd = build(replica.synced_seq, sent_at);
+            Self::deliver(replica, seq, cmd)
+        });
+    }
+
+    /// Returns whether the replica should be kept.
+    fn deliver(replica: &mut Replica<T>, seq: u64, cmd: T) -> bool {
+        match replica.tx.send_now(cmd) {
+            Ok(()) => {
+                replica.synced_seq = Some(seq);
+                true
+            }
+            Err(TokioActorCacheError::ChannelFull { .. }) => {
+                trace::warn_event!("replica is lagging behind, resyncing");
+                replica.needs_sync = true;
+                true
+            }
+            Err(_) => {
+                trace::warn_event!("replica dropped");
+                false
+            }
+        }
+    }
+}
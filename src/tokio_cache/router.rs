@@ -0,0 +1,237 @@
+//! Choosing which cluster node owns a key.
+
+use std::fmt::Display;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::tokio_cache::compute::hash_id;
+#[cfg(feature = "serde")]
+use crate::tokio_cache::dump::RouterSpec;
+
+/// Maps a key to the id of the node that stores it.
+pub trait KeyRouter<K: ?Sized> {
+    /// Return a node id in `0..n_node`; the same key must always map to the same id.
+    fn route(&self, key: &K, n_node: u64) -> u64;
+
+    /// What `snapshot_all` records of this router for `restore` to build it again, `None` when
+    /// it can't be described.
+    #[cfg(feature = "serde")]
+    fn spec(&self) -> Option<RouterSpec> {
+        None
+    }
+}
+
+/// Routes by the CRC16 of the key's `Display` output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc16Router;
+
+impl<K: Display + ?Sized> KeyRouter<K> for Crc16Router {
+    fn route(&self, key: &K, n_node: u64) -> u64 {
+        hash_id(&key.to_string(), n_node)
+    }
+
+    #[cfg(feature = "serde")]
+    fn spec(&self) -> Option<RouterSpec> {
+        Some(RouterSpec::Crc16)
+    }
+}
+
+/// Routes by the key's `Hash` implementation, so keys need not implement `Display`. This is the
+/// default router of every cluster.
+#[derive(Debug, Clone, Copy)]
+pub struct HashRouter<S = FnvBuildHasher> {
+    build_hasher: S,
+    /// Set when built `with_seed`, which only then can be built again.
+    seed: Option<u64>,
+}
+
+impl<S> HashRouter<S> {
+    pub fn new(build_hasher: S) -> Self {
+        Self {
+            build_hasher,
+            seed: None,
+        }
+    }
+
+    /// The seed it was built `with_seed`, `None` when built `new`.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+impl HashRouter {
+    /// Clusters built with the same seed route every key to the same node id, across processes.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            build_hasher: FnvBuildHasher::with_seed(seed),
+            seed: Some(seed),
+        }
+    }
+}
+
+impl Default for HashRouter {
+    fn default() -> Self {
+        Self::with_seed(0)
+    }
+}
+
+impl<K: Hash + ?Sized, S: BuildHasher> KeyRouter<K> for HashRouter<S> {
+    fn route(&self, key: &K, n_node: u64) -> u64 {
+        self.build_hasher.hash_one(key) % n_node
+    }
+
+    #[cfg(feature = "serde")]
+    fn spec(&self) -> Option<RouterSpec> {
+        self.seed().map(|seed| RouterSpec::Hash { seed })
+    }
+}
+
+/// Routes by weighted rendezvous hashing: every node bids for a key with the key's hash on that
+/// node, scaled by the node's weight, and the highest bid owns the key. A node gets a share of
+/// the keys in proportion to its weight, 1 unless set, and changing the weight of one node only
+/// moves keys to or from that node. Weights are shared by every clone. Routing a key hashes it
+/// once per node, and reads the weights without taking a lock.
+#[derive(Debug, Clone)]
+pub struct WeightedRouter<S = FnvBuildHasher> {
+    build_hasher: S,
+    /// Set when built `with_seed`, which only then can be built again.
+    seed: Option<u64>,
+    /// The weights set, by ascending node id. Replaced whole by `set_weight`.
+    weights: Arc<ArcSwap<Vec<(u64, u32)>>>,
+}
+
+impl<S> WeightedRouter<S> {
+    pub fn new(build_hasher: S) -> Self {
+        Self {
+            build_hasher,
+            seed: None,
+            weights: Arc::default(),
+        }
+    }
+
+    /// The seed it was built `with_seed`, `None` when built `new`.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn weight(&self, id: u64) -> u32 {
+        let weights = self.weights.load();
+        weights
+            .binary_search_by_key(&id, |(set, _)| *set)
+            .map_or(1, |i| weights[i].1)
+    }
+
+    /// Give node `id` `weight` shares of the keys. A node of weight 0 gets none unless every
+    /// node has weight 0, when keys are spread evenly.
+    pub fn set_weight(&self, id: u64, weight: u32) {
+        self.weights.rcu(|weights| {
+            let mut weights = Vec::clone(weights);
+            match weights.binary_search_by_key(&id, |(set, _)| *set) {
+                Ok(i) => weights[i].1 = weight,
+                Err(i) => weights.insert(i, (id, weight)),
+            }
+            weights
+        });
+    }
+}
+
+impl WeightedRouter {
+    /// Clusters built with the same seed and weights route every key to the same node id,
+    /// across processes.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            build_hasher: FnvBuildHasher::with_seed(seed),
+            seed: Some(seed),
+            weights: Arc::default(),
+        }
+    }
+}
+
+impl Default for WeightedRouter {
+    fn default() -> Self {
+        Self::with_seed(0)
+    }
+}
+
+impl<K: Hash + ?Sized, S: BuildHasher> KeyRouter<K> for WeightedRouter<S> {
+    fn route(&self, key: &K, n_node: u64) -> u64 {
+        let weights = self.weights.load();
+        // Both go up by node id, so each weight set is found in one walk.
+        let mut set = weights.iter().peekable();
+        (0..n_node)
+            .map(|id| {
+                let weight = set.next_if(|(set_id, _)| *set_id == id);
+                (id, weight.map_or(1, |(_, weight)| *weight))
+            })
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(id, weight)| (id, bid(self.build_hasher.hash_one((key, id)), weight)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or_else(|| self.build_hasher.hash_one(key) % n_node, |(id, _)| id)
+    }
+
+    #[cfg(feature = "serde")]
+    fn spec(&self) -> Option<RouterSpec> {
+        self.seed().map(|seed| RouterSpec::Weighted {
+            seed,
+            weights: Vec::clone(&self.weights.load()),
+        })
+    }
+}
+
+/// `weight / -ln(u)` for `u` in (0, 1] drawn from `hash`, so that the highest of the bids of
+/// nodes falls to each with a chance in proportion to its weight.
+fn bid(hash: u64, weight: u32) -> f64 {
+    // Spread the bits of `hash` first, as FNV leaves the low ones of nearby inputs alike.
+    let mut mixed = hash ^ (hash >> 33);
+    mixed = mixed.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    mixed ^= mixed >> 33;
+    let unit = ((mixed >> 11) + 1) as f64 / (1u64 << 53) as f64;
+    f64::from(weight) / -unit.ln()
+}
+
+/// Builds seeded FNV-1a hashers. Unlike `RandomState`, the output only depends on the seed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FnvBuildHasher {
+    seed: u64,
+}
+
+impl FnvBuildHasher {
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for FnvBuildHasher {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        FnvHasher {
+            state: FnvHasher::OFFSET_BASIS ^ self.seed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FnvHasher {
+    state: u64,
+}
+
+impl FnvHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.state
+    }
+}
@@ -0,0 +1,53 @@
+//! What the cache actors need from an async runtime: somewhere to run and a clock to tick by.
+//! Tokio provides both unless a cache is started with `new_on`, behind the `runtime` feature, so
+//! applications on async-std or smol can run the actors on their own executor.
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// A future for a `Runtime` to run to completion in the background.
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Resolves once a `Runtime::sleep_until` deadline has passed.
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// The ticks of a `Runtime::interval`.
+pub type Ticks = Pin<Box<dyn Stream<Item = ()> + Send>>;
+
+/// Spawning and timers, as the actors of `HashMapCache`, `HashSetCache` and `VecCache` use them.
+/// The handles only need the executor to poll their futures, except that `with_timeout` still
+/// times out on tokio's timer, and clusters, near caches and the other helpers that run tasks of
+/// their own still spawn them on tokio.
+pub trait Runtime: Debug + Send + Sync + 'static {
+    /// Run `task` to completion in the background, detached.
+    fn spawn(&self, task: Task);
+
+    /// Resolves once `deadline` has passed.
+    fn sleep_until(&self, deadline: Instant) -> Sleep;
+
+    /// Ticks right away and then every `period`, catching up on ticks missed in between.
+    fn interval(&self, period: Duration) -> Ticks;
+}
+
+/// The tokio runtime the caller is on, which caches run on unless told otherwise.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn spawn(&self, task: Task) {
+        tokio::spawn(task);
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+
+    fn interval(&self, period: Duration) -> Ticks {
+        Box::pin(IntervalStream::new(tokio::time::interval(period)).map(drop))
+    }
+}
@@ -0,0 +1,459 @@
+//! A RESP front-end, so redis-cli and processes written in other languages can use caches hosted
+//! in this process. Supports GET, SET, DEL, EXPIRE, TTL, MGET, SADD, SMEMBERS, LPUSH and LRANGE.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use bytes::Bytes;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::oneshot;
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::option::{InsertOptions, InsertPolicy};
+use crate::tokio_cache::trace;
+
+/// Largest bulk string accepted from a client, as in Redis.
+const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+/// Most arguments accepted in one command.
+const MAX_ARGS: usize = 1024 * 1024;
+
+/// Serves string commands from `strings`, set commands from `sets` and list commands from
+/// `lists`. Each type lives in its own cache, so unlike in Redis one key can name a string, a
+/// set and a list at the same time; `DEL` removes all of them.
+pub struct RespServer<C> {
+    strings: HashMapCache<Bytes, Bytes, C>,
+    sets: HashMapCache<Bytes, BTreeSet<Bytes>, C>,
+    lists: HashMapCache<Bytes, Vec<Bytes>, C>,
+}
+
+impl<C> RespServer<C>
+where
+    C: Send + Sync + 'static,
+{
+    pub fn new(
+        strings: HashMapCache<Bytes, Bytes, C>,
+        sets: HashMapCache<Bytes, BTreeSet<Bytes>, C>,
+        lists: HashMapCache<Bytes, Vec<Bytes>, C>,
+    ) -> Self {
+        Self {
+            strings,
+            sets,
+            lists,
+        }
+    }
+
+    /// Bind `addr` and `serve` on it.
+    pub async fn listen(self, addr: impl ToSocketAddrs) -> io::Result<()> {
+        self.serve(TcpListener::bind(addr).await?).await
+    }
+
+    /// Accept connections until `listener` fails, serving each one on its own task.
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        let server = Arc::new(self);
+        loop {
+            let (stream, _addr) = listener.accept().await?;
+            let server = server.clone();
+            tokio::spawn(async move {
+                if let Err(_err) = server.connection(stream).await {
+                    trace::warn_event!(error = %_err, "RESP connection failed");
+                }
+            });
+        }
+    }
+
+    async fn connection(&self, stream: TcpStream) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut out = Vec::new();
+        loop {
+            out.clear();
+            let reply = match read_command(&mut reader).await {
+                Ok(Some(args)) => self.execute(args).await,
+                Ok(None) => return Ok(()),
+                // The stream can't be resynchronized after a framing error, so answer and hang up.
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    Reply::Error(format!("ERR Protocol error: {err}")).write(&mut out);
+                    writer.write_all(&out).await?;
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            };
+            reply.write(&mut out);
+            writer.write_all(&out).await?;
+        }
+    }
+
+    async fn execute(&self, args: Vec<Bytes>) -> Reply {
+        let Some((name, args)) = args.split_first() else {
+            return Reply::Error("ERR empty command".to_string());
+        };
+        let name = String::from_utf8_lossy(name).to_ascii_uppercase();
+        let result = match name.as_str() {
+            "GET" => self.get(args).await,
+            "SET" => self.set(args).await,
+            "DEL" => self.del(args).await,
+            "EXPIRE" => self.expire(args).await,
+            "TTL" => self.ttl(args).await,
+            "MGET" => self.mget(args).await,
+            "SADD" => self.sadd(args).await,
+            "SMEMBERS" => self.smembers(args).await,
+            "LPUSH" => self.lpush(args).await,
+            "LRANGE" => self.lrange(args).await,
+            _ => return Reply::Error(format!("ERR unknown command '{name}'")),
+        };
+        result.unwrap_or_else(|err| err.reply(&name))
+    }
+
+    async fn get(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        Ok(Reply::Bulk(self.strings.get(key.clone()).await?))
+    }
+
+    /// `SET key val [EX seconds | PX milliseconds | EXAT unix-seconds | PXAT unix-milliseconds]
+    /// [NX | XX]`
+    async fn set(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key, val, flags @ ..] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        let mut options = InsertOptions {
+            get: true,
+            ..InsertOptions::default()
+        };
+        let mut flags = flags.iter();
+        while let Some(flag) = flags.next() {
+            match flag.to_ascii_uppercase().as_slice() {
+                b"EX" => options.ex = Some(Duration::from_secs(parse_ttl(flags.next())?)),
+                b"PX" => options.ex = Some(Duration::from_millis(parse_ttl(flags.next())?)),
+                b"EXAT" => {
+                    options.exat = Some(UNIX_EPOCH + Duration::from_secs(parse_ttl(flags.next())?));
+                }
+                b"PXAT" => {
+                    options.exat =
+                        Some(UNIX_EPOCH + Duration::from_millis(parse_ttl(flags.next())?));
+                }
+                b"NX" => options.policy = InsertPolicy::IfAbsent,
+                b"XX" => options.policy = InsertPolicy::IfPresent,
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+        let old = self.strings.set(key.clone(), val.clone(), options).await?;
+        match options.policy.allows(old.is_some()) {
+            true => Ok(Reply::Ok),
+            false => Ok(Reply::Bulk(None)),
+        }
+    }
+
+    async fn del(&self, keys: &[Bytes]) -> Result<Reply, CommandError> {
+        if keys.is_empty() {
+            return Err(CommandError::WrongArity);
+        }
+        let strings = self.strings.remove(keys).await?;
+        let sets = self.sets.remove(keys).await?;
+        let lists = self.lists.remove(keys).await?;
+        let n_removed = (0..keys.len())
+            .filter(|&i| strings[i].is_some() || sets[i].is_some() || lists[i].is_some())
+            .count();
+        Ok(Reply::Integer(n_removed as i64))
+    }
+
+    async fn expire(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key, secs] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        let ex = Some(Duration::from_secs(parse_ttl(Some(secs))?));
+        let string = self.strings.expire(key.clone(), ex).await?;
+        let set = self.sets.expire(key.clone(), ex).await?;
+        let list = self.lists.expire(key.clone(), ex).await?;
+        Ok(Reply::Integer(i64::from(string || set || list)))
+    }
+
+    /// Seconds left, rounded like Redis; -1 without a TTL and -2 for missing keys.
+    async fn ttl(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        let key = [key.clone()];
+        let ttl = if self.strings.contains_key(&key).await?[0] {
+            self.strings.ttl(&key).await?[0]
+        } else if self.sets.contains_key(&key).await?[0] {
+            self.sets.ttl(&key).await?[0]
+        } else if self.lists.contains_key(&key).await?[0] {
+            self.lists.ttl(&key).await?[0]
+        } else {
+            return Ok(Reply::Integer(-2));
+        };
+        let secs = ttl.map_or(-1, |ttl| ((ttl.as_millis() + 500) / 1000) as i64);
+        Ok(Reply::Integer(secs))
+    }
+
+    async fn mget(&self, keys: &[Bytes]) -> Result<Reply, CommandError> {
+        if keys.is_empty() {
+            return Err(CommandError::WrongArity);
+        }
+        let vals = self.strings.mget(keys).await?;
+        Ok(Reply::Array(vals.into_iter().map(Reply::Bulk).collect()))
+    }
+
+    async fn sadd(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key, members @ ..] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        if members.is_empty() {
+            return Err(CommandError::WrongArity);
+        }
+        let members = members.to_vec();
+        let n_added = upsert(&self.sets, key, move |set| {
+            members
+                .into_iter()
+                .filter(|member| set.insert(member.clone()))
+                .count()
+        })
+        .await?;
+        Ok(Reply::Integer(n_added as i64))
+    }
+
+    async fn smembers(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        let members = self.sets.get(key.clone()).await?.unwrap_or_default();
+        Ok(Reply::Array(
+            members
+                .into_iter()
+                .map(|member| Reply::Bulk(Some(member)))
+                .collect(),
+        ))
+    }
+
+    async fn lpush(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key, vals @ ..] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        if vals.is_empty() {
+            return Err(CommandError::WrongArity);
+        }
+        let vals = vals.to_vec();
+        let len = upsert(&self.lists, key, move |list| {
+            // Each val is pushed to the head in turn, so the last one ends up first.
+            list.splice(0..0, vals.into_iter().rev());
+            list.len()
+        })
+        .await?;
+        Ok(Reply::Integer(len as i64))
+    }
+
+    async fn lrange(&self, args: &[Bytes]) -> Result<Reply, CommandError> {
+        let [key, start, stop] = args else {
+            return Err(CommandError::WrongArity);
+        };
+        let (start, stop) = (parse_int(start)?, parse_int(stop)?);
+        let list = self.lists.get(key.clone()).await?.unwrap_or_default();
+        let range = list_range(list.len(), start, stop);
+        Ok(Reply::Array(
+            list[range]
+                .iter()
+                .map(|val| Reply::Bulk(Some(val.clone())))
+                .collect(),
+        ))
+    }
+}
+
+/// Run `f` on the value of `key` inside the actor, starting from an empty value when `key` isn't
+/// cached, and return what `f` returned.
+async fn upsert<V, C, F, R>(
+    cache: &HashMapCache<Bytes, V, C>,
+    key: &Bytes,
+    f: F,
+) -> Result<R, TokioActorCacheError>
+where
+    V: Clone + Default,
+    F: FnOnce(&mut V) -> R + Clone + Send + 'static,
+    R: Send + 'static,
+{
+    loop {
+        cache
+            .insert(key.clone(), V::default(), None, InsertPolicy::IfAbsent)
+            .await?;
+        let (result_tx, result_rx) = oneshot::channel();
+        let f = f.clone();
+        let is_updated = cache
+            .update_value(key.clone(), move |val| {
+                let _ = result_tx.send(f(val));
+            })
+            .await?;
+        // Otherwise the key was removed between the two commands; start over.
+        if is_updated && let Ok(result) = result_rx.await {
+            return Ok(result);
+        }
+    }
+}
+
+/// Redis list indices: negative ones count from the end, and out-of-range ones are clamped.
+fn list_range(len: usize, start: i64, stop: i64) -> Range<usize> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop {
+        return 0..0;
+    }
+    start as usize..stop as usize + 1
+}
+
+fn parse_int(arg: &[u8]) -> Result<i64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse().ok())
+        .ok_or(CommandError::NotInteger)
+}
+
+fn parse_ttl(arg: Option<&Bytes>) -> Result<u64, CommandError> {
+    let arg = arg.ok_or(CommandError::Syntax)?;
+    match u64::try_from(parse_int(arg)?) {
+        Ok(ttl) if ttl > 0 => Ok(ttl),
+        _ => Err(CommandError::InvalidExpireTime),
+    }
+}
+
+enum CommandError {
+    WrongArity,
+    Syntax,
+    NotInteger,
+    InvalidExpireTime,
+    Cache(TokioActorCacheError),
+}
+
+impl From<TokioActorCacheError> for CommandError {
+    fn from(err: TokioActorCacheError) -> Self {
+        CommandError::Cache(err)
+    }
+}
+
+impl CommandError {
+    fn reply(self, command: &str) -> Reply {
+        let message = match self {
+            CommandError::WrongArity => format!(
+                "ERR wrong number of arguments for '{}' command",
+                command.to_ascii_lowercase()
+            ),
+            CommandError::Syntax => "ERR syntax error".to_string(),
+            CommandError::NotInteger => "ERR value is not an integer or out of range".to_string(),
+            CommandError::InvalidExpireTime => format!(
+                "ERR invalid expire time in '{}' command",
+                command.to_ascii_lowercase()
+            ),
+            CommandError::Cache(err) => format!("ERR {err}"),
+        };
+        Reply::Error(message)
+    }
+}
+
+enum Reply {
+    Ok,
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Bytes>),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Reply::Ok => out.extend_from_slice(b"+OK\r\n"),
+            Reply::Error(message) => {
+                out.push(b'-');
+                // A line break would end the error early and desync the client.
+                out.extend(message.bytes().filter(|b| !matches!(b, b'\r' | b'\n')));
+                out.extend_from_slice(b"\r\n");
+            }
+            Reply::Integer(n) => out.extend_from_slice(format!(":{n}\r\n").as_bytes()),
+            Reply::Bulk(None) => out.extend_from_slice(b"$-1\r\n"),
+            Reply::Bulk(Some(val)) => {
+                out.extend_from_slice(format!("${}\r\n", val.len()).as_bytes());
+                out.extend_from_slice(val);
+                out.extend_from_slice(b"\r\n");
+            }
+            Reply::Array(replies) => {
+                out.extend_from_slice(format!("*{}\r\n", replies.len()).as_bytes());
+                for reply in replies {
+                    reply.write(out);
+                }
+            }
+        }
+    }
+}
+
+/// Read a command sent as an array of bulk strings, the way Redis clients send them. `None` once
+/// the client has disconnected.
+async fn read_command<R>(reader: &mut R) -> io::Result<Option<Vec<Bytes>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let Some(line) = read_line(reader).await? else {
+        return Ok(None);
+    };
+    let n_args = parse_header(&line, b'*', MAX_ARGS)?;
+    let mut args = Vec::with_capacity(n_args);
+    for _ in 0..n_args {
+        let line = read_line(reader)
+            .await?
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        let len = parse_header(&line, b'$', MAX_BULK_LEN)?;
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg).await?;
+        if !arg.ends_with(b"\r\n") {
+            return Err(protocol_error("bulk string not terminated by CRLF"));
+        }
+        arg.truncate(len);
+        args.push(Bytes::from(arg));
+    }
+    Ok(Some(args))
+}
+
+async fn read_line<R>(reader: &mut R) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line).await? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\r\n") {
+        return Err(protocol_error("line not terminated by CRLF"));
+    }
+    line.truncate(line.len() - 2);
+    Ok(Some(line))
+}
+
+/// Parse a `*<n>` or `$<len>` line.
+fn parse_header(line: &[u8], prefix: u8, max: usize) -> io::Result<usize> {
+    let Some((&first, n)) = line.split_first() else {
+        return Err(protocol_error("empty line"));
+    };
+    if first != prefix {
+        return Err(protocol_error(format!("expected '{}'", prefix as char)));
+    }
+    std::str::from_utf8(n)
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|&n| n <= max)
+        .ok_or_else(|| protocol_error("invalid length"))
+}
+
+fn protocol_error(reason: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.into())
+}
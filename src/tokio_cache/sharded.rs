@@ -0,0 +1,772 @@
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::oneshot;
+
+use crate::tokio_cache::channel::{CacheSender, SendMode};
+use crate::tokio_cache::cmd::{HashMapCmd, ValueUpdate};
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::histogram::TtlHistogram;
+use crate::tokio_cache::hm::{HashMapCache, owned_keys};
+use crate::tokio_cache::ops::mode_pairs;
+use crate::tokio_cache::option::{
+    DroppedReceiver, ExpirationPolicy, InsertEntry, InsertOptions, InsertPolicy, ShedPolicy,
+    TtlOnWrite,
+};
+use crate::tokio_cache::router::{HashRouter, KeyRouter};
+
+/// Picks the channel of a shard that a command goes over.
+type Lane<K, V, C> = fn(&HashMapCache<K, V, C>) -> &CacheSender<HashMapCmd<K, V>>;
+
+/// A `HashMapCache` split over several actors by key hash, for when a single actor can't keep up.
+/// It lives in one process: unlike a cluster there are no node ids, health checks or replication,
+/// and every shard counts towards the expiration policy's capacity.
+///
+/// Multi-key commands send one request per shard and answer in the order of their keys.
+#[derive(Debug, Clone)]
+pub struct ShardedHashMapCache<K, V, C> {
+    pub(crate) shards: Vec<HashMapCache<K, V, C>>,
+    pub(crate) router: HashRouter,
+}
+
+impl<K, V, C> ShardedHashMapCache<K, V, C>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    mode_pairs! {
+        fn ttl / try_ttl(keys: &[K]) -> Result<Vec<Option<Duration>>, TokioActorCacheError>
+            => ttl_with;
+        fn get_all / try_get_all() -> Result<HashMap<K, V>, TokioActorCacheError> => get_all_with;
+        /// The `n` most used keys over all shards, most used first.
+        fn hot_keys / try_hot_keys(n: usize) -> Result<Vec<(K, u64)>, TokioActorCacheError>
+            => hot_keys_with;
+        /// The `n` keys unused for longest over all shards, idlest first.
+        fn idle_keys / try_idle_keys(n: usize) -> Result<Vec<(K, Duration)>, TokioActorCacheError>
+            => idle_keys_with;
+        /// The TTL histograms of all shards added up.
+        fn ttl_histogram / try_ttl_histogram() -> Result<TtlHistogram, TokioActorCacheError>
+            => ttl_histogram_with;
+        fn expiring_within / try_expiring_within(within: Duration)
+            -> Result<usize, TokioActorCacheError> => expiring_within_with;
+        fn clear / try_clear() -> Result<(), TokioActorCacheError> => clear_with;
+        /// `barrier` on every shard.
+        fn barrier / try_barrier() -> Result<(), TokioActorCacheError> => barrier_with;
+        /// See `HashMapCache::pin`.
+        fn pin / try_pin(keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> => pin_with;
+        /// See `HashMapCache::unpin`.
+        fn unpin / try_unpin(keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> => unpin_with;
+        /// The expiration policy of the shards together, holding as many entries as they do.
+        fn policy / try_policy() -> Result<ExpirationPolicy, TokioActorCacheError> => policy_with;
+        /// Give every shard its share of `policy`, see `HashMapCache::set_policy`.
+        fn set_policy / try_set_policy(policy: ExpirationPolicy)
+            -> Result<(), TokioActorCacheError> => set_policy_with;
+        /// Give every shard its share of `capacity`, see `HashMapCache::set_capacity`.
+        fn set_capacity / try_set_capacity(capacity: usize)
+            -> Result<bool, TokioActorCacheError> => set_capacity_with;
+        fn mget / try_mget(keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> => mget_with;
+        /// Insert every entry, sending one command per shard.
+        fn minsert_entries / try_minsert_entries(
+            entries: &[InsertEntry<K, V>],
+        ) -> Result<(), TokioActorCacheError> => minsert_entries_with;
+        fn get / try_get(key: K) -> Result<Option<V>, TokioActorCacheError> => get_with;
+        /// See `HashMapCache::get_versioned`.
+        fn get_versioned / try_get_versioned(key: K)
+            -> Result<Option<(V, u64)>, TokioActorCacheError> => get_versioned_with;
+        /// See `HashMapCache::insert_if_version`.
+        fn insert_if_version / try_insert_if_version(key: K, val: V, expected_version: u64)
+            -> Result<Option<u64>, TokioActorCacheError> => insert_if_version_with;
+        /// See `HashMapCache::expire`.
+        fn expire / try_expire(key: K, ex: Option<Duration>) -> Result<bool, TokioActorCacheError>
+            => expire_with;
+        /// See `HashMapCache::expire_at`.
+        fn expire_at / try_expire_at(key: K, at: SystemTime) -> Result<bool, TokioActorCacheError>
+            => expire_at_with;
+        /// See `HashMapCache::set`.
+        fn set / try_set(
+            key: K,
+            val: V,
+            options: InsertOptions,
+        ) -> Result<Option<V>, TokioActorCacheError> => set_with;
+        fn insert / try_insert(
+            key: K,
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) -> Result<(), TokioActorCacheError> => insert_with;
+        /// See `HashMapCache::insert_sync`.
+        fn insert_sync / try_insert_sync(
+            key: K,
+            val: V,
+            ex: Option<Duration>,
+            policy: InsertPolicy,
+        ) -> Result<(), TokioActorCacheError> => insert_sync_with;
+    }
+
+    pub async fn try_remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        self.remove_with(keys.to_vec(), SendMode::Try).await
+    }
+
+    pub async fn try_contains_key(&self, keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> {
+        self.contains_key_with(keys.to_vec(), SendMode::Try).await
+    }
+
+    pub async fn try_minsert(
+        &self,
+        keys: &[K],
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        let entries = InsertEntry::zip(keys, vals, ex, policy)?;
+        self.minsert_entries_with(&entries, SendMode::Try).await
+    }
+
+    pub async fn try_update_value<F>(&self, key: K, f: F) -> Result<bool, TokioActorCacheError>
+    where
+        F: FnOnce(&mut V) + Send + 'static,
+    {
+        self.update_value_with(key, f, SendMode::Try).await
+    }
+
+    pub async fn try_get_borrowed<Q>(&self, key: &Q) -> Result<Option<V>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.get_with(key.to_owned(), SendMode::Try).await
+    }
+
+    pub async fn try_contains_key_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<bool>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.contains_key_with(owned_keys(keys), SendMode::Try)
+            .await
+    }
+
+    pub async fn try_remove_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.remove_with(owned_keys(keys), SendMode::Try).await
+    }
+
+    pub async fn remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        self.remove_with(keys.to_vec(), SendMode::Wait).await
+    }
+
+    pub async fn contains_key(&self, keys: &[K]) -> Result<Vec<bool>, TokioActorCacheError> {
+        self.contains_key_with(keys.to_vec(), SendMode::Wait).await
+    }
+
+    pub async fn minsert(
+        &self,
+        keys: &[K],
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        let entries = InsertEntry::zip(keys, vals, ex, policy)?;
+        self.minsert_entries_with(&entries, SendMode::Wait).await
+    }
+
+    /// See `HashMapCache::update_value`.
+    pub async fn update_value<F>(&self, key: K, f: F) -> Result<bool, TokioActorCacheError>
+    where
+        F: FnOnce(&mut V) + Send + 'static,
+    {
+        self.update_value_with(key, f, SendMode::Wait).await
+    }
+
+    /// `get` by a borrowed form of the key, see `HashMapCache::get_borrowed`.
+    pub async fn get_borrowed<Q>(&self, key: &Q) -> Result<Option<V>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.get_with(key.to_owned(), SendMode::Wait).await
+    }
+
+    /// `contains_key` by borrowed forms of the keys.
+    pub async fn contains_key_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<bool>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.contains_key_with(owned_keys(keys), SendMode::Wait)
+            .await
+    }
+
+    /// `remove` by borrowed forms of the keys.
+    pub async fn remove_borrowed<Q>(
+        &self,
+        keys: &[&Q],
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+    {
+        self.remove_with(owned_keys(keys), SendMode::Wait).await
+    }
+
+    /// The shards, e.g. to dump or replicate them one by one.
+    pub fn shards(&self) -> &[HashMapCache<K, V, C>] {
+        &self.shards
+    }
+
+    /// Number of commands queued over all shards.
+    pub fn queue_depth(&self) -> usize {
+        self.shards.iter().map(HashMapCache::queue_depth).sum()
+    }
+
+    /// Apply `with_timeout` to every shard.
+    pub fn with_timeout(mut self, after: Duration) -> Self {
+        self.shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.with_timeout(after))
+            .collect();
+        self
+    }
+
+    /// Apply `with_ttl_jitter` to every shard.
+    pub fn with_ttl_jitter(mut self, fraction: f64) -> Self {
+        self.shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.with_ttl_jitter(fraction))
+            .collect();
+        self
+    }
+
+    /// Apply `with_ttl_on_write` to every shard.
+    pub fn with_ttl_on_write(mut self, on_write: TtlOnWrite) -> Self {
+        self.shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.with_ttl_on_write(on_write))
+            .collect();
+        self
+    }
+
+    /// Apply `with_cleanup_budget` to every shard.
+    pub fn with_cleanup_budget(mut self, max_entries: usize) -> Self {
+        self.shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.with_cleanup_budget(max_entries))
+            .collect();
+        self
+    }
+
+    /// Apply `with_dropped_receiver` to every shard.
+    pub fn with_dropped_receiver(mut self, on_dropped: DroppedReceiver) -> Self {
+        self.shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.with_dropped_receiver(on_dropped))
+            .collect();
+        self
+    }
+
+    /// Apply `with_ingestion_limit` to every shard, each of which handles up to `max_per_tick`.
+    pub fn with_ingestion_limit(mut self, max_per_tick: usize, shed: ShedPolicy) -> Self {
+        self.shards = self
+            .shards
+            .into_iter()
+            .map(|shard| shard.with_ingestion_limit(max_per_tick, shed))
+            .collect();
+        self
+    }
+
+    /// `shed_commands` of every shard added up.
+    pub fn shed_commands(&self) -> u64 {
+        self.shards.iter().map(HashMapCache::shed_commands).sum()
+    }
+
+    /// `dropped_receivers` of every shard added up.
+    pub fn dropped_receivers(&self) -> u64 {
+        self.shards
+            .iter()
+            .map(HashMapCache::dropped_receivers)
+            .sum()
+    }
+
+    async fn ttl_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<Option<Duration>>, TokioActorCacheError> {
+        self.scatter(keys.to_vec(), mode, HashMapCache::reads, |keys, resp_tx| {
+            HashMapCmd::Ttl { keys, resp_tx }
+        })
+        .await
+    }
+
+    async fn get_all_with(&self, mode: SendMode) -> Result<HashMap<K, V>, TokioActorCacheError> {
+        let mut res = HashMap::new();
+        for shard in &self.shards {
+            let hm = shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::GetAll { resp_tx })
+                .await?;
+            res.extend(hm.into_vals());
+        }
+
+        Ok(res)
+    }
+
+    async fn hot_keys_with(
+        &self,
+        n: usize,
+        mode: SendMode,
+    ) -> Result<Vec<(K, u64)>, TokioActorCacheError> {
+        // The `n` hottest keys are among the `n` hottest of their shards.
+        let mut res = Vec::new();
+        for shard in &self.shards {
+            let hot_keys = shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::HotKeys { n, resp_tx })
+                .await?;
+            res.extend(hot_keys);
+        }
+        res.sort_by_key(|(_, calls)| Reverse(*calls));
+        res.truncate(n);
+
+        Ok(res)
+    }
+
+    async fn idle_keys_with(
+        &self,
+        n: usize,
+        mode: SendMode,
+    ) -> Result<Vec<(K, Duration)>, TokioActorCacheError> {
+        let mut res = Vec::new();
+        for shard in &self.shards {
+            let idle_keys = shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::IdleKeys { n, resp_tx })
+                .await?;
+            res.extend(idle_keys);
+        }
+        res.sort_by_key(|(_, idle)| Reverse(*idle));
+        res.truncate(n);
+
+        Ok(res)
+    }
+
+    async fn ttl_histogram_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<TtlHistogram, TokioActorCacheError> {
+        let mut res = TtlHistogram::default();
+        for shard in &self.shards {
+            let histogram = shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::TtlHistogram { resp_tx })
+                .await?;
+            res.merge(&histogram);
+        }
+
+        Ok(res)
+    }
+
+    async fn expiring_within_with(
+        &self,
+        within: Duration,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        let mut res = 0;
+        for shard in &self.shards {
+            res += shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::ExpiringWithin {
+                    within,
+                    resp_tx,
+                })
+                .await?;
+        }
+
+        Ok(res)
+    }
+
+    async fn barrier_with(&self, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        for shard in &self.shards {
+            shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::Barrier { resp_tx })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn policy_with(&self, mode: SendMode) -> Result<ExpirationPolicy, TokioActorCacheError> {
+        let mut res = ExpirationPolicy::None;
+        for shard in &self.shards {
+            let policy = shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::Policy { resp_tx })
+                .await?;
+            let capacity = res.capacity().unwrap_or(0) + policy.capacity().unwrap_or(0);
+            res = policy.with_capacity(capacity).unwrap_or(res);
+        }
+
+        Ok(res)
+    }
+
+    async fn set_policy_with(
+        &self,
+        policy: ExpirationPolicy,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let policy = shard_policy(policy, self.shards.len());
+        for shard in &self.shards {
+            shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::SetPolicy { policy, resp_tx })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_capacity_with(
+        &self,
+        capacity: usize,
+        mode: SendMode,
+    ) -> Result<bool, TokioActorCacheError> {
+        let capacity = capacity.div_ceil(self.shards.len());
+        let mut res = true;
+        for shard in &self.shards {
+            res &= shard
+                .tx
+                .request(mode, |resp_tx| HashMapCmd::SetCapacity {
+                    capacity,
+                    resp_tx,
+                })
+                .await?;
+        }
+
+        Ok(res)
+    }
+
+    async fn clear_with(&self, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        for shard in &self.shards {
+            shard.check_writable()?;
+            shard.tx.send(HashMapCmd::Clear, mode).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_with(
+        &self,
+        keys: Vec<K>,
+        mode: SendMode,
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        for shard in &self.shards {
+            shard.check_writable()?;
+        }
+        self.scatter(keys, mode, writes, |keys, resp_tx| HashMapCmd::Remove {
+            keys,
+            resp_tx,
+        })
+        .await
+    }
+
+    async fn pin_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<bool>, TokioActorCacheError> {
+        self.set_pinned(keys, true, mode).await
+    }
+
+    async fn unpin_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<bool>, TokioActorCacheError> {
+        self.set_pinned(keys, false, mode).await
+    }
+
+    async fn set_pinned(
+        &self,
+        keys: &[K],
+        pinned: bool,
+        mode: SendMode,
+    ) -> Result<Vec<bool>, TokioActorCacheError> {
+        for shard in &self.shards {
+            shard.check_writable()?;
+        }
+        self.scatter(keys.to_vec(), mode, writes, |keys, resp_tx| {
+            HashMapCmd::Pin {
+                keys,
+                pinned,
+                resp_tx,
+            }
+        })
+        .await
+    }
+
+    async fn contains_key_with(
+        &self,
+        keys: Vec<K>,
+        mode: SendMode,
+    ) -> Result<Vec<bool>, TokioActorCacheError> {
+        self.scatter(keys, mode, HashMapCache::reads, |keys, resp_tx| {
+            HashMapCmd::ContainsKey { keys, resp_tx }
+        })
+        .await
+    }
+
+    async fn mget_with(
+        &self,
+        keys: &[K],
+        mode: SendMode,
+    ) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        self.scatter(keys.to_vec(), mode, HashMapCache::reads, |keys, resp_tx| {
+            HashMapCmd::MGet { keys, resp_tx }
+        })
+        .await
+    }
+
+    async fn minsert_entries_with(
+        &self,
+        entries: &[InsertEntry<K, V>],
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let mut batches = HashMap::<usize, Vec<_>>::new();
+        for entry in entries {
+            let batch = batches.entry(self.shard_id(&entry.key)).or_default();
+            batch.push(entry.clone());
+        }
+        for (id, entries) in batches {
+            let shard = &self.shards[id];
+            shard.check_writable()?;
+            shard.tx.send(HashMapCmd::MInsert { entries }, mode).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_with(&self, key: K, mode: SendMode) -> Result<Option<V>, TokioActorCacheError> {
+        self.shard(&key)
+            .reads()
+            .request(mode, |resp_tx| HashMapCmd::Get { key, resp_tx })
+            .await
+    }
+
+    async fn get_versioned_with(
+        &self,
+        key: K,
+        mode: SendMode,
+    ) -> Result<Option<(V, u64)>, TokioActorCacheError> {
+        self.shard(&key)
+            .reads()
+            .request(mode, |resp_tx| HashMapCmd::GetVersioned { key, resp_tx })
+            .await
+    }
+
+    async fn insert_if_version_with(
+        &self,
+        key: K,
+        val: V,
+        expected_version: u64,
+        mode: SendMode,
+    ) -> Result<Option<u64>, TokioActorCacheError> {
+        let shard = self.shard(&key);
+        shard.check_writable()?;
+        shard
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::InsertIfVersion {
+                key,
+                val,
+                expected_version,
+                resp_tx,
+            })
+            .await?
+    }
+
+    async fn update_value_with<F>(
+        &self,
+        key: K,
+        f: F,
+        mode: SendMode,
+    ) -> Result<bool, TokioActorCacheError>
+    where
+        F: FnOnce(&mut V) + Send + 'static,
+    {
+        let shard = self.shard(&key);
+        shard.check_writable()?;
+        let update = ValueUpdate(Box::new(f));
+        shard
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::Update {
+                key,
+                update,
+                resp_tx,
+            })
+            .await
+    }
+
+    async fn expire_with(
+        &self,
+        key: K,
+        ex: Option<Duration>,
+        mode: SendMode,
+    ) -> Result<bool, TokioActorCacheError> {
+        let shard = self.shard(&key);
+        shard.check_writable()?;
+        shard
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::Expire { key, ex, resp_tx })
+            .await
+    }
+
+    async fn expire_at_with(
+        &self,
+        key: K,
+        at: SystemTime,
+        mode: SendMode,
+    ) -> Result<bool, TokioActorCacheError> {
+        let shard = self.shard(&key);
+        shard.check_writable()?;
+        shard
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::ExpireAt { key, at, resp_tx })
+            .await
+    }
+
+    async fn set_with(
+        &self,
+        key: K,
+        val: V,
+        options: InsertOptions,
+        mode: SendMode,
+    ) -> Result<Option<V>, TokioActorCacheError> {
+        let shard = self.shard(&key);
+        shard.check_writable()?;
+        shard
+            .tx
+            .request(mode, |resp_tx| HashMapCmd::Set {
+                key,
+                val,
+                options,
+                resp_tx,
+            })
+            .await?
+    }
+
+    async fn insert_sync_with(
+        &self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let options = InsertOptions {
+            ex,
+            exat: None,
+            tti: None,
+            policy,
+            get: false,
+        };
+        self.set_with(key, val, options, mode).await?;
+        Ok(())
+    }
+
+    async fn insert_with(
+        &self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let shard = self.shard(&key);
+        shard.check_writable()?;
+        let insert_cmd = HashMapCmd::Insert {
+            key,
+            val,
+            ex,
+            policy,
+        };
+        shard.tx.send(insert_cmd, mode).await
+    }
+
+    /// Send one command per shard owning some of `keys` over the channel `lane` picks, and put
+    /// the answers back in the order of `keys`.
+    async fn scatter<T>(
+        &self,
+        keys: Vec<K>,
+        mode: SendMode,
+        lane: Lane<K, V, C>,
+        build: impl Fn(Vec<K>, oneshot::Sender<Vec<T>>) -> HashMapCmd<K, V>,
+    ) -> Result<Vec<T>, TokioActorCacheError> {
+        let n_key = keys.len();
+        let mut batches = HashMap::<usize, (Vec<usize>, Vec<K>)>::new();
+        for (i, key) in keys.into_iter().enumerate() {
+            let batch = batches.entry(self.shard_id(&key)).or_default();
+            batch.0.push(i);
+            batch.1.push(key);
+        }
+
+        let mut res: Vec<Option<T>> = (0..n_key).map(|_| None).collect();
+        for (id, (indices, keys)) in batches {
+            let vals = lane(&self.shards[id])
+                .request(mode, |resp_tx| build(keys, resp_tx))
+                .await?;
+            for (i, val) in indices.into_iter().zip(vals) {
+                res[i] = Some(val);
+            }
+        }
+
+        // Every shard answers once per key it is asked about.
+        res.into_iter()
+            .map(|val| val.ok_or(TokioActorCacheError::ActorGone))
+            .collect()
+    }
+
+    fn shard_id(&self, key: &K) -> usize {
+        self.router.route(key, self.shards.len() as u64) as usize
+    }
+
+    fn shard(&self, key: &K) -> &HashMapCache<K, V, C> {
+        &self.shards[self.shard_id(key)]
+    }
+}
+
+/// Each shard's share of `expiration_policy`, so that the shards together hold about as many
+/// entries as a single cache would.
+pub(crate) fn shard_policy(
+    expiration_policy: ExpirationPolicy,
+    n_shard: usize,
+) -> ExpirationPolicy {
+    match expiration_policy {
+        ExpirationPolicy::LFU(capacity) => ExpirationPolicy::LFU(capacity.div_ceil(n_shard)),
+        ExpirationPolicy::LRU(capacity) => ExpirationPolicy::LRU(capacity.div_ceil(n_shard)),
+        ExpirationPolicy::None => ExpirationPolicy::None,
+    }
+}
+
+fn writes<K, V, C>(shard: &HashMapCache<K, V, C>) -> &CacheSender<HashMapCmd<K, V>> {
+    &shard.tx
+}
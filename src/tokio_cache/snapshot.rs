@@ -0,0 +1,69 @@
+//! Immutable, versioned views of a cache's entries, for code that iterates over all of them.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Every live entry of a `HashMapCache` as of `version`, shared rather than copied by whoever
+/// holds it. Two snapshots with the same version hold the same entries.
+#[derive(Debug)]
+pub struct VersionedSnapshot<K, V> {
+    /// Counts the writes the cache has applied, so a reader can tell whether its snapshot is still
+    /// current.
+    pub version: u64,
+    pub entries: Arc<HashMap<K, V>>,
+}
+
+impl<K, V> Clone for VersionedSnapshot<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+/// An actor's version and the last snapshot it handed out, which it hands out again until the
+/// next write rather than copy its entries anew. The snapshot is kept type-erased so the actor's
+/// bookkeeping needn't be generic over its entries.
+#[derive(Debug, Default)]
+pub(crate) struct Versions {
+    version: u64,
+    last: Option<Box<dyn Any + Send>>,
+}
+
+impl Versions {
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Note a write.
+    pub(crate) fn bump(&mut self) {
+        self.version += 1;
+    }
+
+    /// A snapshot of the current version, built by `entries` if the last one is out of date.
+    pub(crate) fn snapshot<K, V>(
+        &mut self,
+        entries: impl FnOnce() -> HashMap<K, V>,
+    ) -> VersionedSnapshot<K, V>
+    where
+        K: Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        let last = self
+            .last
+            .as_ref()
+            .and_then(|last| last.downcast_ref::<VersionedSnapshot<K, V>>())
+            .filter(|last| last.version == self.version);
+        if let Some(last) = last {
+            return last.clone();
+        }
+        let snapshot = VersionedSnapshot {
+            version: self.version,
+            entries: Arc::new(entries()),
+        };
+        self.last = Some(Box::new(snapshot.clone()));
+        snapshot
+    }
+}
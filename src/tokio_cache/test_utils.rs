@@ -0,0 +1,34 @@
+//! Deterministic time for testing code that relies on TTLs.
+//!
+//! Caches only read the time through `tokio::time`, so on a runtime whose clock is paused, e.g.
+//! under `#[tokio::test(start_paused = true)]`, entries age with virtual time and an hour-long
+//! TTL runs out in an instant:
+//!
+//! ```ignore
+//! use tokio_cache::tokio_cache::test_utils::{TICK, advance};
+//!
+//! #[tokio::test(start_paused = true)]
+//! async fn session_expires() {
+//!     let cache = HashMapCache::new(ExpirationPolicy::None, 32).await.unwrap();
+//!     let ex = Duration::from_secs(3600);
+//!     cache.insert("a", 1, Some(ex), InsertPolicy::Always).await.unwrap();
+//!     advance(ex + TICK).await;
+//!     assert_eq!(cache.get("a").await.unwrap(), None);
+//! }
+//! ```
+
+use std::time::Duration;
+
+pub use tokio::time::{pause, resume};
+
+/// How often actors drop expired entries and evict those over capacity. An entry stays readable
+/// until the first tick after its TTL has run out, so wait for one more tick than the TTL.
+pub const TICK: Duration = crate::tokio_cache::data_struct::TICK;
+
+/// Let `by` pass on a paused clock, running every tick in between. Unlike
+/// `tokio::time::advance`, the clock only moves once every task is idle, so commands already
+/// sent, such as an `insert`, are handled at the current time first. On a clock that isn't
+/// paused this just sleeps.
+pub async fn advance(by: Duration) {
+    tokio::time::sleep(by).await;
+}
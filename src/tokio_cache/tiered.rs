@@ -0,0 +1,237 @@
+//! A `HashMapCache` with a disk tier behind it, for caches larger than memory.
+//!
+//! Entries the memory tier evicts are written to a directory, one JSON file each, and brought
+//! back into memory the next time they are read.
+
+use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::tokio_cache::error::TokioActorCacheError;
+use crate::tokio_cache::hm::HashMapCache;
+use crate::tokio_cache::option::InsertPolicy;
+use crate::tokio_cache::removal::{Removal, RemovalReason};
+use crate::tokio_cache::trace;
+
+/// Keeps the entries its memory tier holds in memory and those it evicted on disk. The memory
+/// tier must have an LRU or LFU policy, or nothing ever spills.
+///
+/// Spilling happens in the background, so an entry read right after its eviction may be found
+/// in neither tier for a moment. `insert` checks its policy against the memory tier only.
+#[derive(Debug, Clone)]
+pub struct TieredCache<K, V, C> {
+    memory: HashMapCache<K, V, C>,
+    disk: DiskTier,
+}
+
+impl<K, V, C> TieredCache<K, V, C>
+where
+    K: Clone + Debug + Eq + Hash + Send + Sync + Serialize + DeserializeOwned + 'static,
+    V: Clone + Debug + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Spill what `memory` evicts into `dir`, created if missing. Files already in `dir` are
+    /// served as if spilled by this cache.
+    pub async fn new(
+        memory: HashMapCache<K, V, C>,
+        dir: impl AsRef<Path>,
+    ) -> Result<Self, TokioActorCacheError> {
+        Self::with_disk_ttl(memory, dir, None).await
+    }
+
+    /// `new`, with spilled entries dropped once they have been on disk for `ttl`. A spilled
+    /// entry loses the TTL it had in memory, so set this when the memory tier uses TTLs.
+    pub async fn with_disk_ttl(
+        memory: HashMapCache<K, V, C>,
+        dir: impl AsRef<Path>,
+        ttl: Option<Duration>,
+    ) -> Result<Self, TokioActorCacheError> {
+        let dir = dir.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|err| TokioActorCacheError::io(&dir, err))?;
+        let disk = DiskTier { dir, ttl };
+        tokio::spawn(spill(disk.clone(), memory.removals().await?));
+        Ok(Self { memory, disk })
+    }
+
+    pub fn memory(&self) -> &HashMapCache<K, V, C> {
+        &self.memory
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.disk.dir
+    }
+
+    /// Read `key` from memory, or else from disk, moving it back into memory.
+    pub async fn get(&self, key: K) -> Result<Option<V>, TokioActorCacheError> {
+        if let Some(val) = self.memory.get(key.clone()).await? {
+            return Ok(Some(val));
+        }
+        let Some(spilled) = self.disk.take::<K, V>(&key).await? else {
+            return Ok(None);
+        };
+        let ex = spilled.ttl();
+        self.memory
+            .insert(key, spilled.val.clone(), ex, InsertPolicy::IfAbsent)
+            .await?;
+        Ok(Some(spilled.val))
+    }
+
+    /// Insert into memory, dropping any copy of `key` on disk.
+    pub async fn insert(
+        &self,
+        key: K,
+        val: V,
+        ex: Option<Duration>,
+        policy: InsertPolicy,
+    ) -> Result<(), TokioActorCacheError> {
+        self.disk.remove(&key).await?;
+        self.memory.insert(key, val, ex, policy).await
+    }
+
+    /// Remove `keys` from both tiers, returning each value from whichever held it.
+    pub async fn remove(&self, keys: &[K]) -> Result<Vec<Option<V>>, TokioActorCacheError> {
+        let mut removed = self.memory.remove(keys).await?;
+        for (key, val) in keys.iter().zip(&mut removed) {
+            let spilled = self.disk.take::<K, V>(key).await?;
+            if val.is_none() {
+                *val = spilled.map(|spilled| spilled.val);
+            }
+        }
+        Ok(removed)
+    }
+
+    pub async fn clear(&self) -> Result<(), TokioActorCacheError> {
+        self.memory.clear().await?;
+        self.disk.clear().await
+    }
+}
+
+/// Write what the memory tier evicts to disk, until it stops.
+async fn spill<K, V>(disk: DiskTier, mut removals: UnboundedReceiver<Removal<K, V>>)
+where
+    K: Hash + Send + Serialize,
+    V: Send + Serialize,
+{
+    while let Some(removal) = removals.recv().await {
+        // The entry is lost when it can't be written, like any evicted one.
+        if let RemovalReason::Evicted(_) = removal.reason
+            && let Err(_err) = disk.put(removal.key, removal.val).await
+        {
+            trace::warn_event!(err = %_err, "cannot spill evicted entry");
+        }
+    }
+}
+
+/// One spilled entry, in a file named after the hash of its key.
+#[derive(Debug, Serialize, Deserialize)]
+struct Spilled<K, V> {
+    key: K,
+    val: V,
+    /// Milliseconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at_ms: Option<u64>,
+}
+
+impl<K, V> Spilled<K, V> {
+    /// Time left before the entry expires, `None` when it never does.
+    fn ttl(&self) -> Option<Duration> {
+        self.expires_at_ms
+            .map(|ms| Duration::from_millis(ms.saturating_sub(unix_ms(SystemTime::now()))))
+    }
+
+    fn is_expired(&self) -> bool {
+        self.ttl().is_some_and(|ttl| ttl.is_zero())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DiskTier {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+}
+
+impl DiskTier {
+    fn path<K: Hash>(&self, key: &K) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    async fn put<K, V>(&self, key: K, val: V) -> Result<(), TokioActorCacheError>
+    where
+        K: Hash + Serialize,
+        V: Serialize,
+    {
+        let path = self.path(&key);
+        let spilled = Spilled {
+            key,
+            val,
+            expires_at_ms: self.ttl.map(|ttl| unix_ms(SystemTime::now() + ttl)),
+        };
+        let bytes = serde_json::to_vec(&spilled).map_err(|err| TokioActorCacheError::Dump {
+            reason: err.to_string(),
+        })?;
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|err| TokioActorCacheError::io(&path, err))
+    }
+
+    /// Remove and return the live entry of `key`. A file holding another key, one whose hash
+    /// collides, is left alone.
+    async fn take<K, V>(&self, key: &K) -> Result<Option<Spilled<K, V>>, TokioActorCacheError>
+    where
+        K: Eq + Hash + DeserializeOwned,
+        V: DeserializeOwned,
+    {
+        let path = self.path(key);
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(TokioActorCacheError::io(&path, err)),
+        };
+        let spilled: Spilled<K, V> =
+            serde_json::from_slice(&bytes).map_err(|err| TokioActorCacheError::Dump {
+                reason: err.to_string(),
+            })?;
+        if spilled.key != *key {
+            return Ok(None);
+        }
+        self.remove(key).await?;
+        Ok((!spilled.is_expired()).then_some(spilled))
+    }
+
+    async fn remove<K: Hash>(&self, key: &K) -> Result<(), TokioActorCacheError> {
+        let path = self.path(key);
+        match tokio::fs::remove_file(&path).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(TokioActorCacheError::io(&path, err))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn clear(&self) -> Result<(), TokioActorCacheError> {
+        let io = |err| TokioActorCacheError::io(&self.dir, err);
+        let mut entries = tokio::fs::read_dir(&self.dir).await.map_err(io)?;
+        while let Some(entry) = entries.next_entry().await.map_err(io)? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                tokio::fs::remove_file(&path)
+                    .await
+                    .map_err(|err| TokioActorCacheError::io(&path, err))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
@@ -0,0 +1,25 @@
+//! Instrumentation macros that forward to `tracing` and compile to nothing without the `tracing` feature.
+
+macro_rules! warn_event {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    }};
+}
+
+macro_rules! debug_event {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    }};
+}
+
+/// Enter a span named after the command for the rest of the enclosing block.
+macro_rules! command_span {
+    ($cmd:expr) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("command", cmd = $cmd.name()).entered();
+    };
+}
+
+pub(crate) use {command_span, debug_event, warn_event};
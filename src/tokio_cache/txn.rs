@@ -0,0 +1,87 @@
+//! The participant side of the two-phase commit behind `HashMapCacheCluster::transact`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::tokio_cache::option::InsertEntry;
+
+/// How long a node holds prepared writes for their commit, after which it drops them as if
+/// aborted, e.g. when the cluster handle that prepared them has gone away.
+pub(crate) const PREPARED_FOR: Duration = Duration::from_secs(30);
+
+/// Identifies a transaction across the nodes it prepares on.
+pub(crate) fn next_txn_id() -> u64 {
+    static NEXT_TXN_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_TXN_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The writes of a transaction to one node, staged until the commit or abort.
+#[derive(Debug)]
+pub(crate) struct Prepared<K, V> {
+    pub(crate) inserts: Vec<InsertEntry<K, V>>,
+    pub(crate) removes: Vec<K>,
+    expires_at: Instant,
+}
+
+impl<K, V> Prepared<K, V>
+where
+    K: Eq,
+{
+    pub(crate) fn new(inserts: Vec<InsertEntry<K, V>>, removes: Vec<K>) -> Self {
+        Self {
+            inserts,
+            removes,
+            expires_at: Instant::now() + PREPARED_FOR,
+        }
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &K> {
+        self.inserts
+            .iter()
+            .map(|entry| &entry.key)
+            .chain(&self.removes)
+    }
+
+    fn holds(&self, key: &K) -> bool {
+        self.keys().any(|held| held == key)
+    }
+}
+
+/// The transactions a node has prepared and not yet committed or aborted, by id. A key belongs to
+/// at most one of them, so two transactions never commit over each other.
+#[derive(Debug)]
+pub(crate) struct PreparedTxns<K, V>(HashMap<u64, Prepared<K, V>>);
+
+impl<K, V> Default for PreparedTxns<K, V> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<K, V> PreparedTxns<K, V>
+where
+    K: Eq,
+{
+    /// Whether another transaction holds any key of `prepared`.
+    pub(crate) fn conflicts(&self, prepared: &Prepared<K, V>) -> bool {
+        self.0
+            .values()
+            .any(|other| prepared.keys().any(|key| other.holds(key)))
+    }
+
+    pub(crate) fn insert(&mut self, txn: u64, prepared: Prepared<K, V>) {
+        self.0.insert(txn, prepared);
+    }
+
+    pub(crate) fn take(&mut self, txn: u64) -> Option<Prepared<K, V>> {
+        self.0.remove(&txn)
+    }
+
+    /// Drop the transactions held past `PREPARED_FOR`.
+    pub(crate) fn expire(&mut self, now: Instant) {
+        self.0.retain(|_, prepared| prepared.expires_at > now);
+    }
+}
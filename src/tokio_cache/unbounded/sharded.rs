@@ -0,0 +1,35 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::tokio_cache::channel::Unbounded;
+use crate::tokio_cache::error::ConfigError;
+use crate::tokio_cache::option::ExpirationPolicy;
+use crate::tokio_cache::router::HashRouter;
+use crate::tokio_cache::sharded::shard_policy;
+use crate::tokio_cache::unbounded::hm::HashMapCache;
+
+pub type ShardedHashMapCache<K, V> =
+    crate::tokio_cache::sharded::ShardedHashMapCache<K, V, Unbounded>;
+
+impl<K, V> ShardedHashMapCache<K, V>
+where
+    K: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+    V: Debug + Clone + Eq + Hash + Send + Sync + 'static,
+{
+    /// Spawn `n_shard` actors, at least one, sharing the capacity of `expiration_policy`.
+    pub async fn new(
+        expiration_policy: ExpirationPolicy,
+        n_shard: usize,
+    ) -> Result<Self, ConfigError> {
+        let n_shard = n_shard.max(1);
+        let policy = shard_policy(expiration_policy, n_shard);
+        let mut shards = Vec::with_capacity(n_shard);
+        for _ in 0..n_shard {
+            shards.push(HashMapCache::new(policy).await?);
+        }
+        Ok(Self {
+            shards,
+            router: HashRouter::default(),
+        })
+    }
+}
@@ -0,0 +1,1090 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::tokio_cache::channel::{CacheReceiver, CacheSender, SendMode, reply};
+use crate::tokio_cache::cmd::VecCmd;
+use crate::tokio_cache::data_struct::{NextExpiration, TICK, ValueWithState};
+#[cfg(feature = "serde")]
+use crate::tokio_cache::dump::{self, Dumped};
+use crate::tokio_cache::error::{ConfigError, TokioActorCacheError};
+use crate::tokio_cache::index::{EvictionIndex, RankIndex};
+use crate::tokio_cache::jitter::TtlJitter;
+use crate::tokio_cache::ops::cache_ops;
+use crate::tokio_cache::option::{
+    ExpirationPolicy, InsertPolicy, RemovePolicy, ReplicaWrites, VecEnd,
+};
+use crate::tokio_cache::removal::Eviction;
+use crate::tokio_cache::replication::{Replicas, ReplicationInfo, SyncStats, next_cache_id};
+use crate::tokio_cache::runtime::{Runtime, TokioRuntime};
+use crate::tokio_cache::trace;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, Clone)]
+pub struct VecCache<V, C> {
+    pub(crate) tx: CacheSender<VecCmd<V>>,
+    channel: PhantomData<C>,
+    id: u64,
+    replicating: Arc<AtomicBool>,
+    replica_writes: ReplicaWrites,
+    ttl_jitter: TtlJitter,
+    // Shared with the actor, see `with_cleanup_budget`; `usize::MAX` without one.
+    cleanup_budget: Arc<AtomicUsize>,
+}
+
+impl<V, C> VecCache<V, C>
+where
+    V: Clone,
+{
+    cache_ops! {
+        VecCmd;
+
+        tx fn replica_count / try_replica_count() -> usize => ReplicaCount;
+        tx fn is_replica / try_is_replica() -> bool => IsReplica;
+        tx fn replication_info / try_replication_info() -> ReplicationInfo => ReplicationInfo;
+        /// The remaining time to live of each of `vals`, taken from its first occurrence; `None`
+        /// for vals without one or that aren't cached.
+        tx fn ttl / try_ttl(vals: &[V]) -> Vec<Option<Duration>> => Ttl { vals: vals.to_vec() };
+        write fn clear / try_clear() => Clear;
+        /// Resolves once the actor has handled every command queued before it, e.g. to wait for
+        /// a batch of fire-and-forget writes without acknowledging each of them.
+        tx fn barrier / try_barrier() -> () => Barrier;
+        /// Delete the occurrences of each of `vals` that `policy` picks. Returns how many were
+        /// deleted per requested val.
+        write fn remove / try_remove(vals: &[V], policy: RemovePolicy) -> Vec<usize> => Remove {
+            vals: vals.to_vec(),
+            policy,
+        };
+        tx fn contains / try_contains(vals: &[V]) -> Vec<bool> => Contains {
+            vals: vals.to_vec(),
+        };
+        tx fn get_all / try_get_all() -> Vec<V> => GetAll;
+        /// Append `val`, or with `InsertPolicy::IfPresent` give the first copy cached the new TTL
+        /// where it is. Copies past their TTL don't count as cached.
+        write fn push / try_push(val: V, ex: Option<Duration>, policy: InsertPolicy) => Push {
+            val,
+            ex,
+            policy,
+        };
+        /// Remove and return the first element.
+        write fn pop_front / try_pop_front() -> Option<V> => PopFront;
+        /// Remove and return the last element.
+        write fn pop_back / try_pop_back() -> Option<V> => PopBack;
+        /// Keep only the first `len` elements.
+        write fn truncate / try_truncate(len: usize) => Truncate { len };
+        tx fn get_index / try_get_index(index: usize) -> Option<V> => GetIndex { index };
+        /// Overwrite the element at `index`, keeping its TTL. Returns whether `index` was in
+        /// bounds.
+        write fn set_index / try_set_index(index: usize, val: V) -> bool => SetIndex { index, val };
+        /// Keep at most `max_len` elements, or any number with `None`, by dropping them from
+        /// `trim_from` as pushes go over, e.g. `Front` for a buffer of the most recent pushes.
+        /// Elements already over are dropped right away.
+        tx fn set_max_len / try_set_max_len(max_len: Option<usize>, trim_from: VecEnd) => MaxLen {
+            max_len,
+            trim_from,
+        };
+    }
+
+    pub async fn try_stop_replicating(&self) -> Result<(), TokioActorCacheError> {
+        self.stop_replicating_with(SendMode::Try).await
+    }
+
+    pub async fn try_replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
+        self.replicate_with(master, SendMode::Try).await
+    }
+
+    pub async fn try_promote_to_master(&self) -> Result<(), TokioActorCacheError> {
+        self.promote_to_master_with(SendMode::Try).await
+    }
+
+    pub async fn try_failover(old: &Self, new: &Self) -> Result<usize, TokioActorCacheError> {
+        Self::failover_with(old, new, SendMode::Try).await
+    }
+
+    /// Make `replica` follow this cache; a master fans its writes out to all of its replicas.
+    pub async fn try_add_replica(&self, replica: &Self) -> Result<(), TokioActorCacheError> {
+        replica.replicate_with(self, SendMode::Try).await
+    }
+
+    pub async fn try_mpush(
+        &self,
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        self.check_writable()?;
+        let mpush_cmd = Self::mpush_cmd(vals, ex, policy)?;
+        self.tx.send(mpush_cmd, SendMode::Try).await
+    }
+
+    pub async fn stop_replicating(&self) -> Result<(), TokioActorCacheError> {
+        self.stop_replicating_with(SendMode::Wait).await
+    }
+
+    pub async fn replicate(&self, master: &Self) -> Result<(), TokioActorCacheError> {
+        self.replicate_with(master, SendMode::Wait).await
+    }
+
+    /// Stop replicating and take writes again, even if the old master is gone.
+    pub async fn promote_to_master(&self) -> Result<(), TokioActorCacheError> {
+        self.promote_to_master_with(SendMode::Wait).await
+    }
+
+    /// Promote `new` and re-point the other replicas of `old` to it. Returns how many were moved.
+    pub async fn failover(old: &Self, new: &Self) -> Result<usize, TokioActorCacheError> {
+        Self::failover_with(old, new, SendMode::Wait).await
+    }
+
+    /// Make `replica` follow this cache; a master fans its writes out to all of its replicas.
+    pub async fn add_replica(&self, replica: &Self) -> Result<(), TokioActorCacheError> {
+        replica.replicate_with(self, SendMode::Wait).await
+    }
+
+    pub async fn mpush(
+        &self,
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<(), TokioActorCacheError> {
+        self.check_writable()?;
+        let mpush_cmd = Self::mpush_cmd(vals, ex, policy)?;
+        self.tx.send(mpush_cmd, SendMode::Wait).await
+    }
+
+    /// Identifies this cache in `ReplicationInfo::master_id`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Number of commands queued for the actor, e.g. to shed load before `try_*` calls fail
+    /// with `ChannelFull`.
+    pub fn queue_depth(&self) -> usize {
+        self.tx.queue_depth()
+    }
+
+    /// Fail requests with `TokioActorCacheError::Timeout` when the actor hasn't answered within `after`.
+    pub fn with_timeout(mut self, after: Duration) -> Self {
+        self.tx.set_timeout(Some(after));
+        self
+    }
+
+    /// Choose whether direct writes are accepted while this cache is replicating.
+    pub fn with_replica_writes(mut self, replica_writes: ReplicaWrites) -> Self {
+        self.replica_writes = replica_writes;
+        self
+    }
+
+    /// Spread TTLs by up to `± fraction` of their length, e.g. `0.1` for ±10%, so entries written
+    /// together with the same TTL don't all expire on the same tick.
+    pub fn with_ttl_jitter(self, fraction: f64) -> Self {
+        self.ttl_jitter.set(fraction);
+        self
+    }
+
+    /// Remove at most `max_elements` expired or evicted elements at a time, and look at no more
+    /// deadlines than that for them, so a mass expiration doesn't hold up commands until it is
+    /// done. The rest are carried over to the next tick and to the commands that follow, each of
+    /// which removes up to `max_elements` more first. Meanwhile `contains` and `ttl` already
+    /// treat the expired ones as gone, while other reads may still see them. Applies to every
+    /// handle.
+    pub fn with_cleanup_budget(self, max_elements: usize) -> Self {
+        self.cleanup_budget
+            .store(max_elements.max(1), Ordering::Release);
+        self
+    }
+
+    fn mpush_cmd(
+        vals: &[V],
+        ex: &[Option<Duration>],
+        policy: &[InsertPolicy],
+    ) -> Result<VecCmd<V>, TokioActorCacheError> {
+        TokioActorCacheError::check_len(vals.len(), &[ex.len(), policy.len()])?;
+
+        Ok(VecCmd::MPush {
+            vals: vals.to_vec(),
+            ex: ex.to_vec(),
+            policy: policy.to_vec(),
+        })
+    }
+
+    fn check_writable(&self) -> Result<(), TokioActorCacheError> {
+        if self.replica_writes == ReplicaWrites::Reject && self.replicating.load(Ordering::Acquire)
+        {
+            return Err(TokioActorCacheError::ReadOnlyReplica);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn stop_replicating_with(
+        &self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        let master = self
+            .tx
+            .request(mode, |resp_tx| VecCmd::StopReplicating { resp_tx })
+            .await?;
+        self.replicating.store(false, Ordering::Release);
+        if let Some(master) = master {
+            let remove_replica_cmd = VecCmd::RemoveReplica {
+                replica: self.tx.clone(),
+            };
+            // A master that is gone has no replica left to drop.
+            match master.send(remove_replica_cmd, mode).await {
+                Ok(()) | Err(TokioActorCacheError::ActorGone) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn promote_to_master_with(&self, mode: SendMode) -> Result<(), TokioActorCacheError> {
+        let master = self
+            .tx
+            .request(mode, |resp_tx| VecCmd::StopReplicating { resp_tx })
+            .await?;
+        self.replicating.store(false, Ordering::Release);
+        if let Some(master) = master {
+            let remove_replica_cmd = VecCmd::RemoveReplica {
+                replica: self.tx.clone(),
+            };
+            // Best effort: the old master may be gone, which is usually why a replica gets promoted.
+            let _ = master.send(remove_replica_cmd, mode).await;
+        }
+        Ok(())
+    }
+
+    async fn failover_with(
+        old: &Self,
+        new: &Self,
+        mode: SendMode,
+    ) -> Result<usize, TokioActorCacheError> {
+        new.promote_to_master_with(mode).await?;
+        old.tx
+            .request(mode, |resp_tx| VecCmd::Failover {
+                new_master: new.tx.clone(),
+                new_master_id: new.id,
+                resp_tx,
+            })
+            .await
+    }
+
+    /// Detach from the current master, then register with `master`, which sends its full state and
+    /// forwards every later write.
+    pub(crate) async fn replicate_with(
+        &self,
+        master: &Self,
+        mode: SendMode,
+    ) -> Result<(), TokioActorCacheError> {
+        // A cache replicating itself would forward its writes back to itself forever.
+        if self.tx.same_channel(&master.tx) {
+            return Ok(());
+        }
+        self.stop_replicating_with(mode).await?;
+        let replicate_cmd = VecCmd::Replicate {
+            master: master.tx.clone(),
+            master_id: master.id,
+        };
+        self.tx.send(replicate_cmd, mode).await?;
+        self.replicating.store(true, Ordering::Release);
+        let add_replica_cmd = VecCmd::AddReplica {
+            replica: self.tx.clone(),
+        };
+        master.tx.send(add_replica_cmd, mode).await
+    }
+
+    pub(crate) fn spawn(
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<VecCmd<V>>,
+        rx: CacheReceiver<VecCmd<V>>,
+    ) -> Result<Self, ConfigError>
+    where
+        V: Debug + Eq + Hash + Send + 'static,
+    {
+        Self::spawn_on(Arc::new(TokioRuntime), expiration_policy, tx, rx)
+    }
+
+    /// Like `spawn`, but runs the actor on `runtime` and ticks by its clock.
+    pub(crate) fn spawn_on(
+        runtime: Arc<dyn Runtime>,
+        expiration_policy: ExpirationPolicy,
+        tx: CacheSender<VecCmd<V>>,
+        rx: CacheReceiver<VecCmd<V>>,
+    ) -> Result<Self, ConfigError>
+    where
+        V: Debug + Eq + Hash + Send + 'static,
+    {
+        expiration_policy.validate()?;
+        let vec = Vec::with_capacity(expiration_policy.preallocated());
+        let id = next_cache_id();
+        let ttl_jitter = TtlJitter::new(id);
+        let cleanup_budget = Arc::new(AtomicUsize::new(usize::MAX));
+        let actor = VecActor {
+            vec,
+            expiration_policy,
+            replica_of: None,
+            sync_stats: SyncStats::default(),
+            replicas: Replicas::default(),
+            ttl_jitter: ttl_jitter.clone(),
+            max_len: None,
+            trim_from: VecEnd::default(),
+            expiry_index: RankIndex::default(),
+            expiry_backlog: false,
+            ids: ElementIds::default(),
+            eviction_index: EvictionIndex::default(),
+            cleanup_budget: cleanup_budget.clone(),
+        };
+        runtime.spawn(Box::pin(actor.run(rx, runtime.clone())));
+
+        Ok(Self {
+            tx,
+            channel: PhantomData,
+            id,
+            replicating: Arc::new(AtomicBool::new(false)),
+            replica_writes: ReplicaWrites::default(),
+            ttl_jitter,
+            cleanup_budget,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<V, C> VecCache<V, C>
+where
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// Serialize the live elements, in order with their remaining TTLs and call counts, for `load` to
+    /// bring back, e.g. after a restart or in another process.
+    pub async fn try_dump(&self) -> Result<Vec<u8>, TokioActorCacheError> {
+        self.dump_with(SendMode::Try).await
+    }
+
+    /// Add what `dump` serialized, overwriting entries that are already cached.
+    pub async fn try_load(&self, bytes: &[u8]) -> Result<(), TokioActorCacheError> {
+        self.load_with(bytes, SendMode::Try).await
+    }
+
+    pub async fn dump(&self) -> Result<Vec<u8>, TokioActorCacheError> {
+        self.dump_with(SendMode::Wait).await
+    }
+
+    pub async fn load(&self, bytes: &[u8]) -> Result<(), TokioActorCacheError> {
+        self.load_with(bytes, SendMode::Wait).await
+    }
+
+    async fn dump_with(&self, mode: SendMode) -> Result<Vec<u8>, TokioActorCacheError> {
+        let (expiration_policy, vec) = self
+            .tx
+            .request(mode, |resp_tx| VecCmd::Dump { resp_tx })
+            .await?;
+        let now = Instant::now();
+        let vals = vec
+            .into_iter()
+            .filter_map(|val_with_state| {
+                Dumped::new(
+                    val_with_state.val,
+                    val_with_state.expiration,
+                    val_with_state.call_cnt,
+                    now,
+                )
+            })
+            .collect::<Vec<Dumped<V>>>();
+        dump::encode(expiration_policy, &vals)
+    }
+
+    async fn load_with(&self, bytes: &[u8], mode: SendMode) -> Result<(), TokioActorCacheError> {
+        self.check_writable()?;
+        let now = Instant::now();
+        let vals = dump::decode::<V>(bytes)?
+            .into_iter()
+            .map(|entry| ValueWithState {
+                expiration: entry.expiration(now),
+                call_cnt: entry.call_cnt,
+                last_accessed: now,
+                val: entry.item,
+                pinned: false,
+                tti: None,
+                refresh_cost: Duration::ZERO,
+                version: 0,
+            })
+            .collect::<Vec<ValueWithState<V>>>();
+        self.tx.send(VecCmd::Load { vals }, mode).await
+    }
+}
+
+struct VecActor<V> {
+    vec: Vec<ValueWithState<V>>,
+    expiration_policy: ExpirationPolicy,
+    replica_of: Option<CacheSender<VecCmd<V>>>,
+    sync_stats: SyncStats,
+    replicas: Replicas<VecCmd<V>>,
+    ttl_jitter: TtlJitter,
+    max_len: Option<usize>,
+    trim_from: VecEnd,
+    // The ids of the elements with a TTL by when it runs out.
+    expiry_index: RankIndex<Instant, u64>,
+    // Set while expired elements are left over from a pass that ran out of its cleanup budget.
+    expiry_backlog: bool,
+    ids: ElementIds,
+    eviction_index: EvictionIndex<u64>,
+    cleanup_budget: Arc<AtomicUsize>,
+}
+
+/// Ids that stay with the elements of a `VecActor` as those before them go and their positions
+/// shift, for the eviction index to refer to them by.
+#[derive(Debug, Default)]
+struct ElementIds {
+    // The id of every element, by position.
+    ids: Vec<u64>,
+    positions: HashMap<u64, usize>,
+    next_id: u64,
+}
+
+impl ElementIds {
+    fn position(&self, id: u64) -> Option<usize> {
+        self.positions.get(&id).copied()
+    }
+
+    /// Number an element pushed at the back.
+    fn push(&mut self) -> u64 {
+        self.next_id += 1;
+        self.positions.insert(self.next_id, self.ids.len());
+        self.ids.push(self.next_id);
+        self.next_id
+    }
+
+    /// Drop the ids at the ascending `indices` and shift those after them down, which costs no
+    /// more than shifting the elements themselves.
+    fn remove_at(&mut self, indices: &[usize]) {
+        let Some(&first) = indices.first() else {
+            return;
+        };
+        let mut i = 0;
+        let mut to_remove = indices.iter().peekable();
+        self.ids.retain(|id| {
+            let keep = to_remove.next_if_eq(&&i).is_none();
+            if !keep {
+                self.positions.remove(id);
+            }
+            i += 1;
+            keep
+        });
+        for (position, id) in self.ids.iter().enumerate().skip(first) {
+            self.positions.insert(*id, position);
+        }
+    }
+
+    /// Number `len` elements afresh, e.g. once they were all replaced.
+    fn reset(&mut self, len: usize) {
+        self.ids.clear();
+        self.positions.clear();
+        for _ in 0..len {
+            self.push();
+        }
+    }
+}
+
+impl<V> VecActor<V>
+where
+    V: Clone + Eq + Hash,
+{
+    async fn run(mut self, mut rx: CacheReceiver<VecCmd<V>>, runtime: Arc<dyn Runtime>) {
+        let mut ticker = runtime.interval(TICK);
+        loop {
+            tokio::select! {
+                _ = ticker.next() => self.tick(),
+
+                // Expire short TTLs without waiting for the next tick. A backlog is left to the
+                // ticks and the commands in between, or it would hold them up after all.
+                _ = self.next_expiration().elapsed(&*runtime),
+                    if self.replica_of.is_none() && !self.expiry_backlog =>
+                {
+                    self.expire_due();
+                }
+
+                // Handle commands.
+                command = rx.recv() => {
+                    match command {
+                        Some(cmd) => self.handle(cmd),
+                        // Every handle is gone.
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        // Elements are addressed by position, so there is no change log and lagging replicas
+        // always get the full state; every change is numbered 0.
+        // Drop replicas that are gone and bring new or lagging ones up to date.
+        self.replicas.prune();
+        self.replicas.sync(0, |_, sent_at| VecCmd::Sync {
+            sent_at,
+            vec: self.vec.clone(),
+        });
+
+        // Replicas follow the expirations and evictions of their master.
+        if self.replica_of.is_some() {
+            return;
+        }
+
+        self.expire_due();
+        self.evict();
+    }
+
+    /// Drop the elements whose TTL has run out, taking only those due off the expiry index and
+    /// no more than the cleanup budget allows, stale ones included. Those left over keep the next
+    /// expiration due and are picked up where this pass stopped.
+    fn expire_due(&mut self) {
+        let now = Instant::now();
+        let budget = self.cleanup_budget.load(Ordering::Acquire);
+        let mut expired_indices = Vec::new();
+        let mut n_examined = 0;
+        while n_examined < budget
+            && self.next_expiration().is_due(now)
+            && let Some((_, id)) = self.expiry_index.pop_lowest()
+        {
+            n_examined += 1;
+            let Some(i) = self.ids.position(id) else {
+                continue;
+            };
+            match self.vec[i].expiration {
+                Some(exp) if exp <= now => expired_indices.push(i),
+                // Given a later TTL since it was pushed.
+                Some(exp) => self.expiry_index.push(exp, id),
+                None => {}
+            }
+        }
+        self.expiry_backlog = self.next_expiration().is_due(now);
+        if !expired_indices.is_empty() {
+            // `remove_at` walks the indices in order, once each.
+            expired_indices.sort_unstable();
+            expired_indices.dedup();
+            trace::debug_event!(expired = expired_indices.len(), "expired entries");
+            self.remove_at(expired_indices);
+        }
+    }
+
+    /// Whether `val_with_state` is still to be served, which it isn't once its TTL has run out
+    /// even if its removal was carried over, see `with_cleanup_budget`.
+    fn is_live(&self, val_with_state: &ValueWithState<V>, now: Instant) -> bool {
+        !self.expiry_backlog || val_with_state.expired(now).is_none()
+    }
+
+    /// When the earliest element on the expiry index runs out.
+    fn next_expiration(&self) -> NextExpiration {
+        NextExpiration::at(self.expiry_index.lowest())
+    }
+
+    /// Put every element with a TTL back on the expiry index, dropping what went stale.
+    fn refill_expiry_index(&mut self) {
+        self.expiry_index.clear();
+        for (val_with_state, &id) in self.vec.iter().zip(&self.ids.ids) {
+            if let Some(exp) = val_with_state.expiration {
+                self.expiry_index.push(exp, id);
+            }
+        }
+    }
+
+    /// Invalidate cache according to expiration policy, taking the elements over capacity off
+    /// the eviction index.
+    fn evict(&mut self) {
+        let (capacity, eviction) = match self.expiration_policy {
+            ExpirationPolicy::LFU(capacity) => (capacity, Eviction::Lfu),
+            ExpirationPolicy::LRU(capacity) => (capacity, Eviction::Lru),
+            ExpirationPolicy::None => return,
+        };
+        if self.eviction_index.keep(eviction) {
+            self.refill_eviction_index();
+        }
+        // Leave what is over the cleanup budget to the next tick or write.
+        let budget = self.cleanup_budget.load(Ordering::Acquire);
+        let n_exceed = self.vec.len().saturating_sub(capacity).min(budget);
+        let mut evicted_ids = HashSet::new();
+        while evicted_ids.len() < n_exceed
+            && let Some(id) = self.eviction_index.pop_current(|id| {
+                let val_with_state = &self.vec[self.ids.position(*id)?];
+                (!evicted_ids.contains(id))
+                    .then_some((val_with_state.call_cnt, val_with_state.last_accessed))
+            })
+        {
+            evicted_ids.insert(id);
+        }
+        if evicted_ids.is_empty() {
+            return;
+        }
+        let mut evicted_indices = evicted_ids
+            .into_iter()
+            .filter_map(|id| self.ids.position(id))
+            .collect::<Vec<usize>>();
+        // `remove_at` walks the indices in order.
+        evicted_indices.sort_unstable();
+        trace::debug_event!(
+            evicted = evicted_indices.len(),
+            "evicted entries over capacity"
+        );
+        self.remove_at(evicted_indices);
+    }
+
+    /// Put every element back on the eviction index, dropping what went stale.
+    fn refill_eviction_index(&mut self) {
+        self.eviction_index.clear();
+        for (val_with_state, &id) in self.vec.iter().zip(&self.ids.ids) {
+            if let Some(rank) = self
+                .eviction_index
+                .rank(val_with_state.call_cnt, val_with_state.last_accessed)
+            {
+                self.eviction_index.push(rank, id);
+            }
+        }
+    }
+
+    fn handle(&mut self, cmd: VecCmd<V>) {
+        trace::command_span!(cmd);
+
+        // Never serve an element past its TTL, even when its timer hasn't fired yet.
+        if self.replica_of.is_none() && self.next_expiration().is_due(Instant::now()) {
+            self.expire_due();
+        }
+
+        match cmd {
+            VecCmd::StopReplicating { resp_tx } => {
+                self.sync_stats.stop();
+                reply(resp_tx, self.replica_of.take());
+            }
+            VecCmd::IsReplica { resp_tx } => {
+                let is_replica = self.replica_of.is_some();
+
+                reply(resp_tx, is_replica);
+            }
+            VecCmd::ReplicationInfo { resp_tx } => {
+                let info = self.sync_stats.info(self.replicas.len());
+
+                reply(resp_tx, info);
+            }
+            VecCmd::Replicate { master, master_id } => {
+                self.replica_of = Some(master);
+                self.sync_stats.start(master_id);
+            }
+            VecCmd::AddReplica { replica } => {
+                self.replicas.add(replica);
+                self.replicas.sync(0, |_, sent_at| VecCmd::Sync {
+                    sent_at,
+                    vec: self.vec.clone(),
+                });
+            }
+            VecCmd::RemoveReplica { replica } => {
+                self.replicas.remove(&replica);
+            }
+            VecCmd::ReplicaCount { resp_tx } => {
+                reply(resp_tx, self.replicas.len());
+            }
+            VecCmd::Failover {
+                new_master,
+                new_master_id,
+                resp_tx,
+            } => {
+                let mut moved = 0;
+                for replica in self.replicas.drain() {
+                    if replica.same_channel(&new_master) {
+                        continue;
+                    }
+                    let replicate_cmd = VecCmd::Replicate {
+                        master: new_master.clone(),
+                        master_id: new_master_id,
+                    };
+                    if replica.send_now(replicate_cmd).is_err() {
+                        continue;
+                    }
+                    if new_master.send_now(VecCmd::AddReplica { replica }).is_ok() {
+                        moved += 1;
+                    }
+                }
+
+                reply(resp_tx, moved);
+            }
+            // Writes forwarded by the master are dropped once replication has stopped.
+            VecCmd::Sync { vec, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, vec.len());
+                    self.vec = vec;
+                    self.ids.reset(self.vec.len());
+                    self.refill_expiry_index();
+                    self.eviction_index = EvictionIndex::default();
+                    self.replicas.forward(0, |sent_at| VecCmd::Sync {
+                        sent_at,
+                        vec: self.vec.clone(),
+                    });
+                }
+            }
+            VecCmd::Append { val, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, 1);
+                    self.append(val);
+                }
+            }
+            VecCmd::RemoveAt { indices, sent_at } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, indices.len());
+                    self.remove_at(indices);
+                }
+            }
+            VecCmd::SetAt {
+                index,
+                val,
+                sent_at,
+            } => {
+                if self.replica_of.is_some() {
+                    self.sync_stats.record(sent_at, 1);
+                    self.set_at(index, val);
+                }
+            }
+            #[cfg(feature = "serde")]
+            VecCmd::Dump { resp_tx } => {
+                reply(resp_tx, (self.expiration_policy, self.vec.clone()));
+            }
+            #[cfg(feature = "serde")]
+            VecCmd::Load { vals } => {
+                for val_with_state in vals {
+                    self.append(val_with_state);
+                }
+                if self.replica_of.is_none() {
+                    self.trim();
+                    self.evict();
+                }
+            }
+            VecCmd::Ttl { vals, resp_tx } => {
+                let now = Instant::now();
+                // One result per requested val, not per matching element.
+                let ttl = vals
+                    .iter()
+                    .map(|val| {
+                        let i = self.vec.iter().position(|val_with_state| {
+                            val_with_state.val == *val && self.is_live(val_with_state, now)
+                        })?;
+                        let val_with_state = &mut self.vec[i];
+                        val_with_state.call_cnt += 1;
+                        val_with_state.last_accessed = now;
+                        val_with_state
+                            .expiration
+                            .and_then(|ex| ex.checked_duration_since(now))
+                    })
+                    .collect::<Vec<Option<Duration>>>();
+
+                reply(resp_tx, ttl);
+            }
+            VecCmd::Barrier { resp_tx } => {
+                reply(resp_tx, ());
+            }
+            VecCmd::Clear => {
+                self.vec.clear();
+                self.ids.reset(0);
+                self.expiry_index.clear();
+                self.eviction_index.clear();
+                self.replicas.forward(0, |sent_at| VecCmd::Sync {
+                    sent_at,
+                    vec: Vec::new(),
+                });
+            }
+            VecCmd::Remove {
+                vals,
+                policy,
+                resp_tx,
+            } => {
+                let n_removed = self.remove(&vals, policy);
+
+                reply(resp_tx, n_removed);
+            }
+            VecCmd::Contains { vals, resp_tx } => {
+                let is_exist = self.touch(vals);
+
+                reply(resp_tx, is_exist);
+            }
+            VecCmd::GetAll { resp_tx } => {
+                let vals = self
+                    .vec
+                    .iter_mut()
+                    .map(|val_with_state| {
+                        val_with_state.call_cnt += 1;
+                        val_with_state.last_accessed = Instant::now();
+                        val_with_state.val.clone()
+                    })
+                    .collect::<Vec<V>>();
+
+                reply(resp_tx, vals);
+            }
+            VecCmd::Snapshot { resp_tx } => {
+                reply(resp_tx, self.vec.clone());
+            }
+            VecCmd::Migrate { vals, resp_tx } => {
+                for val_with_state in vals {
+                    self.append(val_with_state);
+                }
+                if self.replica_of.is_none() {
+                    self.trim();
+                    self.evict();
+                }
+
+                reply(resp_tx, ());
+            }
+            VecCmd::MPush { vals, ex, policy } => {
+                for ((val, ex), policy) in vals.into_iter().zip(ex).zip(policy) {
+                    self.push(val, ex, policy);
+                }
+            }
+            VecCmd::Push { val, ex, policy } => {
+                self.push(val, ex, policy);
+            }
+            VecCmd::PopFront { resp_tx } => {
+                let val = self.take_at(0);
+
+                reply(resp_tx, val);
+            }
+            VecCmd::PopBack { resp_tx } => {
+                let val = match self.vec.len() {
+                    0 => None,
+                    len => self.take_at(len - 1),
+                };
+
+                reply(resp_tx, val);
+            }
+            VecCmd::Truncate { len } => {
+                if len < self.vec.len() {
+                    self.remove_at((len..self.vec.len()).collect());
+                }
+            }
+            VecCmd::GetIndex { index, resp_tx } => {
+                let val = self.vec.get_mut(index).map(|val_with_state| {
+                    val_with_state.call_cnt += 1;
+                    val_with_state.last_accessed = Instant::now();
+                    val_with_state.val.clone()
+                });
+
+                reply(resp_tx, val);
+            }
+            VecCmd::SetIndex {
+                index,
+                val,
+                resp_tx,
+            } => {
+                let is_set = match self.vec.get(index) {
+                    Some(val_with_state) => {
+                        let val_with_state = ValueWithState {
+                            val,
+                            expiration: val_with_state.expiration,
+                            call_cnt: val_with_state.call_cnt + 1,
+                            last_accessed: Instant::now(),
+                            pinned: false,
+                            tti: None,
+                            refresh_cost: Duration::ZERO,
+                            version: 0,
+                        };
+                        self.set_at(index, val_with_state);
+                        true
+                    }
+                    None => false,
+                };
+
+                reply(resp_tx, is_set);
+            }
+            VecCmd::MaxLen { max_len, trim_from } => {
+                self.max_len = max_len;
+                self.trim_from = trim_from;
+                if self.replica_of.is_none() {
+                    self.trim();
+                }
+            }
+        }
+    }
+
+    /// Bump the state of every stored element equal to one of `vals` and report which were found.
+    fn touch(&mut self, vals: Vec<V>) -> Vec<bool> {
+        let now = Instant::now();
+        let expiry_backlog = self.expiry_backlog;
+        let mut found_set = HashSet::with_capacity(vals.len());
+        for val_with_state in &mut self.vec {
+            // As `is_live`, which can't be called while the elements are borrowed.
+            let live = !expiry_backlog || val_with_state.expired(now).is_none();
+            if live && vals.contains(&val_with_state.val) {
+                val_with_state.call_cnt += 1;
+                val_with_state.last_accessed = Instant::now();
+                found_set.insert(val_with_state.val.clone());
+            }
+        }
+
+        vals.into_iter()
+            .map(|val| found_set.contains(&val))
+            .collect::<Vec<bool>>()
+    }
+
+    fn push(&mut self, val: V, ex: Option<Duration>, policy: InsertPolicy) {
+        let expiration = self.ttl_jitter.expiration(ex);
+        let last_accessed = Instant::now();
+
+        // Elements past their TTL whose removal is still to come don't count as present.
+        let existing = self.vec.iter().position(|val_with_state| {
+            val_with_state.val == val && val_with_state.expired(last_accessed).is_none()
+        });
+        if !policy.allows(existing.is_some()) {
+            return;
+        }
+        let call_cnt = existing.map_or(0, |i| self.vec[i].call_cnt + 1);
+        // Only overwriting, as for the other caches, so the element present takes the new TTL
+        // where it is rather than being pushed again.
+        if policy == InsertPolicy::IfPresent
+            && let Some(i) = existing
+        {
+            let val_with_state = ValueWithState {
+                expiration,
+                call_cnt,
+                last_accessed,
+                ..self.vec[i].clone()
+            };
+            self.set_at(i, val_with_state);
+            return;
+        }
+        let val_with_state = ValueWithState {
+            val,
+            expiration,
+            call_cnt,
+            last_accessed,
+            pinned: false,
+            tti: None,
+            refresh_cost: Duration::ZERO,
+            version: 0,
+        };
+        self.append(val_with_state);
+
+        // Keep the length within capacity between ticks too, except on replicas, which follow
+        // their master.
+        if self.replica_of.is_none() {
+            self.trim();
+            self.evict();
+        }
+    }
+
+    /// Push `val_with_state` and forward it to the replicas.
+    fn append(&mut self, val_with_state: ValueWithState<V>) {
+        self.replicas.forward(0, |sent_at| VecCmd::Append {
+            sent_at,
+            val: val_with_state.clone(),
+        });
+        let id = self.ids.push();
+        if let Some(exp) = val_with_state.expiration {
+            self.expiry_index.push(exp, id);
+        }
+        if let Some(rank) = self
+            .eviction_index
+            .rank(val_with_state.call_cnt, val_with_state.last_accessed)
+        {
+            self.eviction_index.push(rank, id);
+        }
+        self.vec.push(val_with_state);
+        if self.expiry_index.is_bloated(self.vec.len()) {
+            self.refill_expiry_index();
+        }
+        if self.eviction_index.is_bloated(self.vec.len()) {
+            self.refill_eviction_index();
+        }
+    }
+
+    /// Delete the occurrences of `vals` that `policy` picks and count them per val.
+    fn remove(&mut self, vals: &[V], policy: RemovePolicy) -> Vec<usize> {
+        // Counted at the first position of each val, so repeated vals share one count.
+        let first_pos = |val: &V| vals.iter().position(|v| v == val);
+        let mut n_removed = vec![0; vals.len()];
+        let indices = self
+            .vec
+            .iter()
+            .enumerate()
+            .filter_map(|(i, val_with_state)| {
+                let pos = first_pos(&val_with_state.val)?;
+                if policy == RemovePolicy::First && n_removed[pos] > 0 {
+                    return None;
+                }
+                n_removed[pos] += 1;
+                Some(i)
+            })
+            .collect::<Vec<usize>>();
+        if !indices.is_empty() {
+            self.remove_at(indices);
+        }
+
+        vals.iter()
+            .map(|val| first_pos(val).map_or(0, |pos| n_removed[pos]))
+            .collect()
+    }
+
+    /// Drop elements from `trim_from` until at most `max_len` are left.
+    fn trim(&mut self) {
+        let Some(max_len) = self.max_len else {
+            return;
+        };
+        let len = self.vec.len();
+        if len <= max_len {
+            return;
+        }
+        let indices = match self.trim_from {
+            VecEnd::Front => (0..len - max_len).collect(),
+            VecEnd::Back => (max_len..len).collect(),
+        };
+        self.remove_at(indices);
+    }
+
+    /// Remove the element at `index` and have the replicas do the same.
+    fn take_at(&mut self, index: usize) -> Option<V> {
+        if index >= self.vec.len() {
+            return None;
+        }
+        let val_with_state = self.vec.remove(index);
+        self.ids.remove_at(&[index]);
+        self.replicas.forward(0, |sent_at| VecCmd::RemoveAt {
+            sent_at,
+            indices: vec![index],
+        });
+        Some(val_with_state.val)
+    }
+
+    /// Overwrite the element at `index` and have the replicas do the same.
+    fn set_at(&mut self, index: usize, val_with_state: ValueWithState<V>) {
+        let Some(slot) = self.vec.get_mut(index) else {
+            return;
+        };
+        self.replicas.forward(0, |sent_at| VecCmd::SetAt {
+            sent_at,
+            index,
+            val: val_with_state.clone(),
+        });
+        if let Some(exp) = val_with_state.expiration {
+            self.expiry_index.push(exp, self.ids.ids[index]);
+        }
+        *slot = val_with_state;
+        if self.expiry_index.is_bloated(self.vec.len()) {
+            self.refill_expiry_index();
+        }
+    }
+
+    /// Remove the elements at the ascending `indices` and have the replicas do the same.
+    fn remove_at(&mut self, indices: Vec<usize>) {
+        let mut i = 0;
+        let mut to_remove = indices.iter().peekable();
+        self.vec.retain(|_| {
+            let keep = to_remove.next_if_eq(&&i).is_none();
+            i += 1;
+            keep
+        });
+        self.ids.remove_at(&indices);
+        self.replicas.forward(0, |sent_at| VecCmd::RemoveAt {
+            sent_at,
+            indices: indices.clone(),
+        });
+    }
+}
@@ -0,0 +1,44 @@
+//! Bookkeeping for `HashMapCache::warm_from`, so other tasks can wait until the cache is warm.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Entries sent to the actor per message while warming up.
+pub(crate) const WARMUP_BATCH: usize = 256;
+
+/// Entries sent to the actor per message by `HashMapCache::bulk_load`.
+pub(crate) const BULK_LOAD_CHUNK: usize = 64 * 1024;
+
+/// Number of warmups in progress, shared by every handle of a cache.
+#[derive(Debug, Clone)]
+pub(crate) struct Warmup(Arc<watch::Sender<usize>>);
+
+impl Default for Warmup {
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(0)))
+    }
+}
+
+impl Warmup {
+    /// Count a warmup as in progress until the returned guard is dropped, including when the
+    /// warmup fails or its future is dropped.
+    pub(crate) fn start(&self) -> WarmupGuard {
+        self.0.send_modify(|pending| *pending += 1);
+        WarmupGuard(self.0.clone())
+    }
+
+    pub(crate) async fn ready(&self) {
+        let mut pending = self.0.subscribe();
+        // `self` holds the sender, so the channel can't close while waiting.
+        let _ = pending.wait_for(|pending| *pending == 0).await;
+    }
+}
+
+pub(crate) struct WarmupGuard(Arc<watch::Sender<usize>>);
+
+impl Drop for WarmupGuard {
+    fn drop(&mut self) {
+        self.0.send_modify(|pending| *pending -= 1);
+    }
+}
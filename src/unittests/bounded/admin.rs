@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{
+        Router,
+        body::{self, Body},
+        http::{Method, Request, StatusCode},
+    };
+    use serde::de::DeserializeOwned;
+    use tower::ServiceExt;
+
+    use crate::tokio_cache::{
+        admin::{AdminCaches, CacheStats, Entry, Replication, admin_router},
+        bounded::hm::HashMapCache,
+        option::{ExpirationPolicy, InsertPolicy},
+    };
+
+    async fn send(router: &Router, method: Method, uri: &str) -> (StatusCode, Vec<u8>) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.clone().oneshot(request).await.unwrap();
+        let status = resp.status();
+        let body = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    async fn get_json<T: DeserializeOwned>(router: &Router, uri: &str) -> T {
+        let (status, body) = send(router, Method::GET, uri).await;
+        assert_eq!(status, StatusCode::OK);
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn setup() -> (HashMapCache<String, i32>, Router) {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        for (key, val) in [("user:1", 1), ("user:2", 2), ("session:1", 3)] {
+            hm_cache
+                .insert(key.to_string(), val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let router = admin_router(AdminCaches::new().register("users", hm_cache.clone()));
+        (hm_cache, router)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stats_and_keys() {
+        let (hm_cache, router) = setup().await;
+        hm_cache.get("user:2".to_string()).await.unwrap();
+
+        let stats: CacheStats = get_json(&router, "/users/stats").await;
+        assert_eq!(stats.entries, 3);
+        assert_eq!(stats.hot_keys[0].key, "user:2");
+        assert_eq!(stats.persistent, 3);
+        assert!(stats.ttl_histogram.iter().all(|bucket| bucket.entries == 0));
+
+        let keys: Vec<String> = get_json(&router, "/users/keys").await;
+        assert_eq!(keys, vec!["session:1", "user:1", "user:2"]);
+        let keys: Vec<String> = get_json(&router, "/users/keys?pattern=user:*").await;
+        assert_eq!(keys, vec!["user:1", "user:2"]);
+        let keys: Vec<String> = get_json(&router, "/users/keys?pattern=*:?").await;
+        assert_eq!(keys.len(), 3);
+
+        let (status, _) = send(&router, Method::GET, "/orders/stats").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_entry() {
+        let (hm_cache, router) = setup().await;
+        hm_cache
+            .expire("user:1".to_string(), Some(Duration::from_secs(60)))
+            .await
+            .unwrap();
+
+        let entry: Entry = get_json(&router, "/users/entry/user:1").await;
+        assert_eq!(entry.key, "user:1");
+        assert_eq!(entry.val, serde_json::json!(1));
+        assert!(entry.ttl_ms.unwrap() > 59_000);
+
+        let (status, _) = send(&router, Method::DELETE, "/users/entry/user:1").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&router, Method::GET, "/users/entry/user:1").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&router, Method::DELETE, "/users/entry/user:1").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flush_and_replication() {
+        let (hm_cache, router) = setup().await;
+        let (status, _) = send(&router, Method::POST, "/users/flush").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(hm_cache.get_all().await.unwrap().is_empty());
+
+        let replication: Replication = get_json(&router, "/users/replication").await;
+        assert_eq!(replication.role, "master");
+        assert_eq!(replication.replicas, 0);
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tokio_cache::{
+        blocking::BlockingHashMapCache,
+        bounded::hm::HashMapCache,
+        option::{ExpirationPolicy, InsertPolicy},
+    };
+
+    #[test]
+    fn test_spawn() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache =
+            BlockingHashMapCache::spawn(|| HashMapCache::<i32, i32>::new(expiration_policy, 32))
+                .unwrap();
+        std::thread::scope(|s| {
+            for i in 0..4 {
+                let hm_cache = hm_cache.clone();
+                s.spawn(move || {
+                    hm_cache
+                        .insert(i, i * 10, None, InsertPolicy::Always)
+                        .unwrap();
+                });
+            }
+        });
+        assert_eq!(
+            hm_cache.mget(&[0, 1, 2, 3, 4]).unwrap(),
+            vec![Some(0), Some(10), Some(20), Some(30), None]
+        );
+
+        assert!(hm_cache.update_value(1, |val| *val += 1).unwrap());
+        assert_eq!(hm_cache.get(1).unwrap(), Some(11));
+        assert_eq!(hm_cache.remove(&[1]).unwrap(), vec![Some(11)]);
+        assert_eq!(hm_cache.contains_key(&[1, 2]).unwrap(), vec![false, true]);
+
+        assert!(hm_cache.expire(2, Some(Duration::from_secs(10))).unwrap());
+        assert!(hm_cache.ttl(&[2]).unwrap()[0].is_some());
+        hm_cache.clear().unwrap();
+        assert!(hm_cache.get_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_new_on_existing_runtime() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = runtime
+            .block_on(HashMapCache::<&str, i32>::new(expiration_policy, 32))
+            .unwrap();
+        let blocking = BlockingHashMapCache::new(hm_cache.clone(), runtime.handle().clone());
+
+        std::thread::spawn(move || {
+            blocking.insert("a", 1, None, InsertPolicy::Always).unwrap();
+        })
+        .join()
+        .unwrap();
+        // Visible through the async handle.
+        assert_eq!(runtime.block_on(hm_cache.get("a")).unwrap(), Some(1));
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache,
+        bytes_cache::BytesCache,
+        option::{ExpirationPolicy, InsertPolicy},
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bytes_cache() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", Bytes::from(vec![0; 40]), None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let bytes_cache = BytesCache::new(hm_cache, 100).await.unwrap();
+        assert_eq!(bytes_cache.total_bytes().await.unwrap(), 40);
+
+        let body = Bytes::from(vec![1; 30]);
+        for key in ["b", "c"] {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            bytes_cache
+                .insert(key, body.clone(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(bytes_cache.try_total_bytes().await.unwrap(), 100);
+
+        // Reads share the cached buffer.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        let cached = bytes_cache.get("b").await.unwrap().unwrap();
+        assert_eq!(cached.as_ptr(), body.as_ptr());
+        let range = bytes_cache.get_range("b", 10..50).await.unwrap().unwrap();
+        assert_eq!(range.len(), 20);
+        assert_eq!(range.as_ptr(), body[10..].as_ptr());
+
+        // The least recently used goes first, and only as many as needed.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        bytes_cache
+            .insert("d", Bytes::from(vec![2; 20]), None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(bytes_cache.get("a").await.unwrap(), None);
+        assert_eq!(bytes_cache.total_bytes().await.unwrap(), 80);
+
+        // Overwrites count the new value only.
+        bytes_cache
+            .insert("d", Bytes::from(vec![2; 5]), None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(bytes_cache.total_bytes().await.unwrap(), 65);
+
+        bytes_cache.set_max_bytes(40).await.unwrap();
+        assert_eq!(bytes_cache.max_bytes().await.unwrap(), 40);
+        assert_eq!(bytes_cache.get("c").await.unwrap(), None);
+        assert_eq!(bytes_cache.total_bytes().await.unwrap(), 35);
+
+        bytes_cache.remove(&["b"]).await.unwrap();
+        assert_eq!(bytes_cache.total_bytes().await.unwrap(), 5);
+        bytes_cache.cache().clear().await.unwrap();
+        assert_eq!(bytes_cache.total_bytes().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache,
+        compression::{Codec, CompressedHashMapCache, Compression},
+        option::{ExpirationPolicy, InsertPolicy},
+    };
+
+    fn codecs() -> Vec<Codec> {
+        vec![
+            #[cfg(feature = "lz4")]
+            Codec::Lz4,
+            #[cfg(feature = "zstd")]
+            Codec::Zstd(3),
+        ]
+    }
+
+    fn json_blob() -> Bytes {
+        let rows: Vec<String> = (0..100)
+            .map(|i| format!(r#"{{"id":{i},"name":"user","active":true}}"#))
+            .collect();
+        Bytes::from(format!("[{}]", rows.join(",")))
+    }
+
+    #[tokio::test]
+    async fn test_compression() {
+        for codec in codecs() {
+            let compression = Compression { codec, min_len: 64 };
+            let expiration_policy = ExpirationPolicy::None;
+            let hm_cache = CompressedHashMapCache::new(
+                HashMapCache::new(expiration_policy, 32).await.unwrap(),
+                compression,
+            );
+            let blob = json_blob();
+            let small = Bytes::from_static(b"{}");
+            hm_cache
+                .insert("blob", blob.clone(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            hm_cache
+                .insert("small", small.clone(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
+
+            let packed = hm_cache.cache().get("blob").await.unwrap().unwrap();
+            assert!(packed.is_compressed());
+            assert!(packed.stored_len() < blob.len());
+            assert_eq!(packed.len(), blob.len());
+            let packed = hm_cache.cache().get("small").await.unwrap().unwrap();
+            assert!(!packed.is_compressed());
+
+            assert_eq!(hm_cache.get("blob").await.unwrap(), Some(blob.clone()));
+            assert_eq!(hm_cache.try_get("small").await.unwrap(), Some(small));
+            assert_eq!(hm_cache.get("missing").await.unwrap(), None);
+            assert_eq!(
+                hm_cache.remove(&["blob", "missing"]).await.unwrap(),
+                vec![Some(blob), None]
+            );
+            assert_eq!(hm_cache.get("blob").await.unwrap(), None);
+        }
+    }
+}
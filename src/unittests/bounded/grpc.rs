@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+    use tonic::transport::{Channel, Server, server::TcpIncoming};
+
+    use crate::tokio_cache::{
+        bounded::hm_cluster::HashMapCacheCluster,
+        grpc::{
+            CacheNode, CacheServiceClient, Event, GetRequest, MGetRequest, RemoveRequest,
+            SetRequest, SubscribeRequest, Value,
+        },
+        option::ExpirationPolicy,
+    };
+
+    async fn connect() -> CacheServiceClient<Channel> {
+        let expiration_policy = ExpirationPolicy::None;
+        let cluster = HashMapCacheCluster::<Bytes, Bytes>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = CacheNode::new(cluster).into_service();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpIncoming::from(listener)),
+        );
+        CacheServiceClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap()
+    }
+
+    async fn set(client: &mut CacheServiceClient<Channel>, key: &str, val: &str) {
+        let request = SetRequest {
+            key: Bytes::copy_from_slice(key.as_bytes()),
+            val: Bytes::copy_from_slice(val.as_bytes()),
+            ttl_ms: None,
+        };
+        client.set(request).await.unwrap();
+    }
+
+    fn keys(keys: &[&str]) -> Vec<Bytes> {
+        keys.iter()
+            .map(|key| Bytes::copy_from_slice(key.as_bytes()))
+            .collect()
+    }
+
+    fn value(val: Option<&str>) -> Value {
+        Value {
+            val: val.map(|val| Bytes::copy_from_slice(val.as_bytes())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_set_remove() {
+        let mut client = connect().await;
+        set(&mut client, "a", "1").await;
+        set(&mut client, "b", "2").await;
+
+        let request = GetRequest {
+            key: Bytes::from_static(b"a"),
+        };
+        let resp = client.get(request).await.unwrap().into_inner();
+        assert_eq!(resp.val, Some(Bytes::from_static(b"1")));
+
+        let request = MGetRequest {
+            keys: keys(&["a", "c", "b"]),
+        };
+        let resp = client.m_get(request).await.unwrap().into_inner();
+        assert_eq!(
+            resp.vals,
+            vec![value(Some("1")), value(None), value(Some("2"))]
+        );
+
+        let request = RemoveRequest {
+            keys: keys(&["a", "c"]),
+        };
+        let resp = client.remove(request).await.unwrap().into_inner();
+        assert_eq!(resp.vals, vec![value(Some("1")), value(None)]);
+        let request = GetRequest {
+            key: Bytes::from_static(b"a"),
+        };
+        let resp = client.get(request).await.unwrap().into_inner();
+        assert_eq!(resp.val, None);
+    }
+
+    #[tokio::test]
+    async fn test_set_with_ttl() {
+        let mut client = connect().await;
+        let request = SetRequest {
+            key: Bytes::from_static(b"a"),
+            val: Bytes::from_static(b"1"),
+            ttl_ms: Some(100),
+        };
+        client.set(request).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let request = GetRequest {
+            key: Bytes::from_static(b"a"),
+        };
+        let resp = client.get(request).await.unwrap().into_inner();
+        assert_eq!(resp.val, None);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let mut client = connect().await;
+        set(&mut client, "a", "1").await;
+        set(&mut client, "b", "1").await;
+
+        let request = SubscribeRequest { keys: keys(&["a"]) };
+        let mut events = client.subscribe(request).await.unwrap().into_inner();
+        set(&mut client, "b", "2").await;
+        set(&mut client, "a", "2").await;
+        client
+            .remove(RemoveRequest { keys: keys(&["a"]) })
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let event = tokio::time::timeout(Duration::from_secs(1), events.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            received.push(event);
+        }
+        let event = |val: Option<&str>| Event {
+            key: Bytes::from_static(b"a"),
+            val: val.map(|val| Bytes::copy_from_slice(val.as_bytes())),
+        };
+        assert_eq!(
+            received,
+            vec![event(Some("1")), event(Some("2")), event(None)]
+        );
+    }
+}
@@ -1,17 +1,43 @@
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
-
-    use crate::tokio_cache::{bounded::hm::HashMapCache, option::ExpirationPolicy};
-
-    #[tokio::test]
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, SystemTime},
+    };
+
+    use crate::tokio_cache::{
+        bounded::hm::{ArcHashMapCache, HashMapCache},
+        error::{ConfigError, TokioActorCacheError},
+        near::NearCache,
+        option::{
+            DroppedReceiver, ExpirationPolicy, InsertEntry, InsertOptions, InsertPolicy,
+            ReplicaWrites, TtlOnWrite,
+        },
+        pipeline::HashMapReply,
+        removal::{Eviction, Removal, RemovalReason},
+        replication::Role,
+        test_utils::{TICK, advance},
+    };
+
+    #[tokio::test(start_paused = true)]
     async fn test_try_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert("a", 1, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -26,14 +52,21 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert("a", 1, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -47,7 +80,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm_cluster1.insert("a", 10, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -56,14 +92,21 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert("a", 1, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -74,14 +117,21 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert("a", 1, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -96,14 +146,21 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert("a", 1, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -117,7 +174,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm_cluster1.insert("a", 10, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -126,14 +186,21 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert("a", 1, None, false).await.unwrap();
+        hm_cluster1
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get("a").await.unwrap();
 
@@ -144,43 +211,52 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
-            .insert("a", 10, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hm_cache.try_ttl(&["a", "b"]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
-        hm_cache.insert("b", 12, None, false).await.unwrap();
-        hm_cache.insert("c", 20, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 12, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("c", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hm = hm_cache.get_all().await.unwrap();
-        assert_eq!(!hm.is_empty(), true);
+        assert!(!hm.is_empty());
         hm_cache.try_clear().await.unwrap();
         let hm = hm_cache.get_all().await.unwrap();
-        assert_eq!(hm.is_empty(), true);
+        assert!(hm.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_mget() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -188,16 +264,16 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -205,16 +281,16 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_contains_keys() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -225,17 +301,20 @@ mod tests {
         assert_eq!(is_contains_keys, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
             .try_minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -243,17 +322,20 @@ mod tests {
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
             .try_minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -261,10 +343,10 @@ mod tests {
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .try_minsert(
                 &["a", "b", "c"],
@@ -274,7 +356,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -287,16 +369,16 @@ mod tests {
         assert_eq!(val_c, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .try_minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -308,51 +390,206 @@ mod tests {
         assert_eq!(val_c, Some(30));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_inconsistent_len() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         let res = hm_cache
             .try_minsert(
                 &["a", "b"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await;
         assert!(res.is_err());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_try_minsert_inconsistent_len_error() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        let res = hm_cache
+            .try_minsert(
+                &["a", "b"],
+                &[10, 20, 30],
+                &[None, None],
+                &[InsertPolicy::Always; 2],
+            )
+            .await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::InconsistentLen {
+                expected: 2,
+                got: 3
+            })
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_try_insert_channel_full() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 1).await.unwrap();
+
+        // The actor cannot drain the channel until this task yields.
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let res = hm_cache
+            .try_insert("b", 20, None, InsertPolicy::Always)
+            .await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::ChannelFull {
+                len: 1,
+                capacity: 1
+            })
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queue_depth() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 4).await.unwrap();
+        assert_eq!(hm_cache.queue_depth(), 0);
+
+        // The actor cannot drain the channel until this task yields.
+        for (key, val) in [("a", 1), ("b", 2), ("c", 3)] {
+            hm_cache
+                .try_insert(key, val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(hm_cache.queue_depth(), 3);
+
+        hm_cache.get("a").await.unwrap();
+        assert_eq!(hm_cache.queue_depth(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_lane() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_read_lane(8);
+        // Answered once the actor has picked up the lane.
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        // The write is still queued when the read arrives, and the read is served first.
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        // `get_all` stays on the main channel, behind the write.
+        assert_eq!(hm_cache.get_all().await.unwrap().get("a"), Some(&10));
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_barrier() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_read_lane(8);
+        assert_eq!(hm_cache.get(0).await.unwrap(), None);
+
+        for key in 0..10 {
+            hm_cache
+                .try_insert(key, key * 10, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        hm_cache.barrier().await.unwrap();
+        assert_eq!(hm_cache.get(9).await.unwrap(), Some(90));
+        hm_cache.remove(&[9]).await.unwrap();
+        hm_cache.try_barrier().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_sync() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_read_lane(8);
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        // Unlike `insert`, the write is applied before the read lane is asked.
+        hm_cache
+            .insert_sync("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(10));
+        hm_cache
+            .try_insert_sync("a", 20, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expire_on_paused_time() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        let ex = Duration::from_secs(3600);
+        hm_cache
+            .insert("a", 10, Some(ex), InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        advance(ex - TICK).await;
+        assert_eq!(hm_cache.ttl(&["a"]).await.unwrap(), vec![Some(TICK)]);
+        advance(TICK * 2).await;
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.try_insert("a", 10, None, false).await.unwrap();
-        hm_cache.try_insert("a", 20, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .try_insert("a", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
-            .try_insert("a", 20, None, true)
+            .try_insert("a", 20, None, InsertPolicy::IfAbsent)
             .await
             .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
-            .try_insert("b", 20, Some(Duration::from_secs(1)), false)
+            .try_insert("b", 20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -362,52 +599,246 @@ mod tests {
         assert_eq!(val_b, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
-            .insert("a", 10, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hm_cache.ttl(&["a", "b"]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_expire() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert!(
+            hm_cache
+                .expire("a", Some(Duration::from_secs(10)))
+                .await
+                .unwrap()
+        );
+        assert!(!hm_cache.expire("b", None).await.unwrap());
+        let ttl = hm_cache.ttl(&["a"]).await.unwrap();
+        assert!(ttl[0] > Some(Duration::from_secs(9)));
+
+        assert!(hm_cache.expire("a", None).await.unwrap());
+        assert_eq!(hm_cache.ttl(&["a"]).await.unwrap(), vec![None]);
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expire_follows_ttl_changes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        for (key, ttl) in [("a", 10_000), ("b", 50), ("c", 10)] {
+            hm_cache
+                .insert(
+                    key,
+                    1,
+                    Some(Duration::from_millis(ttl)),
+                    InsertPolicy::Always,
+                )
+                .await
+                .unwrap();
+        }
+        // Shortened, lengthened by an overwrite and lifted.
+        assert!(
+            hm_cache
+                .expire("a", Some(Duration::from_millis(20)))
+                .await
+                .unwrap()
+        );
+        hm_cache
+            .insert("b", 2, Some(Duration::from_secs(10)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert!(hm_cache.expire("c", None).await.unwrap());
+
+        advance(Duration::from_millis(60)).await;
+        assert_eq!(
+            hm_cache.mget(&["a", "b", "c"]).await.unwrap(),
+            vec![None, Some(2), Some(1)]
+        );
+        advance(Duration::from_secs(10)).await;
+        assert_eq!(
+            hm_cache.mget(&["a", "b", "c"]).await.unwrap(),
+            vec![None, None, Some(1)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expire_at() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_ttl_jitter(0.5);
+        let at = SystemTime::now() + Duration::from_secs(10);
+        let exat = InsertOptions {
+            exat: Some(at),
+            ..Default::default()
+        };
+        hm_cache.set("a", 1, exat).await.unwrap();
+        hm_cache
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert!(hm_cache.expire_at("b", at).await.unwrap());
+        assert!(!hm_cache.try_expire_at("c", at).await.unwrap());
+
+        // Not spread by the jitter, unlike relative TTLs.
+        advance(Duration::from_millis(9_900)).await;
+        assert_eq!(
+            hm_cache.mget(&["a", "b"]).await.unwrap(),
+            vec![Some(1), Some(2)]
+        );
+        advance(Duration::from_millis(200)).await;
+        assert_eq!(hm_cache.mget(&["a", "b"]).await.unwrap(), vec![None, None]);
+
+        // A time that has passed expires the entry right away.
+        hm_cache
+            .insert("a", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert!(
+            hm_cache
+                .expire_at("a", SystemTime::UNIX_EPOCH)
+                .await
+                .unwrap()
+        );
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_negative_caching() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<u32, Option<&str>>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let n_loads = AtomicUsize::new(0);
+        let load = |id: u32| {
+            n_loads.fetch_add(1, Ordering::Relaxed);
+            async move { Ok::<_, TokioActorCacheError>((id == 1).then_some("one")) }
+        };
+        let ex = Some(Duration::from_secs(60));
+        let negative_ttl = Duration::from_secs(5);
+
+        for _ in 0..3 {
+            let one = hm_cache.get_or_insert_with(1, ex, negative_ttl, || load(1));
+            assert_eq!(one.await.unwrap(), Some("one"));
+            let two = hm_cache.get_or_insert_with(2, ex, negative_ttl, || load(2));
+            assert_eq!(two.await.unwrap(), None);
+        }
+        assert_eq!(n_loads.load(Ordering::Relaxed), 2);
+
+        // Misses are remembered for the shorter TTL only.
+        advance(Duration::from_secs(6)).await;
+        let two = hm_cache.get_or_insert_with(2, ex, negative_ttl, || load(2));
+        assert_eq!(two.await.unwrap(), None);
+        let one = hm_cache.get_or_insert_with(1, ex, negative_ttl, || load(1));
+        assert_eq!(one.await.unwrap(), Some("one"));
+        assert_eq!(n_loads.load(Ordering::Relaxed), 3);
+
+        // Failed loads aren't cached.
+        let failed = hm_cache.get_or_insert_with(3, ex, negative_ttl, || async {
+            Err(TokioActorCacheError::Cancelled)
+        });
+        assert!(failed.await.is_err());
+        assert_eq!(hm_cache.get(3).await.unwrap(), None);
+
+        hm_cache.try_insert_negative(1, negative_ttl).await.unwrap();
+        let one = hm_cache.get_or_insert_with(1, ex, negative_ttl, || load(1));
+        assert_eq!(one.await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_get_with_refresh() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, usize>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let n_loads = AtomicUsize::new(0);
+        let load = || async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok::<_, TokioActorCacheError>(n_loads.fetch_add(1, Ordering::Relaxed) + 1)
+        };
+        let ex = Some(Duration::from_secs(60));
+
+        assert_eq!(hm_cache.get_with_refresh("a", ex, load).await.unwrap(), 1);
+        // Far from the expiry, against a load of a second, hits are never refreshed.
+        for _ in 0..100 {
+            assert_eq!(hm_cache.get_with_refresh("a", ex, load).await.unwrap(), 1);
+        }
+
+        // Half a load from the expiry, a hit is refreshed more often than not, and the refreshed
+        // entry is far from its expiry again.
+        advance(Duration::from_millis(59_500)).await;
+        for _ in 0..100 {
+            hm_cache.get_with_refresh("a", ex, load).await.unwrap();
+        }
+        assert_eq!(n_loads.load(Ordering::Relaxed), 2);
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(2));
+
+        let failed =
+            hm_cache.get_with_refresh("b", ex, || async { Err(TokioActorCacheError::Cancelled) });
+        assert!(failed.await.is_err());
+        assert_eq!(hm_cache.get("b").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
-        hm_cache.insert("b", 12, None, false).await.unwrap();
-        hm_cache.insert("c", 20, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 12, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("c", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hm = hm_cache.get_all().await.unwrap();
-        assert_eq!(!hm.is_empty(), true);
+        assert!(!hm.is_empty());
         hm_cache.clear().await.unwrap();
         let hm = hm_cache.get_all().await.unwrap();
-        assert_eq!(hm.is_empty(), true);
+        assert!(hm.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mget() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -415,16 +846,16 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -432,16 +863,16 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains_keys() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -449,17 +880,20 @@ mod tests {
         assert_eq!(is_contains_keys, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -467,17 +901,20 @@ mod tests {
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -485,10 +922,10 @@ mod tests {
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
@@ -498,7 +935,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -511,16 +948,16 @@ mod tests {
         assert_eq!(val_c, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -532,48 +969,63 @@ mod tests {
         assert_eq!(val_c, Some(30));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_inconsistent_len() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         let res = hm_cache
             .minsert(
                 &["a", "b"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await;
         assert!(res.is_err());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
-        hm_cache.insert("a", 20, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
-        hm_cache.insert("a", 20, None, true).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 20, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
         hm_cache
-            .insert("b", 20, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -583,12 +1035,2088 @@ mod tests {
         assert_eq!(val_b, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy, 32).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pipeline() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let mut pipeline = hm_cache.pipeline();
+        pipeline
+            .insert("a", 1, None, InsertPolicy::Always)
+            .insert("b", 2, None, InsertPolicy::Always)
+            .get("a")
+            .remove(vec!["b"])
+            .contains_key(vec!["a", "b"]);
+        let replies = pipeline.execute().await.unwrap();
+        assert_eq!(replies.len(), 5);
+        assert!(matches!(replies[0], HashMapReply::Done));
+        assert!(matches!(replies[1], HashMapReply::Done));
+        assert!(matches!(replies[2], HashMapReply::Get(Some(1))));
+        assert!(matches!(&replies[3], HashMapReply::Remove(vals) if vals == &vec![Some(2)]));
+        assert!(matches!(
+            &replies[4],
+            HashMapReply::ContainsKey(flags) if flags == &vec![true, false]
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_transaction() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("c", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let replies = hm_cache
+            .transaction(|txn| {
+                txn.get("a")
+                    .insert("b", 2, None, InsertPolicy::Always)
+                    .remove(vec!["c"]);
+            })
+            .await
+            .unwrap();
+        assert!(matches!(replies[0], HashMapReply::Get(Some(1))));
+        assert!(matches!(replies[1], HashMapReply::Done));
+        assert!(matches!(&replies[2], HashMapReply::Remove(vals) if vals == &vec![Some(3)]));
+
+        // Readers never see one key of a transaction written without the other.
+        hm_cache
+            .insert("b", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let writer = {
+            let hm_cache = hm_cache.clone();
+            tokio::spawn(async move {
+                for i in 0..50 {
+                    hm_cache
+                        .transaction(|txn| {
+                            txn.insert("a", i, None, InsertPolicy::Always).insert(
+                                "b",
+                                i,
+                                None,
+                                InsertPolicy::Always,
+                            );
+                        })
+                        .await
+                        .unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        for _ in 0..50 {
+            let vals = hm_cache.mget(&["a", "b"]).await.unwrap();
+            assert_eq!(vals[0], vals[1]);
+            tokio::task::yield_now().await;
+        }
+        writer.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_empty_pipeline() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replies = hm_cache.pipeline().execute().await.unwrap();
+        assert!(replies.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_arc_values_are_shared() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = ArcHashMapCache::<&str, Vec<u8>>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let blob = Arc::new(vec![0u8; 1024]);
+        hm_cache
+            .insert("a", Arc::clone(&blob), None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = hm_cache.get("a").await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&blob, &val));
+        let vals = hm_cache.mget(&["a"]).await.unwrap();
+        assert!(Arc::ptr_eq(&blob, vals[0].as_ref().unwrap()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_borrowed_key_lookups() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a".to_string(), 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get_borrowed("a").await.unwrap(), Some(1));
+        assert_eq!(
+            hm_cache.contains_key_borrowed(&["a", "b"]).await.unwrap(),
+            vec![true, false]
+        );
+
+        // Served by the actor, so a write only sent, even through a clone, is seen.
+        let clone = hm_cache.clone();
+        clone
+            .insert("a".to_string(), 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get_borrowed("a").await.unwrap(), Some(2));
+        assert_eq!(
+            hm_cache.remove_borrowed(&["a", "b"]).await.unwrap(),
+            vec![Some(2), None]
+        );
+        assert_eq!(hm_cache.get_borrowed("a").await.unwrap(), None);
+
+        // Entries past their TTL are skipped.
+        let ex = Some(Duration::from_millis(10));
+        hm_cache
+            .insert_sync("b".to_string(), 3, ex, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.try_get_borrowed("b").await.unwrap(), Some(3));
+        advance(Duration::from_millis(5)).await;
+        assert_eq!(
+            hm_cache.try_contains_key_borrowed(&["b"]).await.unwrap(),
+            vec![true]
+        );
+        advance(Duration::from_millis(10)).await;
+        assert_eq!(hm_cache.get_borrowed("b").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_borrowed_key_lookups_count_as_use() {
+        let expiration_policy = ExpirationPolicy::LRU(2);
+        let hm_cache = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for (i, key) in ["a", "b"].into_iter().enumerate() {
+            hm_cache
+                .insert(key.to_string(), i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            advance(Duration::from_millis(1)).await;
+        }
+
+        // Reading "a" makes "b" the least recently used.
+        assert_eq!(hm_cache.get_borrowed("a").await.unwrap(), Some(0));
+        advance(Duration::from_millis(1)).await;
+        hm_cache
+            .insert("c".to_string(), 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(
+            hm_cache
+                .contains_key_borrowed(&["a", "b", "c"])
+                .await
+                .unwrap(),
+            vec![true, false, true]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replica_receives_writes_before_next_tick() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm1.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm2.replicate(&hm1).await.unwrap();
+
+        hm1.insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm1.remove(&["a"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(hm2.get("a").await.unwrap(), None);
+        assert_eq!(hm2.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stopped_replica_ignores_master_writes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm2.replicate(&hm1).await.unwrap();
+        hm2.stop_replicating().await.unwrap();
+
+        hm1.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(hm2.get("a").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_add_replica_fans_out() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        master.add_replica(&replica1).await.unwrap();
+        master.add_replica(&replica2).await.unwrap();
+        assert_eq!(master.replica_count().await.unwrap(), 2);
+
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(replica1.get("a").await.unwrap(), Some(1));
+        assert_eq!(replica2.get("a").await.unwrap(), Some(1));
+
+        replica1.stop_replicating().await.unwrap();
+        assert_eq!(master.replica_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_only_replica_rejects_writes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_replica_writes(ReplicaWrites::Reject);
+        replica.replicate(&master).await.unwrap();
+
+        let res = replica.insert("a", 1, None, InsertPolicy::Always).await;
+        assert_eq!(res, Err(TokioActorCacheError::ReadOnlyReplica));
+        let res = replica.remove(&["a"]).await;
+        assert_eq!(res, Err(TokioActorCacheError::ReadOnlyReplica));
+        assert_eq!(replica.get("a").await.unwrap(), None);
+
+        replica.stop_replicating().await.unwrap();
+        replica
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("a").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_your_writes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        replica.read_your_writes(true).await.unwrap();
+        replica.replicate(&master).await.unwrap();
+        assert_eq!(replica.get("a").await.unwrap(), Some(1));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // Written to the replica only, so the master answers.
+        replica
+            .insert("a", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        replica
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("a").await.unwrap(), Some(1));
+        assert_eq!(
+            replica.mget(&["a", "b"]).await.unwrap(),
+            vec![Some(1), None]
+        );
+
+        // The master's own write makes the replica's copy current again.
+        master
+            .insert("b", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(replica.get("b").await.unwrap(), Some(3));
+
+        // Writes through the master are seen once they have been acknowledged.
+        master
+            .insert_sync("c", 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("c").await.unwrap(), Some(4));
+
+        replica.read_your_writes(false).await.unwrap();
+        replica
+            .insert("d", 5, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("d").await.unwrap(), Some(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_forward_writes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_replica_writes(ReplicaWrites::Reject)
+            .with_forward_writes(true);
+        replica.replicate(&master).await.unwrap();
+
+        replica
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        replica.barrier().await.unwrap();
+        assert_eq!(master.get("a").await.unwrap(), Some(1));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(replica.get("a").await.unwrap(), Some(1));
+
+        // Answered by the master.
+        assert_eq!(replica.remove(&["a"]).await.unwrap(), vec![Some(1)]);
+        assert_eq!(master.get("a").await.unwrap(), None);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(replica.get("a").await.unwrap(), None);
+
+        // Once promoted, writes are applied locally.
+        replica.stop_replicating().await.unwrap();
+        replica
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("b").await.unwrap(), Some(2));
+        assert_eq!(master.get("b").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_forward_batched_writes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_replica_writes(ReplicaWrites::Reject)
+            .with_forward_writes(true);
+        replica.replicate(&master).await.unwrap();
+
+        let mut pipeline = replica.pipeline();
+        pipeline
+            .insert("a", 1, None, InsertPolicy::Always)
+            .insert("b", 2, None, InsertPolicy::Always)
+            .get("a");
+        let replies = pipeline.execute().await.unwrap();
+        assert!(matches!(replies[2], HashMapReply::Get(Some(1))));
+        assert_eq!(
+            master.mget(&["a", "b"]).await.unwrap(),
+            vec![Some(1), Some(2)]
+        );
+
+        // Shrinking is a write too, so it is the master's entries that go.
+        assert_eq!(replica.shrink(0).await.unwrap(), 2);
+        assert_eq!(master.mget(&["a", "b"]).await.unwrap(), vec![None, None]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_verify_replication() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        master
+            .minsert_entries(&[
+                InsertEntry::new("a", 1),
+                InsertEntry::new("b", 2),
+                InsertEntry::new("c", 3),
+            ])
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let check = master.verify_replication(&replica).await.unwrap();
+        assert!(check.is_consistent());
+        assert_eq!((check.master_entries, check.replica_entries), (3, 3));
+
+        // Direct writes to the replica drift from the master.
+        replica
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        replica
+            .insert("d", 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let check = master.try_verify_replication(&replica).await.unwrap();
+        let mut diverged = check.diverged;
+        diverged.sort();
+        assert_eq!(diverged, vec!["a", "d"]);
+        assert_eq!((check.master_entries, check.replica_entries), (3, 4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replication_info() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        master
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert!(replica.is_replica().await.unwrap());
+        let info = replica.replication_info().await.unwrap();
+        assert_eq!(info.role, Role::Replica);
+        assert_eq!(info.master_id, Some(master.id()));
+        assert_eq!(info.entries_synced, 2);
+        assert!(info.last_sync.is_some());
+        assert!(info.lag.is_some());
+
+        assert!(!master.is_replica().await.unwrap());
+        let info = master.replication_info().await.unwrap();
+        assert_eq!(info.role, Role::Master);
+        assert_eq!(info.master_id, None);
+        assert_eq!(info.replicas, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failover() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica1 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica2 = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        master.add_replica(&replica1).await.unwrap();
+        master.add_replica(&replica2).await.unwrap();
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let moved = HashMapCache::failover(&master, &replica1).await.unwrap();
+        assert_eq!(moved, 1);
+        assert!(!replica1.is_replica().await.unwrap());
+        assert_eq!(master.replica_count().await.unwrap(), 0);
+
+        replica1
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let info = replica2.replication_info().await.unwrap();
+        assert_eq!(info.master_id, Some(replica1.id()));
+        assert_eq!(replica2.get("a").await.unwrap(), Some(1));
+        assert_eq!(replica2.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_promote_to_master() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_replica_writes(ReplicaWrites::Reject);
+        replica.replicate(&master).await.unwrap();
+
+        replica.promote_to_master().await.unwrap();
+        replica
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("a").await.unwrap(), Some(1));
+        assert_eq!(master.replica_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lagging_replica_catches_up_with_delta() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<i32, i32>::new(expiration_policy, 1024)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<i32, i32>::new(expiration_policy, 1)
+            .await
+            .unwrap();
+        let keys = (0..500).collect::<Vec<i32>>();
+        master
+            .minsert(&keys, &keys, &[None; 500], &[InsertPolicy::Always; 500])
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // The replica's channel only holds one command, so most of these get dropped.
+        for i in 0..50 {
+            master
+                .insert(i, -i, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(
+            replica.get_all().await.unwrap(),
+            master.get_all().await.unwrap()
+        );
+        let info = replica.replication_info().await.unwrap();
+        assert!(info.entries_synced < 1000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_near_cache_invalidated_by_remote_write() {
+        let expiration_policy = ExpirationPolicy::None;
+        let remote = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let near = NearCache::new(remote.clone(), 8).await.unwrap();
+        remote
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(near.get("a").await.unwrap(), Some(1));
+        assert_eq!(near.local_len(), 1);
+
+        remote
+            .insert("a", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.local_len(), 0);
+        assert_eq!(near.get("a").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_near_cache_evicts_least_recently_used() {
+        let expiration_policy = ExpirationPolicy::None;
+        let remote = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let near = NearCache::new(remote, 2).await.unwrap();
+        near.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        near.insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        near.insert("c", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        near.get("a").await.unwrap();
+        near.get("b").await.unwrap();
+        near.get("a").await.unwrap();
+        near.get("c").await.unwrap();
+        assert_eq!(near.local_len(), 2);
+
+        near.remote().remove(&["b", "c"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.local_len(), 1);
+        assert_eq!(near.get("a").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_near_cache_is_not_a_replica() {
+        let expiration_policy = ExpirationPolicy::None;
+        let remote = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let near = NearCache::new(remote.clone(), 8).await.unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        replica.replicate(&remote).await.unwrap();
+        assert_eq!(remote.replica_count().await.unwrap(), 1);
+
+        // Only the real replica is moved to the new master.
+        let new_master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        new_master.replicate(&remote).await.unwrap();
+        assert_eq!(HashMapCache::failover(&remote, &new_master).await, Ok(1));
+
+        // And the near cache still hears of writes to its remote.
+        remote
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(near.get("a").await.unwrap(), Some(1));
+        remote.update_value("a", |val| *val += 1).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.local_len(), 0);
+        assert_eq!(near.get("a").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_near_cache_of_replica_invalidated_by_master() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        let near = NearCache::new(replica, 8).await.unwrap();
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        master
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.get("a").await.unwrap(), Some(1));
+        assert_eq!(near.get("b").await.unwrap(), Some(2));
+
+        master
+            .insert("a", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.local_len(), 1);
+        assert_eq!(near.get("a").await.unwrap(), Some(3));
+
+        master.clear().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.local_len(), 0);
+        assert_eq!(near.get("b").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 2, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(1));
+
+        hm_cache
+            .insert("a", 3, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_returns_old_value() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        let xx_get = InsertOptions {
+            policy: InsertPolicy::IfPresent,
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(hm_cache.set("a", 1, xx_get).await.unwrap(), None);
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        let get = InsertOptions {
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(hm_cache.set("a", 1, get).await.unwrap(), None);
+        assert_eq!(hm_cache.set("a", 2, xx_get).await.unwrap(), Some(1));
+        assert_eq!(
+            hm_cache
+                .set("a", 3, InsertOptions::default())
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_minsert_entries() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy, 32).await.unwrap();
+        hm_cache
+            .insert("c", 0, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .minsert_entries(&[
+                InsertEntry::new("a", 1),
+                InsertEntry::new("b", 2).with_ex(Duration::from_secs(10)),
+                InsertEntry::new("c", 3).with_policy(InsertPolicy::IfAbsent),
+            ])
+            .await
+            .unwrap();
+
+        let vals = hm_cache.mget(&["a", "b", "c"]).await.unwrap();
+        assert_eq!(vals, vec![Some(1), Some(2), Some(0)]);
+        let ttl = hm_cache.ttl(&["a", "b"]).await.unwrap();
+        assert_eq!(ttl[0], None);
+        assert!(ttl[1].is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_update_value() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, Vec<i32>>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, Vec<i32>>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        master
+            .insert("a", vec![1, 2, 3], None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        assert!(master.update_value("a", |vals| vals.push(4)).await.unwrap());
+        assert!(!master.update_value("b", |vals| vals.push(4)).await.unwrap());
+        assert_eq!(master.get("a").await.unwrap(), Some(vec![1, 2, 3, 4]));
+        assert_eq!(master.get("b").await.unwrap(), None);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(replica.get("a").await.unwrap(), Some(vec![1, 2, 3, 4]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_value() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, Vec<i32>>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", vec![1, 2, 3], None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            hm_cache.read_value("a", |vals| vals.len()).await.unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            hm_cache.try_read_value("b", |vals| vals[0]).await.unwrap(),
+            None
+        );
+        assert_eq!(hm_cache.hot_keys(1).await.unwrap(), vec![("a", 1)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hash_fields() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, HashMap<&str, i32>>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, HashMap<&str, i32>>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+
+        // The first field creates the hash.
+        assert_eq!(master.hset("user", "age", 30).await.unwrap(), None);
+        assert_eq!(master.try_hset("user", "score", 7).await.unwrap(), None);
+        assert_eq!(master.hset("user", "age", 31).await.unwrap(), Some(30));
+        assert_eq!(master.hget("user", "age").await.unwrap(), Some(31));
+        assert_eq!(master.try_hget("user", "name").await.unwrap(), None);
+        assert_eq!(master.hget("nobody", "age").await.unwrap(), None);
+        assert_eq!(master.ttl(&["user"]).await.unwrap(), vec![None]);
+
+        assert_eq!(master.hdel("user", &["age", "name"]).await.unwrap(), 1);
+        assert_eq!(master.try_hdel("nobody", &["age"]).await.unwrap(), 0);
+        assert_eq!(
+            master.hgetall("user").await.unwrap(),
+            Some(HashMap::from([("score", 7)]))
+        );
+        assert_eq!(master.try_hgetall("nobody").await.unwrap(), None);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            replica.hgetall("user").await.unwrap(),
+            Some(HashMap::from([("score", 7)]))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_jitter() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_ttl_jitter(0.5);
+        let keys = (0..50).collect::<Vec<i32>>();
+        let ex = Duration::from_secs(10);
+        hm_cache
+            .minsert(
+                &keys,
+                &keys,
+                &vec![Some(ex); keys.len()],
+                &vec![InsertPolicy::Always; keys.len()],
+            )
+            .await
+            .unwrap();
+
+        let ttl = hm_cache
+            .ttl(&keys)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|ttl| ttl.unwrap())
+            .collect::<Vec<Duration>>();
+        assert!(
+            ttl.iter()
+                .all(|ttl| *ttl > Duration::from_secs(4) && *ttl <= Duration::from_secs(15))
+        );
+        assert!(ttl.iter().any(|ttl| *ttl < Duration::from_secs(9)));
+        assert!(ttl.iter().any(|ttl| *ttl > Duration::from_secs(11)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bulk_load() {
+        let expiration_policy = ExpirationPolicy::LRU(1_000);
+        let hm_cache = HashMapCache::<u32, u32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let mut removals = hm_cache.removals().await.unwrap();
+        hm_cache
+            .insert(0, 100, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let entries = (0..100_000).map(|i| (i, i, (i == 1).then_some(Duration::from_secs(1))));
+        assert_eq!(hm_cache.bulk_load(entries).await.unwrap(), 100_000);
+
+        // Evicted down to capacity once everything was in.
+        assert_eq!(hm_cache.usage().await.unwrap().entries, 1_000);
+        let hm = hm_cache.get_all().await.unwrap();
+        assert!(hm.iter().all(|(key, val)| key == val));
+        let removal = removals.try_recv().unwrap();
+        assert_eq!(removal.reason, RemovalReason::Evicted(Eviction::Lru));
+
+        assert_eq!(hm_cache.bulk_load(Vec::new()).await.unwrap(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hard_capacity() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_hard_capacity(2);
+        for key in ["a", "b"] {
+            hm_cache
+                .insert_sync(key, 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let res = hm_cache
+            .insert_sync("c", 1, None, InsertPolicy::Always)
+            .await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::CapacityExceeded { capacity: 2 })
+        );
+        let res = hm_cache.try_set("c", 1, InsertOptions::default()).await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::CapacityExceeded { capacity: 2 })
+        );
+        hm_cache
+            .insert("d", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.mget(&["c", "d"]).await.unwrap(), vec![None, None]);
+
+        // Cached keys can still be overwritten.
+        let get = InsertOptions {
+            get: true,
+            ..Default::default()
+        };
+        let old = hm_cache.set("a", 2, get).await.unwrap();
+        assert_eq!(old, Some(1));
+        hm_cache.remove(&["b"]).await.unwrap();
+        hm_cache
+            .insert_sync("c", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_soft_capacity_under_hard_capacity() {
+        let expiration_policy = ExpirationPolicy::LRU(2);
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_hard_capacity(4);
+        for key in 0..4 {
+            hm_cache
+                .insert_sync(key, key, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(hm_cache.usage().await.unwrap().entries, 4);
+        let res = hm_cache.insert_sync(4, 4, None, InsertPolicy::Always).await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::CapacityExceeded { capacity: 4 })
+        );
+
+        // Evicted down to the soft limit on the next tick.
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(hm_cache.usage().await.unwrap().entries, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_capacity_enforced_on_insert() {
+        let expiration_policy = ExpirationPolicy::LRU(2);
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let keys = (0..100).collect::<Vec<i32>>();
+        hm_cache
+            .minsert(
+                &keys,
+                &keys,
+                &vec![None; keys.len()],
+                &vec![InsertPolicy::Always; keys.len()],
+            )
+            .await
+            .unwrap();
+
+        // No tick has run yet.
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_eviction_below_capacity() {
+        for expiration_policy in [ExpirationPolicy::LFU(5), ExpirationPolicy::LRU(5)] {
+            let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+                .await
+                .unwrap();
+            for key in ["a", "b", "c"] {
+                hm_cache
+                    .insert(key, 1, None, InsertPolicy::Always)
+                    .await
+                    .unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            assert_eq!(hm_cache.get_all().await.unwrap().len(), 3);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lru_evicts_in_order_of_use() {
+        let expiration_policy = ExpirationPolicy::LRU(3);
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for key in ["a", "b", "c"] {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hm_cache
+                .insert(key, 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        for (used, key) in [("a", "d"), ("c", "e")] {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hm_cache.get(used).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hm_cache
+                .insert(key, 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let mut keys = hm_cache
+            .get_all()
+            .await
+            .unwrap()
+            .into_keys()
+            .collect::<Vec<&str>>();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["c", "d", "e"]);
+
+        // Many more writes than entries, each evicting the oldest.
+        let hm_cache = HashMapCache::<i32, i32>::new(ExpirationPolicy::LRU(10), 32)
+            .await
+            .unwrap();
+        for key in 0..1_000 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hm_cache
+                .insert(key, key, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let mut keys = hm_cache
+            .get_all()
+            .await
+            .unwrap()
+            .into_keys()
+            .collect::<Vec<i32>>();
+        keys.sort_unstable();
+        assert_eq!(keys, (990..1_000).collect::<Vec<i32>>());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lfu_keeps_most_used() {
+        let expiration_policy = ExpirationPolicy::LFU(2);
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for (key, n_get) in [("a", 2), ("b", 1), ("c", 0)] {
+            hm_cache
+                .insert(key, 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            for _ in 0..n_get {
+                hm_cache.get(key).await.unwrap();
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let mut keys = hm_cache
+            .get_all()
+            .await
+            .unwrap()
+            .into_keys()
+            .collect::<Vec<&str>>();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hot_and_idle_keys() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for key in ["a", "b", "c"] {
+            hm_cache
+                .insert(key, 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        // Time is paused, so let some pass for `c` to be the idlest.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        for key in ["a", "a", "a", "b"] {
+            hm_cache.get(key).await.unwrap();
+        }
+
+        let hot_keys = vec![("a", 3), ("b", 1)];
+        assert_eq!(hm_cache.hot_keys(2).await.unwrap(), hot_keys);
+        // Reading the stats isn't a use.
+        assert_eq!(hm_cache.try_hot_keys(2).await.unwrap(), hot_keys);
+        assert_eq!(hm_cache.hot_keys(10).await.unwrap().len(), 3);
+
+        let idle_keys = hm_cache.idle_keys(1).await.unwrap();
+        assert_eq!(idle_keys.len(), 1);
+        assert_eq!(idle_keys[0].0, "c");
+        assert!(hm_cache.idle_keys(0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_if_version() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get_versioned("a").await.unwrap(), None);
+        let version = hm_cache
+            .insert_if_version("a", 1, 0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            hm_cache.get_versioned("a").await.unwrap(),
+            Some((1, version))
+        );
+
+        // A writer still holding the old version loses.
+        assert_eq!(hm_cache.insert_if_version("a", 2, 0).await.unwrap(), None);
+        let next = hm_cache
+            .try_insert_if_version("a", 2, version)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(next > version);
+        assert_eq!(
+            hm_cache.insert_if_version("a", 3, version).await.unwrap(),
+            None
+        );
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(2));
+
+        hm_cache.update_value("a", |val| *val += 1).await.unwrap();
+        let (val, updated) = hm_cache.get_versioned("a").await.unwrap().unwrap();
+        assert_eq!(val, 3);
+        assert!(updated > next);
+
+        // Removing and inserting again doesn't bring an old version back.
+        hm_cache.remove(&["a"]).await.unwrap();
+        hm_cache
+            .insert("a", 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let (_, reinserted) = hm_cache.try_get_versioned("a").await.unwrap().unwrap();
+        assert!(reinserted > updated);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pin() {
+        let expiration_policy = ExpirationPolicy::LRU(3);
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("t", 0, Some(Duration::from_secs(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        let is_cached = hm_cache.pin(&["a", "t", "z"]).await.unwrap();
+        assert_eq!(is_cached, vec![true, true, false]);
+        for (key, val) in [("b", 2), ("c", 3), ("d", 4)] {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hm_cache
+                .insert(key, val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        // Neither evicted as the least recently used nor expired, even once overwritten.
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("e", 5, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        advance(Duration::from_secs(2)).await;
+        let all = hm_cache.get_all().await.unwrap();
+        assert_eq!(all, HashMap::from([("a", 10), ("t", 0), ("e", 5)]));
+        assert_eq!(hm_cache.expiring_within(Duration::ZERO).await.unwrap(), 0);
+
+        assert_eq!(hm_cache.try_unpin(&["t"]).await.unwrap(), vec![true]);
+        assert_eq!(hm_cache.get("t").await.unwrap(), None);
+        assert_eq!(hm_cache.unpin(&["t"]).await.unwrap(), vec![false]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_capacity() {
+        let expiration_policy = ExpirationPolicy::LRU(4);
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for (key, val) in [("a", 1), ("b", 2), ("c", 3), ("d", 4)] {
+            hm_cache
+                .insert(key, val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        assert!(hm_cache.set_capacity(2).await.unwrap());
+        assert_eq!(hm_cache.policy().await.unwrap(), ExpirationPolicy::LRU(2));
+        let all = hm_cache.get_all().await.unwrap();
+        assert_eq!(all, HashMap::from([("c", 3), ("d", 4)]));
+
+        // Growing evicts nothing, and later writes are held to the new capacity.
+        hm_cache
+            .try_set_policy(ExpirationPolicy::LFU(3))
+            .await
+            .unwrap();
+        hm_cache
+            .insert("e", 5, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache.get("e").await.unwrap();
+        hm_cache
+            .insert("f", 6, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 3);
+        assert_eq!(hm_cache.get("e").await.unwrap(), Some(5));
+
+        hm_cache.set_policy(ExpirationPolicy::None).await.unwrap();
+        assert!(!hm_cache.try_set_capacity(1).await.unwrap());
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_budget() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_cleanup_budget(3);
+        let mut removals = hm_cache.removals().await.unwrap();
+        let keys = (0..10).collect::<Vec<_>>();
+        let ex = [Some(Duration::from_secs(1)); 10];
+        hm_cache
+            .minsert(&keys, &keys, &ex, &[InsertPolicy::Always; 10])
+            .await
+            .unwrap();
+        hm_cache
+            .insert(10, 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        // A few passes of 3 each so far, yet none of the expired entries can be read.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let vals = hm_cache.mget(&keys).await.unwrap();
+        assert_eq!(vals, vec![None; 10]);
+        assert_eq!(hm_cache.contains_key(&[9]).await.unwrap(), vec![false]);
+        let mut n_expired = 0;
+        while removals.try_recv().is_ok() {
+            n_expired += 1;
+        }
+        assert!((3..10).contains(&n_expired));
+
+        advance(TICK * 4).await;
+        while removals.try_recv().is_ok() {
+            n_expired += 1;
+        }
+        assert_eq!(n_expired, 10);
+        assert_eq!(hm_cache.get_all().await.unwrap(), HashMap::from([(10, 10)]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_budget_counts_stale_deadlines() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_cleanup_budget(3);
+        let mut removals = hm_cache.removals().await.unwrap();
+        let keys = (0..10).collect::<Vec<_>>();
+        let ex = [Some(Duration::from_secs(1)); 10];
+        hm_cache
+            .minsert(&keys, &keys, &ex, &[InsertPolicy::Always; 10])
+            .await
+            .unwrap();
+        // Their old deadlines come due first, and each pass only looks at 3.
+        for key in 0..9 {
+            assert!(hm_cache.expire(key, None).await.unwrap());
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(hm_cache.get(9).await.unwrap(), None);
+        advance(TICK * 4).await;
+        assert_eq!(removals.try_recv().unwrap().key, 9);
+        assert!(removals.try_recv().is_err());
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 9);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_budget_evictions() {
+        let expiration_policy = ExpirationPolicy::LRU(2);
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_hard_capacity(32)
+            .with_cleanup_budget(3);
+        for key in 0..8 {
+            hm_cache
+                .insert(key, key, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 8);
+
+        advance(TICK).await;
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 5);
+        advance(TICK).await;
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 2);
+        advance(TICK).await;
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dropped_receiver() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        // Sends the read, then gives up on it before the actor gets to it.
+        let abandon_get = || tokio::time::timeout(Duration::ZERO, hm_cache.get("a"));
+
+        abandon_get().await.unwrap_err();
+        hm_cache.barrier().await.unwrap();
+        assert_eq!(hm_cache.dropped_receivers(), 0);
+
+        let _ = hm_cache
+            .clone()
+            .with_dropped_receiver(DroppedReceiver::Count);
+        abandon_get().await.unwrap_err();
+        hm_cache.barrier().await.unwrap();
+        assert_eq!(hm_cache.dropped_receivers(), 1);
+        assert_eq!(hm_cache.hot_keys(1).await.unwrap(), vec![("a", 2)]);
+
+        // Skipped reads aren't uses, but abandoned writes are still applied.
+        let _ = hm_cache
+            .clone()
+            .with_dropped_receiver(DroppedReceiver::Skip);
+        abandon_get().await.unwrap_err();
+        tokio::time::timeout(Duration::ZERO, hm_cache.set("b", 2, Default::default()))
+            .await
+            .unwrap_err();
+        hm_cache.barrier().await.unwrap();
+        assert_eq!(hm_cache.dropped_receivers(), 3);
+        assert_eq!(hm_cache.hot_keys(1).await.unwrap(), vec![("a", 2)]);
+        assert_eq!(hm_cache.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancellable_reads() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let (op, get) = hm_cache.get_cancellable("a");
+        assert_eq!(get.await.unwrap(), Some(1));
+        assert!(!hm_cache.cancel(op));
+
+        // Any handle can cancel, before the read is even sent.
+        let (op, mget) = hm_cache.mget_cancellable(&["a", "b"]);
+        assert!(hm_cache.clone().cancel(op));
+        assert_eq!(mget.await, Err(TokioActorCacheError::Cancelled));
+
+        // Dropping the future cancels the read it sent.
+        let (op, get) = hm_cache.get_cancellable("a");
+        tokio::time::timeout(Duration::ZERO, get).await.unwrap_err();
+        assert!(!hm_cache.cancel(op));
+        assert_eq!(hm_cache.hot_keys(1).await.unwrap(), vec![("a", 1)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_versioned_snapshot() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let snapshot = hm_cache.snapshot().await.unwrap();
+        assert_eq!(snapshot.version, 0);
+        assert!(snapshot.entries.is_empty());
+
+        for (key, val) in [("a", 1), ("b", 2)] {
+            hm_cache
+                .insert(key, val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let snapshot = hm_cache.snapshot().await.unwrap();
+        assert_eq!(snapshot.version, 2);
+        assert_eq!(*snapshot.entries, HashMap::from([("a", 1), ("b", 2)]));
+        // Without writes in between the entries aren't copied again, nor counted as used.
+        let again = hm_cache.try_snapshot().await.unwrap();
+        assert!(Arc::ptr_eq(&snapshot.entries, &again.entries));
+        assert_eq!(hm_cache.hot_keys(1).await.unwrap()[0].1, 0);
+
+        hm_cache.update_value("a", |val| *val += 1).await.unwrap();
+        hm_cache.remove(&["b"]).await.unwrap();
+        assert_eq!(hm_cache.version().await.unwrap(), 4);
+        let latest = hm_cache.snapshot().await.unwrap();
+        assert_eq!(latest.version, 4);
+        assert_eq!(*latest.entries, HashMap::from([("a", 2)]));
+        assert_eq!(snapshot.entries.len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_get_all_while_writing() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<i32, i32>::new(expiration_policy, 1024)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<i32, i32>::new(expiration_policy, 1024)
+            .await
+            .unwrap();
+        let keys = (0..500).collect::<Vec<_>>();
+        master
+            .minsert(&keys, &keys, &[None; 500], &[InsertPolicy::Always; 500])
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+
+        // Writes right after the actor hands its entries out leave what it handed out alone.
+        let (all, ..) = tokio::join!(
+            master.get_all(),
+            master.insert(0, -1, None, InsertPolicy::Always),
+            master.remove(&[1]),
+        );
+        let all = all.unwrap();
+        assert_eq!(all.len(), 500);
+        assert!(all.iter().all(|(key, val)| key == val));
+
+        let all = master.get_all().await.unwrap();
+        assert_eq!((all.len(), all[&0]), (499, -1));
+        assert!(!all.contains_key(&1));
+        assert_eq!(replica.try_get_all().await.unwrap(), all);
+        // Both reads still count as uses of every entry.
+        let hot_keys = master.hot_keys(499).await.unwrap();
+        assert!(hot_keys.iter().all(|&(_, call_cnt)| call_cnt >= 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_histogram() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for (key, ex) in [
+            ("a", Some(Duration::from_millis(500))),
+            ("b", Some(Duration::from_secs(90))),
+            ("c", Some(Duration::from_secs(100))),
+            ("d", None),
+        ] {
+            hm_cache
+                .insert(key, 1, ex, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let histogram = hm_cache.ttl_histogram().await.unwrap();
+        assert_eq!(histogram.persistent(), 1);
+        assert_eq!(histogram.expiring(), 3);
+        let buckets = histogram
+            .buckets()
+            .filter(|(_, entries)| *entries > 0)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            vec![
+                (Some(Duration::from_secs(1)), 1),
+                (Some(Duration::from_secs(128)), 2),
+            ]
+        );
+
+        assert_eq!(hm_cache.expiring_within(Duration::ZERO).await.unwrap(), 0);
+        assert_eq!(
+            hm_cache
+                .expiring_within(Duration::from_secs(1))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            hm_cache
+                .try_expiring_within(Duration::from_secs(100))
+                .await
+                .unwrap(),
+            3
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_dump_load() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache1 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cache2 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache1
+            .insert(
+                "a".to_string(),
+                1,
+                Some(Duration::from_secs(10)),
+                InsertPolicy::Always,
+            )
+            .await
+            .unwrap();
+        hm_cache1
+            .insert("b".to_string(), 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache1.get("a".to_string()).await.unwrap();
+
+        let bytes = hm_cache1.dump().await.unwrap();
+        hm_cache2.load(&bytes).await.unwrap();
+
+        assert_eq!(
+            hm_cache2.hot_keys(1).await.unwrap(),
+            vec![("a".to_string(), 1)]
+        );
+        let ttl = hm_cache2
+            .ttl(&["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        assert!(ttl[0].is_some_and(|ttl| ttl > Duration::from_secs(9)));
+        assert_eq!(ttl[1], None);
+        assert_eq!(
+            hm_cache2.get_all().await.unwrap(),
+            std::collections::HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+        assert!(matches!(
+            hm_cache2.try_load(b"not a dump").await,
+            Err(TokioActorCacheError::Dump { .. })
+        ));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[tokio::test(start_paused = true)]
+    async fn test_dump_load_encrypted() {
+        use crate::tokio_cache::encryption::{self, EncryptionKey, Keyring};
+
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache1 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cache2 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache1
+            .insert("a".to_string(), 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let key1 = EncryptionKey::new(1, [7; 32]);
+        let keyring = Keyring::new(key1.clone());
+
+        let sealed = hm_cache1.dump_encrypted(&keyring).await.unwrap();
+        assert_eq!(encryption::key_id(&sealed).unwrap(), 1);
+        assert!(!sealed.windows(7).any(|item| item == br#"["a",1]"#));
+        assert!(matches!(
+            hm_cache2.load(&sealed).await,
+            Err(TokioActorCacheError::Dump { .. })
+        ));
+
+        // After rotating, snapshots of the old key still open, and can be sealed again.
+        let keyring = Keyring::new(EncryptionKey::new(2, [9; 32])).with_previous(key1);
+        let resealed = keyring.reseal(&sealed).unwrap();
+        assert_eq!(encryption::key_id(&resealed).unwrap(), 2);
+        hm_cache2.load_encrypted(&resealed, &keyring).await.unwrap();
+        assert_eq!(hm_cache2.get("a".to_string()).await.unwrap(), Some(1));
+
+        let mut tampered = resealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            hm_cache2.try_load_encrypted(&tampered, &keyring).await,
+            Err(TokioActorCacheError::Encryption { .. })
+        ));
+        let other = Keyring::new(EncryptionKey::new(3, [9; 32]));
+        assert!(matches!(
+            hm_cache2.try_load_encrypted(&sealed, &other).await,
+            Err(TokioActorCacheError::Encryption { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_export_import_json() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache1 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cache2 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache1
+            .insert(
+                "a".to_string(),
+                1,
+                Some(Duration::from_secs(10)),
+                InsertPolicy::Always,
+            )
+            .await
+            .unwrap();
+        hm_cache1
+            .insert("b".to_string(), 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("bounded-hm-export-{}.jsonl", hm_cache1.id()));
+
+        assert_eq!(hm_cache1.export_json(&path).await.unwrap(), 2);
+        let text = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains(r#"{"key":"b","val":2}"#));
+
+        assert_eq!(hm_cache2.import_json(&path).await.unwrap(), 2);
+        assert_eq!(
+            hm_cache2.get_all().await.unwrap(),
+            hm_cache1.get_all().await.unwrap()
+        );
+        let ttl = hm_cache2.ttl(&["a".to_string()]).await.unwrap();
+        assert!(ttl[0].is_some_and(|ttl| ttl > Duration::from_secs(9)));
+
+        // A hand-written fixture; blank lines are skipped, the third one is bad.
+        tokio::fs::write(&path, "{\"key\":\"c\",\"val\":3}\n\nnot json\n")
+            .await
+            .unwrap();
+        let err = hm_cache2.try_import_json(&path).await.unwrap_err();
+        assert!(
+            matches!(err, TokioActorCacheError::Dump { reason } if reason.starts_with("line 3"))
+        );
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert!(matches!(
+            hm_cache2.import_json(&path).await,
+            Err(TokioActorCacheError::Io { .. })
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_warm_from() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy, 1)
+            .await
+            .unwrap();
+        // Nothing to wait for yet.
+        hm_cache.ready().await;
+
+        let entries = tokio_stream::iter((0..1000).map(|i| (i, i * 10, None)));
+        let warming_cache = hm_cache.clone();
+        let warmup = tokio::spawn(async move { warming_cache.warm_from(entries).await });
+        tokio::task::yield_now().await;
+        hm_cache.ready().await;
+
+        assert_eq!(warmup.await.unwrap().unwrap(), 1000);
+        let hm = hm_cache.get_all().await.unwrap();
+        assert_eq!(hm.len(), 1000);
+        assert_eq!(hm[&999], 9990);
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_snapshot_versioning() {
+        use crate::tokio_cache::dump::SnapshotHeader;
+
+        let expiration_policy = ExpirationPolicy::LFU(10);
+        let hm_cache1 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cache2 = HashMapCache::<String, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for (key, val) in [("a", 1), ("b", 2)] {
+            hm_cache1
+                .insert(key.to_string(), val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let bytes = hm_cache1.dump().await.unwrap();
+
+        let header = SnapshotHeader::read(&bytes).unwrap();
+        assert_eq!(header.version, 1);
+        assert!(matches!(
+            header.expiration_policy,
+            ExpirationPolicy::LFU(10)
+        ));
+        assert_eq!(header.n_entries, 2);
+
+        // A later version that appends a header field older readers don't know about.
+        let header_len = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        let mut newer = bytes[..12 + header_len].to_vec();
+        newer[4..6].copy_from_slice(&2u16.to_le_bytes());
+        newer[8..12].copy_from_slice(&(header_len as u32 + 4).to_le_bytes());
+        newer.extend_from_slice(&[0xAB; 4]);
+        newer.extend_from_slice(&bytes[12 + header_len..]);
+        hm_cache2.load(&newer).await.unwrap();
+        assert_eq!(hm_cache2.get_all().await.unwrap().len(), 2);
+
+        // One that older readers must refuse.
+        let mut incompatible = bytes.clone();
+        incompatible[6..8].copy_from_slice(&2u16.to_le_bytes());
+        assert!(matches!(
+            hm_cache2.load(&incompatible).await,
+            Err(TokioActorCacheError::Dump { .. })
+        ));
+        assert!(hm_cache2.load(&bytes[..bytes.len() - 1]).await.is_err());
+        assert!(hm_cache2.load(b"TACX").await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_removals() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let mut removals = hm_cache.removals().await.unwrap();
+
+        hm_cache
+            .insert("a", 1, Some(Duration::from_secs(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache.remove(&["b", "c"]).await.unwrap();
+        advance(Duration::from_secs(1) + TICK).await;
+        hm_cache
+            .insert("d", 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache.clear().await.unwrap();
+        hm_cache.get_all().await.unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(removal) = removals.try_recv() {
+            received.push(removal);
+        }
+        assert_eq!(
+            received,
+            vec![
+                Removal {
+                    key: "b",
+                    val: 2,
+                    reason: RemovalReason::Replaced
+                },
+                Removal {
+                    key: "b",
+                    val: 3,
+                    reason: RemovalReason::ManualRemove
+                },
+                Removal {
+                    key: "a",
+                    val: 1,
+                    reason: RemovalReason::TtlElapsed
+                },
+                Removal {
+                    key: "d",
+                    val: 4,
+                    reason: RemovalReason::ManualRemove
+                },
+            ]
+        );
+
+        // Dropping the receiver stops the reports without affecting the cache.
+        drop(removals);
+        hm_cache
+            .insert("e", 5, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache.remove(&["e"]).await.unwrap();
+        assert!(hm_cache.get_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tti() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let mut removals = hm_cache.removals().await.unwrap();
+        let idle = InsertOptions {
+            ex: Some(Duration::from_secs(10)),
+            tti: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        hm_cache.set("read", 1, idle).await.unwrap();
+        hm_cache.set("unread", 2, idle).await.unwrap();
+        let entries = [InsertEntry::new("entry", 3).with_tti(Duration::from_secs(2))];
+        hm_cache.minsert_entries(&entries).await.unwrap();
+
+        // Reads keep an entry alive past its idle window, but not past its TTL.
+        for _ in 0..3 {
+            advance(Duration::from_secs(1)).await;
+            assert_eq!(hm_cache.get("read").await.unwrap(), Some(1));
+        }
+        assert_eq!(hm_cache.get("unread").await.unwrap(), None);
+        assert_eq!(hm_cache.get("entry").await.unwrap(), None);
+        for _ in 0..6 {
+            advance(Duration::from_secs(1)).await;
+            assert_eq!(hm_cache.get("read").await.unwrap(), Some(1));
+        }
+        advance(Duration::from_secs(1)).await;
+        assert_eq!(hm_cache.get("read").await.unwrap(), None);
+
+        let mut received = Vec::new();
+        while let Ok(removal) = removals.try_recv() {
+            received.push((removal.key, removal.reason));
+        }
+        received.sort_by_key(|(key, _)| *key);
+        assert_eq!(
+            received,
+            vec![
+                ("entry", RemovalReason::IdleTimeout),
+                ("read", RemovalReason::TtlElapsed),
+                ("unread", RemovalReason::IdleTimeout),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_removals_on_eviction() {
+        // "a" is used least recently but more often than "b".
+        for (expiration_policy, eviction, key, val) in [
+            (ExpirationPolicy::LRU(1), Eviction::Lru, "a", 1),
+            (ExpirationPolicy::LFU(1), Eviction::Lfu, "b", 2),
+        ] {
+            let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+                .await
+                .unwrap();
+            let mut removals = hm_cache.try_removals().await.unwrap();
+            hm_cache
+                .insert("a", 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            hm_cache.get("a").await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hm_cache
+                .insert("b", 2, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            hm_cache.get_all().await.unwrap();
+
+            assert_eq!(
+                removals.try_recv().unwrap(),
+                Removal {
+                    key,
+                    val,
+                    reason: RemovalReason::Evicted(eviction)
+                }
+            );
+            assert!(removals.try_recv().is_err());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_removals_on_replica() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        let mut master_removals = master.removals().await.unwrap();
+        let mut replica_removals = replica.removals().await.unwrap();
+
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        master.remove(&["a"]).await.unwrap();
+        advance(TICK).await;
+        replica.get_all().await.unwrap();
+
+        assert_eq!(
+            master_removals.try_recv().unwrap().reason,
+            RemovalReason::ManualRemove
+        );
+        assert!(replica_removals.try_recv().is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_on_write() {
+        let ex = Duration::from_secs(10);
+        let ttl = |hm_cache: HashMapCache<&'static str, i32>| async move {
+            hm_cache.ttl(&["a"]).await.unwrap()[0].unwrap()
+        };
+
+        let hm_cache = HashMapCache::new(ExpirationPolicy::None, 32).await.unwrap();
+        hm_cache
+            .insert("a", 1, Some(ex), InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.ttl(&["a"]).await.unwrap(), vec![None]);
+
+        let hm_cache = HashMapCache::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap()
+            .with_ttl_on_write(TtlOnWrite::Keep);
+        hm_cache
+            .insert("a", 1, Some(ex), InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        hm_cache
+            .insert("a", 2, Some(Duration::from_secs(60)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(ttl(hm_cache.clone()).await, Duration::from_secs(6));
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(2));
+
+        let hm_cache = HashMapCache::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap()
+            .with_ttl_on_write(TtlOnWrite::Extend);
+        hm_cache
+            .insert("a", 1, Some(ex), InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        hm_cache
+            .set(
+                "a",
+                2,
+                InsertOptions {
+                    ex: Some(ex),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(ttl(hm_cache.clone()).await, Duration::from_secs(16));
+        // Without a TTL, the write keeps the expiration.
+        hm_cache
+            .insert("a", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(ttl(hm_cache.clone()).await, Duration::from_secs(16));
+        // New keys get the TTL of the write.
+        hm_cache
+            .insert("b", 1, Some(ex), InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.ttl(&["b"]).await.unwrap(), vec![Some(ex)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_shorter_than_tick() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let mut removals = hm_cache.removals().await.unwrap();
+        hm_cache
+            .insert(
+                "a",
+                1,
+                Some(Duration::from_millis(10)),
+                InsertPolicy::Always,
+            )
+            .await
+            .unwrap();
+        hm_cache
+            .insert(
+                "b",
+                2,
+                Some(Duration::from_millis(20)),
+                InsertPolicy::Always,
+            )
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(9)).await;
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(1));
+
+        // Expired by its own timer, long before the next tick.
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        let removal = removals.try_recv().unwrap();
+        assert_eq!(
+            (removal.key, removal.reason),
+            ("a", RemovalReason::TtlElapsed)
+        );
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hm_cache.mget(&["a", "b"]).await.unwrap(), vec![None, None]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config() {
+        let new =
+            |expiration_policy, buffer| HashMapCache::<i32, i32>::new(expiration_policy, buffer);
+        assert_eq!(
+            new(ExpirationPolicy::LRU(0), 32).await.err(),
+            Some(ConfigError::ZeroCapacity)
+        );
+        assert_eq!(
+            new(ExpirationPolicy::LFU(usize::MAX), 32).await.err(),
+            Some(ConfigError::CapacityTooLarge {
+                capacity: usize::MAX,
+                max: ExpirationPolicy::MAX_CAPACITY,
+            })
+        );
+        assert_eq!(
+            new(ExpirationPolicy::None, 0).await.err(),
+            Some(ConfigError::ZeroBuffer)
+        );
+        assert!(new(ExpirationPolicy::LRU(1), 1).await.is_ok());
+        // Room for the whole capacity isn't taken up front.
+        assert!(
+            new(ExpirationPolicy::LRU(ExpirationPolicy::MAX_CAPACITY), 1)
+                .await
+                .is_ok()
+        );
+    }
 }
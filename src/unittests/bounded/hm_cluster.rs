@@ -1,46 +1,263 @@
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{collections::HashMap, time::Duration};
 
-    use crate::tokio_cache::{bounded::hm_cluster::HashMapCacheCluster, option::ExpirationPolicy};
+    use crate::tokio_cache::{
+        bounded::{hm::HashMapCache, hm_cluster::HashMapCacheCluster},
+        error::{ConfigError, TokioActorCacheError},
+        health::NodeStatus,
+        mirror::ClusterReplicator,
+        option::{ClusterConfig, ExpirationPolicy, InsertEntry, InsertPolicy, NodeConfig},
+        rebalance::RebalanceProgress,
+        router::{Crc16Router, HashRouter, KeyRouter, WeightedRouter},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
-            .insert("a", 10, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hm_cluster.try_ttl(&["a", "b"]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_cluster_replicator() {
+        let expiration_policy = ExpirationPolicy::None;
+        let primary = HashMapCacheCluster::new(expiration_policy, 32, 2)
+            .await
+            .unwrap();
+        let standby = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap()
+            .with_router(Crc16Router);
+        primary
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let replicator = ClusterReplicator::start(&primary, &standby).await.unwrap();
+        assert!(replicator.is_running());
+
+        for (i, key) in ["b", "c", "d", "e"].into_iter().enumerate() {
+            primary
+                .insert(key, i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        primary
+            .insert("f", 5, Some(Duration::from_secs(60)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        primary.remove(&["c"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(
+            standby.get_all().await.unwrap(),
+            primary.get_all().await.unwrap()
+        );
+        let ttl = standby.ttl(&["f"]).await.unwrap();
+        assert!(ttl[0].1.is_some());
+
+        // The standby keeps what it has once stopped.
+        replicator.stop().await;
+        primary
+            .insert("g", 6, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        primary.remove(&["a"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(standby.get("g").await.unwrap(), None);
+        assert_eq!(standby.get("a").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_barrier() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        for key in ["a", "b", "c"] {
+            hm_cluster
+                .insert(key, 10, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        hm_cluster.barrier().await.unwrap();
+        hm_cluster.try_barrier().await.unwrap();
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_snapshot_all_restore() {
+        use crate::tokio_cache::dump::ClusterManifest;
+
+        let config = ClusterConfig::new(ExpirationPolicy::None, 3).with_node(
+            2,
+            NodeConfig {
+                expiration_policy: Some(ExpirationPolicy::LRU(100)),
+                buffer: None,
+            },
+        );
+        let hm_cluster = HashMapCacheCluster::<String, i32>::with_config(config)
+            .await
+            .unwrap();
+        let keys = (0..20).map(|i| i.to_string()).collect::<Vec<String>>();
+        for (i, key) in keys.iter().enumerate() {
+            let ex = (i == 0).then_some(Duration::from_secs(60));
+            hm_cluster
+                .insert(key.clone(), i as i32, ex, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let dir =
+            std::env::temp_dir().join(format!("bounded-hm-cluster-{}", hm_cluster.nodes[&0].id()));
+
+        let manifest = hm_cluster.snapshot_all(&dir).await.unwrap();
+        assert_eq!(manifest.n_node, 3);
+        assert_eq!(
+            manifest
+                .nodes
+                .iter()
+                .map(|node| node.id)
+                .collect::<Vec<u64>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            manifest
+                .nodes
+                .iter()
+                .map(|node| node.n_entries)
+                .sum::<u64>(),
+            20
+        );
+        assert_eq!(ClusterManifest::read(&dir).await.unwrap(), manifest);
+
+        let restored = HashMapCacheCluster::<String, i32>::restore(&dir, 32)
+            .await
+            .unwrap();
+        assert_eq!(
+            restored.get_all().await.unwrap(),
+            hm_cluster.get_all().await.unwrap()
+        );
+        // Every key is found on the node it routes to.
+        assert_eq!(
+            restored.mget(&keys).await.unwrap(),
+            (0..20).map(Some).collect::<Vec<Option<i32>>>()
+        );
+        assert!(restored.ttl(&keys[..1]).await.unwrap()[0].1.is_some());
+
+        // A cluster routing by something else can't be restored from it.
+        assert_eq!(
+            HashMapCacheCluster::<String, i32, WeightedRouter>::restore(&dir, 32)
+                .await
+                .err(),
+            Some(TokioActorCacheError::RouterMismatch)
+        );
+
+        tokio::fs::remove_file(dir.join(ClusterManifest::FILE))
+            .await
+            .unwrap();
+        assert!(matches!(
+            HashMapCacheCluster::<String, i32>::restore(&dir, 32).await,
+            Err(TokioActorCacheError::Io { .. })
+        ));
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_snapshot_all_restore_router() {
+        use crate::tokio_cache::dump::RouterSpec;
+
+        let router = WeightedRouter::with_seed(7);
+        router.set_weight(1, 3);
+        let hm_cluster = HashMapCacheCluster::<String, i32>::new(ExpirationPolicy::None, 32, 3)
+            .await
+            .unwrap()
+            .with_router(router);
+        let keys = (0..50).map(|i| i.to_string()).collect::<Vec<String>>();
+        for (i, key) in keys.iter().enumerate() {
+            hm_cluster
+                .insert(key.clone(), i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "bounded-hm-cluster-router-{}",
+            hm_cluster.nodes[&0].id()
+        ));
+
+        let manifest = hm_cluster.snapshot_all(&dir).await.unwrap();
+        assert_eq!(
+            manifest.router,
+            Some(RouterSpec::Weighted {
+                seed: 7,
+                weights: vec![(1, 3)]
+            })
+        );
+
+        let restored = HashMapCacheCluster::<String, i32, WeightedRouter>::restore(&dir, 32)
+            .await
+            .unwrap();
+        assert_eq!(restored.router.weight(1), 3);
+        // Every key is found on the node it routes to.
+        assert_eq!(
+            restored.mget(&keys).await.unwrap(),
+            (0..50).map(Some).collect::<Vec<Option<i32>>>()
+        );
+        assert_eq!(
+            HashMapCacheCluster::<String, i32>::restore(&dir, 32)
+                .await
+                .err(),
+            Some(TokioActorCacheError::RouterMismatch)
+        );
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_try_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
-        hm_cluster.insert("b", 12, None, false).await.unwrap();
-        hm_cluster.insert("c", 20, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("b", 12, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("c", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hm = hm_cluster.get_all().await.unwrap();
-        assert_eq!(!hm.is_empty(), true);
+        assert!(!hm.is_empty());
         hm_cluster.try_clear().await.unwrap();
         let hm = hm_cluster.get_all().await.unwrap();
-        assert_eq!(hm.is_empty(), true);
+        assert!(hm.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_mget() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -48,16 +265,18 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -65,16 +284,18 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_contains_keys() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -85,17 +306,22 @@ mod tests {
         assert_eq!(is_contains_keys, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
             .try_minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -103,17 +329,22 @@ mod tests {
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
             .try_minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -121,10 +352,12 @@ mod tests {
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .try_minsert(
                 &["a", "b", "c"],
@@ -134,7 +367,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -147,16 +380,18 @@ mod tests {
         assert_eq!(val_c, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .try_minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -168,51 +403,71 @@ mod tests {
         assert_eq!(val_c, Some(30));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_inconsistent_len() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         let res = hm_cluster
             .try_minsert(
                 &["a", "b"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await;
         assert!(res.is_err());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.try_insert("a", 10, None, false).await.unwrap();
-        hm_cluster.try_insert("a", 20, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .try_insert("a", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
-            .try_insert("a", 20, None, true)
+            .try_insert("a", 20, None, InsertPolicy::IfAbsent)
             .await
             .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
-            .try_insert("b", 20, Some(Duration::from_secs(1)), false)
+            .try_insert("b", 20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -222,19 +477,39 @@ mod tests {
         assert_eq!(val_b, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.try_insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[test]
+    fn test_crc16_router_over_u16_nodes() {
+        // With more nodes than CRC16 values, every key stays on the node of its CRC.
+        let ids = ["a", "b", "c"].map(|key| Crc16Router.route(key, 3));
+        assert!(ids.iter().all(|id| *id < 3));
+        let ids = ["a", "b", "c"].map(|key| Crc16Router.route(key, 1 << 20));
+        assert!(ids.iter().all(|id| *id <= u64::from(u16::MAX)));
+        assert_eq!(
+            Crc16Router.route("a", 1 << 20),
+            Crc16Router.route("a", 1 << 17)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_hash_id() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         let keys = vec![
             "a".to_string(),
             "b".to_string(),
@@ -245,49 +520,67 @@ mod tests {
             "g".to_string(),
         ];
         for (k, v) in keys.into_iter().enumerate() {
-            hm_cluster.insert(k, v.clone(), None, false).await.unwrap();
+            hm_cluster
+                .insert(k, v.clone(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
             let val = hm_cluster.get(k).await.unwrap();
             assert_eq!(val, Some(v));
         }
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
-            .insert("a", 10, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hm_cluster.ttl(&["a", "b"]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
-        hm_cluster.insert("b", 12, None, false).await.unwrap();
-        hm_cluster.insert("c", 20, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("b", 12, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("c", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hm = hm_cluster.get_all().await.unwrap();
-        assert_eq!(!hm.is_empty(), true);
+        assert!(!hm.is_empty());
         hm_cluster.clear().await.unwrap();
         let hm = hm_cluster.get_all().await.unwrap();
-        assert_eq!(hm.is_empty(), true);
+        assert!(hm.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mget() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -295,16 +588,18 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -312,16 +607,18 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains_keys() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -332,17 +629,22 @@ mod tests {
         assert_eq!(is_contains_keys, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -350,17 +652,22 @@ mod tests {
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -368,10 +675,12 @@ mod tests {
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
@@ -381,7 +690,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -394,16 +703,18 @@ mod tests {
         assert_eq!(val_c, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -415,48 +726,71 @@ mod tests {
         assert_eq!(val_c, Some(30));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_inconsistent_len() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         let res = hm_cluster
             .minsert(
                 &["a", "b"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await;
         assert!(res.is_err());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
-        hm_cluster.insert("a", 20, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
-        hm_cluster.insert("a", 20, None, true).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 20, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
-            .insert("b", 20, Some(Duration::from_secs(1)), false)
+            .insert("b", 20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -466,12 +800,549 @@ mod tests {
         assert_eq!(val_b, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_cluster() {
+        let expiration_policy = ExpirationPolicy::None;
+        let primary = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        let standby = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        standby.replicate(&primary).await.unwrap();
+
+        primary
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        primary
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        primary
+            .insert("c", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(
+            standby.get_all().await.unwrap(),
+            primary.get_all().await.unwrap()
+        );
+        assert_eq!(standby.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_cluster_size_mismatch() {
+        let expiration_policy = ExpirationPolicy::None;
+        let primary = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        let standby = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 2)
+            .await
+            .unwrap();
+        let res = standby.replicate(&primary).await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::ClusterSizeMismatch {
+                expected: 3,
+                got: 2
+            })
+        );
+    }
+
+    #[derive(Debug, Clone)]
+    struct FirstNode;
+
+    impl KeyRouter<&str> for FirstNode {
+        fn route(&self, _key: &&str, _n_node: u64) -> u64 {
+            0
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_custom_router() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap()
+            .with_router(FirstNode);
+        hm_cluster
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let first_node = hm_cluster.nodes.get(&0).unwrap();
+        assert_eq!(first_node.get_all().await.unwrap().len(), 2);
+        assert_eq!(hm_cluster.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tuple_keys() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<(u8, u8), i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert((1, 2), 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert((2, 1), 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        assert_eq!(hm_cluster.get((1, 2)).await.unwrap(), Some(3));
+        assert_eq!(hm_cluster.get((2, 1)).await.unwrap(), Some(4));
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_crc16_router() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap()
+            .with_router(Crc16Router);
+        hm_cluster
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let node = hm_cluster.nodes.get(&Crc16Router.route("a", 3)).unwrap();
+        assert_eq!(node.get("a").await.unwrap(), Some(1));
+    }
+
+    /// A node whose actor died along with the runtime it was spawned on.
+    fn dead_node() -> HashMapCache<String, i32> {
+        std::thread::spawn(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(HashMapCache::new(ExpirationPolicy::None, 32))
+                .unwrap()
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fail_fast_on_dead_node() {
+        let expiration_policy = ExpirationPolicy::None;
+        let mut hm_cluster = HashMapCacheCluster::<String, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster.nodes.insert(1, dead_node());
+        let hm_cluster = hm_cluster.with_health_check(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let status = hm_cluster.cluster_status();
+        assert_eq!(status[&0], NodeStatus::Up);
+        assert_eq!(status[&1], NodeStatus::Down);
+        assert_eq!(status[&2], NodeStatus::Up);
+
+        // Keys of the dead node fail right away instead of landing on another node.
+        let mut n_live = 0;
+        for i in 0..20 {
+            let key = i.to_string();
+            let inserted = hm_cluster
+                .insert(key.clone(), i, None, InsertPolicy::Always)
+                .await;
+            let got = hm_cluster.get(key.clone()).await;
+            if HashRouter::default().route(&key, 3) == 1 {
+                assert_eq!(inserted, Err(TokioActorCacheError::ActorGone));
+                assert_eq!(got, Err(TokioActorCacheError::ActorGone));
+            } else {
+                inserted.unwrap();
+                assert_eq!(got, Ok(Some(i)));
+                n_live += 1;
+            }
+        }
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), n_live);
+    }
+
+    /// A node whose actor runs on a runtime of its own, which dies when that runtime shuts down.
+    fn node_on_own_runtime() -> (HashMapCache<String, i32>, tokio::runtime::Runtime) {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let node = std::thread::scope(|scope| {
+            scope
+                .spawn(|| rt.block_on(HashMapCache::new(ExpirationPolicy::None, 32)))
+                .join()
+                .unwrap()
+        })
+        .unwrap();
+        (node, rt)
+    }
+
+    #[tokio::test]
+    async fn test_dead_node_served_by_replica() {
+        let expiration_policy = ExpirationPolicy::None;
+        let mut hm_cluster = HashMapCacheCluster::<String, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        let (node, rt) = node_on_own_runtime();
+        hm_cluster.nodes.insert(1, node);
+        let standby = HashMapCacheCluster::<String, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        standby.replicate(&hm_cluster).await.unwrap();
+        let hm_cluster = hm_cluster.with_health_check(Duration::from_millis(10));
+
+        for i in 0..20 {
+            hm_cluster
+                .insert(i.to_string(), i, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        hm_cluster.barrier().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        rt.shutdown_background();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(hm_cluster.cluster_status()[&1], NodeStatus::Down);
+
+        // The dead node's keys are served by its replica.
+        for i in 0..20 {
+            assert_eq!(hm_cluster.get(i.to_string()).await, Ok(Some(i)));
+        }
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), 20);
+
+        // So are writes to them.
+        let key = (0..20)
+            .map(|i| i.to_string())
+            .find(|key| HashRouter::default().route(key, 3) == 1)
+            .unwrap();
+        hm_cluster
+            .insert(key.clone(), -1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(standby.nodes[&1].get(key.clone()).await, Ok(Some(-1)));
+
+        // Without a replica, they fail fast again.
+        standby.stop_replicating().await.unwrap();
+        assert_eq!(
+            hm_cluster.get(key).await,
+            Err(TokioActorCacheError::ActorGone)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_with_config_node_override() {
+        let hot_node = NodeConfig {
+            expiration_policy: Some(ExpirationPolicy::LRU(1)),
+            ..Default::default()
+        };
+        let config = ClusterConfig::new(ExpirationPolicy::None, 3)
+            .with_buffer(64)
+            .with_node(0, hot_node);
+        let hm_cluster = HashMapCacheCluster::<String, i32>::with_config(config)
+            .await
+            .unwrap();
+        for i in 0..30 {
+            hm_cluster
+                .insert(i.to_string(), i, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let hot = hm_cluster.nodes[&0].get_all().await.unwrap();
+        assert_eq!(hot.len(), 1);
+        let rest = hm_cluster.nodes[&1].get_all().await.unwrap().len()
+            + hm_cluster.nodes[&2].get_all().await.unwrap().len();
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), rest + 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rebalance() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        for (i, key) in ["a", "b", "c", "d", "e", "f"].into_iter().enumerate() {
+            hm_cluster
+                .insert(key, i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let misplaced = hm_cluster.nodes[&1].get_all().await.unwrap().len()
+            + hm_cluster.nodes[&2].get_all().await.unwrap().len();
+        assert!(misplaced > 0);
+
+        let hm_cluster = hm_cluster.with_router(FirstNode);
+        let mut progress = hm_cluster.rebalance();
+        let progress = progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap()
+            .clone();
+        assert_eq!(
+            progress,
+            RebalanceProgress {
+                moved: misplaced,
+                remaining: 0,
+                finished: Some(Ok(())),
+            }
+        );
+        assert_eq!(hm_cluster.nodes[&0].get_all().await.unwrap().len(), 6);
+        assert_eq!(hm_cluster.get("f").await.unwrap(), Some(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rebalance_keeps_entry_state() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        let keys = ["a", "b", "c", "d", "e", "f"];
+        for (i, key) in keys.into_iter().enumerate() {
+            hm_cluster
+                .insert(key, i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let key = *keys
+            .iter()
+            .find(|key| hm_cluster.router.route(key, 3) != 0)
+            .unwrap();
+        let node = hm_cluster.nodes[&hm_cluster.router.route(&key, 3)].clone();
+        node.insert(key, 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        node.pin(&[key]).await.unwrap();
+        let versioned = node.get_versioned(key).await.unwrap();
+
+        // Node 0 has no room for them, so they stay where they are.
+        let first = hm_cluster.nodes[&0].clone().with_hard_capacity(0);
+        let hm_cluster = hm_cluster.with_router(FirstNode);
+        let mut progress = hm_cluster.rebalance();
+        let finished = progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap()
+            .finished
+            .clone();
+        assert_eq!(
+            finished,
+            Some(Err(TokioActorCacheError::CapacityExceeded { capacity: 0 }))
+        );
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), 6);
+
+        let _ = first.with_hard_capacity(usize::MAX);
+        let mut progress = hm_cluster.rebalance();
+        progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap();
+        let first = &hm_cluster.nodes[&0];
+        assert_eq!(first.get_versioned(key).await.unwrap(), versioned);
+        // Still pinned, so shrinking leaves it.
+        first.shrink(0).await.unwrap();
+        assert_eq!(first.get(key).await.unwrap(), Some(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_minsert_keeps_ex_nx_per_key() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("c", 0, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .minsert(
+                &["a", "b", "c"],
+                &[1, 2, 3],
+                &[None, Some(Duration::from_secs(10)), None],
+                &[
+                    InsertPolicy::Always,
+                    InsertPolicy::Always,
+                    InsertPolicy::IfAbsent,
+                ],
+            )
+            .await
+            .unwrap();
+
+        let ttl = hm_cluster.ttl(&["a", "b"]).await.unwrap();
+        assert_eq!(ttl[0].1, None);
+        assert!(ttl[1].1.is_some());
+        assert_eq!(
+            hm_cluster.mget(&["a", "b", "c"]).await.unwrap(),
+            vec![Some(1), Some(2), Some(0)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mget_ordered_with_dead_node() {
+        let expiration_policy = ExpirationPolicy::None;
+        let mut hm_cluster = HashMapCacheCluster::<String, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        for i in 0..20 {
+            hm_cluster
+                .insert(i.to_string(), i, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        hm_cluster.nodes.insert(1, dead_node());
+
+        let keys = (0..20).map(|i| i.to_string()).collect::<Vec<_>>();
+        let res = hm_cluster.mget_ordered(&keys).await;
+        assert_eq!(res.len(), 20);
+        for (i, val) in res.into_iter().enumerate() {
+            if HashRouter::default().route(&keys[i], 3) == 1 {
+                assert_eq!(val, Err(TokioActorCacheError::ActorGone));
+            } else {
+                assert_eq!(val, Ok(Some(i as i32)));
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_minsert_entries() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        let entries = (0..10)
+            .map(|i| InsertEntry::new(i.to_string(), i))
+            .collect::<Vec<_>>();
+        hm_cluster.minsert_entries(&entries).await.unwrap();
+
+        let hm = hm_cluster.get_all().await.unwrap();
+        assert_eq!(hm.len(), 10);
+        assert_eq!(hm["7"], 7);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_transact() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<String, i32>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        // Keys on three different nodes.
+        let mut keys = HashMap::new();
+        for i in 0.. {
+            let key = i.to_string();
+            keys.entry(HashRouter::default().route(&key, 3))
+                .or_insert(key);
+            if keys.len() == 3 {
+                break;
+            }
+        }
+        let (a, b, c) = (keys[&0].clone(), keys[&1].clone(), keys[&2].clone());
+        hm_cluster
+            .insert(a.clone(), 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        // Move the value of `a` to `b`.
+        let removed = hm_cluster
+            .transact(&[InsertEntry::new(b.clone(), 1)], std::slice::from_ref(&a))
+            .await
+            .unwrap();
+        assert_eq!(removed, vec![Some(1)]);
+        let vals = hm_cluster.mget(&[a.clone(), b.clone()]).await.unwrap();
+        assert_eq!(vals, vec![None, Some(1)]);
+
+        // A node that can't take its part leaves the others untouched too.
+        hm_cluster.nodes[&2].clone().with_hard_capacity(0);
+        let res = hm_cluster
+            .transact(
+                &[
+                    InsertEntry::new(a.clone(), 1),
+                    InsertEntry::new(c.clone(), 1),
+                ],
+                std::slice::from_ref(&b),
+            )
+            .await;
+        assert_eq!(
+            res.unwrap_err(),
+            TokioActorCacheError::CapacityExceeded { capacity: 0 }
+        );
+        let vals = hm_cluster.mget(&[a, b, c]).await.unwrap();
+        assert_eq!(vals, vec![None, Some(1), None]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 32, 0).await;
+        assert_eq!(hm_cluster.err(), Some(ConfigError::NoNodes));
+
+        // Every node is checked, overrides included.
+        let config = ClusterConfig::new(expiration_policy, 2).with_node(
+            1,
+            NodeConfig {
+                buffer: Some(0),
+                ..NodeConfig::default()
+            },
+        );
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::with_config(config).await;
+        assert_eq!(hm_cluster.err(), Some(ConfigError::ZeroBuffer));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_node_weight() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<u64, u64>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap()
+            .with_router(WeightedRouter::default());
+        let entries = (0..900)
+            .map(|i| InsertEntry::new(i, i))
+            .collect::<Vec<InsertEntry<u64, u64>>>();
+        hm_cluster.minsert_entries(&entries).await.unwrap();
+        let mut before = HashMap::new();
+        for (id, node) in &hm_cluster.nodes {
+            before.insert(*id, node.get_all().await.unwrap());
+        }
+        assert!(
+            before
+                .values()
+                .all(|entries| (200..400).contains(&entries.len()))
+        );
+
+        assert_eq!(
+            hm_cluster.set_node_weight(3, 2).err(),
+            Some(TokioActorCacheError::NodeNotExists { id: 3 })
+        );
+        let mut progress = hm_cluster.set_node_weight(0, 2).unwrap();
+        let progress = progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap()
+            .clone();
+        assert_eq!(progress.finished, Some(Ok(())));
+
+        // Node 0 takes about half of the keys, all of them from the other nodes, which keep the
+        // rest of theirs.
+        let on_0 = hm_cluster.nodes[&0].get_all().await.unwrap();
+        assert!((360..540).contains(&on_0.len()));
+        assert!(before[&0].keys().all(|key| on_0.contains_key(key)));
+        for id in [1, 2] {
+            let after = hm_cluster.nodes[&id].get_all().await.unwrap();
+            assert!(after.keys().all(|key| before[&id].contains_key(key)));
+        }
+        assert_eq!(progress.moved, on_0.len() - before[&0].len());
+        assert_eq!(hm_cluster.get(899).await.unwrap(), Some(899));
+    }
 }
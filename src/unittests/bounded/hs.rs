@@ -2,16 +2,27 @@
 mod tests {
     use std::{collections::HashSet, time::Duration};
 
-    use crate::tokio_cache::{bounded::hs::HashSetCache, option::ExpirationPolicy};
+    use crate::tokio_cache::{
+        bounded::{hm::HashMapCache, hs::HashSetCache},
+        option::{ExpirationPolicy, InsertPolicy},
+        test_utils::{TICK, advance},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert(1, None, false).await.unwrap();
+        hm_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -26,14 +37,21 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert(1, None, false).await.unwrap();
+        hm_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -47,7 +65,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm_cluster1.insert(10, None, false).await.unwrap();
+        hm_cluster1
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -56,14 +77,21 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert(1, None, false).await.unwrap();
+        hm_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -74,14 +102,21 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert(1, None, false).await.unwrap();
+        hm_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -96,14 +131,21 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert(1, None, false).await.unwrap();
+        hm_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -117,7 +159,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm_cluster1.insert(10, None, false).await.unwrap();
+        hm_cluster1
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -126,14 +171,21 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hm_cluster2 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.insert(1, None, false).await.unwrap();
+        hm_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -144,59 +196,75 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
-            .insert(10, Some(Duration::from_secs(1)), false)
+            .insert(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hs_cache.try_ttl(&[10, 20]).await.unwrap();
         println!("{:?}", ttl);
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.insert(10, None, false).await.unwrap();
-        hs_cache.insert(20, None, false).await.unwrap();
-        hs_cache.insert(30, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = hs_cache.get_all().await.unwrap();
         assert_eq!(hs, HashSet::from([10, 20, 30]));
         hs_cache.try_clear().await.unwrap();
         let hs = hs_cache.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let vals = hs_cache.try_remove(&[10, 20, 30, 40]).await.unwrap();
         assert_eq!(vals, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.insert(10, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let vals = hs_cache.try_contains(&[10]).await.unwrap();
         assert_eq!(vals, vec![true]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
             .try_minsert(
                 &[10, 20, 30],
@@ -205,7 +273,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -214,25 +282,32 @@ mod tests {
         assert_eq!(vals, HashSet::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
-            .try_minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .try_minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = hs_cache.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.try_insert(10, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
-            .try_insert(20, Some(Duration::from_secs(1)), false)
+            .try_insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .try_insert(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -240,70 +315,95 @@ mod tests {
         assert_eq!(val, HashSet::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.try_insert(10, None, false).await.unwrap();
-        hs_cache.try_insert(20, None, false).await.unwrap();
-        hs_cache.try_insert(30, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        hs_cache
+            .try_insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .try_insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .try_insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hs_cache.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
-            .insert(10, Some(Duration::from_secs(1)), false)
+            .insert(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hs_cache.ttl(&[10, 20]).await.unwrap();
         println!("{:?}", ttl);
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.insert(10, None, false).await.unwrap();
-        hs_cache.insert(20, None, false).await.unwrap();
-        hs_cache.insert(30, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = hs_cache.get_all().await.unwrap();
         assert_eq!(hs, HashSet::from([10, 20, 30]));
         hs_cache.clear().await.unwrap();
         let hs = hs_cache.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let vals = hs_cache.remove(&[10, 20, 30, 40]).await.unwrap();
         assert_eq!(vals, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.insert(10, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let vals = hs_cache.contains(&[10]).await.unwrap();
         assert_eq!(vals, vec![true]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
             .minsert(
                 &[10, 20, 30],
@@ -312,7 +412,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -321,25 +421,32 @@ mod tests {
         assert_eq!(vals, HashSet::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
         hs_cache
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = hs_cache.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.insert(10, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hs_cache
-            .insert(20, Some(Duration::from_secs(1)), false)
+            .insert(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -347,14 +454,413 @@ mod tests {
         assert_eq!(val, HashSet::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy, 32).await;
-        hs_cache.insert(10, None, false).await.unwrap();
-        hs_cache.insert(20, None, false).await.unwrap();
-        hs_cache.insert(30, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hs_cache.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_from_hash_map_keys() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hs_cache = HashSetCache::<&str>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .replicate_from(&hm_cache, |key, _val| *key)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from(["a", "b"]));
+
+        hm_cache.remove(&["a"]).await.unwrap();
+        hm_cache
+            .insert("c", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from(["b", "c"]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_from_shared_projection() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hs_cache
+            .replicate_from(&hm_cache, |_key, val| val % 2)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("c", 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache.remove(&["a"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([0, 1]));
+
+        hm_cache.remove(&["b"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([0]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cache = HashSetCache::new(expiration_policy, 32).await.unwrap();
+        let ex = Some(Duration::from_secs(10));
+        hs_cache
+            .insert(10, ex, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert!(hs_cache.get_all().await.unwrap().is_empty());
+
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(10, ex, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        assert_eq!(hs_cache.ttl(&[10]).await.unwrap(), vec![None]);
+
+        hs_cache
+            .insert(10, ex, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert!(hs_cache.ttl(&[10]).await.unwrap()[0].is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_capacity_enforced_on_insert() {
+        let expiration_policy = ExpirationPolicy::LFU(3);
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for val in 0..10 {
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        // No tick has run yet.
+        assert_eq!(hs_cache.get_all().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lru_evicts_in_order_of_use() {
+        let expiration_policy = ExpirationPolicy::LRU(3);
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for val in 0..3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        for (used, val) in [(0, 3), (2, 4)] {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hs_cache.contains(&[used]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([2, 3, 4]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_eviction_below_capacity() {
+        for expiration_policy in [ExpirationPolicy::LFU(5), ExpirationPolicy::LRU(5)] {
+            let hs_cache = HashSetCache::<i32>::new(expiration_policy, 32)
+                .await
+                .unwrap();
+            for val in 0..3 {
+                hs_cache
+                    .insert(val, None, InsertPolicy::Always)
+                    .await
+                    .unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            assert_eq!(hs_cache.get_all().await.unwrap().len(), 3);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hot_and_idle_vals() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for val in [1, 2, 3] {
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        // Time is paused, so let some pass for `1` to be the idlest.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        hs_cache.contains(&[2, 2, 3]).await.unwrap();
+
+        assert_eq!(hs_cache.hot_vals(2).await.unwrap(), vec![(2, 2), (3, 1)]);
+        let idle_vals = hs_cache.try_idle_vals(1).await.unwrap();
+        assert_eq!(idle_vals.len(), 1);
+        assert_eq!(idle_vals[0].0, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_get_all_counts_as_use() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        for val in [1, 2] {
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        hs_cache.contains(&[1]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([1, 2]));
+        assert_eq!(hs_cache.hot_vals(2).await.unwrap(), vec![(1, 2), (2, 1)]);
+        let idle_vals = hs_cache.idle_vals(2).await.unwrap();
+        assert!(idle_vals.iter().all(|(_, idle)| idle.is_zero()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_dump_load() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cache1 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let hs_cache2 = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        hs_cache1
+            .insert(1, Some(Duration::from_secs(10)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache1
+            .insert(2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        // Expired but not swept yet, so left out of the dump.
+        hs_cache1
+            .insert(3, Some(Duration::from_millis(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let bytes = hs_cache1.try_dump().await.unwrap();
+        hs_cache2.load(&bytes).await.unwrap();
+
+        assert_eq!(hs_cache2.get_all().await.unwrap(), HashSet::from([1, 2]));
+        let ttl = hs_cache2.ttl(&[1, 2]).await.unwrap();
+        assert!(ttl[0].is_some_and(|ttl| ttl > Duration::from_secs(9)));
+        assert_eq!(ttl[1], None);
+    }
+
+    async fn set_of(vals: &[i32]) -> HashSetCache<i32> {
+        let hs_cache = HashSetCache::new(ExpirationPolicy::None, 32).await.unwrap();
+        for val in vals {
+            hs_cache
+                .insert(*val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        hs_cache
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_algebra() {
+        let a = set_of(&[1, 2, 3]).await;
+        let b = set_of(&[2, 3, 4]).await;
+        // Expired but not swept yet, so left out.
+        b.insert(5, Some(Duration::from_millis(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        assert_eq!(a.union_with(&b).await.unwrap(), HashSet::from([1, 2, 3, 4]));
+        assert_eq!(a.intersect(&b).await.unwrap(), HashSet::from([2, 3]));
+        assert_eq!(a.difference(&b).await.unwrap(), HashSet::from([1]));
+        assert_eq!(b.try_difference(&a).await.unwrap(), HashSet::from([4]));
+        // Reading them doesn't count as a use.
+        assert_eq!(a.hot_vals(1).await.unwrap()[0].1, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_store_set_algebra() {
+        let a = set_of(&[1, 2, 3]).await;
+        let b = set_of(&[2, 3, 4]).await;
+        let c = set_of(&[3]).await;
+        let dst = set_of(&[9]).await;
+        let replica = set_of(&[]).await;
+        replica.replicate(&dst).await.unwrap();
+
+        assert_eq!(dst.store_union(&[&a, &b, &c]).await.unwrap(), 4);
+        assert_eq!(dst.get_all().await.unwrap(), HashSet::from([1, 2, 3, 4]));
+        assert_eq!(dst.store_intersect(&[&a, &b, &c]).await.unwrap(), 1);
+        assert_eq!(dst.get_all().await.unwrap(), HashSet::from([3]));
+        assert_eq!(dst.try_store_difference(&[&a, &b]).await.unwrap(), 1);
+        assert_eq!(dst.get_all().await.unwrap(), HashSet::from([1]));
+        assert_eq!(dst.ttl(&[1]).await.unwrap(), vec![None]);
+
+        // The destination may be one of the sets.
+        assert_eq!(a.store_union(&[&a, &c, &dst]).await.unwrap(), 3);
+        assert_eq!(a.get_all().await.unwrap(), HashSet::from([1, 2, 3]));
+
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(replica.get_all().await.unwrap(), HashSet::from([1]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_srandmember_and_spop() {
+        let hs_cache = set_of(&[1, 2, 3, 4, 5]).await;
+        let replica = set_of(&[]).await;
+        replica.replicate(&hs_cache).await.unwrap();
+        let all = HashSet::from([1, 2, 3, 4, 5]);
+
+        let picked = hs_cache.srandmember(3).await.unwrap();
+        assert_eq!(picked.len(), 3);
+        assert_eq!(picked.iter().collect::<HashSet<_>>().len(), 3);
+        assert!(picked.iter().all(|val| all.contains(val)));
+        // Sampling leaves the set as is, and asking for more than it holds returns everything.
+        let picked = hs_cache.try_srandmember(10).await.unwrap();
+        assert_eq!(picked.into_iter().collect::<HashSet<_>>(), all);
+        assert_eq!(hs_cache.srandmember(0).await.unwrap(), Vec::<i32>::new());
+
+        let popped = hs_cache.spop(2).await.unwrap();
+        assert_eq!(popped.len(), 2);
+        let left = hs_cache.get_all().await.unwrap();
+        assert_eq!(left.len(), 3);
+        assert!(
+            popped
+                .iter()
+                .all(|val| all.contains(val) && !left.contains(val))
+        );
+        let popped = hs_cache.try_spop(10).await.unwrap();
+        assert_eq!(popped.into_iter().collect::<HashSet<_>>(), left);
+        assert!(hs_cache.get_all().await.unwrap().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert!(replica.get_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_shorter_than_tick() {
+        let hs_cache = set_of(&[]).await;
+        hs_cache
+            .insert(1, Some(Duration::from_millis(10)), InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(9)).await;
+        assert_eq!(hs_cache.contains(&[1]).await.unwrap(), vec![true]);
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(hs_cache.contains(&[1]).await.unwrap(), vec![false]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_budget() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_cleanup_budget(3);
+        let vals = (0..20).collect::<Vec<_>>();
+        let ex = [Some(Duration::from_secs(1)); 20];
+        hs_cache
+            .minsert(&vals, &ex, &[InsertPolicy::Always; 20])
+            .await
+            .unwrap();
+        hs_cache
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        // A few passes of 3 each so far, yet none of the expired vals are found.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(hs_cache.contains(&vals).await.unwrap(), vec![false; 20]);
+        assert_eq!(hs_cache.ttl(&[0]).await.unwrap(), vec![None]);
+        assert!(hs_cache.get_all().await.unwrap().len() > 1);
+
+        advance(TICK * 8).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([20]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_budget_evictions() {
+        let master = HashSetCache::<i32>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let replica = HashSetCache::<i32>::new(ExpirationPolicy::LRU(2), 32)
+            .await
+            .unwrap()
+            .with_cleanup_budget(3);
+        replica.replicate(&master).await.unwrap();
+        let vals = (0..8).collect::<Vec<_>>();
+        master
+            .minsert(&vals, &[None; 8], &[InsertPolicy::Always; 8])
+            .await
+            .unwrap();
+        advance(TICK).await;
+        assert_eq!(replica.get_all().await.unwrap().len(), 8);
+
+        // Left to evict on its own, the former replica does so 3 a tick, looked at twice a tick.
+        replica.stop_replicating().await.unwrap();
+        let mut lens = vec![8];
+        for _ in 0..8 {
+            advance(TICK / 2).await;
+            lens.push(replica.get_all().await.unwrap().len());
+        }
+        lens.dedup();
+        assert_eq!(lens, vec![8, 5, 2]);
+    }
 }
@@ -2,60 +2,92 @@
 mod tests {
     use std::{collections::HashSet, time::Duration};
 
-    use crate::tokio_cache::{bounded::hs_cluster::HashSetCacheCluster, option::ExpirationPolicy};
+    use crate::tokio_cache::{
+        bounded::hs_cluster::HashSetCacheCluster,
+        option::{ClusterConfig, ExpirationPolicy, InsertPolicy, NodeConfig},
+        rebalance::RebalanceProgress,
+        router::KeyRouter,
+        test_utils::{TICK, advance},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .insert(10, Some(Duration::from_secs(1)), false)
+            .insert(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hs_cluster.try_ttl(&[10, 20]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
-        hs_cluster.insert(20, None, false).await.unwrap();
-        hs_cluster.insert(30, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = hs_cluster.get_all().await.unwrap();
         assert_eq!(hs, HashSet::from([10, 20, 30]));
         hs_cluster.try_clear().await.unwrap();
         let hs = hs_cluster.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let vals = hs_cluster.try_remove(&[10, 20, 30, 40]).await.unwrap();
         assert_eq!(vals, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let vals = hs_cluster.try_contains(&[10]).await.unwrap();
         assert_eq!(vals, vec![true]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
             .try_minsert(
                 &[10, 20, 30],
@@ -64,7 +96,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -73,25 +105,36 @@ mod tests {
         assert_eq!(vals, HashSet::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .try_minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .try_minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = hs_cluster.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.try_insert(10, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .try_insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hs_cluster
-            .try_insert(20, Some(Duration::from_secs(1)), false)
+            .try_insert(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -99,21 +142,34 @@ mod tests {
         assert_eq!(val, HashSet::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.try_insert(10, None, false).await.unwrap();
-        hs_cluster.try_insert(20, None, false).await.unwrap();
-        hs_cluster.try_insert(30, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .try_insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .try_insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .try_insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hs_cluster.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_hash_id() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         let keys = vec![
             "a".to_string(),
             "b".to_string(),
@@ -124,66 +180,95 @@ mod tests {
             "g".to_string(),
         ];
         for k in keys.clone() {
-            hs_cluster.insert(k, None, false).await.unwrap();
+            hs_cluster
+                .insert(k, None, InsertPolicy::Always)
+                .await
+                .unwrap();
         }
 
         let vals = hs_cluster.get_all().await.unwrap();
         assert_eq!(vals.len(), keys.len());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .insert(10, Some(Duration::from_secs(1)), false)
+            .insert(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hs_cluster.ttl(&[10, 20]).await.unwrap();
         println!("{:?}", ttl);
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
-        hs_cluster.insert(20, None, false).await.unwrap();
-        hs_cluster.insert(30, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = hs_cluster.get_all().await.unwrap();
         assert_eq!(hs, HashSet::from([10, 20, 30]));
         hs_cluster.clear().await.unwrap();
         let hs = hs_cluster.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let vals = hs_cluster.remove(&[10, 20, 30, 40]).await.unwrap();
         assert_eq!(vals, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let vals = hs_cluster.contains(&[10]).await.unwrap();
         assert_eq!(vals, vec![true]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
             .minsert(
                 &[10, 20, 30],
@@ -192,7 +277,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -201,25 +286,36 @@ mod tests {
         assert_eq!(vals, HashSet::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = hs_cluster.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .insert(20, Some(Duration::from_secs(1)), false)
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -227,14 +323,108 @@ mod tests {
         assert_eq!(val, HashSet::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
-        hs_cluster.insert(20, None, false).await.unwrap();
-        hs_cluster.insert(30, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hs_cluster.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_nodes_are_distinct() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        for val in 0..30 {
+            hs_cluster
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let ids = hs_cluster
+            .nodes
+            .values()
+            .map(|node| node.id())
+            .collect::<HashSet<_>>();
+        assert_eq!(ids.len(), 3);
+
+        let mut n_vals = Vec::new();
+        for node in hs_cluster.nodes.values() {
+            n_vals.push(node.get_all().await.unwrap().len());
+        }
+        assert_eq!(n_vals.iter().sum::<usize>(), 30);
+        assert!(n_vals.iter().all(|n| *n < 30));
+    }
+
+    #[derive(Debug, Clone)]
+    struct LastNode;
+
+    impl KeyRouter<i32> for LastNode {
+        fn route(&self, _val: &i32, n_node: u64) -> u64 {
+            n_node - 1
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rebalance() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        for val in 0..10 {
+            hs_cluster
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let hs_cluster = hs_cluster.with_router(LastNode);
+        let mut progress = hs_cluster.rebalance();
+        progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap();
+        assert_eq!(hs_cluster.nodes[&2].get_all().await.unwrap().len(), 10);
+        assert_eq!(hs_cluster.get_all().await.unwrap(), (0..10).collect());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_with_config() {
+        // Node 0 evicts down to one value, the others keep all of theirs.
+        let config = ClusterConfig::new(ExpirationPolicy::None, 2).with_node(
+            0,
+            NodeConfig {
+                expiration_policy: Some(ExpirationPolicy::LRU(1)),
+                buffer: Some(1),
+            },
+        );
+        let hs_cluster = HashSetCacheCluster::<i32>::with_config(config)
+            .await
+            .unwrap();
+        for node in [&hs_cluster.nodes[&0], &hs_cluster.nodes[&1]] {
+            for val in [1, 2] {
+                node.insert(val, None, InsertPolicy::Always).await.unwrap();
+            }
+        }
+        advance(TICK).await;
+        assert_eq!(hs_cluster.nodes[&0].get_all().await.unwrap().len(), 1);
+        assert_eq!(hs_cluster.nodes[&1].get_all().await.unwrap().len(), 2);
+    }
 }
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache,
+        error::TokioActorCacheError,
+        join,
+        option::{ExpirationPolicy, InsertPolicy},
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mget() {
+        let users = HashMapCache::<u32, &str>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let sessions = HashMapCache::<&str, u32>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let counts = HashMapCache::<&str, usize>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        users
+            .insert(1, "alice", None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        sessions
+            .insert("s1", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        counts
+            .insert("s1", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let (found_users, found_sessions) = join::mget2(&users, &[1, 2], &sessions, &["s1"])
+            .await
+            .unwrap();
+        assert_eq!(found_users, vec![Some("alice"), None]);
+        assert_eq!(found_sessions, vec![Some(1)]);
+
+        let found = join::mget3(&users, &[1], &sessions, &["s2"], &counts, &["s1"]).await;
+        assert_eq!(
+            found.unwrap(),
+            (vec![Some("alice")], vec![None], vec![Some(3)])
+        );
+
+        // An error of either cache fails the whole read.
+        let impatient = sessions.clone().with_timeout(Duration::ZERO);
+        let found = join::mget2(&users, &[1], &impatient, &["s1"]).await;
+        assert_eq!(
+            found.unwrap_err(),
+            TokioActorCacheError::Timeout {
+                after: Duration::ZERO
+            }
+        );
+    }
+}
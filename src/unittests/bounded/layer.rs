@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use tower::{Layer, Service, ServiceExt, service_fn};
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache, layer::CacheLayer, option::ExpirationPolicy,
+    };
+
+    /// Answers `"{path}:{n}"`, `n` counting calls from 1; paths starting with `_` aren't cached.
+    async fn counting_service(
+        ttl: Duration,
+        stale_while_revalidate: Duration,
+    ) -> (
+        impl Service<String, Response = Bytes, Error = Infallible> + Clone,
+        Arc<AtomicUsize>,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let inner = service_fn(move |path: String| {
+            let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move { Ok::<_, Infallible>(Bytes::from(format!("{path}:{n}"))) }
+        });
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<String, Bytes>::new(expiration_policy, 32)
+            .await
+            .unwrap();
+        let key = |path: &String| (!path.starts_with('_')).then(|| path.clone());
+        let layer =
+            CacheLayer::new(hm_cache, key, ttl).stale_while_revalidate(stale_while_revalidate);
+        (layer.layer(inner), calls)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_layer() {
+        let (svc, calls) = counting_service(Duration::from_secs(10), Duration::ZERO).await;
+        let resp = svc.clone().oneshot("a".to_string()).await.unwrap();
+        assert_eq!(resp, Bytes::from("a:1"));
+        let resp = svc.clone().oneshot("a".to_string()).await.unwrap();
+        assert_eq!(resp, Bytes::from("a:1"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let resp = svc.clone().oneshot("b".to_string()).await.unwrap();
+        assert_eq!(resp, Bytes::from("b:2"));
+
+        // Not cached.
+        svc.clone().oneshot("_c".to_string()).await.unwrap();
+        let resp = svc.clone().oneshot("_c".to_string()).await.unwrap();
+        assert_eq!(resp, Bytes::from("_c:4"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_layer_expires() {
+        let (svc, calls) = counting_service(Duration::from_millis(100), Duration::ZERO).await;
+        svc.clone().oneshot("a".to_string()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let resp = svc.clone().oneshot("a".to_string()).await.unwrap();
+        assert_eq!(resp, Bytes::from("a:2"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stale_while_revalidate() {
+        let (svc, calls) =
+            counting_service(Duration::from_millis(100), Duration::from_secs(10)).await;
+        svc.clone().oneshot("a".to_string()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // Stale: served as is while refreshed in the background, once.
+        let resp = svc.clone().oneshot("a".to_string()).await.unwrap();
+        assert_eq!(resp, Bytes::from("a:1"));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let resp = svc.clone().oneshot("a".to_string()).await.unwrap();
+        assert_eq!(resp, Bytes::from("a:2"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
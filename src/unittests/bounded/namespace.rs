@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache,
+        namespace::NamespacedCache,
+        option::{ExpirationPolicy, InsertPolicy},
+        removal::{Eviction, RemovalReason},
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_quota_evicts_within_namespace() {
+        let cache = HashMapCache::<(&str, &str), i32>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let mut removals = cache.removals().await.unwrap();
+        let tenants = NamespacedCache::new(cache).await.unwrap();
+        tenants.set_quota("a", Some(2)).await.unwrap();
+
+        for (i, key) in ["k1", "k2", "k3"].into_iter().enumerate() {
+            tenants
+                .insert("a", key, i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            tenants
+                .insert("b", key, i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // Only the tenant over its quota loses its least recently used entry.
+        assert_eq!(tenants.namespace_len("a").await.unwrap(), 2);
+        assert_eq!(tenants.get("a", "k1").await.unwrap(), None);
+        assert_eq!(tenants.get("b", "k1").await.unwrap(), Some(0));
+        let removal = removals.try_recv().unwrap();
+        assert_eq!(removal.key, ("a", "k1"));
+        assert_eq!(removal.reason, RemovalReason::Evicted(Eviction::Lru));
+        assert_eq!(
+            tenants.namespace_counts().await.unwrap(),
+            HashMap::from([("a", 2), ("b", 3)])
+        );
+
+        // The default quota applies to tenants without their own.
+        tenants.try_set_default_quota(Some(1)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        tenants
+            .insert("b", "k4", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(tenants.namespace_len("b").await.unwrap(), 1);
+        assert_eq!(tenants.get("b", "k4").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clear_namespace() {
+        let cache = HashMapCache::<(u32, u32), u32>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        cache
+            .insert((1, 1), 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        // Entries cached before wrapping are counted too.
+        let tenants = NamespacedCache::new(cache.clone()).await.unwrap();
+        for key in 0..3 {
+            tenants
+                .insert(2, key, key, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            tenants.namespace_counts().await.unwrap(),
+            HashMap::from([(1, 1), (2, 3)])
+        );
+
+        assert_eq!(tenants.clear_namespace(2).await.unwrap(), 3);
+        assert_eq!(tenants.try_clear_namespace(3).await.unwrap(), 0);
+        assert_eq!(
+            tenants.namespace_counts().await.unwrap(),
+            HashMap::from([(1, 1)])
+        );
+        assert_eq!(tenants.get(2, 0).await.unwrap(), None);
+
+        assert_eq!(
+            tenants.remove(1, &[1, 2]).await.unwrap(),
+            vec![Some(1), None]
+        );
+        assert_eq!(tenants.namespace_len(1).await.unwrap(), 0);
+        cache.clear().await.unwrap();
+        assert!(tenants.namespace_counts().await.unwrap().is_empty());
+    }
+}
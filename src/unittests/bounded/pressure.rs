@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache,
+        option::{ExpirationPolicy, InsertEntry},
+        pressure::{CapacityBounds, CapacityController, MemoryGauge, PressureConfig},
+        registry::CacheRegistry,
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_adjust() {
+        let registry = CacheRegistry::new();
+        let sessions = HashMapCache::<u64, u64>::new(ExpirationPolicy::LRU(100), 32)
+            .await
+            .unwrap();
+        let users = HashMapCache::<u64, u64>::new(ExpirationPolicy::LFU(100), 32)
+            .await
+            .unwrap();
+        let unbounded = HashMapCache::<u64, u64>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        registry.register("sessions", sessions.clone());
+        registry.register("users", users.clone());
+        registry.register("unbounded", unbounded.clone());
+        let entries = (0..100)
+            .map(|i| InsertEntry::new(i, i))
+            .collect::<Vec<InsertEntry<u64, u64>>>();
+        sessions.minsert_entries(&entries).await.unwrap();
+
+        let bytes = Arc::new(AtomicUsize::new(2000));
+        let gauge = {
+            let bytes = bytes.clone();
+            MemoryGauge::custom(move || Some(bytes.load(Ordering::Relaxed)))
+        };
+        let config =
+            PressureConfig::new(500, 1000).with_bounds("users", CapacityBounds::new(95, 105));
+
+        // Over the high watermark every cache that evicts shrinks by a tenth, within its bounds.
+        for expected in [2, 1] {
+            let n_changed = CapacityController::adjust(&registry, &gauge, &config)
+                .await
+                .unwrap();
+            assert_eq!(n_changed, expected);
+        }
+        assert_eq!(sessions.policy().await.unwrap(), ExpirationPolicy::LRU(81));
+        assert_eq!(users.policy().await.unwrap(), ExpirationPolicy::LFU(95));
+        assert_eq!(unbounded.policy().await.unwrap(), ExpirationPolicy::None);
+        assert_eq!(sessions.get_all().await.unwrap().len(), 81);
+
+        // Between the watermarks nothing changes.
+        bytes.store(800, Ordering::Relaxed);
+        let n_changed = CapacityController::adjust(&registry, &gauge, &config)
+            .await
+            .unwrap();
+        assert_eq!(n_changed, 0);
+
+        bytes.store(100, Ordering::Relaxed);
+        let n_changed = CapacityController::adjust(&registry, &gauge, &config)
+            .await
+            .unwrap();
+        assert_eq!(n_changed, 2);
+        assert_eq!(sessions.policy().await.unwrap(), ExpirationPolicy::LRU(90));
+        assert_eq!(users.policy().await.unwrap(), ExpirationPolicy::LFU(105));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_controller() {
+        let registry = CacheRegistry::new();
+        let hm_cache = HashMapCache::<u64, u64>::new(ExpirationPolicy::LRU(100), 32)
+            .await
+            .unwrap();
+        registry.register("hm", hm_cache.clone());
+        let gauge = MemoryGauge::custom(|| Some(2000));
+        let mut config = PressureConfig::new(500, 1000);
+        config.default_bounds = CapacityBounds::new(50, 200);
+
+        let controller = CapacityController::spawn(registry, gauge, config);
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        assert_eq!(hm_cache.policy().await.unwrap(), ExpirationPolicy::LRU(50));
+
+        drop(controller);
+        hm_cache.set_capacity(100).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        assert_eq!(hm_cache.policy().await.unwrap(), ExpirationPolicy::LRU(100));
+    }
+}
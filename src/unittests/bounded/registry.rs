@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache,
+        error::TokioActorCacheError,
+        option::{CacheConfig, ExpirationPolicy, InsertEntry, InsertPolicy},
+        registry::CacheRegistry,
+        unbounded::hm::HashMapCache as UnboundedHashMapCache,
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stats() {
+        let registry = CacheRegistry::new();
+        let sessions = HashMapCache::<u64, u64>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let users = UnboundedHashMapCache::<String, String>::new(ExpirationPolicy::None)
+            .await
+            .unwrap();
+        registry.register("sessions", sessions.clone());
+        registry.register("users", users.clone());
+        let entries = (0..100)
+            .map(|i| InsertEntry::new(i, i))
+            .collect::<Vec<InsertEntry<u64, u64>>>();
+        sessions.minsert_entries(&entries).await.unwrap();
+        users
+            .insert("a".to_string(), "b".to_string(), None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let stats = registry.stats().await.unwrap();
+        assert_eq!(registry.names(), vec!["sessions", "users"]);
+        assert_eq!(stats.entries, 101);
+        assert_eq!(stats.caches["sessions"].entries, 100);
+        assert_eq!(stats.caches["users"].entries, 1);
+        assert_eq!(
+            stats.bytes,
+            stats
+                .caches
+                .values()
+                .map(|usage| usage.bytes)
+                .sum::<usize>()
+        );
+        assert!(stats.caches["sessions"].bytes >= 100 * 2 * size_of::<u64>());
+
+        assert!(registry.unregister("users"));
+        assert!(!registry.unregister("users"));
+        assert_eq!(registry.stats().await.unwrap().entries, 100);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_clear_and_shrink() {
+        let registry = CacheRegistry::new();
+        let hm_cache1 = HashMapCache::<u64, u64>::new(ExpirationPolicy::LFU(1000), 32)
+            .await
+            .unwrap();
+        let hm_cache2 = HashMapCache::<u64, u64>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        registry.register("hm1", hm_cache1.clone());
+        registry.register("hm2", hm_cache2.clone());
+        let entries = (0..100)
+            .map(|i| InsertEntry::new(i, i))
+            .collect::<Vec<InsertEntry<u64, u64>>>();
+        hm_cache1.minsert_entries(&entries).await.unwrap();
+        hm_cache2.minsert_entries(&entries[..50]).await.unwrap();
+        // Keys used most are kept by LFU.
+        hm_cache1.mget(&[98, 99]).await.unwrap();
+
+        let stats = registry.stats().await.unwrap();
+        let n_evicted = registry.shrink(stats.bytes / 2).await.unwrap();
+        assert!(n_evicted >= 75);
+        let stats = registry.stats().await.unwrap();
+        assert!(stats.caches["hm1"].entries <= 50);
+        assert!(stats.caches["hm2"].entries <= 25);
+        assert_eq!(
+            hm_cache1.mget(&[98, 99]).await.unwrap(),
+            vec![Some(98), Some(99)]
+        );
+
+        assert_eq!(
+            hm_cache2.shrink(10).await.unwrap(),
+            stats.caches["hm2"].entries - 10
+        );
+        registry.clear().await.unwrap();
+        let stats = registry.stats().await.unwrap();
+        assert_eq!(stats.entries, 0);
+        assert_eq!(stats.bytes, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_get_or_create() {
+        let registry = CacheRegistry::new();
+        let config = CacheConfig::new(ExpirationPolicy::LRU(10)).with_buffer(8);
+        let sessions = registry
+            .get_or_create::<String, u64>("sessions", config)
+            .await
+            .unwrap();
+        sessions
+            .insert("a".to_string(), 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        // Elsewhere in the program, without the handle.
+        let same = registry
+            .get_or_create::<String, u64>("sessions", config)
+            .await
+            .unwrap();
+        assert_eq!(same.id(), sessions.id());
+        assert_eq!(same.get("a".to_string()).await.unwrap(), Some(1));
+        let found = registry
+            .get::<HashMapCache<String, u64>>("sessions")
+            .unwrap();
+        assert_eq!(found.id(), sessions.id());
+        assert!(
+            registry
+                .get::<HashMapCache<String, u32>>("sessions")
+                .is_none()
+        );
+        assert!(registry.get::<HashMapCache<String, u64>>("users").is_none());
+        assert_eq!(
+            registry
+                .get_or_create::<String, u32>("sessions", config)
+                .await
+                .unwrap_err(),
+            TokioActorCacheError::CacheTypeMismatch {
+                name: "sessions".to_string()
+            }
+        );
+
+        let users = registry
+            .get_or_create_unbounded::<u64, String>("users", config)
+            .await
+            .unwrap();
+        assert_eq!(
+            registry
+                .get::<UnboundedHashMapCache<u64, String>>("users")
+                .unwrap()
+                .id(),
+            users.id()
+        );
+        assert_eq!(registry.names(), vec!["sessions", "users"]);
+    }
+
+    #[tokio::test]
+    async fn test_global() {
+        let hm_cache = HashMapCache::<u64, u64>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let name = format!("registry-test-{}", hm_cache.id());
+        CacheRegistry::global().register(name.clone(), hm_cache);
+        assert!(CacheRegistry::global().names().contains(&name));
+        assert!(CacheRegistry::global().unregister(&name));
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use crate::tokio_cache::{
+        bounded::{hm::HashMapCache, hs::HashSetCache, vec::VecCache},
+        option::{ExpirationPolicy, InsertPolicy},
+        runtime::{Runtime, Sleep, Task, Ticks, TokioRuntime},
+        test_utils::advance,
+    };
+
+    /// Tokio underneath, counting what the actors ask of it.
+    #[derive(Debug, Default)]
+    struct CountingRuntime {
+        spawned: AtomicUsize,
+        sleeps: AtomicUsize,
+        intervals: AtomicUsize,
+    }
+
+    impl Runtime for CountingRuntime {
+        fn spawn(&self, task: Task) {
+            self.spawned.fetch_add(1, Ordering::Relaxed);
+            TokioRuntime.spawn(task);
+        }
+
+        fn sleep_until(&self, deadline: Instant) -> Sleep {
+            self.sleeps.fetch_add(1, Ordering::Relaxed);
+            TokioRuntime.sleep_until(deadline)
+        }
+
+        fn interval(&self, period: Duration) -> Ticks {
+            self.intervals.fetch_add(1, Ordering::Relaxed);
+            TokioRuntime.interval(period)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_new_on() {
+        let runtime = Arc::new(CountingRuntime::default());
+        let hm = HashMapCache::<i32, i32>::new_on(runtime.clone(), ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let hs = HashSetCache::<i32>::new_on(runtime.clone(), ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let vec = VecCache::<i32>::new_on(runtime.clone(), ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+
+        // TTLs shorter than a tick run out on the runtime's timer.
+        let ex = Some(Duration::from_millis(10));
+        hm.insert(1, 1, ex, InsertPolicy::Always).await.unwrap();
+        hs.insert(1, ex, InsertPolicy::Always).await.unwrap();
+        vec.push(1, ex, InsertPolicy::Always).await.unwrap();
+        assert_eq!(hm.get(1).await.unwrap(), Some(1));
+        advance(Duration::from_millis(20)).await;
+        assert_eq!(hm.get(1).await.unwrap(), None);
+        assert_eq!(hs.contains(&[1]).await.unwrap(), vec![false]);
+        assert_eq!(vec.contains(&[1]).await.unwrap(), vec![false]);
+
+        assert_eq!(runtime.spawned.load(Ordering::Relaxed), 3);
+        assert_eq!(runtime.intervals.load(Ordering::Relaxed), 3);
+        assert!(runtime.sleeps.load(Ordering::Relaxed) >= 3);
+    }
+}
@@ -0,0 +1,202 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use bytes::Bytes;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache, option::ExpirationPolicy, server::RespServer,
+    };
+
+    #[derive(Debug, PartialEq)]
+    enum Reply {
+        Status(String),
+        Error(String),
+        Integer(i64),
+        Bulk(Option<Vec<u8>>),
+        Array(Vec<Reply>),
+    }
+
+    struct Client(BufReader<TcpStream>);
+
+    impl Client {
+        async fn connect() -> Self {
+            let expiration_policy = ExpirationPolicy::None;
+            let server = RespServer::new(
+                HashMapCache::<Bytes, Bytes>::new(expiration_policy, 32)
+                    .await
+                    .unwrap(),
+                HashMapCache::<Bytes, BTreeSet<Bytes>>::new(expiration_policy, 32)
+                    .await
+                    .unwrap(),
+                HashMapCache::<Bytes, Vec<Bytes>>::new(expiration_policy, 32)
+                    .await
+                    .unwrap(),
+            );
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(server.serve(listener));
+            Self(BufReader::new(TcpStream::connect(addr).await.unwrap()))
+        }
+
+        async fn send_raw(&mut self, bytes: &[u8]) -> Reply {
+            self.0.get_mut().write_all(bytes).await.unwrap();
+            self.read_reply().await
+        }
+
+        async fn cmd(&mut self, args: &[&str]) -> Reply {
+            let mut bytes = format!("*{}\r\n", args.len()).into_bytes();
+            for arg in args {
+                bytes.extend_from_slice(format!("${}\r\n{arg}\r\n", arg.len()).as_bytes());
+            }
+            self.send_raw(&bytes).await
+        }
+
+        async fn read_reply(&mut self) -> Reply {
+            let mut line = String::new();
+            self.0.read_line(&mut line).await.unwrap();
+            let line = line.trim_end_matches("\r\n");
+            let (kind, rest) = line.split_at(1);
+            match kind {
+                "+" => Reply::Status(rest.to_string()),
+                "-" => Reply::Error(rest.to_string()),
+                ":" => Reply::Integer(rest.parse().unwrap()),
+                "$" => match rest.parse::<i64>().unwrap() {
+                    -1 => Reply::Bulk(None),
+                    len => {
+                        let mut bulk = vec![0; len as usize + 2];
+                        self.0.read_exact(&mut bulk).await.unwrap();
+                        bulk.truncate(len as usize);
+                        Reply::Bulk(Some(bulk))
+                    }
+                },
+                "*" => {
+                    let mut replies = Vec::new();
+                    for _ in 0..rest.parse::<usize>().unwrap() {
+                        replies.push(Box::pin(self.read_reply()).await);
+                    }
+                    Reply::Array(replies)
+                }
+                _ => panic!("unexpected reply {line:?}"),
+            }
+        }
+    }
+
+    fn bulk(val: &str) -> Reply {
+        Reply::Bulk(Some(val.as_bytes().to_vec()))
+    }
+
+    fn ok() -> Reply {
+        Reply::Status("OK".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_get_set() {
+        let mut client = Client::connect().await;
+        assert_eq!(client.cmd(&["GET", "a"]).await, Reply::Bulk(None));
+        assert_eq!(client.cmd(&["SET", "a", "1"]).await, ok());
+        assert_eq!(client.cmd(&["get", "a"]).await, bulk("1"));
+
+        assert_eq!(
+            client.cmd(&["SET", "a", "2", "NX"]).await,
+            Reply::Bulk(None)
+        );
+        assert_eq!(
+            client.cmd(&["SET", "b", "2", "XX"]).await,
+            Reply::Bulk(None)
+        );
+        assert_eq!(client.cmd(&["SET", "a", "2", "XX"]).await, ok());
+        assert_eq!(
+            client.cmd(&["MGET", "a", "b"]).await,
+            Reply::Array(vec![bulk("2"), Reply::Bulk(None)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_del_expire_ttl() {
+        let mut client = Client::connect().await;
+        client.cmd(&["SET", "a", "1", "EX", "100"]).await;
+        client.cmd(&["SET", "b", "1"]).await;
+        client.cmd(&["SADD", "c", "x"]).await;
+        assert_eq!(client.cmd(&["TTL", "a"]).await, Reply::Integer(100));
+        assert_eq!(client.cmd(&["TTL", "b"]).await, Reply::Integer(-1));
+        assert_eq!(client.cmd(&["TTL", "d"]).await, Reply::Integer(-2));
+        let in_a_minute = SystemTime::now() + Duration::from_secs(60);
+        let exat = in_a_minute.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        client
+            .cmd(&["SET", "e", "1", "EXAT", &exat.to_string()])
+            .await;
+        let Reply::Integer(ttl) = client.cmd(&["TTL", "e"]).await else {
+            panic!("TTL replies with an integer");
+        };
+        assert!((58..=60).contains(&ttl));
+
+        assert_eq!(client.cmd(&["EXPIRE", "c", "50"]).await, Reply::Integer(1));
+        assert_eq!(client.cmd(&["EXPIRE", "d", "50"]).await, Reply::Integer(0));
+        assert_eq!(client.cmd(&["TTL", "c"]).await, Reply::Integer(50));
+
+        assert_eq!(client.cmd(&["DEL", "a", "c", "d"]).await, Reply::Integer(2));
+        assert_eq!(client.cmd(&["GET", "a"]).await, Reply::Bulk(None));
+        assert_eq!(client.cmd(&["SMEMBERS", "c"]).await, Reply::Array(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_sets_and_lists() {
+        let mut client = Client::connect().await;
+        assert_eq!(
+            client.cmd(&["SADD", "s", "b", "a", "b"]).await,
+            Reply::Integer(2)
+        );
+        assert_eq!(
+            client.cmd(&["SADD", "s", "a", "c"]).await,
+            Reply::Integer(1)
+        );
+        assert_eq!(
+            client.cmd(&["SMEMBERS", "s"]).await,
+            Reply::Array(vec![bulk("a"), bulk("b"), bulk("c")])
+        );
+
+        assert_eq!(
+            client.cmd(&["LPUSH", "l", "a", "b"]).await,
+            Reply::Integer(2)
+        );
+        assert_eq!(client.cmd(&["LPUSH", "l", "c"]).await, Reply::Integer(3));
+        assert_eq!(
+            client.cmd(&["LRANGE", "l", "0", "-1"]).await,
+            Reply::Array(vec![bulk("c"), bulk("b"), bulk("a")])
+        );
+        assert_eq!(
+            client.cmd(&["LRANGE", "l", "-2", "10"]).await,
+            Reply::Array(vec![bulk("b"), bulk("a")])
+        );
+        assert_eq!(
+            client.cmd(&["LRANGE", "l", "2", "1"]).await,
+            Reply::Array(vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let mut client = Client::connect().await;
+        assert_eq!(
+            client.cmd(&["GET"]).await,
+            Reply::Error("ERR wrong number of arguments for 'get' command".to_string())
+        );
+        assert_eq!(
+            client.cmd(&["SET", "a", "1", "EX", "x"]).await,
+            Reply::Error("ERR value is not an integer or out of range".to_string())
+        );
+        assert_eq!(
+            client.cmd(&["FLUSHALL"]).await,
+            Reply::Error("ERR unknown command 'FLUSHALL'".to_string())
+        );
+        // Still usable after command errors.
+        assert_eq!(client.cmd(&["SET", "a", "1"]).await, ok());
+
+        let reply = client.send_raw(b"GET a\r\n").await;
+        assert!(matches!(reply, Reply::Error(err) if err.starts_with("ERR Protocol error")));
+    }
+}
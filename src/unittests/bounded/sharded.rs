@@ -0,0 +1,305 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use crate::tokio_cache::{
+        bounded::sharded::ShardedHashMapCache,
+        error::TokioActorCacheError,
+        option::{ExpirationPolicy, InsertOptions, InsertPolicy},
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct UserId(u32);
+
+    #[tokio::test(start_paused = true)]
+    async fn test_keys_without_display() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        for i in 0..100 {
+            sharded
+                .insert(UserId(i), i * 10, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(sharded.get(UserId(42)).await.unwrap(), Some(420));
+        assert_eq!(sharded.get_all().await.unwrap().len(), 100);
+        // Every shard holds some of the keys.
+        for shard in sharded.shards() {
+            assert!(!shard.get_all().await.unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mget_keeps_key_order() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        sharded
+            .minsert(
+                &["a", "b", "c", "d"],
+                &[10, 20, 30, 40],
+                &[None; 4],
+                &[InsertPolicy::Always; 4],
+            )
+            .await
+            .unwrap();
+        let vals = sharded.mget(&["d", "x", "b", "a", "c"]).await.unwrap();
+        assert_eq!(vals, vec![Some(40), None, Some(20), Some(10), Some(30)]);
+        let is_contains_keys = sharded.try_contains_key(&["x", "c"]).await.unwrap();
+        assert_eq!(is_contains_keys, vec![false, true]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_sync() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        for (key, val) in [("a", 10), ("b", 20)] {
+            sharded
+                .insert_sync(key, val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            sharded.mget(&["a", "b"]).await.unwrap(),
+            vec![Some(10), Some(20)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_remove_and_clear() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        sharded
+            .minsert(
+                &["a", "b", "c"],
+                &[10, 20, 30],
+                &[None; 3],
+                &[InsertPolicy::Always; 3],
+            )
+            .await
+            .unwrap();
+        let vals = sharded.try_remove(&["c", "d", "a"]).await.unwrap();
+        assert_eq!(vals, vec![Some(30), None, Some(10)]);
+        assert_eq!(sharded.get_all().await.unwrap().len(), 1);
+        sharded.clear().await.unwrap();
+        assert!(sharded.get_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_and_expire() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        sharded
+            .insert("a", 10, Some(Duration::from_secs(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        sharded
+            .insert("b", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let ttl = sharded.ttl(&["a", "b", "c"]).await.unwrap();
+        assert!(ttl[0].is_some_and(|ttl| ttl <= Duration::from_secs(1)));
+        assert_eq!(ttl[1..], [None, None]);
+
+        assert!(sharded.expire("a", None).await.unwrap());
+        assert!(!sharded.expire("c", None).await.unwrap());
+        assert_eq!(sharded.ttl(&["a"]).await.unwrap(), vec![None]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_and_update_value() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        let options = InsertOptions {
+            get: true,
+            ..Default::default()
+        };
+        let key = String::from("a");
+        assert_eq!(sharded.set(key.clone(), 10, options).await.unwrap(), None);
+        assert_eq!(
+            sharded.set(key.clone(), 20, options).await.unwrap(),
+            Some(10)
+        );
+        assert!(sharded.update_value(key, |val| *val += 1).await.unwrap());
+        assert_eq!(sharded.get_borrowed("a").await.unwrap(), Some(21));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_capacity_is_shared() {
+        let expiration_policy = ExpirationPolicy::LRU(8);
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        for i in 0..100 {
+            sharded
+                .insert(i, i, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        // Evictions run on the tick.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(sharded.get_all().await.unwrap().len() <= 8);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hot_keys() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        for key in ["a", "b", "c", "d"] {
+            sharded
+                .insert(key, 0, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        for (key, n_get) in [("a", 1), ("b", 4), ("c", 2), ("d", 3)] {
+            for _ in 0..n_get {
+                sharded.get(key).await.unwrap();
+            }
+        }
+        let hot_keys: Vec<_> = sharded
+            .hot_keys(2)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(hot_keys, vec!["b", "d"]);
+    }
+
+    #[tokio::test]
+    async fn test_insert_if_version() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        for key in 0..8 {
+            let version = sharded.insert_if_version(key, key, 0).await.unwrap();
+            assert!(version.is_some());
+            assert_eq!(sharded.insert_if_version(key, -key, 0).await.unwrap(), None);
+            let (val, current) = sharded.try_get_versioned(key).await.unwrap().unwrap();
+            assert_eq!((val, Some(current)), (key, version));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pin() {
+        let expiration_policy = ExpirationPolicy::LRU(4);
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 2)
+            .await
+            .unwrap();
+        sharded
+            .insert(0, 0, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        sharded
+            .insert(1, 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(
+            sharded.pin(&[1, 2, 0]).await.unwrap(),
+            vec![true, false, true]
+        );
+
+        sharded.set_capacity(0).await.unwrap();
+        let all = sharded.get_all().await.unwrap();
+        assert_eq!(all, HashMap::from([(0, 0), (1, 1)]));
+        sharded.try_unpin(&[0, 1]).await.unwrap();
+        sharded.set_capacity(0).await.unwrap();
+        assert!(sharded.get_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_capacity() {
+        let expiration_policy = ExpirationPolicy::LRU(32);
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        assert_eq!(sharded.policy().await.unwrap(), expiration_policy);
+        for key in 0..32 {
+            sharded
+                .insert(key, key, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        assert!(sharded.set_capacity(8).await.unwrap());
+        assert_eq!(sharded.policy().await.unwrap(), ExpirationPolicy::LRU(8));
+        assert!(sharded.get_all().await.unwrap().len() <= 8);
+
+        sharded
+            .try_set_policy(ExpirationPolicy::None)
+            .await
+            .unwrap();
+        assert_eq!(sharded.try_policy().await.unwrap(), ExpirationPolicy::None);
+        assert!(!sharded.set_capacity(8).await.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_histogram() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 32, 4)
+            .await
+            .unwrap();
+        for (key, secs) in [("a", 10), ("b", 20), ("c", 30), ("d", 40)] {
+            sharded
+                .insert(
+                    key,
+                    0,
+                    Some(Duration::from_secs(secs)),
+                    InsertPolicy::Always,
+                )
+                .await
+                .unwrap();
+        }
+        sharded
+            .insert("e", 0, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let histogram = sharded.ttl_histogram().await.unwrap();
+        assert_eq!(histogram.expiring(), 4);
+        assert_eq!(histogram.persistent(), 1);
+        let within = sharded
+            .expiring_within(Duration::from_secs(25))
+            .await
+            .unwrap();
+        assert_eq!(within, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_channel_full() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 1, 1)
+            .await
+            .unwrap();
+        sharded
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(sharded.queue_depth(), 1);
+        let res = sharded
+            .try_insert("b", 20, None, InsertPolicy::Always)
+            .await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::ChannelFull {
+                len: 1,
+                capacity: 1
+            })
+        );
+    }
+}
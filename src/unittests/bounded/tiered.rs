@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tokio_cache::{
+        bounded::hm::HashMapCache,
+        option::{ExpirationPolicy, InsertPolicy},
+        test_utils::TICK,
+        tiered::TieredCache,
+    };
+
+    async fn n_spilled(cache: &TieredCache<String, u64, impl Sized>) -> usize {
+        let mut entries = tokio::fs::read_dir(cache.dir()).await.unwrap();
+        let mut n = 0;
+        while entries.next_entry().await.unwrap().is_some() {
+            n += 1;
+        }
+        n
+    }
+
+    /// Spilling runs in the background, so wait for it.
+    async fn wait_spilled(cache: &TieredCache<String, u64, impl Sized>, n: usize) {
+        for _ in 0..100 {
+            if n_spilled(cache).await == n {
+                return;
+            }
+            tokio::time::sleep(TICK / 10).await;
+        }
+        panic!(
+            "expected {n} spilled entries, found {}",
+            n_spilled(cache).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spill_and_promote() {
+        let memory = HashMapCache::<String, u64>::new(ExpirationPolicy::LRU(2), 32)
+            .await
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("bounded-tiered-{}", memory.id()));
+        // Left over by a failed run.
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        let tiered = TieredCache::new(memory, &dir).await.unwrap();
+        for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            tiered
+                .insert(key.to_string(), i as u64, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        wait_spilled(&tiered, 2).await;
+        assert_eq!(tiered.memory().get("a".to_string()).await.unwrap(), None);
+
+        // Read back from disk and moved into memory, evicting another entry in turn.
+        assert_eq!(tiered.get("a".to_string()).await.unwrap(), Some(0));
+        assert_eq!(tiered.memory().get("a".to_string()).await.unwrap(), Some(0));
+        wait_spilled(&tiered, 2).await;
+        assert_eq!(tiered.get("b".to_string()).await.unwrap(), Some(1));
+
+        assert_eq!(
+            tiered
+                .remove(&["a".to_string(), "x".to_string()])
+                .await
+                .unwrap(),
+            vec![Some(0), None]
+        );
+        assert_eq!(tiered.get("a".to_string()).await.unwrap(), None);
+        tiered.clear().await.unwrap();
+        assert_eq!(n_spilled(&tiered).await, 0);
+        assert_eq!(tiered.get("c".to_string()).await.unwrap(), None);
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disk_ttl() {
+        let memory = HashMapCache::<String, u64>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("bounded-tiered-ttl-{}", memory.id()));
+        // Left over by a failed run.
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        let tiered = TieredCache::with_disk_ttl(memory, &dir, Some(Duration::ZERO))
+            .await
+            .unwrap();
+        tiered
+            .insert("a".to_string(), 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        // Entries evicted by `shrink` spill too.
+        assert_eq!(tiered.memory().shrink(0).await.unwrap(), 1);
+        wait_spilled(&tiered, 1).await;
+
+        assert_eq!(tiered.get("a".to_string()).await.unwrap(), None);
+        assert_eq!(n_spilled(&tiered).await, 0);
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -3,16 +3,23 @@ mod tests {
 
     use std::time::Duration;
 
-    use crate::tokio_cache::{bounded::vec::VecCache, option::ExpirationPolicy};
+    use crate::tokio_cache::{
+        bounded::vec::VecCache,
+        option::{ExpirationPolicy, InsertPolicy, RemovePolicy, VecEnd},
+        test_utils::{TICK, advance},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -27,14 +34,17 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -48,7 +58,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm_cluster1.push(10, None, false).await.unwrap();
+        hm_cluster1
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -57,14 +70,17 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
         hm_cluster2.try_replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -75,14 +91,17 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -97,14 +116,17 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -118,7 +140,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm_cluster1.push(10, None, false).await.unwrap();
+        hm_cluster1
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -127,14 +152,17 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy, 32).await.unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -145,56 +173,77 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
-            .push(10, Some(Duration::from_secs(1)), false)
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = vec_cache.try_ttl(&[10, 20]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
-        vec_cache.push(30, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = vec_cache.get_all().await.unwrap();
         assert_eq!(hs, Vec::from([10, 20, 30]));
         vec_cache.try_clear().await.unwrap();
         let hs = vec_cache.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        let val = vec_cache.try_remove(&[10, 20]).await.unwrap();
-        assert_eq!(val, vec![true, false]);
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = vec_cache
+            .try_remove(&[10, 20], RemovePolicy::All)
+            .await
+            .unwrap();
+        assert_eq!(val, vec![1, 0]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cache.try_contains(&[10, 20, 30]).await.unwrap();
         assert_eq!(val, vec![true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_mpush_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
             .try_mpush(
                 &[10, 20, 30],
@@ -203,34 +252,41 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
         let val = vec_cache.get_all().await.unwrap();
-        assert_eq!(val, Vec::new());
+        assert_eq!(val, Vec::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_mpush() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
-            .try_mpush(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .try_mpush(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = vec_cache.get_all().await.unwrap();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_push_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.try_push(10, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
-            .try_push(20, Some(Duration::from_secs(1)), false)
+            .try_push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .try_push(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -238,67 +294,159 @@ mod tests {
         assert_eq!(val, Vec::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_push() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.try_push(10, None, false).await.unwrap();
-        vec_cache.try_push(20, None, false).await.unwrap();
-        vec_cache.try_push(30, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .try_push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .try_push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .try_push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cache.get_all().await.unwrap();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
-            .push(10, Some(Duration::from_secs(1)), false)
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = vec_cache.ttl(&[10, 20]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_multiple_elements() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        for (val, ex) in [
+            (10, Some(1)),
+            (20, None),
+            (30, Some(3)),
+            (10, Some(5)),
+            (40, None),
+        ] {
+            vec_cache
+                .push(val, ex.map(Duration::from_secs), InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        // One result per requested value, from its first occurrence.
+        let ttl = vec_cache.ttl(&[10, 30]).await.unwrap();
+        assert_eq!(
+            ttl,
+            vec![Some(Duration::from_secs(1)), Some(Duration::from_secs(3))]
+        );
+        let ttl = vec_cache.ttl(&[50, 10, 40]).await.unwrap();
+        assert_eq!(ttl, vec![None, Some(Duration::from_secs(1)), None]);
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
-        vec_cache.push(30, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = vec_cache.get_all().await.unwrap();
         assert_eq!(hs, Vec::from([10, 20, 30]));
         vec_cache.clear().await.unwrap();
         let hs = vec_cache.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        let val = vec_cache.remove(&[10, 20]).await.unwrap();
-        assert_eq!(val, vec![true, false]);
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = vec_cache
+            .remove(&[10, 20], RemovePolicy::All)
+            .await
+            .unwrap();
+        assert_eq!(val, vec![1, 0]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_remove_deletes_elements() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        let replica = VecCache::new(expiration_policy, 32).await.unwrap();
+        replica.replicate(&vec_cache).await.unwrap();
+        for val in [10, 20, 10, 30, 10, 20] {
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let n_removed = vec_cache
+            .remove(&[10, 40], RemovePolicy::First)
+            .await
+            .unwrap();
+        assert_eq!(n_removed, vec![1, 0]);
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![20, 10, 30, 10, 20]);
+
+        let n_removed = vec_cache
+            .remove(&[10, 20, 10], RemovePolicy::All)
+            .await
+            .unwrap();
+        assert_eq!(n_removed, vec![2, 2, 2]);
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![30]);
+        assert_eq!(
+            vec_cache.contains(&[10, 20]).await.unwrap(),
+            vec![false, false]
+        );
+
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(replica.get_all().await.unwrap(), vec![30]);
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cache.contains(&[10, 20, 30]).await.unwrap();
         assert_eq!(val, vec![true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
             .mpush(
                 &[10, 20, 30],
@@ -307,34 +455,41 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
         let val = vec_cache.get_all().await.unwrap();
-        assert_eq!(val, Vec::new());
+        assert_eq!(val, Vec::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
-            .mpush(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .mpush(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = vec_cache.get_all().await.unwrap();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
         vec_cache
-            .push(20, Some(Duration::from_secs(1)), false)
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -342,14 +497,252 @@ mod tests {
         assert_eq!(val, Vec::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy, 32).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
-        vec_cache.push(30, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cache.get_all().await.unwrap();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert!(vec_cache.get_all().await.unwrap().is_empty());
+
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![10]);
+
+        vec_cache
+            .push(10, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![10, 20]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy_if_present_updates_in_place() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        let ex = Some(Duration::from_millis(10));
+        vec_cache.push(10, ex, InsertPolicy::Always).await.unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![10, 20]);
+
+        // Kept where it was, with the new TTL.
+        advance(TICK).await;
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![10, 20]);
+        assert!(vec_cache.ttl(&[10]).await.unwrap()[0].is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy_skips_expired() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_cleanup_budget(1);
+        let vals = (0..10).collect::<Vec<_>>();
+        let ex = [Some(Duration::from_millis(10)); 10];
+        vec_cache
+            .mpush(&vals, &ex, &[InsertPolicy::Always; 10])
+            .await
+            .unwrap();
+
+        // Past their TTL, though the last of them are yet to be removed.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        vec_cache
+            .push(9, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        vec_cache
+            .push(8, Some(Duration::from_secs(1)), InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+
+        advance(TICK * 12).await;
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![9]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_positional_ops() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        let replica = VecCache::new(expiration_policy, 32).await.unwrap();
+        replica.replicate(&vec_cache).await.unwrap();
+        assert_eq!(vec_cache.pop_front().await.unwrap(), None);
+        assert_eq!(vec_cache.try_pop_back().await.unwrap(), None);
+        for val in [1, 2, 3, 4, 5] {
+            vec_cache
+                .push(val, Some(Duration::from_secs(10)), InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(vec_cache.pop_front().await.unwrap(), Some(1));
+        assert_eq!(vec_cache.pop_back().await.unwrap(), Some(5));
+        assert_eq!(vec_cache.get_index(1).await.unwrap(), Some(3));
+        assert_eq!(vec_cache.try_get_index(3).await.unwrap(), None);
+        assert!(vec_cache.set_index(1, 30).await.unwrap());
+        assert!(!vec_cache.try_set_index(3, 40).await.unwrap());
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![2, 30, 4]);
+        // The overwritten element keeps its TTL.
+        assert_eq!(
+            vec_cache.ttl(&[30]).await.unwrap(),
+            vec![Some(Duration::from_secs(10))]
+        );
+
+        vec_cache.truncate(1).await.unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![2]);
+        vec_cache.try_truncate(5).await.unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![2]);
+
+        vec_cache.push(6, None, InsertPolicy::Always).await.unwrap();
+        assert!(vec_cache.set_index(1, 7).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(replica.get_all().await.unwrap(), vec![2, 7]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lru_evicts_in_order_of_use() {
+        let expiration_policy = ExpirationPolicy::LRU(3);
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        for val in [1, 2, 3, 4] {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![2, 3, 4]);
+
+        // Still the least recently used once the elements before them have shifted.
+        assert_eq!(vec_cache.pop_front().await.unwrap(), Some(2));
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        vec_cache.contains(&[3]).await.unwrap();
+        for val in [5, 6] {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![3, 5, 6]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_len_trims_front() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy, 32).await.unwrap();
+        vec_cache.set_max_len(Some(3), VecEnd::Front).await.unwrap();
+        for val in 1..=5 {
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![3, 4, 5]);
+
+        // Lowering it trims right away; lifting it lets the vec grow again.
+        vec_cache
+            .try_set_max_len(Some(2), VecEnd::Front)
+            .await
+            .unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![4, 5]);
+        vec_cache.set_max_len(None, VecEnd::Front).await.unwrap();
+        vec_cache.push(6, None, InsertPolicy::Always).await.unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![4, 5, 6]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_budget() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::<i32>::new(expiration_policy, 32)
+            .await
+            .unwrap()
+            .with_cleanup_budget(3);
+        let vals = (0..20).collect::<Vec<_>>();
+        let ex = [Some(Duration::from_secs(1)); 20];
+        vec_cache
+            .mpush(&vals, &ex, &[InsertPolicy::Always; 20])
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        // A few passes of 3 each so far, yet none of the expired vals are found.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(vec_cache.contains(&vals).await.unwrap(), vec![false; 20]);
+        assert_eq!(vec_cache.ttl(&[0]).await.unwrap(), vec![None]);
+        assert!(vec_cache.get_all().await.unwrap().len() > 1);
+
+        advance(TICK * 8).await;
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![20]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_budget_evictions() {
+        let master = VecCache::<i32>::new(ExpirationPolicy::None, 32)
+            .await
+            .unwrap();
+        let replica = VecCache::<i32>::new(ExpirationPolicy::LRU(2), 32)
+            .await
+            .unwrap()
+            .with_cleanup_budget(3);
+        replica.replicate(&master).await.unwrap();
+        let vals = (0..8).collect::<Vec<_>>();
+        master
+            .mpush(&vals, &[None; 8], &[InsertPolicy::Always; 8])
+            .await
+            .unwrap();
+        advance(TICK).await;
+        assert_eq!(replica.get_all().await.unwrap().len(), 8);
+
+        // Left to evict on its own, the former replica does so 3 a tick, looked at twice a tick.
+        replica.stop_replicating().await.unwrap();
+        let mut lens = vec![8];
+        for _ in 0..8 {
+            advance(TICK / 2).await;
+            lens.push(replica.get_all().await.unwrap().len());
+        }
+        lens.dedup();
+        assert_eq!(lens, vec![8, 5, 2]);
+    }
 }
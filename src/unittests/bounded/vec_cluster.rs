@@ -3,59 +3,97 @@ mod tests {
 
     use std::time::Duration;
 
-    use crate::tokio_cache::{bounded::vec_cluster::VecCacheCluster, option::ExpirationPolicy};
+    use crate::tokio_cache::{
+        bounded::vec_cluster::VecCacheCluster,
+        error::TokioActorCacheError,
+        option::{ClusterConfig, ExpirationPolicy, InsertPolicy, NodeConfig, RemovePolicy},
+        rebalance::RebalanceProgress,
+        router::{KeyRouter, WeightedRouter},
+        test_utils::{TICK, advance},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
-            .push(10, Some(Duration::from_secs(1)), false)
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = vec_cluster.try_ttl(&[10, 20]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
-        vec_cluster.push(30, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let mut vec = vec_cluster.get_all().await.unwrap();
         vec.sort();
         assert_eq!(vec, Vec::from([10, 20, 30]));
         vec_cluster.try_clear().await.unwrap();
         let hs = vec_cluster.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        let val = vec_cluster.try_remove(&[10, 20]).await.unwrap();
-        assert_eq!(val, vec![true, false]);
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = vec_cluster
+            .try_remove(&[10, 20], RemovePolicy::All)
+            .await
+            .unwrap();
+        assert_eq!(val, vec![1, 0]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cluster.try_contains(&[10, 20, 30]).await.unwrap();
         assert_eq!(val, vec![true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_mpush_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
             .try_mpush(
                 &[10, 20, 30],
@@ -64,21 +102,27 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
         let val = vec_cluster.get_all().await.unwrap();
-        assert_eq!(val, Vec::new());
+        assert_eq!(val, Vec::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_mpush() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
-            .try_mpush(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .try_mpush(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let mut val = vec_cluster.get_all().await.unwrap();
@@ -86,13 +130,18 @@ mod tests {
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_push_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.try_push(10, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
-            .try_push(20, Some(Duration::from_secs(1)), false)
+            .try_push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .try_push(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -100,22 +149,35 @@ mod tests {
         assert_eq!(val, Vec::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_try_push() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.try_push(10, None, false).await.unwrap();
-        vec_cluster.try_push(20, None, false).await.unwrap();
-        vec_cluster.try_push(30, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .try_push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .try_push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .try_push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let mut val = vec_cluster.get_all().await.unwrap();
         val.sort();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_hash_id() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         let vals = vec![
             "a".to_string(),
             "b".to_string(),
@@ -126,7 +188,10 @@ mod tests {
             "g".to_string(),
         ];
         for v in vals.clone() {
-            vec_cluster.push(v.clone(), None, false).await.unwrap();
+            vec_cluster
+                .push(v.clone(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
         }
 
         let mut vec = vec_cluster.get_all().await.unwrap();
@@ -134,57 +199,88 @@ mod tests {
         assert_eq!(vec, vals);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
-            .push(10, Some(Duration::from_secs(1)), false)
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = vec_cluster.ttl(&[10, 20]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
-        vec_cluster.push(30, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let mut vec = vec_cluster.get_all().await.unwrap();
         vec.sort();
         assert_eq!(vec, Vec::from([10, 20, 30]));
         vec_cluster.clear().await.unwrap();
         let hs = vec_cluster.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        let val = vec_cluster.remove(&[10, 20]).await.unwrap();
-        assert_eq!(val, vec![true, false]);
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = vec_cluster
+            .remove(&[10, 20], RemovePolicy::All)
+            .await
+            .unwrap();
+        assert_eq!(val, vec![1, 0]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cluster.contains(&[10, 20, 30]).await.unwrap();
         assert_eq!(val, vec![true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
             .mpush(
                 &[10, 20, 30],
@@ -193,21 +289,27 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
         let val = vec_cluster.get_all().await.unwrap();
-        assert_eq!(val, Vec::new());
+        assert_eq!(val, Vec::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
-            .mpush(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .mpush(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let mut val = vec_cluster.get_all().await.unwrap();
@@ -215,13 +317,18 @@ mod tests {
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
         vec_cluster
-            .push(20, Some(Duration::from_secs(1)), false)
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -229,15 +336,151 @@ mod tests {
         assert_eq!(val, Vec::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
-        vec_cluster.push(30, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let mut val = vec_cluster.get_all().await.unwrap();
         val.sort();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_keyed_by_val() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(40, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let ttl = vec_cluster.ttl(&[20, 10, 30, 40]).await.unwrap();
+        let vals = ttl.iter().map(|(val, _)| *val).collect::<Vec<_>>();
+        assert_eq!(vals, vec![20, 10, 30, 40]);
+        assert_eq!(ttl[0].1, None);
+        assert!(ttl[1].1.is_some());
+        assert_eq!(ttl[2].1, None);
+        assert_eq!(ttl[3].1, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_with_config() {
+        // Node 0 evicts down to one value, the others keep all of theirs.
+        let config = ClusterConfig::new(ExpirationPolicy::None, 2).with_node(
+            0,
+            NodeConfig {
+                expiration_policy: Some(ExpirationPolicy::LRU(1)),
+                buffer: Some(1),
+            },
+        );
+        let vec_cluster = VecCacheCluster::<i32>::with_config(config).await.unwrap();
+        for node in [&vec_cluster.nodes[&0], &vec_cluster.nodes[&1]] {
+            for val in [1, 2] {
+                node.push(val, None, InsertPolicy::Always).await.unwrap();
+            }
+        }
+        advance(TICK).await;
+        assert_eq!(vec_cluster.nodes[&0].get_all().await.unwrap().len(), 1);
+        assert_eq!(vec_cluster.nodes[&1].get_all().await.unwrap().len(), 2);
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct LastNode;
+
+    impl KeyRouter<i32> for LastNode {
+        fn route(&self, _val: &i32, n_node: u64) -> u64 {
+            n_node - 1
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rebalance() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 32, 3)
+            .await
+            .unwrap();
+        for val in [0, 1, 2, 3, 4, 5, 0, 1] {
+            vec_cluster
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let misplaced = vec_cluster.nodes[&0].get_all().await.unwrap().len()
+            + vec_cluster.nodes[&1].get_all().await.unwrap().len();
+
+        let vec_cluster = vec_cluster.with_router(LastNode);
+        let mut progress = vec_cluster.rebalance();
+        let progress = progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap()
+            .clone();
+        assert_eq!(
+            progress,
+            RebalanceProgress {
+                moved: misplaced,
+                remaining: 0,
+                finished: Some(Ok(())),
+            }
+        );
+        let mut vec = vec_cluster.nodes[&2].get_all().await.unwrap();
+        vec.sort();
+        assert_eq!(vec, vec![0, 0, 1, 1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_node_weight() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cluster = VecCacheCluster::<u64>::new(expiration_policy, 32, 3)
+            .await
+            .unwrap()
+            .with_router(WeightedRouter::default());
+        let vals = (0..300).collect::<Vec<u64>>();
+        vec_cluster
+            .mpush(&vals, &[None; 300], &[InsertPolicy::Always; 300])
+            .await
+            .unwrap();
+        let before = vec_cluster.nodes[&0].get_all().await.unwrap();
+
+        assert_eq!(
+            vec_cluster.set_node_weight(3, 2).err(),
+            Some(TokioActorCacheError::NodeNotExists { id: 3 })
+        );
+        let mut progress = vec_cluster.set_node_weight(0, 2).unwrap();
+        let progress = progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap()
+            .clone();
+        assert_eq!(progress.finished, Some(Ok(())));
+
+        // Node 0 takes about half of the values, keeping its own.
+        let on_0 = vec_cluster.nodes[&0].get_all().await.unwrap();
+        assert!((120..180).contains(&on_0.len()));
+        assert!(before.iter().all(|val| on_0.contains(val)));
+        assert_eq!(progress.moved, on_0.len() - before.len());
+        let mut all = vec_cluster.get_all().await.unwrap();
+        all.sort();
+        assert_eq!(all, vals);
+    }
 }
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use tokio::task::LocalSet;
+
+    use crate::tokio_cache::{
+        local::hm::HashMapCache,
+        option::{ExpirationPolicy, InsertPolicy},
+        test_utils::{TICK, advance},
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rc_values() {
+        LocalSet::new()
+            .run_until(async {
+                let expiration_policy = ExpirationPolicy::LRU(2);
+                let hm_cache = HashMapCache::<Rc<str>, Rc<RefCell<i32>>>::new(expiration_policy)
+                    .await
+                    .unwrap();
+                let a = Rc::new(RefCell::new(1));
+                hm_cache
+                    .insert("a".into(), a.clone(), None, InsertPolicy::Always)
+                    .await
+                    .unwrap();
+                // Values are shared with the actor, not copied.
+                *a.borrow_mut() += 1;
+                let val = hm_cache.get("a".into()).await.unwrap().unwrap();
+                assert!(Rc::ptr_eq(&val, &a));
+                assert_eq!(*val.borrow(), 2);
+
+                hm_cache
+                    .insert(
+                        "b".into(),
+                        Rc::new(RefCell::new(3)),
+                        Some(Duration::from_secs(1)),
+                        InsertPolicy::Always,
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(hm_cache.get_all().await.unwrap().len(), 2);
+                advance(Duration::from_secs(1) + TICK).await;
+                assert_eq!(hm_cache.get("b".into()).await.unwrap(), None);
+
+                let replies = hm_cache
+                    .transaction(|txn| {
+                        txn.remove(vec!["a".into()]).get("a".into());
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(replies.len(), 2);
+                assert_eq!(hm_cache.get_all().await.unwrap().len(), 0);
+            })
+            .await;
+    }
+}
@@ -1,40 +1,77 @@
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, time::Duration};
+    use std::{collections::HashMap, sync::Arc, time::Duration};
 
-    use crate::tokio_cache::{option::ExpirationPolicy, unbounded::hm::HashMapCache};
+    use crate::tokio_cache::{
+        error::TokioActorCacheError,
+        near::NearCache,
+        option::{
+            ExpirationPolicy, InsertEntry, InsertOptions, InsertPolicy, ReplicaWrites, ShedPolicy,
+        },
+        pipeline::HashMapReply,
+        removal::{Eviction, RemovalReason},
+        replication::Role,
+        unbounded::hm::{ArcHashMapCache, HashMapCache},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_expiration_policy_lru() {
         let expiration_policy = ExpirationPolicy::LRU(1);
-        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy).await;
-        hm_cache.insert("a", 1, None, false).await.unwrap();
-        hm_cache.insert("b", 1, None, false).await.unwrap();
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        // Time is paused, so let some pass for the entries to differ in recency.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        hm_cache
+            .insert("b", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         tokio::time::sleep(Duration::from_secs(1)).await;
         let hm = hm_cache.get_all().await.unwrap();
         assert_eq!(HashMap::from([("b", 1)]), hm);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_expiration_policy_lfu() {
         let expiration_policy = ExpirationPolicy::LFU(1);
-        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy).await;
-        hm_cache.insert("a", 1, None, false).await.unwrap();
-        hm_cache.insert("a", 1, None, false).await.unwrap();
-        hm_cache.insert("b", 1, None, false).await.unwrap();
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         tokio::time::sleep(Duration::from_secs(1)).await;
         let hm = hm_cache.get_all().await.unwrap();
         assert_eq!(HashMap::from([("a", 1)]), hm);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy).await;
-        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy).await;
+        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
         hm2.replicate(&hm1).await.unwrap();
 
-        hm1.insert("a", 1, None, false).await.unwrap();
+        hm1.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm1.get("a").await.unwrap();
 
@@ -49,14 +86,20 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy).await;
-        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy).await;
+        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
         hm2.replicate(&hm1).await.unwrap();
 
-        hm1.insert("a", 1, None, false).await.unwrap();
+        hm1.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm1.get("a").await.unwrap();
 
@@ -70,7 +113,9 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm1.insert("a", 10, None, false).await.unwrap();
+        hm1.insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm1.get("a").await.unwrap();
 
@@ -79,14 +124,20 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy).await;
-        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy).await;
+        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
         hm2.replicate(&hm1).await.unwrap();
 
-        hm1.insert("a", 1, None, false).await.unwrap();
+        hm1.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm1.get("a").await.unwrap();
 
@@ -97,43 +148,115 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_queue_depth() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+
+        // The actor cannot drain the channel until this task yields.
+        for (key, val) in [("a", 1), ("b", 2), ("c", 3)] {
+            hm_cache
+                .insert(key, val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(hm_cache.queue_depth(), 3);
+
+        hm_cache.get("a").await.unwrap();
+        assert_eq!(hm_cache.queue_depth(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_lane() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy)
+            .await
+            .unwrap()
+            .with_read_lane();
+        // Answered once the actor has picked up the lane.
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        // The write is still queued when the read arrives, and the read is served first.
+        hm_cache
+            .try_insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        // `get_all` stays on the main channel, behind the write.
+        assert_eq!(hm_cache.get_all().await.unwrap().get("a"), Some(&10));
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(10));
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
         hm_cache
-            .insert("a", 10, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hm_cache.ttl(&["a", "b"]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_expire() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert!(
+            hm_cache
+                .expire("a", Some(Duration::from_secs(10)))
+                .await
+                .unwrap()
+        );
+        assert!(!hm_cache.expire("b", None).await.unwrap());
+        let ttl = hm_cache.ttl(&["a"]).await.unwrap();
+        assert!(ttl[0] > Some(Duration::from_secs(9)));
+
+        assert!(hm_cache.expire("a", None).await.unwrap());
+        assert_eq!(hm_cache.ttl(&["a"]).await.unwrap(), vec![None]);
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(10));
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
-        hm_cache.insert("b", 12, None, false).await.unwrap();
-        hm_cache.insert("c", 20, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 12, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("c", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hm = hm_cache.get_all().await.unwrap();
-        assert_eq!(!hm.is_empty(), true);
+        assert!(!hm.is_empty());
         hm_cache.clear().await.unwrap();
         let hm = hm_cache.get_all().await.unwrap();
-        assert_eq!(hm.is_empty(), true);
+        assert!(hm.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mget() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -141,16 +264,16 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -158,16 +281,16 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains_keys() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -175,17 +298,20 @@ mod tests {
         assert_eq!(is_contains_keys, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -193,17 +319,20 @@ mod tests {
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -211,10 +340,10 @@ mod tests {
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
@@ -224,7 +353,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -237,16 +366,16 @@ mod tests {
         assert_eq!(val_c, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
         hm_cache
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -258,48 +387,63 @@ mod tests {
         assert_eq!(val_c, Some(30));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_inconsistent_len() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
         let res = hm_cache
             .minsert(
                 &["a", "b"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await;
         assert!(res.is_err());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
-        hm_cache.insert("a", 20, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
-        hm_cache.insert("a", 20, None, true).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 20, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cache
-            .insert("b", 20, Some(Duration::from_secs(1)), false)
+            .insert("b", 20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -309,12 +453,805 @@ mod tests {
         assert_eq!(val_b, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cache = HashMapCache::new(expiration_policy).await;
-        hm_cache.insert("a", 10, None, false).await.unwrap();
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cache.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pipeline() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let mut pipeline = hm_cache.pipeline();
+        pipeline
+            .insert("a", 1, None, InsertPolicy::Always)
+            .insert("b", 2, None, InsertPolicy::Always)
+            .get("a")
+            .remove(vec!["b"])
+            .contains_key(vec!["a", "b"]);
+        let replies = pipeline.execute().await.unwrap();
+        assert_eq!(replies.len(), 5);
+        assert!(matches!(replies[0], HashMapReply::Done));
+        assert!(matches!(replies[1], HashMapReply::Done));
+        assert!(matches!(replies[2], HashMapReply::Get(Some(1))));
+        assert!(matches!(&replies[3], HashMapReply::Remove(vals) if vals == &vec![Some(2)]));
+        assert!(matches!(
+            &replies[4],
+            HashMapReply::ContainsKey(flags) if flags == &vec![true, false]
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_empty_pipeline() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replies = hm_cache.pipeline().execute().await.unwrap();
+        assert!(replies.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_arc_values_are_shared() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = ArcHashMapCache::<&str, Vec<u8>>::new(expiration_policy)
+            .await
+            .unwrap();
+        let blob = Arc::new(vec![0u8; 1024]);
+        hm_cache
+            .insert("a", Arc::clone(&blob), None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = hm_cache.get("a").await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&blob, &val));
+        let vals = hm_cache.mget(&["a"]).await.unwrap();
+        assert!(Arc::ptr_eq(&blob, vals[0].as_ref().unwrap()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_borrowed_key_lookups() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<String, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a".to_string(), 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get_borrowed("a").await.unwrap(), Some(1));
+        assert_eq!(
+            hm_cache.contains_key_borrowed(&["a", "b"]).await.unwrap(),
+            vec![true, false]
+        );
+        assert_eq!(
+            hm_cache.remove_borrowed(&["a"]).await.unwrap(),
+            vec![Some(1)]
+        );
+        assert_eq!(hm_cache.get_borrowed("a").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replica_receives_writes_before_next_tick() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm1.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm2.replicate(&hm1).await.unwrap();
+
+        hm1.insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm1.remove(&["a"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(hm2.get("a").await.unwrap(), None);
+        assert_eq!(hm2.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stopped_replica_ignores_master_writes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm1 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hm2 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm2.replicate(&hm1).await.unwrap();
+        hm2.stop_replicating().await.unwrap();
+
+        hm1.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(hm2.get("a").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_add_replica_fans_out() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica1 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica2 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        master.add_replica(&replica1).await.unwrap();
+        master.add_replica(&replica2).await.unwrap();
+        assert_eq!(master.replica_count().await.unwrap(), 2);
+
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(replica1.get("a").await.unwrap(), Some(1));
+        assert_eq!(replica2.get("a").await.unwrap(), Some(1));
+
+        replica1.stop_replicating().await.unwrap();
+        assert_eq!(master.replica_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_read_only_replica_rejects_writes() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap()
+            .with_replica_writes(ReplicaWrites::Reject);
+        replica.replicate(&master).await.unwrap();
+
+        let res = replica.insert("a", 1, None, InsertPolicy::Always).await;
+        assert_eq!(res, Err(TokioActorCacheError::ReadOnlyReplica));
+        let res = replica.remove(&["a"]).await;
+        assert_eq!(res, Err(TokioActorCacheError::ReadOnlyReplica));
+        assert_eq!(replica.get("a").await.unwrap(), None);
+
+        replica.stop_replicating().await.unwrap();
+        replica
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("a").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replication_info() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        master
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert!(replica.is_replica().await.unwrap());
+        let info = replica.replication_info().await.unwrap();
+        assert_eq!(info.role, Role::Replica);
+        assert_eq!(info.master_id, Some(master.id()));
+        assert_eq!(info.entries_synced, 2);
+        assert!(info.last_sync.is_some());
+        assert!(info.lag.is_some());
+
+        assert!(!master.is_replica().await.unwrap());
+        let info = master.replication_info().await.unwrap();
+        assert_eq!(info.role, Role::Master);
+        assert_eq!(info.master_id, None);
+        assert_eq!(info.replicas, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failover() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica1 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica2 = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        master.add_replica(&replica1).await.unwrap();
+        master.add_replica(&replica2).await.unwrap();
+        master
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let moved = HashMapCache::failover(&master, &replica1).await.unwrap();
+        assert_eq!(moved, 1);
+        assert!(!replica1.is_replica().await.unwrap());
+        assert_eq!(master.replica_count().await.unwrap(), 0);
+
+        replica1
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let info = replica2.replication_info().await.unwrap();
+        assert_eq!(info.master_id, Some(replica1.id()));
+        assert_eq!(replica2.get("a").await.unwrap(), Some(1));
+        assert_eq!(replica2.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_promote_to_master() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap()
+            .with_replica_writes(ReplicaWrites::Reject);
+        replica.replicate(&master).await.unwrap();
+
+        replica.promote_to_master().await.unwrap();
+        replica
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(replica.get("a").await.unwrap(), Some(1));
+        assert_eq!(master.replica_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_near_cache_invalidated_by_remote_write() {
+        let expiration_policy = ExpirationPolicy::None;
+        let remote = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let near = NearCache::new(remote.clone(), 8).await.unwrap();
+        remote
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(near.get("a").await.unwrap(), Some(1));
+        assert_eq!(near.local_len(), 1);
+
+        remote
+            .insert("a", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.local_len(), 0);
+        assert_eq!(near.get("a").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_near_cache_evicts_least_recently_used() {
+        let expiration_policy = ExpirationPolicy::None;
+        let remote = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let near = NearCache::new(remote, 2).await.unwrap();
+        near.insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        near.insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        near.insert("c", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        near.get("a").await.unwrap();
+        near.get("b").await.unwrap();
+        near.get("a").await.unwrap();
+        near.get("c").await.unwrap();
+        assert_eq!(near.local_len(), 2);
+
+        near.remote().remove(&["b", "c"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(near.local_len(), 1);
+        assert_eq!(near.get("a").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 2, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(1));
+
+        hm_cache
+            .insert("a", 3, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_returns_old_value() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        let xx_get = InsertOptions {
+            policy: InsertPolicy::IfPresent,
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(hm_cache.set("a", 1, xx_get).await.unwrap(), None);
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+
+        let get = InsertOptions {
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(hm_cache.set("a", 1, get).await.unwrap(), None);
+        assert_eq!(hm_cache.set("a", 2, xx_get).await.unwrap(), Some(1));
+        assert_eq!(
+            hm_cache
+                .set("a", 3, InsertOptions::default())
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_minsert_entries() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("c", 0, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .minsert_entries(&[
+                InsertEntry::new("a", 1),
+                InsertEntry::new("b", 2).with_ex(Duration::from_secs(10)),
+                InsertEntry::new("c", 3).with_policy(InsertPolicy::IfAbsent),
+            ])
+            .await
+            .unwrap();
+
+        let vals = hm_cache.mget(&["a", "b", "c"]).await.unwrap();
+        assert_eq!(vals, vec![Some(1), Some(2), Some(0)]);
+        let ttl = hm_cache.ttl(&["a", "b"]).await.unwrap();
+        assert_eq!(ttl[0], None);
+        assert!(ttl[1].is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_update_value() {
+        let expiration_policy = ExpirationPolicy::None;
+        let master = HashMapCache::<&str, Vec<i32>>::new(expiration_policy)
+            .await
+            .unwrap();
+        let replica = HashMapCache::<&str, Vec<i32>>::new(expiration_policy)
+            .await
+            .unwrap();
+        replica.replicate(&master).await.unwrap();
+        master
+            .insert("a", vec![1, 2, 3], None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        assert!(master.update_value("a", |vals| vals.push(4)).await.unwrap());
+        assert!(!master.update_value("b", |vals| vals.push(4)).await.unwrap());
+        assert_eq!(master.get("a").await.unwrap(), Some(vec![1, 2, 3, 4]));
+        assert_eq!(master.get("b").await.unwrap(), None);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(replica.get("a").await.unwrap(), Some(vec![1, 2, 3, 4]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_jitter() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy)
+            .await
+            .unwrap()
+            .with_ttl_jitter(0.5);
+        let keys = (0..50).collect::<Vec<i32>>();
+        let ex = Duration::from_secs(10);
+        hm_cache
+            .minsert(
+                &keys,
+                &keys,
+                &vec![Some(ex); keys.len()],
+                &vec![InsertPolicy::Always; keys.len()],
+            )
+            .await
+            .unwrap();
+
+        let ttl = hm_cache
+            .ttl(&keys)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|ttl| ttl.unwrap())
+            .collect::<Vec<Duration>>();
+        assert!(
+            ttl.iter()
+                .all(|ttl| *ttl > Duration::from_secs(4) && *ttl <= Duration::from_secs(15))
+        );
+        assert!(ttl.iter().any(|ttl| *ttl < Duration::from_secs(9)));
+        assert!(ttl.iter().any(|ttl| *ttl > Duration::from_secs(11)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_capacity_enforced_on_insert() {
+        let expiration_policy = ExpirationPolicy::LRU(2);
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let keys = (0..100).collect::<Vec<i32>>();
+        hm_cache
+            .minsert(
+                &keys,
+                &keys,
+                &vec![None; keys.len()],
+                &vec![InsertPolicy::Always; keys.len()],
+            )
+            .await
+            .unwrap();
+
+        // No tick has run yet.
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_eviction_below_capacity() {
+        for expiration_policy in [ExpirationPolicy::LFU(5), ExpirationPolicy::LRU(5)] {
+            let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+                .await
+                .unwrap();
+            for key in ["a", "b", "c"] {
+                hm_cache
+                    .insert(key, 1, None, InsertPolicy::Always)
+                    .await
+                    .unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            assert_eq!(hm_cache.get_all().await.unwrap().len(), 3);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lfu_keeps_most_used() {
+        let expiration_policy = ExpirationPolicy::LFU(2);
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        for (key, n_get) in [("a", 2), ("b", 1), ("c", 0)] {
+            hm_cache
+                .insert(key, 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+            for _ in 0..n_get {
+                hm_cache.get(key).await.unwrap();
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let mut keys = hm_cache
+            .get_all()
+            .await
+            .unwrap()
+            .into_keys()
+            .collect::<Vec<&str>>();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hot_and_idle_keys() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        for key in ["a", "b", "c"] {
+            hm_cache
+                .insert(key, 1, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        // Time is paused, so let some pass for `c` to be the idlest.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        for key in ["a", "a", "a", "b"] {
+            hm_cache.get(key).await.unwrap();
+        }
+
+        let hot_keys = vec![("a", 3), ("b", 1)];
+        assert_eq!(hm_cache.hot_keys(2).await.unwrap(), hot_keys);
+        // Reading the stats isn't a use.
+        assert_eq!(hm_cache.try_hot_keys(2).await.unwrap(), hot_keys);
+        assert_eq!(hm_cache.hot_keys(10).await.unwrap().len(), 3);
+
+        let idle_keys = hm_cache.idle_keys(1).await.unwrap();
+        assert_eq!(idle_keys.len(), 1);
+        assert_eq!(idle_keys[0].0, "c");
+        assert!(hm_cache.idle_keys(0).await.unwrap().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_dump_load() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache1 = HashMapCache::<String, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hm_cache2 = HashMapCache::<String, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm_cache1
+            .insert(
+                "a".to_string(),
+                1,
+                Some(Duration::from_secs(10)),
+                InsertPolicy::Always,
+            )
+            .await
+            .unwrap();
+        hm_cache1
+            .insert("b".to_string(), 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache1.get("a".to_string()).await.unwrap();
+
+        let bytes = hm_cache1.dump().await.unwrap();
+        hm_cache2.load(&bytes).await.unwrap();
+
+        assert_eq!(
+            hm_cache2.hot_keys(1).await.unwrap(),
+            vec![("a".to_string(), 1)]
+        );
+        let ttl = hm_cache2
+            .ttl(&["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+        assert!(ttl[0].is_some_and(|ttl| ttl > Duration::from_secs(9)));
+        assert_eq!(ttl[1], None);
+        assert_eq!(
+            hm_cache2.get_all().await.unwrap(),
+            HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+        assert!(matches!(
+            hm_cache2.try_load(b"not a dump").await,
+            Err(TokioActorCacheError::Dump { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_export_import_json() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache1 = HashMapCache::<String, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hm_cache2 = HashMapCache::<String, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm_cache1
+            .insert(
+                "a".to_string(),
+                1,
+                Some(Duration::from_secs(10)),
+                InsertPolicy::Always,
+            )
+            .await
+            .unwrap();
+        hm_cache1
+            .insert("b".to_string(), 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let path =
+            std::env::temp_dir().join(format!("unbounded-hm-export-{}.jsonl", hm_cache1.id()));
+
+        assert_eq!(hm_cache1.export_json(&path).await.unwrap(), 2);
+        let text = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains(r#"{"key":"b","val":2}"#));
+
+        assert_eq!(hm_cache2.import_json(&path).await.unwrap(), 2);
+        assert_eq!(
+            hm_cache2.get_all().await.unwrap(),
+            hm_cache1.get_all().await.unwrap()
+        );
+        let ttl = hm_cache2.ttl(&["a".to_string()]).await.unwrap();
+        assert!(ttl[0].is_some_and(|ttl| ttl > Duration::from_secs(9)));
+
+        // A hand-written fixture; blank lines are skipped, the third one is bad.
+        tokio::fs::write(&path, "{\"key\":\"c\",\"val\":3}\n\nnot json\n")
+            .await
+            .unwrap();
+        let err = hm_cache2.try_import_json(&path).await.unwrap_err();
+        assert!(
+            matches!(err, TokioActorCacheError::Dump { reason } if reason.starts_with("line 3"))
+        );
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert!(matches!(
+            hm_cache2.import_json(&path).await,
+            Err(TokioActorCacheError::Io { .. })
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_warm_from() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<i32, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        // Nothing to wait for yet.
+        hm_cache.ready().await;
+
+        let entries = tokio_stream::iter((0..1000).map(|i| (i, i * 10, None)));
+        let warming_cache = hm_cache.clone();
+        let warmup = tokio::spawn(async move { warming_cache.warm_from(entries).await });
+        tokio::task::yield_now().await;
+        hm_cache.ready().await;
+
+        assert_eq!(warmup.await.unwrap().unwrap(), 1000);
+        let hm = hm_cache.get_all().await.unwrap();
+        assert_eq!(hm.len(), 1000);
+        assert_eq!(hm[&999], 9990);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_removals() {
+        let expiration_policy = ExpirationPolicy::LRU(1);
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let mut removals = hm_cache.removals().await.unwrap();
+
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        hm_cache
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache.remove(&["b"]).await.unwrap();
+
+        let reasons = [removals.recv().await, removals.recv().await]
+            .map(|removal| removal.map(|removal| (removal.key, removal.reason)));
+        assert_eq!(
+            reasons,
+            [
+                Some(("a", RemovalReason::Evicted(Eviction::Lru))),
+                Some(("b", RemovalReason::ManualRemove)),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hash_fields() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<i32, HashMap<&str, i32>>::new(expiration_policy)
+            .await
+            .unwrap();
+
+        assert_eq!(hm_cache.hset(1, "x", 10).await.unwrap(), None);
+        assert_eq!(hm_cache.hset(1, "y", 20).await.unwrap(), None);
+        assert_eq!(hm_cache.hget(1, "y").await.unwrap(), Some(20));
+        assert_eq!(hm_cache.hdel(1, &["x", "y"]).await.unwrap(), 2);
+        // An emptied hash stays cached.
+        assert_eq!(hm_cache.hgetall(1).await.unwrap(), Some(HashMap::new()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_shorter_than_tick() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, Some(Duration::from_millis(5)), InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(4)).await;
+        assert_eq!(hm_cache.get("a").await.unwrap(), Some(1));
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(hm_cache.get("a").await.unwrap(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ingestion_limit() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<u32, u32>::new(expiration_policy)
+            .await
+            .unwrap()
+            .with_ingestion_limit(10, ShedPolicy::OldestReads);
+        hm_cache
+            .insert(0, 0, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        // A flood of reads, then writes behind it.
+        let reads = (0..30)
+            .map(|_| {
+                let hm_cache = hm_cache.clone();
+                tokio::spawn(async move { hm_cache.get(0).await })
+            })
+            .collect::<Vec<_>>();
+        tokio::task::yield_now().await;
+        for key in 1..=5 {
+            hm_cache
+                .insert(key, key, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let mut n_shed = 0;
+        for read in reads {
+            match read.await.unwrap() {
+                Ok(val) => assert_eq!(val, Some(0)),
+                Err(err) => {
+                    assert_eq!(err, TokioActorCacheError::Shed);
+                    n_shed += 1;
+                }
+            }
+        }
+        assert!(n_shed > 0 && n_shed < 30);
+        assert_eq!(hm_cache.shed_commands(), n_shed);
+        // Writes are never shed.
+        assert_eq!(hm_cache.get_all().await.unwrap().len(), 6);
+
+        let hm_cache = hm_cache.with_ingestion_limit(10, ShedPolicy::WritesFirst);
+        for key in 10..40 {
+            hm_cache
+                .insert(key, key, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let n_written = hm_cache.get_all().await.unwrap().len() - 6;
+        let n_shed = hm_cache.shed_commands() - n_shed;
+        assert!(n_shed > 0);
+        assert_eq!(n_written as u64 + n_shed, 30);
+    }
 }
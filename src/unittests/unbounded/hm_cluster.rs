@@ -2,12 +2,21 @@
 mod tests {
     use std::time::Duration;
 
-    use crate::tokio_cache::{option::ExpirationPolicy, unbounded::hm_cluster::HashMapCacheCluster};
+    use crate::tokio_cache::{
+        error::TokioActorCacheError,
+        health::NodeStatus,
+        option::{ClusterConfig, ExpirationPolicy, InsertEntry, InsertPolicy, NodeConfig},
+        rebalance::RebalanceProgress,
+        router::{Crc16Router, HashRouter, KeyRouter},
+        unbounded::{hm::HashMapCache, hm_cluster::HashMapCacheCluster},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_hash_id() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         let keys = vec![
             "a".to_string(),
             "b".to_string(),
@@ -18,49 +27,67 @@ mod tests {
             "g".to_string(),
         ];
         for (k, v) in keys.into_iter().enumerate() {
-            hm_cluster.insert(k, v.clone(), None, false).await.unwrap();
+            hm_cluster
+                .insert(k, v.clone(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
             let val = hm_cluster.get(k).await.unwrap();
             assert_eq!(val, Some(v));
         }
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hm_cluster
-            .insert("a", 10, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hm_cluster.ttl(&["a", "b"]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
-        hm_cluster.insert("b", 12, None, false).await.unwrap();
-        hm_cluster.insert("c", 20, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("b", 12, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("c", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hm = hm_cluster.get_all().await.unwrap();
-        assert_eq!(!hm.is_empty(), true);
+        assert!(!hm.is_empty());
         hm_cluster.clear().await.unwrap();
         let hm = hm_cluster.get_all().await.unwrap();
-        assert_eq!(hm.is_empty(), true);
+        assert!(hm.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mget() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -69,16 +96,18 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -86,16 +115,18 @@ mod tests {
         assert_eq!(vals, vec![Some(10), Some(20), Some(30), None]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains_keys() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -106,17 +137,22 @@ mod tests {
         assert_eq!(is_contains_keys, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -124,17 +160,22 @@ mod tests {
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[20, 20, 30],
                 &[None, None, None],
-                &[true, true, true],
+                &[InsertPolicy::IfAbsent; 3],
             )
             .await
             .unwrap();
@@ -142,10 +183,12 @@ mod tests {
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
@@ -155,7 +198,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -168,16 +211,18 @@ mod tests {
         assert_eq!(val_c, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hm_cluster
             .minsert(
                 &["a", "b", "c"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -189,48 +234,71 @@ mod tests {
         assert_eq!(val_c, Some(30));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_inconsistent_len() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         let res = hm_cluster
             .minsert(
                 &["a", "b"],
                 &[10, 20, 30],
                 &[None, None, None],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await;
         assert!(res.is_err());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_not_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
-        hm_cluster.insert("a", 20, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(20));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_nx_if_exists() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
-        hm_cluster.insert("a", 20, None, true).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 20, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hm_cluster
-            .insert("b", 20, Some(Duration::from_secs(1)), false)
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("b", 20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -240,12 +308,389 @@ mod tests {
         assert_eq!(val_b, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3).await;
-        hm_cluster.insert("a", 10, None, false).await.unwrap();
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hm_cluster.get("a").await.unwrap();
         assert_eq!(val, Some(10));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_cluster() {
+        let expiration_policy = ExpirationPolicy::None;
+        let primary = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        let standby = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        standby.replicate(&primary).await.unwrap();
+
+        primary
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        primary
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        primary
+            .insert("c", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(
+            standby.get_all().await.unwrap(),
+            primary.get_all().await.unwrap()
+        );
+        assert_eq!(standby.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_cluster_size_mismatch() {
+        let expiration_policy = ExpirationPolicy::None;
+        let primary = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        let standby = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 2)
+            .await
+            .unwrap();
+        let res = standby.replicate(&primary).await;
+        assert_eq!(
+            res,
+            Err(TokioActorCacheError::ClusterSizeMismatch {
+                expected: 3,
+                got: 2
+            })
+        );
+    }
+
+    #[derive(Debug, Clone)]
+    struct FirstNode;
+
+    impl KeyRouter<&str> for FirstNode {
+        fn route(&self, _key: &&str, _n_node: u64) -> u64 {
+            0
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_custom_router() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap()
+            .with_router(FirstNode);
+        hm_cluster
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let first_node = hm_cluster.nodes.get(&0).unwrap();
+        assert_eq!(first_node.get_all().await.unwrap().len(), 2);
+        assert_eq!(hm_cluster.get("b").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tuple_keys() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<(u8, u8), i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert((1, 2), 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert((2, 1), 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        assert_eq!(hm_cluster.get((1, 2)).await.unwrap(), Some(3));
+        assert_eq!(hm_cluster.get((2, 1)).await.unwrap(), Some(4));
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_crc16_router() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap()
+            .with_router(Crc16Router);
+        hm_cluster
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let node = hm_cluster.nodes.get(&Crc16Router.route("a", 3)).unwrap();
+        assert_eq!(node.get("a").await.unwrap(), Some(1));
+    }
+
+    /// A node whose actor died along with the runtime it was spawned on.
+    fn dead_node() -> HashMapCache<String, i32> {
+        std::thread::spawn(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(HashMapCache::new(ExpirationPolicy::None))
+                .unwrap()
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fail_fast_on_dead_node() {
+        let expiration_policy = ExpirationPolicy::None;
+        let mut hm_cluster = HashMapCacheCluster::<String, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster.nodes.insert(1, dead_node());
+        let hm_cluster = hm_cluster.with_health_check(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let status = hm_cluster.cluster_status();
+        assert_eq!(status[&0], NodeStatus::Up);
+        assert_eq!(status[&1], NodeStatus::Down);
+        assert_eq!(status[&2], NodeStatus::Up);
+
+        // Keys of the dead node fail right away instead of landing on another node.
+        let mut n_live = 0;
+        for i in 0..20 {
+            let key = i.to_string();
+            let inserted = hm_cluster
+                .insert(key.clone(), i, None, InsertPolicy::Always)
+                .await;
+            let got = hm_cluster.get(key.clone()).await;
+            if HashRouter::default().route(&key, 3) == 1 {
+                assert_eq!(inserted, Err(TokioActorCacheError::ActorGone));
+                assert_eq!(got, Err(TokioActorCacheError::ActorGone));
+            } else {
+                inserted.unwrap();
+                assert_eq!(got, Ok(Some(i)));
+                n_live += 1;
+            }
+        }
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), n_live);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_with_config_node_override() {
+        let hot_node = NodeConfig {
+            expiration_policy: Some(ExpirationPolicy::LRU(1)),
+            ..Default::default()
+        };
+        let config = ClusterConfig::new(ExpirationPolicy::None, 3).with_node(0, hot_node);
+        let hm_cluster = HashMapCacheCluster::<String, i32>::with_config(config)
+            .await
+            .unwrap();
+        for i in 0..30 {
+            hm_cluster
+                .insert(i.to_string(), i, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let hot = hm_cluster.nodes[&0].get_all().await.unwrap();
+        assert_eq!(hot.len(), 1);
+        let rest = hm_cluster.nodes[&1].get_all().await.unwrap().len()
+            + hm_cluster.nodes[&2].get_all().await.unwrap().len();
+        assert_eq!(hm_cluster.get_all().await.unwrap().len(), rest + 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rebalance() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        for (i, key) in ["a", "b", "c", "d", "e", "f"].into_iter().enumerate() {
+            hm_cluster
+                .insert(key, i as i32, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let misplaced = hm_cluster.nodes[&1].get_all().await.unwrap().len()
+            + hm_cluster.nodes[&2].get_all().await.unwrap().len();
+        assert!(misplaced > 0);
+
+        let hm_cluster = hm_cluster.with_router(FirstNode);
+        let mut progress = hm_cluster.rebalance();
+        let progress = progress
+            .wait_for(RebalanceProgress::is_finished)
+            .await
+            .unwrap()
+            .clone();
+        assert_eq!(
+            progress,
+            RebalanceProgress {
+                moved: misplaced,
+                remaining: 0,
+                finished: Some(Ok(())),
+            }
+        );
+        assert_eq!(hm_cluster.nodes[&0].get_all().await.unwrap().len(), 6);
+        assert_eq!(hm_cluster.get("f").await.unwrap(), Some(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_minsert_keeps_ex_nx_per_key() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::<&str, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hm_cluster
+            .insert("c", 0, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cluster
+            .minsert(
+                &["a", "b", "c"],
+                &[1, 2, 3],
+                &[None, Some(Duration::from_secs(10)), None],
+                &[
+                    InsertPolicy::Always,
+                    InsertPolicy::Always,
+                    InsertPolicy::IfAbsent,
+                ],
+            )
+            .await
+            .unwrap();
+
+        let ttl = hm_cluster.ttl(&["a", "b"]).await.unwrap();
+        assert_eq!(ttl[0].1, None);
+        assert!(ttl[1].1.is_some());
+        assert_eq!(
+            hm_cluster.mget(&["a", "b", "c"]).await.unwrap(),
+            vec![Some(1), Some(2), Some(0)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mget_ordered_with_dead_node() {
+        let expiration_policy = ExpirationPolicy::None;
+        let mut hm_cluster = HashMapCacheCluster::<String, i32>::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        for i in 0..20 {
+            hm_cluster
+                .insert(i.to_string(), i, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        hm_cluster.nodes.insert(1, dead_node());
+
+        let keys = (0..20).map(|i| i.to_string()).collect::<Vec<_>>();
+        let res = hm_cluster.mget_ordered(&keys).await;
+        assert_eq!(res.len(), 20);
+        for (i, val) in res.into_iter().enumerate() {
+            if HashRouter::default().route(&keys[i], 3) == 1 {
+                assert_eq!(val, Err(TokioActorCacheError::ActorGone));
+            } else {
+                assert_eq!(val, Ok(Some(i as i32)));
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_minsert_entries() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cluster = HashMapCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        let entries = (0..10)
+            .map(|i| InsertEntry::new(i.to_string(), i))
+            .collect::<Vec<_>>();
+        hm_cluster.minsert_entries(&entries).await.unwrap();
+
+        let hm = hm_cluster.get_all().await.unwrap();
+        assert_eq!(hm.len(), 10);
+        assert_eq!(hm["7"], 7);
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_snapshot_all_restore() {
+        let hm_cluster = HashMapCacheCluster::<u32, u32>::new(ExpirationPolicy::LFU(100), 2)
+            .await
+            .unwrap();
+        for key in 0..10 {
+            hm_cluster
+                .insert(key, key * 2, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "unbounded-hm-cluster-{}",
+            hm_cluster.nodes[&0].id()
+        ));
+
+        let manifest = hm_cluster.try_snapshot_all(&dir).await.unwrap();
+        assert_eq!(manifest.nodes.len(), 2);
+        let restored = HashMapCacheCluster::<u32, u32>::restore(&dir)
+            .await
+            .unwrap();
+        assert_eq!(restored.get(7).await.unwrap(), Some(14));
+        assert_eq!(restored.get_all().await.unwrap().len(), 10);
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[cfg(feature = "encryption")]
+    #[tokio::test(start_paused = true)]
+    async fn test_snapshot_all_encrypted() {
+        use crate::tokio_cache::encryption::{EncryptionKey, Keyring};
+
+        let hm_cluster = HashMapCacheCluster::<u32, u32>::new(ExpirationPolicy::None, 2)
+            .await
+            .unwrap();
+        for key in 0..10 {
+            hm_cluster
+                .insert(key, key * 2, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "unbounded-hm-cluster-encrypted-{}",
+            hm_cluster.nodes[&0].id()
+        ));
+        let old_key = EncryptionKey::generate(1);
+        let keyring = Keyring::new(old_key.clone());
+
+        let manifest = hm_cluster
+            .snapshot_all_encrypted(&dir, &keyring)
+            .await
+            .unwrap();
+        assert_eq!(manifest.nodes.len(), 2);
+        assert!(matches!(
+            HashMapCacheCluster::<u32, u32>::restore(&dir).await,
+            Err(TokioActorCacheError::Dump { .. })
+        ));
+
+        let rotated = Keyring::new(EncryptionKey::generate(2)).with_previous(old_key);
+        let restored = HashMapCacheCluster::<u32, u32>::restore_encrypted(&dir, &rotated)
+            .await
+            .unwrap();
+        assert_eq!(restored.get_all().await.unwrap().len(), 10);
+        assert!(matches!(
+            HashMapCacheCluster::<u32, u32>::restore_encrypted(
+                &dir,
+                &Keyring::new(EncryptionKey::generate(2))
+            )
+            .await,
+            Err(TokioActorCacheError::Encryption { .. })
+        ));
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
 }
@@ -2,39 +2,84 @@
 mod tests {
     use std::{collections::HashSet, time::Duration};
 
-    use crate::tokio_cache::{option::ExpirationPolicy, unbounded::hs::HashSetCache};
+    use crate::tokio_cache::{
+        option::{ExpirationPolicy, InsertPolicy},
+        unbounded::{hm::HashMapCache, hs::HashSetCache},
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_expiration_policy_lru() {
         let expiration_policy = ExpirationPolicy::LRU(1);
-        let hs_cache = HashSetCache::<i32>::new(expiration_policy).await;
-        hs_cache.insert(1, None, false).await.unwrap();
-        hs_cache.insert(2, None, false).await.unwrap();
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        hs_cache
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        // Time is paused, so let some pass for the entries to differ in recency.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        hs_cache
+            .insert(2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         tokio::time::sleep(Duration::from_secs(1)).await;
         let hs = hs_cache.get_all().await.unwrap();
         assert_eq!(HashSet::from([(2)]), hs);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_expiration_policy_lfu() {
         let expiration_policy = ExpirationPolicy::LFU(1);
-        let hs_cache = HashSetCache::<i32>::new(expiration_policy).await;
-        hs_cache.insert(1, None, false).await.unwrap();
-        hs_cache.insert(1, None, false).await.unwrap();
-        hs_cache.insert(2, None, false).await.unwrap();
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        hs_cache
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         tokio::time::sleep(Duration::from_secs(1)).await;
         let hs = hs_cache.get_all().await.unwrap();
         assert_eq!(HashSet::from([(1)]), hs);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_get_all_updates_lfu_stats() {
+        let expiration_policy = ExpirationPolicy::LFU(2);
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        for val in [1, 2] {
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        hs_cache.contains(&[2]).await.unwrap();
+        hs_cache.get_all().await.unwrap();
+        hs_cache.get_all().await.unwrap();
+        // 1 and 2 are now used more than 3 will be.
+        hs_cache
+            .insert(3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let hs = hs_cache.get_all().await.unwrap();
+        assert_eq!(HashSet::from([1, 2]), hs);
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster1 = HashSetCache::<i32>::new(expiration_policy).await;
-        let hs_cluster2 = HashSetCache::<i32>::new(expiration_policy).await;
+        let hs_cluster1 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        let hs_cluster2 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
         hs_cluster2.replicate(&hs_cluster1).await.unwrap();
 
-        hs_cluster1.insert(1, None, false).await.unwrap();
+        hs_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hs_cluster1.get_all().await.unwrap();
 
@@ -49,14 +94,17 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster1 = HashSetCache::<i32>::new(expiration_policy).await;
-        let hs_cluster2 = HashSetCache::<i32>::new(expiration_policy).await;
+        let hs_cluster1 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        let hs_cluster2 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
         hs_cluster2.replicate(&hs_cluster1).await.unwrap();
 
-        hs_cluster1.insert(1, None, false).await.unwrap();
+        hs_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hs_cluster1.get_all().await.unwrap();
 
@@ -70,7 +118,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hs_cluster1.insert(10, None, false).await.unwrap();
+        hs_cluster1
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hs_cluster1.get_all().await.unwrap();
 
@@ -79,14 +130,17 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster1 = HashSetCache::<i32>::new(expiration_policy).await;
-        let hs_cluster2 = HashSetCache::<i32>::new(expiration_policy).await;
+        let hs_cluster1 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        let hs_cluster2 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
         hs_cluster2.replicate(&hs_cluster1).await.unwrap();
 
-        hs_cluster1.insert(1, None, false).await.unwrap();
+        hs_cluster1
+            .insert(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hs_cluster1.get_all().await.unwrap();
 
@@ -97,59 +151,75 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
         hs_cache
-            .insert(10, Some(Duration::from_secs(1)), false)
+            .insert(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hs_cache.ttl(&[10, 20]).await.unwrap();
         println!("{:?}", ttl);
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
-        hs_cache.insert(10, None, false).await.unwrap();
-        hs_cache.insert(20, None, false).await.unwrap();
-        hs_cache.insert(30, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = hs_cache.get_all().await.unwrap();
         assert_eq!(hs, HashSet::from([10, 20, 30]));
         hs_cache.clear().await.unwrap();
         let hs = hs_cache.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
         hs_cache
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let vals = hs_cache.remove(&[10, 20, 30, 40]).await.unwrap();
         assert_eq!(vals, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
-        hs_cache.insert(10, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let vals = hs_cache.contains(&[10]).await.unwrap();
         assert_eq!(vals, vec![true]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
         hs_cache
             .minsert(
                 &[10, 20, 30],
@@ -158,7 +228,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -167,25 +237,32 @@ mod tests {
         assert_eq!(vals, HashSet::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
         hs_cache
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = hs_cache.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
-        hs_cache.insert(10, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hs_cache
-            .insert(20, Some(Duration::from_secs(1)), false)
+            .insert(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -193,14 +270,186 @@ mod tests {
         assert_eq!(val, HashSet::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cache = HashSetCache::new(expiration_policy).await;
-        hs_cache.insert(10, None, false).await.unwrap();
-        hs_cache.insert(20, None, false).await.unwrap();
-        hs_cache.insert(30, None, false).await.unwrap();
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hs_cache.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replica_receives_writes_before_next_tick() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs1 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        let hs2 = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        hs1.insert(1, None, InsertPolicy::Always).await.unwrap();
+        hs2.replicate(&hs1).await.unwrap();
+
+        hs1.insert(2, None, InsertPolicy::Always).await.unwrap();
+        hs1.remove(&[1]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(hs2.get_all().await.unwrap(), HashSet::from([2]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_from_hash_map_keys() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hs_cache = HashSetCache::<&str>::new(expiration_policy).await.unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 2, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .replicate_from(&hm_cache, |key, _val| *key)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from(["a", "b"]));
+
+        hm_cache.remove(&["a"]).await.unwrap();
+        hm_cache
+            .insert("c", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from(["b", "c"]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replicate_from_shared_projection() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hm_cache = HashMapCache::<&str, i32>::new(expiration_policy)
+            .await
+            .unwrap();
+        let hs_cache = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        hs_cache
+            .replicate_from(&hm_cache, |_key, val| val % 2)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("a", 1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("b", 3, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache
+            .insert("c", 4, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hm_cache.remove(&["a"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([0, 1]));
+
+        hm_cache.remove(&["b"]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([0]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cache = HashSetCache::new(expiration_policy).await.unwrap();
+        let ex = Some(Duration::from_secs(10));
+        hs_cache
+            .insert(10, ex, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert!(hs_cache.get_all().await.unwrap().is_empty());
+
+        hs_cache
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .insert(10, ex, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        assert_eq!(hs_cache.ttl(&[10]).await.unwrap(), vec![None]);
+
+        hs_cache
+            .insert(10, ex, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert!(hs_cache.ttl(&[10]).await.unwrap()[0].is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_algebra() {
+        let expiration_policy = ExpirationPolicy::None;
+        let a = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        let b = HashSetCache::<i32>::new(expiration_policy).await.unwrap();
+        for (hs_cache, val) in [(&a, 1), (&a, 2), (&b, 2), (&b, 3)] {
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(a.union_with(&b).await.unwrap(), HashSet::from([1, 2, 3]));
+        assert_eq!(a.intersect(&b).await.unwrap(), HashSet::from([2]));
+        assert_eq!(a.difference(&b).await.unwrap(), HashSet::from([1]));
+        assert_eq!(b.store_difference(&[&b, &a]).await.unwrap(), 1);
+        assert_eq!(b.get_all().await.unwrap(), HashSet::from([3]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_srandmember_and_spop() {
+        let hs_cache = HashSetCache::<i32>::new(ExpirationPolicy::None)
+            .await
+            .unwrap();
+        let all = HashSet::from([1, 2, 3]);
+        for val in all.iter().copied() {
+            hs_cache
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let picked = hs_cache.srandmember(2).await.unwrap();
+        assert_eq!(picked.len(), 2);
+        assert!(picked[0] != picked[1] && picked.iter().all(|val| all.contains(val)));
+        let popped = hs_cache.spop(5).await.unwrap();
+        assert_eq!(popped.into_iter().collect::<HashSet<_>>(), all);
+        assert!(hs_cache.get_all().await.unwrap().is_empty());
+        assert!(hs_cache.srandmember(1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_sync() {
+        let hs_cache = HashSetCache::<i32>::new(ExpirationPolicy::None)
+            .await
+            .unwrap();
+        hs_cache
+            .insert_sync(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cache
+            .try_insert_sync(2, Some(Duration::from_secs(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(hs_cache.get_all().await.unwrap(), HashSet::from([1, 2]));
+    }
 }
@@ -2,12 +2,17 @@
 mod tests {
     use std::{collections::HashSet, time::Duration};
 
-    use crate::tokio_cache::{option::ExpirationPolicy, unbounded::hs_cluster::HashSetCacheCluster};
+    use crate::tokio_cache::{
+        option::{ExpirationPolicy, InsertPolicy},
+        unbounded::hs_cluster::HashSetCacheCluster,
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_hash_id() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         let keys = vec![
             "a".to_string(),
             "b".to_string(),
@@ -18,66 +23,95 @@ mod tests {
             "g".to_string(),
         ];
         for k in keys.clone() {
-            hs_cluster.insert(k, None, false).await.unwrap();
+            hs_cluster
+                .insert(k, None, InsertPolicy::Always)
+                .await
+                .unwrap();
         }
 
         let vals = hs_cluster.get_all().await.unwrap();
         assert_eq!(vals.len(), keys.len());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .insert(10, Some(Duration::from_secs(1)), false)
+            .insert(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = hs_cluster.ttl(&[10, 20]).await.unwrap();
         println!("{:?}", ttl);
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
-        hs_cluster.insert(20, None, false).await.unwrap();
-        hs_cluster.insert(30, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = hs_cluster.get_all().await.unwrap();
         assert_eq!(hs, HashSet::from([10, 20, 30]));
         hs_cluster.clear().await.unwrap();
         let hs = hs_cluster.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let vals = hs_cluster.remove(&[10, 20, 30, 40]).await.unwrap();
         assert_eq!(vals, vec![true, true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let vals = hs_cluster.contains(&[10]).await.unwrap();
         assert_eq!(vals, vec![true]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hs_cluster
             .minsert(
                 &[10, 20, 30],
@@ -86,7 +120,7 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
@@ -95,25 +129,36 @@ mod tests {
         assert_eq!(vals, HashSet::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_minsert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
         hs_cluster
-            .minsert(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .minsert(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = hs_cluster.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         hs_cluster
-            .insert(20, Some(Duration::from_secs(1)), false)
+            .insert(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -121,14 +166,53 @@ mod tests {
         assert_eq!(val, HashSet::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_insert() {
         let expiration_policy = ExpirationPolicy::None;
-        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3).await;
-        hs_cluster.insert(10, None, false).await.unwrap();
-        hs_cluster.insert(20, None, false).await.unwrap();
-        hs_cluster.insert(30, None, false).await.unwrap();
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        hs_cluster
+            .insert(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = hs_cluster.get_all().await.unwrap();
         assert_eq!(val, HashSet::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_nodes_are_distinct() {
+        let expiration_policy = ExpirationPolicy::None;
+        let hs_cluster = HashSetCacheCluster::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        for val in 0..30 {
+            hs_cluster
+                .insert(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let ids = hs_cluster
+            .nodes
+            .values()
+            .map(|node| node.id())
+            .collect::<HashSet<_>>();
+        assert_eq!(ids.len(), 3);
+
+        let mut n_vals = Vec::new();
+        for node in hs_cluster.nodes.values() {
+            n_vals.push(node.get_all().await.unwrap().len());
+        }
+        assert_eq!(n_vals.iter().sum::<usize>(), 30);
+        assert!(n_vals.iter().all(|n| *n < 30));
+    }
 }
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::tokio_cache::{
+        option::{ExpirationPolicy, InsertPolicy},
+        unbounded::sharded::ShardedHashMapCache,
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mget_keeps_key_order() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 4)
+            .await
+            .unwrap();
+        sharded
+            .minsert(
+                &["a", "b", "c", "d"],
+                &[10, 20, 30, 40],
+                &[None; 4],
+                &[InsertPolicy::Always; 4],
+            )
+            .await
+            .unwrap();
+        let vals = sharded.try_mget(&["d", "x", "b", "a", "c"]).await.unwrap();
+        assert_eq!(vals, vec![Some(40), None, Some(20), Some(10), Some(30)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_remove() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 3)
+            .await
+            .unwrap();
+        sharded
+            .minsert(
+                &["a", "b", "c"],
+                &[10, 20, 30],
+                &[None; 3],
+                &[InsertPolicy::Always; 3],
+            )
+            .await
+            .unwrap();
+        let vals = sharded.remove(&["c", "d", "a"]).await.unwrap();
+        assert_eq!(vals, vec![Some(30), None, Some(10)]);
+        assert_eq!(sharded.get("b").await.unwrap(), Some(20));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_zero_shards() {
+        let expiration_policy = ExpirationPolicy::None;
+        let sharded = ShardedHashMapCache::new(expiration_policy, 0)
+            .await
+            .unwrap();
+        assert_eq!(sharded.shards().len(), 1);
+        sharded
+            .insert("a", 10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(sharded.get("a").await.unwrap(), Some(10));
+    }
+}
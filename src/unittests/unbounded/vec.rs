@@ -3,39 +3,47 @@ mod tests {
 
     use std::time::Duration;
 
-    use crate::tokio_cache::{option::ExpirationPolicy, unbounded::vec::VecCache};
+    use crate::tokio_cache::{
+        option::{ExpirationPolicy, InsertPolicy, RemovePolicy, VecEnd},
+        unbounded::vec::VecCache,
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_expiration_policy_lru() {
         let expiration_policy = ExpirationPolicy::LRU(1);
-        let hs_cache = VecCache::<i32>::new(expiration_policy).await;
-        hs_cache.push(1, None, false).await.unwrap();
-        hs_cache.push(2, None, false).await.unwrap();
+        let hs_cache = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        hs_cache.push(1, None, InsertPolicy::Always).await.unwrap();
+        // Time is paused, so let some pass for the entries to differ in recency.
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        hs_cache.push(2, None, InsertPolicy::Always).await.unwrap();
         tokio::time::sleep(Duration::from_secs(1)).await;
         let hs = hs_cache.get_all().await.unwrap();
         assert_eq!(Vec::from([(2)]), hs);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_expiration_policy_lfu() {
         let expiration_policy = ExpirationPolicy::LFU(1);
-        let hs_cache = VecCache::<i32>::new(expiration_policy).await;
-        hs_cache.push(1, None, false).await.unwrap();
-        hs_cache.push(1, None, false).await.unwrap();
-        hs_cache.push(3, None, false).await.unwrap();
+        let hs_cache = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        hs_cache.push(1, None, InsertPolicy::Always).await.unwrap();
+        hs_cache.push(1, None, InsertPolicy::Always).await.unwrap();
+        hs_cache.push(3, None, InsertPolicy::Always).await.unwrap();
         tokio::time::sleep(Duration::from_secs(1)).await;
         let hs = hs_cache.get_all().await.unwrap();
         assert_eq!(Vec::from([(1)]), hs);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicated_data_persist() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy).await.unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -50,14 +58,17 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_stop_replicating() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy).await.unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -71,7 +82,10 @@ mod tests {
 
         assert_eq!(val_1, val_2);
 
-        hm_cluster1.push(10, None, false).await.unwrap();
+        hm_cluster1
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -80,14 +94,17 @@ mod tests {
         assert!(val_1 != val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_replicate() {
         let expiration_policy = ExpirationPolicy::None;
-        let hm_cluster1 = VecCache::<i32>::new(expiration_policy).await;
-        let hm_cluster2 = VecCache::<i32>::new(expiration_policy).await;
+        let hm_cluster1 = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        let hm_cluster2 = VecCache::<i32>::new(expiration_policy).await.unwrap();
         hm_cluster2.replicate(&hm_cluster1).await.unwrap();
 
-        hm_cluster1.push(1, None, false).await.unwrap();
+        hm_cluster1
+            .push(1, None, InsertPolicy::Always)
+            .await
+            .unwrap();
 
         let val_1 = hm_cluster1.get_all().await.unwrap();
 
@@ -98,57 +115,120 @@ mod tests {
         assert_eq!(val_1, val_2);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
         vec_cache
-            .push(10, Some(Duration::from_secs(1)), false)
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = vec_cache.ttl(&[10, 20]).await.unwrap();
         println!("{:?}", ttl);
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
+        assert_eq!(ttl[0], Some(Duration::from_secs(1)));
         assert_eq!(ttl[1], None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_multiple_elements() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        for (val, ex) in [
+            (10, Some(1)),
+            (20, None),
+            (30, Some(3)),
+            (10, Some(5)),
+            (40, None),
+        ] {
+            vec_cache
+                .push(val, ex.map(Duration::from_secs), InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        // One result per requested value, from its first occurrence.
+        let ttl = vec_cache.ttl(&[10, 30]).await.unwrap();
+        assert_eq!(
+            ttl,
+            vec![Some(Duration::from_secs(1)), Some(Duration::from_secs(3))]
+        );
+        let ttl = vec_cache.ttl(&[50, 10, 40]).await.unwrap();
+        assert_eq!(ttl, vec![None, Some(Duration::from_secs(1)), None]);
+    }
+
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
-        vec_cache.push(30, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let hs = vec_cache.get_all().await.unwrap();
         assert_eq!(hs, Vec::from([10, 20, 30]));
         vec_cache.clear().await.unwrap();
         let hs = vec_cache.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        let val = vec_cache.remove(&[10, 20]).await.unwrap();
-        assert_eq!(val, vec![true, false]);
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = vec_cache
+            .remove(&[10, 20], RemovePolicy::All)
+            .await
+            .unwrap();
+        assert_eq!(val, vec![1, 0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_remove_first() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        for val in [10, 20, 10] {
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        let n_removed = vec_cache.remove(&[10], RemovePolicy::First).await.unwrap();
+        assert_eq!(n_removed, vec![1]);
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![20, 10]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cache.contains(&[10, 20, 30]).await.unwrap();
         assert_eq!(val, vec![true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
         vec_cache
             .mpush(
                 &[10, 20, 30],
@@ -157,34 +237,41 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
         let val = vec_cache.get_all().await.unwrap();
-        assert_eq!(val, Vec::new());
+        assert_eq!(val, Vec::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
         vec_cache
-            .mpush(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .mpush(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let val = vec_cache.get_all().await.unwrap();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
-        vec_cache.push(10, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         vec_cache
-            .push(20, Some(Duration::from_secs(1)), false)
+            .push(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -192,14 +279,180 @@ mod tests {
         assert_eq!(val, Vec::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cache = VecCache::new(expiration_policy).await;
-        vec_cache.push(10, None, false).await.unwrap();
-        vec_cache.push(20, None, false).await.unwrap();
-        vec_cache.push(30, None, false).await.unwrap();
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cache.get_all().await.unwrap();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replica_receives_writes_before_next_tick() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec1 = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        let vec2 = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        vec1.push(1, None, InsertPolicy::Always).await.unwrap();
+        vec2.replicate(&vec1).await.unwrap();
+
+        vec1.push(2, None, InsertPolicy::Always).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(vec2.get_all().await.unwrap(), vec![1, 2]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replica_follows_expiry() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec1 = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        let vec2 = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        vec2.replicate(&vec1).await.unwrap();
+
+        vec1.push(1, Some(Duration::from_millis(100)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec1.push(2, None, InsertPolicy::Always).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(vec2.get_all().await.unwrap(), vec![2]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_insert_policy() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        assert!(vec_cache.get_all().await.unwrap().is_empty());
+
+        vec_cache
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(10, None, InsertPolicy::IfAbsent)
+            .await
+            .unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![10]);
+
+        vec_cache
+            .push(10, None, InsertPolicy::IfPresent)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![10, 20]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_capacity_enforced_on_push() {
+        let expiration_policy = ExpirationPolicy::LRU(3);
+        let vec_cache = VecCache::<i32>::new(expiration_policy).await.unwrap();
+        for val in 0..10 {
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        // No tick has run yet.
+        assert_eq!(vec_cache.get_all().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_eviction_below_capacity() {
+        for expiration_policy in [ExpirationPolicy::LFU(5), ExpirationPolicy::LRU(5)] {
+            let vec_cache = VecCache::<i32>::new(expiration_policy).await.unwrap();
+            for val in 0..3 {
+                vec_cache
+                    .push(val, None, InsertPolicy::Always)
+                    .await
+                    .unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            assert_eq!(vec_cache.get_all().await.unwrap(), vec![0, 1, 2]);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test(start_paused = true)]
+    async fn test_dump_load() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache1 = VecCache::<String>::new(expiration_policy).await.unwrap();
+        let vec_cache2 = VecCache::<String>::new(expiration_policy).await.unwrap();
+        for val in ["c", "a", "b"] {
+            vec_cache1
+                .push(val.to_string(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+
+        let bytes = vec_cache1.dump().await.unwrap();
+        vec_cache2.load(&bytes).await.unwrap();
+
+        assert_eq!(vec_cache2.get_all().await.unwrap(), vec!["c", "a", "b"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_len_trims_back() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache.set_max_len(Some(2), VecEnd::Back).await.unwrap();
+        for val in 1..=4 {
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![1, 2]);
+        assert_eq!(vec_cache.pop_back().await.unwrap(), Some(2));
+        assert_eq!(vec_cache.get_index(0).await.unwrap(), Some(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_shorter_than_tick() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        vec_cache
+            .push(10, Some(Duration::from_millis(10)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cache
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(11)).await;
+        assert_eq!(vec_cache.get_all().await.unwrap(), vec![20]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_barrier() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cache = VecCache::new(expiration_policy).await.unwrap();
+        for val in [10, 20, 30] {
+            vec_cache
+                .push(val, None, InsertPolicy::Always)
+                .await
+                .unwrap();
+        }
+        vec_cache.barrier().await.unwrap();
+        assert_eq!(vec_cache.pop_back().await.unwrap(), Some(30));
+    }
 }
@@ -3,12 +3,15 @@ mod tests {
 
     use std::time::Duration;
 
-    use crate::tokio_cache::{option::ExpirationPolicy, unbounded::vec_cluster::VecCacheCluster};
+    use crate::tokio_cache::{
+        option::{ExpirationPolicy, InsertPolicy, RemovePolicy},
+        unbounded::vec_cluster::VecCacheCluster,
+    };
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_hash_id() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
         let vals = vec![
             "a".to_string(),
             "b".to_string(),
@@ -19,7 +22,10 @@ mod tests {
             "g".to_string(),
         ];
         for v in vals.clone() {
-            vec_cluster.push(v.clone(), None, false).await.unwrap();
+            vec_cluster
+                .push(v.clone(), None, InsertPolicy::Always)
+                .await
+                .unwrap();
         }
 
         let mut vec = vec_cluster.get_all().await.unwrap();
@@ -27,57 +33,78 @@ mod tests {
         assert_eq!(vec, vals);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_ttl() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
         vec_cluster
-            .push(10, Some(Duration::from_secs(1)), false)
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         let ttl = vec_cluster.ttl(&[10, 20]).await.unwrap();
-        assert!(Some(Duration::from_secs(1)) > ttl[0]);
-        assert_eq!(ttl[1], None);
+        assert_eq!(ttl[0].1, Some(Duration::from_secs(1)));
+        assert_eq!(ttl[1].1, None);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_clear() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
-        vec_cluster.push(30, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let mut vec = vec_cluster.get_all().await.unwrap();
         vec.sort();
         assert_eq!(vec, Vec::from([10, 20, 30]));
         vec_cluster.clear().await.unwrap();
         let hs = vec_cluster.get_all().await.unwrap();
-        assert_eq!(hs.is_empty(), true);
+        assert!(hs.is_empty());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_remove() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        let val = vec_cluster.remove(&[10, 20]).await.unwrap();
-        assert_eq!(val, vec![true, false]);
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        let val = vec_cluster
+            .remove(&[10, 20], RemovePolicy::All)
+            .await
+            .unwrap();
+        assert_eq!(val, vec![1, 0]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_contains() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let val = vec_cluster.contains(&[10, 20, 30]).await.unwrap();
         assert_eq!(val, vec![true, true, false]);
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
         vec_cluster
             .mpush(
                 &[10, 20, 30],
@@ -86,21 +113,25 @@ mod tests {
                     Some(Duration::from_secs(1)),
                     Some(Duration::from_secs(1)),
                 ],
-                &[false, false, false],
+                &[InsertPolicy::Always; 3],
             )
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
         let val = vec_cluster.get_all().await.unwrap();
-        assert_eq!(val, Vec::new());
+        assert_eq!(val, Vec::<i32>::new());
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_mpush() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
         vec_cluster
-            .mpush(&[10, 20, 30], &[None, None, None], &[false, false, false])
+            .mpush(
+                &[10, 20, 30],
+                &[None, None, None],
+                &[InsertPolicy::Always; 3],
+            )
             .await
             .unwrap();
         let mut val = vec_cluster.get_all().await.unwrap();
@@ -108,13 +139,16 @@ mod tests {
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push_ex() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
         vec_cluster
-            .push(20, Some(Duration::from_secs(1)), false)
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, Some(Duration::from_secs(1)), InsertPolicy::Always)
             .await
             .unwrap();
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -122,15 +156,46 @@ mod tests {
         assert_eq!(val, Vec::from([10]));
     }
 
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_push() {
         let expiration_policy = ExpirationPolicy::None;
-        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await;
-        vec_cluster.push(10, None, false).await.unwrap();
-        vec_cluster.push(20, None, false).await.unwrap();
-        vec_cluster.push(30, None, false).await.unwrap();
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
+        vec_cluster
+            .push(10, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(20, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(30, None, InsertPolicy::Always)
+            .await
+            .unwrap();
         let mut val = vec_cluster.get_all().await.unwrap();
         val.sort();
         assert_eq!(val, Vec::from([10, 20, 30]));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_keyed_by_val() {
+        let expiration_policy = ExpirationPolicy::None;
+        let vec_cluster = VecCacheCluster::new(expiration_policy, 3).await.unwrap();
+        vec_cluster
+            .push(10, Some(Duration::from_secs(1)), InsertPolicy::Always)
+            .await
+            .unwrap();
+        vec_cluster
+            .push(40, None, InsertPolicy::Always)
+            .await
+            .unwrap();
+
+        let ttl = vec_cluster.ttl(&[20, 10, 30, 40]).await.unwrap();
+        let vals = ttl.iter().map(|(val, _)| *val).collect::<Vec<_>>();
+        assert_eq!(vals, vec![20, 10, 30, 40]);
+        assert_eq!(ttl[0].1, None);
+        assert!(ttl[1].1.is_some());
+        assert_eq!(ttl[2].1, None);
+        assert_eq!(ttl[3].1, None);
+    }
 }